@@ -0,0 +1,25 @@
+#![feature(test)]
+
+extern crate test;
+
+use ray_tracer_challenge::{canvas::Canvas, color::Color, ppm::ToPPM};
+use test::Bencher;
+
+// A full HD gradient, so samples have one, two and three digits and lines wrap
+fn canvas() -> Canvas {
+    let mut c = Canvas::new(1920, 1080);
+    c.map_pixels(|x, y, _| Color::new(x as f64 / 1920.0, y as f64 / 1080.0, 0.5));
+    c
+}
+
+#[bench]
+fn write_p3_ppm(b: &mut Bencher) {
+    let c = canvas();
+    b.iter(|| c.to_ppm());
+}
+
+#[bench]
+fn write_p6_ppm(b: &mut Bencher) {
+    let c = canvas();
+    b.iter(|| c.to_ppm_p6());
+}
@@ -0,0 +1,44 @@
+#![feature(test)]
+
+extern crate test;
+
+use std::f64::consts::PI;
+
+use ray_tracer_challenge::{camera::Camera, matrix::Matrix, tuple::Tuple, world::World};
+use test::Bencher;
+
+// The default world at 400x400, which is big enough for the parallel render to pull ahead
+fn camera() -> Camera {
+    let mut c = Camera::new(400, 400, PI / 3.0);
+    c.set_transform(Matrix::view_transform(
+        Tuple::point(0.0, 1.5, -5.0),
+        Tuple::point(0.0, 0.0, 0.0),
+        Tuple::vector(0.0, 1.0, 0.0),
+    )).unwrap();
+    c
+}
+
+#[bench]
+fn render_default_world_parallel(b: &mut Bencher) {
+    let (w, c) = (World::default(), camera());
+    b.iter(|| c.render(&w));
+}
+
+#[bench]
+fn render_default_world_sequential(b: &mut Bencher) {
+    let (w, c) = (World::default(), camera());
+    b.iter(|| c.render_sequential(&w));
+}
+
+// Every pixel's ray, without tracing it, which used to invert the view transform each time
+#[bench]
+fn rays_for_every_pixel(b: &mut Bencher) {
+    let c = camera();
+    b.iter(|| {
+        for y in 0..c.vsize {
+            for x in 0..c.hsize {
+                test::black_box(c.ray_for_pixel(x, y));
+            }
+        }
+    });
+}
@@ -0,0 +1,133 @@
+use crate::{camera::Camera, canvas::Canvas, error::Error, matrix::Matrix, world::World};
+
+// Values that can be blended, t going from 0 at self to 1 at other
+pub trait Interpolate {
+    fn interpolate(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Interpolate for f64 {
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for Matrix<4> {
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        self.lerp(*other, t)
+    }
+}
+
+// A value that changes over time, blended linearly between keyframes. Before the first keyframe
+// and after the last it holds still
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Animated<T> {
+    keyframes: Vec<(f64, T)>,
+}
+
+impl<T: Interpolate + Clone> Animated<T> {
+    pub fn fixed(value: T) -> Self {
+        Self {
+            keyframes: vec![(0.0, value)],
+        }
+    }
+
+    // The keyframes can be given in any order, but need at least one
+    pub fn keyframes(mut keyframes: Vec<(f64, T)>) -> Self {
+        assert!(!keyframes.is_empty(), "an animation needs at least one keyframe");
+        keyframes.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap());
+
+        Self { keyframes }
+    }
+
+    // At a keyframe's time this is its value exactly, not a blend that rounds towards it
+    pub fn at(&self, time: f64) -> T {
+        let after = self.keyframes.partition_point(|(t, _)| *t < time);
+        match (self.keyframes.get(after.wrapping_sub(1)), self.keyframes.get(after)) {
+            (_, Some((t, value))) if *t == time => value.clone(),
+            (Some((t0, v0)), Some((t1, v1))) => v0.interpolate(v1, (time - t0) / (t1 - t0)),
+            (Some((_, value)), None) | (None, Some((_, value))) => value.clone(),
+            (None, None) => unreachable!("animations always have a keyframe"),
+        }
+    }
+}
+
+// Renders frames evenly spread over time 0 to 1, the first at 0 and the last at 1, handing each
+// over as soon as it's done. The camera keeps all its settings apart from the transform. Stops
+// at the first frame whose camera transform can't be inverted
+pub fn render_animation(
+    camera: &Camera,
+    camera_transform: &Animated<Matrix<4>>,
+    world: &World,
+    frames: usize,
+    mut on_frame: impl FnMut(usize, Canvas),
+) -> Result<(), Error> {
+    for frame in 0..frames {
+        let time = if frames > 1 { frame as f64 / (frames - 1) as f64 } else { 0.0 };
+        let mut frame_camera = *camera;
+        frame_camera.set_transform(camera_transform.at(time))?;
+
+        on_frame(frame, frame_camera.render(&world.at_time(time)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::{assert_fuzzy_eq, tuple::Tuple, util::FuzzyEq};
+
+    use super::*;
+
+    fn slide() -> Animated<Matrix<4>> {
+        Animated::keyframes(vec![
+            (1.0, Matrix::translation(2.0, 4.0, -6.0)),
+            (0.0, Matrix::translation(0.0, 0.0, 0.0)),
+        ])
+    }
+
+    #[test]
+    fn keyframes_are_reproduced_exactly() {
+        let a = Animated::keyframes(vec![
+            (0.0, Matrix::rotation_y(0.3) * Matrix::translation(0.1, 0.2, 0.3)),
+            (0.5, Matrix::scaling(0.3, 0.7, 1.1)),
+            (1.0, Matrix::rotation_x(PI / 3.0)),
+        ]);
+
+        assert_eq!(Matrix::rotation_y(0.3) * Matrix::translation(0.1, 0.2, 0.3), a.at(0.0));
+        assert_eq!(Matrix::scaling(0.3, 0.7, 1.1), a.at(0.5));
+        assert_eq!(Matrix::rotation_x(PI / 3.0), a.at(1.0));
+    }
+
+    #[test]
+    fn halfway_between_translations_is_their_midpoint() {
+        assert_fuzzy_eq!(Matrix::translation(1.0, 2.0, -3.0), slide().at(0.5));
+        assert_fuzzy_eq!(Tuple::point(0.5, 1.0, -1.5), slide().at(0.25) * Tuple::point(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn animation_holds_still_outside_its_keyframes() {
+        assert_eq!(Matrix::identity(), slide().at(-1.0));
+        assert_eq!(Matrix::translation(2.0, 4.0, -6.0), slide().at(3.0));
+        assert_eq!(4.0, Animated::fixed(4.0).at(0.7));
+    }
+
+    #[test]
+    fn animation_renders_every_frame_at_its_time() {
+        let mut w = World::default();
+        w.animate(0, Animated::keyframes(vec![(0.0, Matrix::identity()), (1.0, Matrix::translation(2.0, 0.0, 0.0))]));
+        let mut camera = Camera::new(5, 5, PI / 2.0);
+        let view = Matrix::view_transform(Tuple::point(0.0, 0.0, -5.0), Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 1.0, 0.0));
+        camera.set_transform(view).unwrap();
+
+        let mut frames = vec![];
+        render_animation(&camera, &Animated::fixed(view), &w, 3, |i, canvas| frames.push((i, canvas))).unwrap();
+
+        assert_eq!(vec![0, 1, 2], frames.iter().map(|(i, _)| *i).collect::<Vec<_>>());
+        assert_eq!(camera.render(&w.at_time(0.0)), frames[0].1);
+        assert_eq!(camera.render(&w.at_time(0.5)), frames[1].1);
+        assert_eq!(camera.render(&w.at_time(1.0)), frames[2].1);
+        assert_ne!(frames[0].1, frames[2].1);
+    }
+}
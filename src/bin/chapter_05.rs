@@ -47,7 +47,7 @@ fn main() {
 
             let xs = sphere.intersect(ray);
 
-            if xs.hit() != None {
+            if xs.hit().is_some() {
                 canvas.write_pixel(x, y, yellow);
             }
             progress.inc(1);
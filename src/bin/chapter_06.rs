@@ -50,8 +50,7 @@ fn main() {
             let xs = sphere.intersect(ray);
             let hit = xs.hit();
 
-            if hit != None {
-                let hit = hit.unwrap();
+            if let Some(hit) = hit {
                 let point = ray.position(hit.t);
                 let normal = hit.object.normal_at(point);
                 let eye = -ray.direction;
@@ -59,7 +58,7 @@ fn main() {
                 let color = hit
                     .object
                     .material()
-                    .lighting(point, light, eye, normal, false);
+                    .lighting(&hit.object, point, light, eye, normal, 1.0);
 
                 canvas.write_pixel(x, y, color);
             }
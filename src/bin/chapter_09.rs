@@ -1,11 +1,13 @@
 use std::f64::consts::PI;
 use std::fs::write;
 
+use indicatif::ProgressBar;
+
 use ray_tracer_challenge::{
     camera::Camera,
     color::Color,
     light::Light,
-    material::Material,
+    material::{Material, Surface},
     matrix::Matrix,
     png::ToPNG,
     shape::Shape,
@@ -24,8 +26,8 @@ fn main() {
 
     let floor_material = Material {
         color: Color::new(1.0, 0.9, 0.9),
-        specular: 0.0,
         pattern: Some(grad),
+        surface: Surface::Lambertian,
         ..Default::default()
     };
 
@@ -37,12 +39,7 @@ fn main() {
         .into();
         
 
-    let middle_material = Material {
-        color: Color::new(0.5, 1.0, 0.1),
-        diffuse: 0.7,
-        specular: 0.3,
-        ..Default::default()
-    };
+    let middle_material = Material::with_color(Color::new(0.5, 1.0, 0.1)).with_diffuse(0.7).with_specular(0.3);
 
     let middle: Shape = SphereBuilder::default()
         .material(middle_material)
@@ -51,12 +48,7 @@ fn main() {
         .unwrap()
         .into();
 
-    let right_material = Material {
-        color: Color::new(0.5, 1.0, 0.1),
-        diffuse: 0.7,
-        specular: 0.3,
-        ..Default::default()
-    };
+    let right_material = Material::with_color(Color::new(0.5, 1.0, 0.1)).with_diffuse(0.7).with_specular(0.3);
 
     let right = SphereBuilder::default()
         .material(right_material)
@@ -65,12 +57,7 @@ fn main() {
         .unwrap()
         .into();
 
-    let left_material = Material {
-        color: Color::new(1.0, 0.8, 0.1),
-        diffuse: 0.7,
-        specular: 0.3,
-        ..Default::default()
-    };
+    let left_material = Material::with_color(Color::new(1.0, 0.8, 0.1)).with_diffuse(0.7).with_specular(0.3);
 
     let left = SphereBuilder::default()
         .material(left_material)
@@ -91,8 +78,10 @@ fn main() {
         Tuple::point(0.0, 1.5, -5.0),
         Tuple::point(0.0, 1.0, 0.0),
         Tuple::vector(0.0, 1.0, 0.0),
-    ));
-    let canvas = camera.render(&world);
+    )).unwrap();
+    let progress = ProgressBar::new((camera.hsize * camera.vsize) as u64);
+    let canvas = camera.render_with_progress(&world, |p| progress.set_position(p.pixels_done as u64));
+    progress.finish();
 
     println!("Writing ./output.png");
     let png = canvas.to_png();
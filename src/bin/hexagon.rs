@@ -0,0 +1,32 @@
+use std::f64::consts::PI;
+use std::fs::write;
+
+use indicatif::ProgressBar;
+
+use ray_tracer_challenge::{
+    camera::Camera, color::Color, light::Light, matrix::Matrix, png::ToPNG, prefabs::hexagon,
+    shape::Shape, tuple::Tuple, world::World,
+};
+
+fn main() {
+    let mut hex: Shape = hexagon();
+    hex.set_transform(Matrix::translation(0.0, 1.0, 0.0) * Matrix::rotation_x(-PI / 6.0));
+
+    let light = Light::point(Tuple::point(-10.0, 10.0, -10.0), Color::white());
+
+    let world = World::new(vec![hex], light);
+    let mut camera = Camera::new(1024, 1024, PI / 3.0);
+
+    camera.set_transform(Matrix::view_transform(
+        Tuple::point(0.0, 1.5, -4.0),
+        Tuple::point(0.0, 1.0, 0.0),
+        Tuple::vector(0.0, 1.0, 0.0),
+    )).unwrap();
+    let progress = ProgressBar::new((camera.hsize * camera.vsize) as u64);
+    let canvas = camera.render_with_progress(&world, |p| progress.set_position(p.pixels_done as u64));
+    progress.finish();
+
+    println!("Writing ./hexagon.png");
+    let png = canvas.to_png();
+    write("./hexagon.png", png).expect("Could not write hexagon.png to disk.");
+}
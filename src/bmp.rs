@@ -0,0 +1,46 @@
+use crate::rgb::ExportOptions;
+
+pub trait ToBMP {
+    fn to_bmp(&self) -> Vec<u8> {
+        self.to_bmp_with(ExportOptions::default())
+    }
+
+    // Uncompressed 24-bit: a 14 byte file header and a 40 byte info header, then the rows from
+    // the bottom of the image up, each pixel blue, green, red and each row padded to a multiple
+    // of four bytes
+    fn to_bmp_with(&self, options: ExportOptions) -> Vec<u8>;
+}
+
+pub(crate) const HEADER_SIZE: usize = 14 + 40;
+
+// Bytes a row of this many pixels takes, padding included
+pub(crate) fn row_size(width: usize) -> usize {
+    (width * 3).next_multiple_of(4)
+}
+
+pub(crate) fn header(width: usize, height: usize) -> Vec<u8> {
+    let image_size = (row_size(width) * height) as u32;
+    // 72 dots per inch
+    let pixels_per_meter = 2835_i32;
+
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+    header.extend(b"BM");
+    header.extend((HEADER_SIZE as u32 + image_size).to_le_bytes());
+    header.extend(0_u32.to_le_bytes());
+    header.extend((HEADER_SIZE as u32).to_le_bytes());
+
+    header.extend(40_u32.to_le_bytes());
+    header.extend((width as i32).to_le_bytes());
+    // Positive, for rows stored bottom up
+    header.extend((height as i32).to_le_bytes());
+    header.extend(1_u16.to_le_bytes());
+    header.extend(24_u16.to_le_bytes());
+    header.extend(0_u32.to_le_bytes());
+    header.extend(image_size.to_le_bytes());
+    header.extend(pixels_per_meter.to_le_bytes());
+    header.extend(pixels_per_meter.to_le_bytes());
+    header.extend(0_u32.to_le_bytes());
+    header.extend(0_u32.to_le_bytes());
+
+    header
+}
@@ -0,0 +1,352 @@
+use crate::{
+    matrix::Matrix,
+    ray::Ray,
+    tuple::Tuple,
+    util::{FuzzyEq, EPSILON},
+};
+
+// An axis-aligned box, empty boxes have their min at +infinity and max at -infinity so adding
+// the first point makes it both
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct BoundingBox {
+    pub min: Tuple,
+    pub max: Tuple,
+}
+
+impl Default for BoundingBox {
+    fn default() -> Self {
+        Self {
+            min: Tuple::point(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Tuple::point(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+}
+
+impl BoundingBox {
+    pub fn new(min: Tuple, max: Tuple) -> Self {
+        Self { min, max }
+    }
+
+    pub fn add_point(&mut self, point: Tuple) {
+        self.min = Tuple::point(
+            self.min.x.min(point.x),
+            self.min.y.min(point.y),
+            self.min.z.min(point.z),
+        );
+        self.max = Tuple::point(
+            self.max.x.max(point.x),
+            self.max.y.max(point.y),
+            self.max.z.max(point.z),
+        );
+    }
+
+    pub fn merge(&mut self, other: BoundingBox) {
+        if other.is_empty() {
+            return;
+        }
+        self.add_point(other.min);
+        self.add_point(other.max);
+    }
+
+    // Nothing has been added to it yet, like a group without children
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn contains_point(&self, point: Tuple) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x)
+            && (self.min.y..=self.max.y).contains(&point.y)
+            && (self.min.z..=self.max.z).contains(&point.z)
+    }
+
+    pub fn contains_box(&self, other: BoundingBox) -> bool {
+        self.contains_point(other.min) && self.contains_point(other.max)
+    }
+
+    // Padded by EPSILON so rays grazing a face (tangent hits on the children) aren't culled by
+    // rounding in transformed bounds
+    pub fn intersects(&self, ray: Ray) -> bool {
+        let padding = Tuple::vector(EPSILON, EPSILON, EPSILON);
+        ray.intersect_aabb(self.min - padding, self.max + padding).is_some()
+    }
+
+    // An empty box stays empty, its corners at infinity would otherwise turn it inside out
+    pub fn transform(&self, transform: Matrix<4>) -> BoundingBox {
+        if self.is_empty() {
+            return *self;
+        }
+        let mut result = BoundingBox::default();
+
+        for x in [self.min.x, self.max.x] {
+            for y in [self.min.y, self.max.y] {
+                for z in [self.min.z, self.max.z] {
+                    let corner = [x, y, z, 1.0];
+
+                    // Zero entries are skipped so infinite boxes (planes) don't turn into NaN,
+                    // an axis that still mixes both infinities is unbounded both ways
+                    let axis = |row: usize| -> f64 {
+                        (0..4)
+                            .filter(|&column| transform[row][column] != 0.0)
+                            .map(|column| transform[row][column] * corner[column])
+                            .sum()
+                    };
+                    let values = [axis(0), axis(1), axis(2)];
+                    let low = values.map(|v| if v.is_nan() { f64::NEG_INFINITY } else { v });
+                    let high = values.map(|v| if v.is_nan() { f64::INFINITY } else { v });
+
+                    result.add_point(Tuple::point(low[0], low[1], low[2]));
+                    result.add_point(Tuple::point(high[0], high[1], high[2]));
+                }
+            }
+        }
+
+        result
+    }
+}
+
+impl FuzzyEq<Self> for BoundingBox {
+    fn fuzzy_eq(&self, other: Self) -> bool {
+        self.min.fuzzy_eq(other.min) && self.max.fuzzy_eq(other.max)
+    }
+
+    fn fuzzy_ne(&self, other: Self) -> bool {
+        !self.fuzzy_eq(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::assert_fuzzy_eq;
+
+    use super::*;
+
+    #[test]
+    fn creating_an_empty_bounding_box() {
+        let b = BoundingBox::default();
+
+        assert_eq!(
+            Tuple::point(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            b.min
+        );
+        assert_eq!(
+            Tuple::point(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            b.max
+        );
+    }
+
+    #[test]
+    fn adding_points_to_empty_bounding_box() {
+        let mut b = BoundingBox::default();
+        b.add_point(Tuple::point(-5.0, 2.0, 0.0));
+        b.add_point(Tuple::point(7.0, 0.0, -3.0));
+
+        assert_fuzzy_eq!(Tuple::point(-5.0, 0.0, -3.0), b.min);
+        assert_fuzzy_eq!(Tuple::point(7.0, 2.0, 0.0), b.max);
+    }
+
+    #[test]
+    fn merging_two_bounding_boxes() {
+        let mut b1 = BoundingBox::new(Tuple::point(-5.0, -2.0, 0.0), Tuple::point(7.0, 4.0, 4.0));
+        let b2 = BoundingBox::new(Tuple::point(8.0, -7.0, -2.0), Tuple::point(14.0, 2.0, 8.0));
+        b1.merge(b2);
+
+        assert_fuzzy_eq!(Tuple::point(-5.0, -7.0, -2.0), b1.min);
+        assert_fuzzy_eq!(Tuple::point(14.0, 4.0, 8.0), b1.max);
+    }
+
+    #[test]
+    fn checking_if_box_contains_point() {
+        let b = BoundingBox::new(Tuple::point(5.0, -2.0, 0.0), Tuple::point(11.0, 4.0, 7.0));
+        let cases = vec![
+            (Tuple::point(5.0, -2.0, 0.0), true),
+            (Tuple::point(11.0, 4.0, 7.0), true),
+            (Tuple::point(8.0, 1.0, 3.0), true),
+            (Tuple::point(3.0, 0.0, 3.0), false),
+            (Tuple::point(8.0, -4.0, 3.0), false),
+            (Tuple::point(8.0, 1.0, -1.0), false),
+            (Tuple::point(13.0, 1.0, 3.0), false),
+            (Tuple::point(8.0, 5.0, 3.0), false),
+            (Tuple::point(8.0, 1.0, 8.0), false),
+        ];
+
+        for (point, expected) in cases {
+            assert_eq!(expected, b.contains_point(point));
+        }
+    }
+
+    #[test]
+    fn checking_if_box_contains_box() {
+        let b = BoundingBox::new(Tuple::point(5.0, -2.0, 0.0), Tuple::point(11.0, 4.0, 7.0));
+        let cases = vec![
+            (
+                Tuple::point(5.0, -2.0, 0.0),
+                Tuple::point(11.0, 4.0, 7.0),
+                true,
+            ),
+            (
+                Tuple::point(6.0, -1.0, 1.0),
+                Tuple::point(10.0, 3.0, 6.0),
+                true,
+            ),
+            (
+                Tuple::point(4.0, -3.0, -1.0),
+                Tuple::point(10.0, 3.0, 6.0),
+                false,
+            ),
+            (
+                Tuple::point(6.0, -1.0, 1.0),
+                Tuple::point(12.0, 5.0, 8.0),
+                false,
+            ),
+        ];
+
+        for (min, max, expected) in cases {
+            assert_eq!(expected, b.contains_box(BoundingBox::new(min, max)));
+        }
+    }
+
+    #[test]
+    fn transforming_a_bounding_box() {
+        let b = BoundingBox::new(Tuple::point(-1.0, -1.0, -1.0), Tuple::point(1.0, 1.0, 1.0));
+        let m = Matrix::rotation_x(PI / 4.0) * Matrix::rotation_y(PI / 4.0);
+        let b2 = b.transform(m);
+
+        let sqrt_2 = 2.0_f64.sqrt();
+        let yz = 1.0 + sqrt_2 / 2.0;
+        assert_fuzzy_eq!(Tuple::point(-sqrt_2, -yz, -yz), b2.min);
+        assert_fuzzy_eq!(Tuple::point(sqrt_2, yz, yz), b2.max);
+    }
+
+    #[test]
+    fn transforming_an_infinite_bounding_box() {
+        let b = BoundingBox::new(
+            Tuple::point(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY),
+            Tuple::point(f64::INFINITY, 0.0, f64::INFINITY),
+        );
+
+        let b2 = b.transform(Matrix::translation(0.0, 2.0, 0.0));
+        assert_eq!(
+            Tuple::point(f64::NEG_INFINITY, 2.0, f64::NEG_INFINITY),
+            b2.min
+        );
+        assert_eq!(Tuple::point(f64::INFINITY, 2.0, f64::INFINITY), b2.max);
+
+        let b3 = b.transform(Matrix::rotation_z(PI / 4.0));
+        assert_eq!(
+            Tuple::point(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            b3.min
+        );
+        assert_eq!(
+            Tuple::point(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            b3.max
+        );
+    }
+
+    #[test]
+    fn intersecting_ray_with_bounding_box_at_origin() {
+        let b = BoundingBox::new(Tuple::point(-1.0, -1.0, -1.0), Tuple::point(1.0, 1.0, 1.0));
+        let cases = vec![
+            (
+                Tuple::point(5.0, 0.5, 0.0),
+                Tuple::vector(-1.0, 0.0, 0.0),
+                true,
+            ),
+            (
+                Tuple::point(-5.0, 0.5, 0.0),
+                Tuple::vector(1.0, 0.0, 0.0),
+                true,
+            ),
+            (
+                Tuple::point(0.5, 5.0, 0.0),
+                Tuple::vector(0.0, -1.0, 0.0),
+                true,
+            ),
+            (
+                Tuple::point(0.5, 0.0, -5.0),
+                Tuple::vector(0.0, 0.0, 1.0),
+                true,
+            ),
+            (
+                Tuple::point(0.0, 0.5, 0.0),
+                Tuple::vector(0.0, 0.0, 1.0),
+                true,
+            ),
+            (
+                Tuple::point(-2.0, 0.0, 0.0),
+                Tuple::vector(2.0, 4.0, 6.0),
+                false,
+            ),
+            (
+                Tuple::point(0.0, -2.0, 0.0),
+                Tuple::vector(6.0, 2.0, 4.0),
+                false,
+            ),
+            (
+                Tuple::point(2.0, 0.0, 2.0),
+                Tuple::vector(0.0, 0.0, -1.0),
+                false,
+            ),
+            (
+                Tuple::point(0.0, 2.0, 2.0),
+                Tuple::vector(0.0, -1.0, 0.0),
+                false,
+            ),
+        ];
+
+        for (origin, direction, expected) in cases {
+            let r = Ray::new(origin, direction.normalize());
+            assert_eq!(expected, b.intersects(r));
+        }
+    }
+
+    #[test]
+    fn intersecting_ray_with_non_cubic_bounding_box() {
+        let b = BoundingBox::new(Tuple::point(5.0, -2.0, 0.0), Tuple::point(11.0, 4.0, 7.0));
+        let cases = vec![
+            (
+                Tuple::point(15.0, 1.0, 2.0),
+                Tuple::vector(-1.0, 0.0, 0.0),
+                true,
+            ),
+            (
+                Tuple::point(8.0, 2.0, 12.0),
+                Tuple::vector(0.0, 0.0, -1.0),
+                true,
+            ),
+            (
+                Tuple::point(9.0, -1.0, -8.0),
+                Tuple::vector(2.0, 4.0, 6.0),
+                false,
+            ),
+            (
+                Tuple::point(12.0, 5.0, 4.0),
+                Tuple::vector(-1.0, 0.0, 0.0),
+                false,
+            ),
+        ];
+
+        for (origin, direction, expected) in cases {
+            let r = Ray::new(origin, direction.normalize());
+            assert_eq!(expected, b.intersects(r));
+        }
+    }
+
+    #[test]
+    fn transforming_an_empty_box_leaves_it_empty() {
+        let empty = BoundingBox::default();
+        let transform = Matrix::rotation_x(PI / 4.0) * Matrix::scaling(-2.0, 3.0, 1.0);
+
+        assert!(empty.is_empty());
+        assert!(!BoundingBox::new(Tuple::point(0.0, 0.0, 0.0), Tuple::point(0.0, 0.0, 0.0)).is_empty());
+        assert_eq!(empty, empty.transform(transform));
+        assert!(!empty.transform(transform).contains_point(Tuple::point(0.0, 0.0, 0.0)));
+
+        // So an empty group in another one adds nothing to its bounds
+        let unit = BoundingBox::new(Tuple::point(-1.0, -1.0, -1.0), Tuple::point(1.0, 1.0, 1.0));
+        let mut merged = unit;
+        merged.merge(empty.transform(transform));
+        assert_eq!(unit, merged);
+    }
+}
@@ -0,0 +1,166 @@
+use std::{cmp::Ordering, sync::OnceLock};
+
+use crate::{
+    bounding_box::BoundingBox,
+    ray::Ray,
+    shape::{Shape, ShapeFuncs},
+    tuple::Tuple,
+};
+
+// Leaves this small are cheaper to test one by one than to split further
+const LEAF_SIZE: usize = 4;
+
+// Halving the objects at every split, this is deeper than any world that fits in memory needs
+const MAX_DEPTH: usize = 64;
+
+// A bounding volume hierarchy over the top level objects of a world, holding their indices.
+// Objects without finite bounds, like planes, can't be placed in it and are always tested
+#[derive(Debug, Clone)]
+pub(crate) struct Bvh {
+    root: Option<Node>,
+    unbounded: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Leaf { bounds: BoundingBox, objects: Vec<usize> },
+    Split { bounds: BoundingBox, left: Box<Node>, right: Box<Node> },
+}
+
+impl Bvh {
+    pub(crate) fn new(objects: &[Shape]) -> Self {
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = objects
+            .iter()
+            .map(|o| o.parent_space_bounds())
+            .enumerate()
+            .partition(|(_, bounds)| is_finite(bounds.min) && is_finite(bounds.max));
+
+        Self {
+            root: (!bounded.is_empty()).then(|| Node::new(bounded)),
+            unbounded: unbounded.into_iter().map(|(i, _)| i).collect(),
+        }
+    }
+
+    // The objects a ray might hit: the unbounded ones, then those in every leaf whose box the
+    // ray passes through, left before right
+    pub(crate) fn candidates(&self, ray: Ray) -> impl Iterator<Item = usize> + '_ {
+        let mut walk = Walk { ray, stack: [None; MAX_DEPTH], depth: 0, leaf: [].iter() };
+        if let Some(root) = &self.root {
+            walk.push(root);
+        }
+
+        self.unbounded.iter().copied().chain(walk)
+    }
+}
+
+// Depth first through the tree, keeping the nodes still to visit on a stack that needs no
+// allocating
+struct Walk<'a> {
+    ray: Ray,
+    stack: [Option<&'a Node>; MAX_DEPTH],
+    depth: usize,
+    leaf: std::slice::Iter<'a, usize>,
+}
+
+impl<'a> Walk<'a> {
+    fn push(&mut self, node: &'a Node) {
+        self.stack[self.depth] = Some(node);
+        self.depth += 1;
+    }
+}
+
+impl Iterator for Walk<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        loop {
+            if let Some(&i) = self.leaf.next() {
+                return Some(i);
+            }
+            if self.depth == 0 {
+                return None;
+            }
+
+            self.depth -= 1;
+            match self.stack[self.depth].take()? {
+                Node::Leaf { bounds, objects } => {
+                    if bounds.intersects(self.ray) {
+                        self.leaf = objects.iter();
+                    }
+                }
+                Node::Split { bounds, left, right } => {
+                    if bounds.intersects(self.ray) {
+                        self.push(right);
+                        self.push(left);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Node {
+    // Splits at the middle object along the axis the centers are most spread out on
+    fn new(mut objects: Vec<(usize, BoundingBox)>) -> Self {
+        let mut bounds = BoundingBox::default();
+        let mut centers = BoundingBox::default();
+        for (_, b) in &objects {
+            bounds.merge(*b);
+            centers.add_point(center(b));
+        }
+
+        if objects.len() <= LEAF_SIZE {
+            return Self::Leaf { bounds, objects: objects.into_iter().map(|(i, _)| i).collect() };
+        }
+
+        let extent = centers.max - centers.min;
+        let axis = |b: &BoundingBox| {
+            let c = center(b);
+            if extent.x >= extent.y && extent.x >= extent.z {
+                c.x
+            } else if extent.y >= extent.z {
+                c.y
+            } else {
+                c.z
+            }
+        };
+        objects.sort_by(|(_, a), (_, b)| axis(a).partial_cmp(&axis(b)).unwrap_or(Ordering::Equal));
+        let right = objects.split_off(objects.len() / 2);
+
+        Self::Split {
+            bounds,
+            left: Box::new(Self::new(objects)),
+            right: Box::new(Self::new(right)),
+        }
+    }
+}
+
+fn center(b: &BoundingBox) -> Tuple {
+    Tuple::point((b.min.x + b.max.x) / 2.0, (b.min.y + b.max.y) / 2.0, (b.min.z + b.max.z) / 2.0)
+}
+
+fn is_finite(t: Tuple) -> bool {
+    t.x.is_finite() && t.y.is_finite() && t.z.is_finite()
+}
+
+// Built the first time a world is intersected, it takes no part in comparing worlds
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BvhCache(OnceLock<Bvh>);
+
+impl BvhCache {
+    pub(crate) fn get(&self, objects: &[Shape]) -> &Bvh {
+        self.0.get_or_init(|| Bvh::new(objects))
+    }
+}
+
+impl PartialEq for BvhCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl PartialOrd for BvhCache {
+    fn partial_cmp(&self, _other: &Self) -> Option<Ordering> {
+        Some(Ordering::Equal)
+    }
+}
@@ -1,103 +1,900 @@
-use std::sync::Mutex;
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
-use crate::{canvas::Canvas, matrix::Matrix, ray::Ray, tuple::Tuple, world::World};
-#[allow(unused_imports)]
-use indicatif::{ProgressBar, ProgressStyle};
+use crate::{
+    canvas::Canvas,
+    color::Color,
+    depth_buffer::DepthBuffer,
+    error::Error,
+    intersection::Intersection,
+    matrix::Matrix,
+    ray::Ray,
+    ray_counters::{self, Counter, RayCounters, RayCounts},
+    sampling::{PixelSampler, Sampler},
+    shape::ShapeFuncs,
+    tuple::Tuple,
+    util::EPSILON,
+    world::{ShadingTrace, World},
+};
 use itertools::Itertools;
-use rayon::prelude::*;
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
+
+// Where inside a pixel the extra samples go. Both split the pixel into one cell per sample, with
+// jittered samples at a random spot in each cell instead of its middle
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Sampling {
+    #[default]
+    Grid,
+    Jittered,
+}
+
+// Extra passes for compositing and denoising, taken from the first hit through the middle of each
+// pixel. Normal maps the world space normal from [-1, 1] onto [0, 1] in each channel, Albedo is
+// the material's color there without any lighting. Misses are black in both
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    Normal,
+    Albedo,
+}
+
+// How pixels turn into directions. Equirectangular covers every direction, with longitude running
+// across the image and the middle looking down -z, and ignores the fov. Fisheye spaces angles
+// evenly out from the middle to fov / 2 at the edge of the biggest circle that fits, and leaves
+// anything outside that circle black. Only the perspective projection has a lens
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(tag = "type"))]
+pub enum Projection {
+    #[default]
+    Perspective,
+    Equirectangular,
+    Fisheye { fov: f64 },
+}
+
+// After the first pass, pixels that stand out from a neighbour by more than the threshold in any
+// channel get this many more jittered samples, which replace what they had
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AdaptiveAa {
+    pub threshold: f64,
+    pub extra_samples: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RenderStats {
+    pub samples: usize,
+    pub refined_pixels: usize,
+    pub rays: RayCounts,
+    pub elapsed: Duration,
+}
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderProgress {
+    pub pixels_done: usize,
+    pub pixels_total: usize,
+    pub elapsed: Duration,
+}
+
+// A piece of the image, with its top left corner at (x0, y0) in the full image
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tile {
+    pub x0: usize,
+    pub y0: usize,
+    pub canvas: Canvas,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionError {
+    OutOfBounds { x0: usize, y0: usize, width: usize, height: usize, hsize: usize, vsize: usize },
+}
+
+impl fmt::Display for RegionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfBounds { x0, y0, width, height, hsize, vsize } => write!(
+                f,
+                "region {}x{} at ({}, {}) doesn't fit in a {}x{} image",
+                width, height, x0, y0, hsize, vsize
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RegionError {}
+
+// How the ray through the middle of a pixel came by its color. The color is developed like the
+// render's, but from that one ray
+#[derive(Debug, Clone, PartialEq)]
+pub struct PixelTrace {
+    pub x: usize,
+    pub y: usize,
+    pub shading: ShadingTrace,
+    pub color: Color,
+}
+
+impl fmt::Display for PixelTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "pixel ({}, {})", self.x, self.y)?;
+        writeln!(f, "{}", self.shading)?;
+        write!(f, "developed {:?}", self.color)
+    }
+}
+
+// Building checks the image size and field of view, and works out the size of a pixel from them
+// Reading one back in goes through the builder, so it's checked and sized like any other
+#[derive(Debug, Clone, Copy, PartialEq, Builder)]
+#[builder(build_fn(private, name = "build_unsized", validate = "Self::validate"))]
+#[cfg_attr(feature = "serde", builder(derive(serde::Deserialize)), builder_struct_attr(serde(default)))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(try_from = "CameraBuilder"))]
 pub struct Camera {
+    #[builder(default = "160")]
     pub hsize: usize,
+    #[builder(default = "120")]
     pub vsize: usize,
+    // Covers the longer side of the image, unless vfov is set. Then fov is across and vfov is up
+    // and down, whatever the shape of the image. square_pixels gives both the larger pixel size,
+    // so the image covers at least both fovs
+    #[builder(default = "std::f64::consts::PI / 2.0")]
     pub fov: f64,
-    pub transform: Matrix<4>,
+    #[builder(default, setter(strip_option))]
+    pub vfov: Option<f64>,
+    #[builder(default)]
+    pub square_pixels: bool,
+    // Set through set_transform, which keeps the inverse that every ray needs up to date
+    #[builder(default)]
+    transform: Matrix<4>,
+    #[builder(default = "1")]
+    pub samples_per_pixel: usize,
+    #[builder(default)]
+    pub sampling: Sampling,
+    #[builder(default, setter(strip_option))]
+    pub adaptive: Option<AdaptiveAa>,
+    // Radius of the lens. At 0 the camera is a pinhole and everything is sharp, otherwise only
+    // what's focal_distance away along the view direction is
+    #[builder(default)]
+    pub aperture: f64,
+    #[builder(default = "1.0")]
+    pub focal_distance: f64,
+    // Seeds the sampler behind the jittered sample, lens and time of every sample, so the same
+    // seed gives the same render
+    #[builder(default)]
+    pub seed: u64,
+    // How many finished rows it takes to report progress. The last row is always reported
+    #[builder(default = "1")]
+    pub rows_per_progress: usize,
+    // Each pixel is scaled by the exposure and then raised to 1 / gamma, once its samples are
+    // averaged. Leaving both at 1 keeps the colors as traced
+    #[builder(default = "1.0")]
+    pub exposure: f64,
+    #[builder(default = "1.0")]
+    pub gamma: f64,
+    #[builder(default)]
+    pub projection: Projection,
+    // Renders on a pool of its own with this many threads, instead of rayon's global one
+    #[builder(default, setter(strip_option))]
+    pub threads: Option<usize>,
+    // Casts every sample at a random time in the frame, so shapes that move blur along their path
+    #[builder(default)]
+    pub motion_blur: bool,
+    #[builder(setter(skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    inverse_transform: Matrix<4>,
+    #[builder(setter(skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
     half_width: f64,
+    #[builder(setter(skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
     half_height: f64,
-    pixel_size: f64,
+    #[builder(setter(skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pixel_width: f64,
+    #[builder(setter(skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pixel_height: f64,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<CameraBuilder> for Camera {
+    type Error = CameraBuilderError;
+
+    fn try_from(builder: CameraBuilder) -> Result<Self, Self::Error> {
+        builder.build()
+    }
+}
+
+impl CameraBuilder {
+    pub fn build(&self) -> Result<Camera, CameraBuilderError> {
+        let mut camera = self.build_unsized()?;
+        camera.size_pixels();
+        camera.inverse_transform = camera.transform.inverse();
+        Ok(camera)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (name, value) in [("hsize", self.hsize), ("vsize", self.vsize)] {
+            if value == Some(0) {
+                return Err(format!("{} must be above 0", name));
+            }
+        }
+        if let Some(Some(0)) = self.threads {
+            return Err("threads must be above 0".to_string());
+        }
+        if let Some(transform) = self.transform {
+            if !transform.is_invertible() {
+                return Err(Error::NonInvertibleTransform { matrix: Box::new(transform) }.to_string());
+            }
+        }
+        for (name, value) in [("fov", self.fov), ("vfov", self.vfov.flatten())] {
+            if let Some(fov) = value {
+                if !(fov > 0.0 && fov < std::f64::consts::PI) {
+                    return Err(format!("{} must be between 0 and pi, got {}", name, fov));
+                }
+            }
+        }
+        if let Some(gamma) = self.gamma {
+            if !(gamma > 0.0 && gamma.is_finite()) {
+                return Err(format!("gamma must be above 0, got {}", gamma));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The cell of the pixel that sample i of a pixel's samples goes in, as its column, the number of
+// columns in its row, its row and the number of rows. There are as many rows as fit a square grid
+// and the samples left over go one each to the first rows, so every cell gets exactly one sample
+fn stratum(i: usize, samples: usize) -> (usize, usize, usize, usize) {
+    let rows = ((samples as f64).sqrt() as usize).max(1);
+    let (columns, wider_rows) = (samples / rows, samples % rows);
+    let in_wider_rows = wider_rows * (columns + 1);
+
+    if i < in_wider_rows {
+        (i % (columns + 1), columns + 1, i / (columns + 1), rows)
+    } else {
+        let i = i - in_wider_rows;
+        (i % columns, columns, wider_rows + i / columns, rows)
+    }
+}
+
+// The pools cameras with a thread count render on, one for each count. Starting the threads is
+// too slow to do for every render, let alone every pass of a progressive one
+fn render_pool(threads: usize) -> Arc<ThreadPool> {
+    static POOLS: Mutex<Vec<Arc<ThreadPool>>> = Mutex::new(Vec::new());
+
+    let mut pools = POOLS.lock().unwrap();
+    if let Some(pool) = pools.iter().find(|p| p.current_num_threads() == threads) {
+        return pool.clone();
+    }
+    let pool = Arc::new(
+        ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .expect("could not start the render threads"),
+    );
+    pools.push(pool.clone());
+    pool
 }
 
 impl Camera {
+    // Panics on a size or field of view that CameraBuilder would reject
     pub fn new(hsize: usize, vsize: usize, fov: f64) -> Self {
-        let half_view = (fov / 2.0).tan();
-        let aspect = hsize as f64 / vsize as f64;
-        let half_width;
-        let half_height;
-
-        if aspect >= 1.0 {
-            half_width = half_view;
-            half_height = half_view / aspect as f64;
+        CameraBuilder::default().hsize(hsize).vsize(vsize).fov(fov).build().unwrap()
+    }
+
+    // Sets the fov across and up and down separately, for anamorphic images. The pixels stretch
+    // to fit both, see square_pixels on the builder to keep them square instead
+    pub fn with_fovs(hsize: usize, vsize: usize, hfov: f64, vfov: f64) -> Self {
+        CameraBuilder::default().hsize(hsize).vsize(vsize).fov(hfov).vfov(vfov).build().unwrap()
+    }
+
+    fn in_pool<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        match self.threads {
+            Some(threads) => render_pool(threads).install(op),
+            None => op(),
+        }
+    }
+
+    fn size_pixels(&mut self) {
+        let half_view = (self.fov / 2.0).tan();
+        let aspect = self.hsize as f64 / self.vsize as f64;
+
+        if let Some(vfov) = self.vfov {
+            self.half_width = half_view;
+            self.half_height = (vfov / 2.0).tan();
+        } else if aspect >= 1.0 {
+            self.half_width = half_view;
+            self.half_height = half_view / aspect;
         } else {
-            half_width = half_view * aspect as f64;
-            half_height = half_view;
+            self.half_width = half_view * aspect;
+            self.half_height = half_view;
         }
 
-        let pixel_size = (half_width * 2.0) / hsize as f64;
-        Self {
-            hsize,
-            vsize,
-            fov,
-            transform: Matrix::identity(),
-            half_width,
-            half_height,
-            pixel_size,
+        self.pixel_width = (self.half_width * 2.0) / self.hsize as f64;
+        self.pixel_height = (self.half_height * 2.0) / self.vsize as f64;
+
+        if self.square_pixels && self.pixel_width != self.pixel_height {
+            let size = self.pixel_width.max(self.pixel_height);
+            self.pixel_width = size;
+            self.pixel_height = size;
+            self.half_width = size * self.hsize as f64 / 2.0;
+            self.half_height = size * self.vsize as f64 / 2.0;
         }
     }
 
-    pub fn set_transform(&mut self, t: Matrix<4>) {
+    // Works out the inverse every ray needs once, here. A transform that can't be inverted is
+    // an error, as no ray could be cast through it, and leaves the camera as it was
+    pub fn set_transform(&mut self, t: Matrix<4>) -> Result<(), Error> {
+        self.inverse_transform = t.try_inverse()?;
         self.transform = t;
+        Ok(())
+    }
+
+    pub fn transform(&self) -> Matrix<4> {
+        self.transform
     }
 
     pub fn ray_for_pixel(&self, x: usize, y: usize) -> Ray {
-        let xoffset: f64 = (x as f64 + 0.5) * self.pixel_size;
-        let yoffset: f64 = (y as f64 + 0.5) * self.pixel_size;
+        self.ray_for_pixel_offset(x, y, 0.5, 0.5)
+    }
+
+    // The offsets run from 0 to 1 across the pixel, from its top left corner
+    pub fn ray_for_pixel_offset(&self, x: usize, y: usize, dx: f64, dy: f64) -> Ray {
+        self.ray_through_lens(x, y, dx, dy, 0.0, 0.0)
+    }
+
+    // The lens offsets are a point on the unit disk, scaled up to the aperture. Every ray through
+    // the pixel meets at the same point on the focal plane
+    pub fn ray_through_lens(&self, x: usize, y: usize, dx: f64, dy: f64, lens_x: f64, lens_y: f64) -> Ray {
+        if let Some(direction) = self.panoramic_direction(x as f64 + dx, y as f64 + dy) {
+            let inverse_view_transform = self.inverse_transform;
+            let origin = inverse_view_transform * Tuple::point(0.0, 0.0, 0.0);
+            return Ray::new(origin, (inverse_view_transform * direction).normalize());
+        }
+
+        let xoffset: f64 = (x as f64 + dx) * self.pixel_width;
+        let yoffset: f64 = (y as f64 + dy) * self.pixel_height;
 
         let world_x = self.half_width - xoffset;
         let world_y = self.half_height - yoffset;
 
-        let inverse_view_transform = self.transform.inverse();
+        let inverse_view_transform = self.inverse_transform;
+
+        let (lens_point, target) = if self.aperture > 0.0 {
+            let f = self.focal_distance;
+            (
+                Tuple::point(lens_x * self.aperture, lens_y * self.aperture, 0.0),
+                Tuple::point(world_x * f, world_y * f, -f),
+            )
+        } else {
+            (Tuple::point(0.0, 0.0, 0.0), Tuple::point(world_x, world_y, -1.0))
+        };
 
-        let wall_point = inverse_view_transform * Tuple::point(world_x, world_y, -1.0);
-        let origin = inverse_view_transform * Tuple::point(0.0, 0.0, 0.0);
+        let wall_point = inverse_view_transform * target;
+        let origin = inverse_view_transform * lens_point;
 
         let direction = (wall_point - origin).normalize();
         Ray::new(origin, direction)
     }
 
+    // The direction in camera space for a spot on the image, measured in pixels from its top left
+    // corner. Camera space has +x on the left of the image, like the perspective projection
+    fn panoramic_direction(&self, px: f64, py: f64) -> Option<Tuple> {
+        use std::f64::consts::PI;
+
+        match self.projection {
+            Projection::Perspective => None,
+            Projection::Equirectangular => {
+                let longitude = (px / self.hsize as f64 - 0.5) * 2.0 * PI;
+                let latitude = (0.5 - py / self.vsize as f64) * PI;
+                Some(Tuple::vector(
+                    -longitude.sin() * latitude.cos(),
+                    latitude.sin(),
+                    -longitude.cos() * latitude.cos(),
+                ))
+            }
+            Projection::Fisheye { fov } => {
+                let (nx, ny) = self.fisheye_offset(px, py);
+                let r = nx.hypot(ny);
+                if r < EPSILON {
+                    return Some(Tuple::vector(0.0, 0.0, -1.0));
+                }
+                let angle = r * fov / 2.0;
+                Some(Tuple::vector(angle.sin() * nx / r, angle.sin() * ny / r, -angle.cos()))
+            }
+        }
+    }
+
+    // Where the spot is in the fisheye circle, which has a radius of 1
+    fn fisheye_offset(&self, px: f64, py: f64) -> (f64, f64) {
+        let radius = self.hsize.min(self.vsize) as f64 / 2.0;
+        ((self.hsize as f64 / 2.0 - px) / radius, (self.vsize as f64 / 2.0 - py) / radius)
+    }
+
+    fn in_image(&self, px: f64, py: f64) -> bool {
+        match self.projection {
+            Projection::Fisheye { .. } => {
+                let (nx, ny) = self.fisheye_offset(px, py);
+                nx.hypot(ny) <= 1.0
+            }
+            _ => true,
+        }
+    }
+
+    // Averages the samples before the canvas sees them
+    fn pixel_color(&self, w: &World, x: usize, y: usize, samples: usize, sampling: Sampling) -> Color {
+        self.develop_sum(self.pixel_samples(w, x, y, 0, samples, sampling).0, samples)
+    }
+
+    // The sum of the samples numbered from first on, which are still linear here, and how many of
+    // them were traced. Samples past the edge of a fisheye image aren't, but still count towards
+    // the average so the edge of the circle stays smooth
+    fn pixel_samples(&self, w: &World, x: usize, y: usize, first: usize, samples: usize, sampling: Sampling) -> (Color, usize) {
+        let samples = samples.max(1);
+        // Each sample gets a slice of the frame to itself, so a moving shape shows up wherever it
+        // spends a whole slice. The slices are dealt out from a random first one per pixel, so
+        // they don't line up with the same corner of every pixel
+        let first_slice = (self.slice_sampler(x, y, first).next_f64() * samples as f64) as usize;
+
+        (0..samples).fold((Color::black(), 0), |(total, traced), i| {
+            let mut sampler = PixelSampler::new(self.seed, x, y, first + i);
+            let (column, columns, row, rows) = stratum(i, samples);
+            let (jx, jy) = match sampling {
+                Sampling::Grid => (0.5, 0.5),
+                Sampling::Jittered => (sampler.next_f64(), sampler.next_f64()),
+            };
+            let dx = (column as f64 + jx) / columns as f64;
+            let dy = (row as f64 + jy) / rows as f64;
+
+            let (lens_x, lens_y) = if self.aperture > 0.0 {
+                sampler.next_in_unit_disk()
+            } else {
+                (0.0, 0.0)
+            };
+
+            if !self.in_image(x as f64 + dx, y as f64 + dy) {
+                return (total, traced);
+            }
+            let time = if self.motion_blur {
+                (((first_slice + i) % samples) as f64 + sampler.next_f64()) / samples as f64
+            } else {
+                0.0
+            };
+            ray_counters::count(Counter::Primary);
+            (total + w.color_at(self.ray_through_lens(x, y, dx, dy, lens_x, lens_y).at_time(time)), traced + 1)
+        })
+    }
+
+    // Counted down from the top of the sample numbers, so it never shares a stream with a sample
+    // of this pass or any later one
+    fn slice_sampler(&self, x: usize, y: usize, first: usize) -> PixelSampler {
+        PixelSampler::new(self.seed, x, y, usize::MAX - first)
+    }
+
+    fn develop_sum(&self, sum: Color, samples: usize) -> Color {
+        self.develop(sum * (1.0 / samples.max(1) as f64))
+    }
+
+    fn canvas_of_sums(&self, sums: &[Color]) -> Canvas {
+        Canvas {
+            width: self.hsize,
+            height: self.vsize,
+            pixels: sums.iter().map(|&sum| self.develop_sum(sum, self.samples_per_pixel)).collect(),
+        }
+    }
+
+    fn develop(&self, color: Color) -> Color {
+        if self.exposure == 1.0 && self.gamma == 1.0 {
+            return color;
+        }
+
+        (color * self.exposure).powf(1.0 / self.gamma)
+    }
+
     pub fn render(&self, w: &World) -> Canvas {
-        #[cfg(feature = "progress_bar")]
-            let sty = ProgressStyle::with_template(
-                "[{elapsed_precise}] {bar:100.white} {pos:>7}/{len:7} {msg}",
-            )
-            .unwrap();
-            #[cfg(feature = "progress_bar")]
-            let pb = ProgressBar::new((self.hsize * self.vsize) as u64);
-            #[cfg(feature = "progress_bar")]
-            pb.set_style(sty);
-        let canvas_mutex = Mutex::new(Canvas::new(self.hsize, self.vsize));
-
-        (0..self.hsize - 1)
-            .cartesian_product(0..self.vsize - 1)
-            .par_bridge()
-            .for_each(|(x, y)| {
-                let ray = self.ray_for_pixel(x, y);
-                let color = w.color_at(ray);
-                let mut canvas = canvas_mutex.lock().unwrap();
-                canvas.write_pixel(x, y, color);
-                #[cfg(feature = "progress_bar")]
-                pb.inc(1)
+        self.render_reporting(w, |_| {}, None).0
+    }
+
+    // Also counts every ray cast along the way when built with the ray-counters feature, which
+    // the plain render leaves out either way
+    pub fn render_with_stats(&self, w: &World) -> (Canvas, RenderStats) {
+        self.render_reporting(w, |_| {}, Some(&Arc::new(RayCounters::default())))
+    }
+
+    // The callback is called from the worker threads, one at a time and with pixels_done never
+    // going down
+    pub fn render_with_progress(&self, w: &World, on_progress: impl Fn(RenderProgress) + Sync) -> Canvas {
+        self.render_reporting(w, on_progress, None).0
+    }
+
+    // Each worker gets whole rows of the canvas to itself, so only the progress count and the
+    // ray counters are shared
+    fn render_reporting(
+        &self,
+        w: &World,
+        on_progress: impl Fn(RenderProgress) + Sync,
+        counters: Option<&Arc<RayCounters>>,
+    ) -> (Canvas, RenderStats) {
+        self.in_pool(|| self.render_rows(w, &on_progress, counters))
+    }
+
+    fn render_rows(
+        &self,
+        w: &World,
+        on_progress: impl Fn(RenderProgress) + Sync,
+        counters: Option<&Arc<RayCounters>>,
+    ) -> (Canvas, RenderStats) {
+        let start = Instant::now();
+        let pixels_total = self.hsize * self.vsize;
+        let rows_done = Mutex::new(0usize);
+        let mut sums = vec![Color::black(); pixels_total];
+
+        let traced: usize = sums.par_chunks_mut(self.hsize).enumerate().map(|(y, row)| {
+            let traced = ray_counters::counting(counters, || self.trace_row(w, 0, y, row));
+
+            let mut rows_done = rows_done.lock().unwrap();
+            *rows_done += 1;
+            if rows_done.is_multiple_of(self.rows_per_progress.max(1)) || *rows_done == self.vsize {
+                on_progress(RenderProgress {
+                    pixels_done: *rows_done * self.hsize,
+                    pixels_total,
+                    elapsed: start.elapsed(),
+                });
+            }
+            traced
+        }).sum();
+
+        let mut canvas = self.canvas_of_sums(&sums);
+        let mut stats = self.refine(w, &sums, &mut canvas, true, counters);
+        stats.samples += traced;
+        stats.rays = counters.map(|c| c.counts()).unwrap_or_default();
+        stats.elapsed = start.elapsed();
+        (canvas, stats)
+    }
+
+    // The same render on the calling thread, for comparing against the parallel one
+    pub fn render_sequential(&self, w: &World) -> Canvas {
+        let mut sums = vec![Color::black(); self.hsize * self.vsize];
+
+        sums.chunks_mut(self.hsize).enumerate().for_each(|(y, row)| {
+            self.trace_row(w, 0, y, row);
+        });
+        let mut canvas = self.canvas_of_sums(&sums);
+        self.refine(w, &sums, &mut canvas, false, None);
+        canvas
+    }
+
+    // Only the pixels of the region get traced, through the same rays as the full image so the two
+    // agree. The adaptive pass is left out, as it would need the pixels around the region too
+    pub fn render_region(&self, w: &World, x0: usize, y0: usize, width: usize, height: usize) -> Result<Canvas, RegionError> {
+        let fits = |start: usize, len: usize, size: usize| start.checked_add(len).is_some_and(|end| end <= size);
+        if !fits(x0, width, self.hsize) || !fits(y0, height, self.vsize) {
+            return Err(RegionError::OutOfBounds { x0, y0, width, height, hsize: self.hsize, vsize: self.vsize });
+        }
+
+        let mut canvas = Canvas::new(width, height);
+        if width > 0 {
+            self.in_pool(|| {
+                canvas.pixels.par_chunks_mut(width).enumerate().for_each(|(y, row)| self.render_row(w, x0, y0 + y, row))
             });
-        #[cfg(feature = "progress_bar")]
-        pb.finish_with_message("Done rendering!");
-        canvas_mutex.into_inner().unwrap()
         }
+        Ok(canvas)
+    }
+
+    // Tiles are tile_size square, apart from those along the right and bottom edges that get
+    // whatever is left over. They're rendered in parallel and come out in no set order
+    pub fn render_tiles(&self, w: &World, tile_size: usize) -> impl Iterator<Item = Tile> {
+        let regions = self.tile_regions(tile_size);
+        let tiles: Vec<_> = self.in_pool(|| regions.par_iter().map(|&region| self.render_tile(w, region)).collect());
+        tiles.into_iter()
+    }
+
+    // Hands each tile over as soon as it's done, for streaming it somewhere without keeping the
+    // whole image around
+    pub fn render_tiles_with(&self, w: &World, tile_size: usize, on_tile: impl Fn(Tile) + Sync) {
+        let regions = self.tile_regions(tile_size);
+        self.in_pool(|| regions.par_iter().for_each(|&region| on_tile(self.render_tile(w, region))));
+    }
+
+    fn tile_regions(&self, tile_size: usize) -> Vec<(usize, usize, usize, usize)> {
+        assert!(tile_size > 0, "tiles need to be at least one pixel across");
+
+        (0..self.vsize)
+            .step_by(tile_size)
+            .cartesian_product((0..self.hsize).step_by(tile_size))
+            .map(|(y0, x0)| (x0, y0, tile_size.min(self.hsize - x0), tile_size.min(self.vsize - y0)))
+            .collect()
+    }
+
+    fn render_tile(&self, w: &World, (x0, y0, width, height): (usize, usize, usize, usize)) -> Tile {
+        let mut canvas = Canvas::new(width, height);
+        canvas.pixels.chunks_mut(width).enumerate().for_each(|(y, row)| self.render_row(w, x0, y0 + y, row));
+
+        Tile { x0, y0, canvas }
+    }
+
+    fn render_row(&self, w: &World, x0: usize, y: usize, row: &mut [Color]) {
+        for (x, pixel) in row.iter_mut().enumerate() {
+            *pixel = self.pixel_color(w, x0 + x, y, self.samples_per_pixel, self.sampling);
+        }
+    }
+
+    // Fills the row with the sum of each pixel's samples, and gives how many were traced
+    fn trace_row(&self, w: &World, x0: usize, y: usize, sums: &mut [Color]) -> usize {
+        sums.iter_mut()
+            .enumerate()
+            .map(|(x, sum)| {
+                let (total, traced) = self.pixel_samples(w, x0 + x, y, 0, self.samples_per_pixel, self.sampling);
+                *sum = total;
+                traced
+            })
+            .sum()
+    }
+
+    // The adaptive pass, once every pixel has its first color. The extra samples carry on from
+    // the numbers of the first ones, and a refined pixel averages all of them. Only the samples
+    // traced here are in the stats
+    fn refine(
+        &self,
+        w: &World,
+        sums: &[Color],
+        canvas: &mut Canvas,
+        parallel: bool,
+        counters: Option<&Arc<RayCounters>>,
+    ) -> RenderStats {
+        let mut stats = RenderStats::default();
+
+        if let Some(aa) = self.adaptive {
+            let (first, extra) = (self.samples_per_pixel.max(1), aa.extra_samples.max(1));
+            let edges = self.high_contrast_pixels(canvas, aa.threshold);
+            let refine = |&(x, y): &(usize, usize)| {
+                let (total, traced) =
+                    ray_counters::counting(counters, || self.pixel_samples(w, x, y, first, extra, Sampling::Jittered));
+                (x, y, total, traced)
+            };
+            let refined: Vec<_> = if parallel {
+                edges.par_iter().map(refine).collect()
+            } else {
+                edges.iter().map(refine).collect()
+            };
+
+            for (x, y, total, traced) in refined {
+                canvas.write_pixel(x, y, self.develop_sum(sums[y * self.hsize + x] + total, first + extra));
+                stats.samples += traced;
+            }
+            stats.refined_pixels = edges.len();
+        }
+
+        stats
+    }
+
+    // The distance to the first hit through the middle of every pixel, without shading anything
+    pub fn render_depth(&self, w: &World) -> DepthBuffer {
+        DepthBuffer {
+            width: self.hsize,
+            height: self.vsize,
+            depths: self.per_pixel(|x, y| self.first_hit(w, x, y).map_or(f64::INFINITY, |(_, hit)| hit.t)),
+        }
+    }
+
+    pub fn render_aov(&self, w: &World, aov: Aov) -> Canvas {
+        let pixels = self.per_pixel(|x, y| {
+            let Some((ray, hit)) = self.first_hit(w, x, y) else {
+                return Color::black();
+            };
+            let point = ray.position(hit.t);
+
+            match aov {
+                Aov::Normal => {
+                    let mut normal = hit.object.normal_at(point);
+                    if normal.dot(ray.direction) > 0.0 {
+                        normal = -normal;
+                    }
+                    Color::new((normal.x + 1.0) / 2.0, (normal.y + 1.0) / 2.0, (normal.z + 1.0) / 2.0)
+                }
+                Aov::Albedo => hit.object.material().color_at(&hit.object, point),
+            }
+        });
+
+        Canvas {
+            width: self.hsize,
+            height: self.vsize,
+            pixels,
+        }
+    }
+
+    // The index in w.objects() of whatever is hit first through the middle of every pixel
+    pub fn render_object_ids(&self, w: &World) -> Vec<Option<usize>> {
+        self.per_pixel(|x, y| self.first_hit(w, x, y).and_then(|(_, hit)| w.object_index(&hit.object)))
+    }
+
+    // Gives every object index a color of its own, by stepping the hue around by the golden
+    // ratio. Pixels without an object are black
+    pub fn object_id_canvas(&self, ids: &[Option<usize>]) -> Canvas {
+        let id_color = |id: usize| {
+            let hue = (id as f64 * 0.618_033_988_749_895).fract() * 6.0;
+            let x = 1.0 - (hue % 2.0 - 1.0).abs();
+            match hue as usize {
+                0 => Color::new(1.0, x, 0.0),
+                1 => Color::new(x, 1.0, 0.0),
+                2 => Color::new(0.0, 1.0, x),
+                3 => Color::new(0.0, x, 1.0),
+                4 => Color::new(x, 0.0, 1.0),
+                _ => Color::new(1.0, 0.0, x),
+            }
+        };
+
+        Canvas {
+            width: self.hsize,
+            height: self.vsize,
+            pixels: ids.iter().map(|id| id.map_or(Color::black(), id_color)).collect(),
+        }
+    }
+
+    // The same picture with its longer side at most max_dim pixels, keeping every other setting
+    pub fn render_preview(&self, w: &World, max_dim: usize) -> Canvas {
+        let scale = (max_dim.max(1) as f64 / self.hsize.max(self.vsize) as f64).min(1.0);
+        let mut preview = *self;
+        preview.hsize = ((self.hsize as f64 * scale).round() as usize).max(1);
+        preview.vsize = ((self.vsize as f64 * scale).round() as usize).max(1);
+        preview.size_pixels();
+
+        preview.render(w)
+    }
+
+    // A left and right eye, each moved half the separation sideways and turned in to look at the
+    // point convergence ahead of the camera, so that's where the two images line up
+    pub fn render_stereo(&self, w: &World, eye_separation: f64, convergence: f64) -> Result<(Canvas, Canvas), Error> {
+        if !(convergence > 0.0 && convergence.is_finite()) {
+            return Err(Error::InvalidConvergence { distance: convergence });
+        }
+        if !eye_separation.is_finite() {
+            return Err(Error::InvalidEyeSeparation { separation: eye_separation });
+        }
+        let eye = |offset: f64| -> Result<Canvas, Error> {
+            let toe_in = Matrix::view_transform(
+                Tuple::point(offset, 0.0, 0.0),
+                Tuple::point(0.0, 0.0, -convergence),
+                Tuple::vector(0.0, 1.0, 0.0),
+            );
+            let mut camera = *self;
+            camera.set_transform(toe_in * self.transform)?;
+            Ok(camera.render(w))
+        };
+
+        // Camera space has +x on the left of the image
+        Ok((eye(eye_separation / 2.0)?, eye(-eye_separation / 2.0)?))
+    }
+
+    pub fn debug_pixel(&self, w: &World, x: usize, y: usize) -> PixelTrace {
+        let shading = w.trace_shading(self.ray_for_pixel(x, y));
+        let color = self.develop(shading.color);
+
+        PixelTrace { x, y, shading, color }
+    }
+
+    // The ray through the middle of the pixel and what it hits first. Pixels outside a fisheye
+    // circle have no ray at all
+    fn first_hit(&self, w: &World, x: usize, y: usize) -> Option<(Ray, Intersection)> {
+        if !self.in_image(x as f64 + 0.5, y as f64 + 0.5) {
+            return None;
+        }
+
+        let ray = self.ray_for_pixel(x, y);
+        w.intersect(ray).hit().map(|hit| (ray, hit))
+    }
+
+    // One value for every pixel, row by row, worked out on the render threads
+    fn per_pixel<T: Send>(&self, value: impl Fn(usize, usize) -> T + Sync) -> Vec<T> {
+        self.in_pool(|| {
+            (0..self.hsize * self.vsize)
+                .into_par_iter()
+                .map(|i| value(i % self.hsize, i / self.hsize))
+                .collect()
+        })
+    }
+
+    pub fn render_progressive<'a>(&'a self, w: &'a World) -> ProgressiveRender<'a> {
+        ProgressiveRender {
+            camera: self,
+            world: w,
+            canvas: Canvas::new(self.hsize, self.vsize),
+            sums: vec![Color::black(); self.hsize * self.vsize],
+            traced: vec![false; self.hsize * self.vsize],
+            block: Some(8),
+            pixels_traced: 0,
+        }
+    }
+
+    fn high_contrast_pixels(&self, canvas: &Canvas, threshold: f64) -> Vec<(usize, usize)> {
+        (0..self.hsize)
+            .cartesian_product(0..self.vsize)
+            .filter(|&(x, y)| {
+                let c = canvas.pixel_at(x, y);
+                let neighbours = [
+                    (x.checked_sub(1), Some(y)),
+                    (Some(x + 1).filter(|&x| x < self.hsize), Some(y)),
+                    (Some(x), y.checked_sub(1)),
+                    (Some(x), Some(y + 1).filter(|&y| y < self.vsize)),
+                ];
+
+                neighbours.into_iter().any(|n| match n {
+                    (Some(nx), Some(ny)) => c.max_difference(canvas.pixel_at(nx, ny)) > threshold,
+                    _ => false,
+                })
+            })
+            .collect()
+    }
+}
+
+// Renders in passes of 8, 4, 2 and then 1 pixel blocks, each block showing the color of its top
+// left pixel. That pixel is traced at full resolution, so later passes keep it and every pixel is
+// only ever traced once
+pub struct ProgressiveRender<'a> {
+    camera: &'a Camera,
+    world: &'a World,
+    canvas: Canvas,
+    // The samples of each traced pixel, which the adaptive pass adds to
+    sums: Vec<Color>,
+    traced: Vec<bool>,
+    block: Option<usize>,
+    pixels_traced: usize,
+}
+
+impl ProgressiveRender<'_> {
+    // None once the full resolution pass is done
+    pub fn next_pass(&mut self) -> Option<&Canvas> {
+        let block = self.block?;
+        let (hsize, vsize) = (self.camera.hsize, self.camera.vsize);
+        let corners: Vec<_> = (0..vsize)
+            .step_by(block)
+            .cartesian_product((0..hsize).step_by(block))
+            .map(|(y, x)| (x, y))
+            .collect();
+
+        let (camera, world) = (self.camera, self.world);
+        let untraced: Vec<_> = corners.iter().filter(|&&(x, y)| !self.traced[y * hsize + x]).collect();
+        let new: Vec<_> = camera.in_pool(|| {
+            untraced
+                .into_par_iter()
+                .map(|&(x, y)| (x, y, camera.pixel_samples(world, x, y, 0, camera.samples_per_pixel, camera.sampling).0))
+                .collect()
+        });
+        self.pixels_traced += new.len();
+        for (x, y, sum) in new {
+            self.traced[y * hsize + x] = true;
+            self.sums[y * hsize + x] = sum;
+            self.canvas.write_pixel(x, y, camera.develop_sum(sum, camera.samples_per_pixel));
+        }
+
+        for (x0, y0) in corners {
+            let color = self.canvas.pixel_at(x0, y0);
+            for (x, y) in (x0..(x0 + block).min(hsize)).cartesian_product(y0..(y0 + block).min(vsize)) {
+                self.canvas.write_pixel(x, y, color);
+            }
+        }
+
+        if block == 1 {
+            let (sums, canvas) = (&self.sums, &mut self.canvas);
+            camera.in_pool(|| camera.refine(world, sums, canvas, true, None));
+        }
+        self.block = (block > 1).then_some(block / 2);
+        Some(&self.canvas)
+    }
+
+    pub fn pixels_traced(&self) -> usize {
+        self.pixels_traced
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::f64::consts::PI;
 
-    use crate::{assert_fuzzy_eq, color::Color, tuple::Tuple, util::FuzzyEq};
+    use crate::{assert_fuzzy_eq, color::Color, light::Light, tuple::Tuple, util::FuzzyEq, world::Background};
 
     use super::*;
 
@@ -111,19 +908,21 @@ mod tests {
         assert_eq!(hsize, c.hsize);
         assert_eq!(vsize, c.vsize);
         fov.fuzzy_eq(c.fov);
-        assert_fuzzy_eq!(Matrix::identity(), c.transform);
+        assert_fuzzy_eq!(Matrix::identity(), c.transform());
     }
 
     #[test]
     fn pixel_size_horizontal_canvas() {
         let c = Camera::new(200, 125, PI / 2.0);
-        0.01.fuzzy_eq(c.pixel_size);
+        assert_fuzzy_eq!(0.01, c.pixel_width);
+        assert_fuzzy_eq!(0.01, c.pixel_height);
     }
 
     #[test]
     fn pixel_size_vertical_canvas() {
         let c = Camera::new(125, 200, PI / 2.0);
-        0.01.fuzzy_eq(c.pixel_size);
+        assert_fuzzy_eq!(0.01, c.pixel_width);
+        assert_fuzzy_eq!(0.01, c.pixel_height);
     }
 
     #[test]
@@ -147,7 +946,7 @@ mod tests {
     #[test]
     fn construct_ray_when_camera_is_transformed() {
         let mut c = Camera::new(201, 101, PI / 2.0);
-        c.set_transform(Matrix::rotation_y(PI / 4.0) * Matrix::translation(0.0, -2.0, 5.0));
+        c.set_transform(Matrix::rotation_y(PI / 4.0) * Matrix::translation(0.0, -2.0, 5.0)).unwrap();
 
         let r = c.ray_for_pixel(100, 50);
         assert_fuzzy_eq!(Tuple::point(0.0, 2.0, -5.0), r.origin);
@@ -164,11 +963,897 @@ mod tests {
         let from = Tuple::point(0.0, 0.0, -5.0);
         let to = Tuple::point(0.0, 0.0, 0.0);
         let up = Tuple::vector(0.0, 1.0, 0.0);
-        c.set_transform(Matrix::view_transform(from, to, up));
+        c.set_transform(Matrix::view_transform(from, to, up)).unwrap();
         let img_canvas = c.render(&w);
         assert_fuzzy_eq!(
             Color::new(0.38066, 0.47583, 0.2855),
             img_canvas.pixel_at(5, 5)
         );
     }
+
+    fn facing_default_world(mut c: Camera) -> Camera {
+        c.set_transform(Matrix::view_transform(
+            Tuple::point(0.0, 0.0, -5.0),
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0),
+        )).unwrap();
+        c
+    }
+
+    fn default_world_camera(size: usize) -> Camera {
+        facing_default_world(Camera::new(size, size, PI / 2.0))
+    }
+
+    #[test]
+    fn offset_ray_through_middle_of_pixel_is_center_ray() {
+        let mut c = Camera::new(201, 101, PI / 2.0);
+        c.set_transform(Matrix::rotation_y(PI / 4.0) * Matrix::translation(0.0, -2.0, 5.0)).unwrap();
+
+        for (x, y) in [(0, 0), (100, 50), (37, 88)] {
+            assert_eq!(c.ray_for_pixel(x, y), c.ray_for_pixel_offset(x, y, 0.5, 0.5));
+        }
+    }
+
+    #[test]
+    fn single_sample_matches_center_rays_exactly() {
+        let w = World::default();
+        let c = default_world_camera(21);
+        let image = c.render(&w);
+
+        for (x, y) in (0..21).cartesian_product(0..21) {
+            assert_eq!(w.color_at(c.ray_for_pixel(x, y)), image.pixel_at(x, y));
+        }
+    }
+
+    #[test]
+    fn supersampling_smooths_silhouette_only() {
+        let w = World::default();
+        let single = default_world_camera(21);
+        let image = single.render(&w);
+        let supersampled = Camera { samples_per_pixel: 4, ..single }.render(&w);
+        let jittered = Camera { samples_per_pixel: 4, sampling: Sampling::Jittered, ..single }.render(&w);
+
+        let mut silhouette_changed = false;
+        for (x, y) in (1..20).cartesian_product(1..20) {
+            let c = image.pixel_at(x, y);
+            let neighbours = [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)].map(|(x, y)| image.pixel_at(x, y));
+            let flat = neighbours.iter().all(|n| n.fuzzy_eq(c));
+            let edge = neighbours.iter().any(|n| c.fuzzy_eq(Color::black()) != n.fuzzy_eq(Color::black()));
+
+            if flat {
+                assert_fuzzy_eq!(c, supersampled.pixel_at(x, y));
+                assert_fuzzy_eq!(c, jittered.pixel_at(x, y));
+            }
+            if edge && c.fuzzy_ne(supersampled.pixel_at(x, y)) {
+                silhouette_changed = true;
+            }
+        }
+        assert!(silhouette_changed);
+    }
+
+    fn adaptive(camera: Camera) -> Camera {
+        Camera { adaptive: Some(AdaptiveAa { threshold: 0.1, extra_samples: 16 }), ..camera }
+    }
+
+    #[test]
+    fn flat_scene_is_never_refined() {
+        let w = World::new(vec![], Light::default());
+        let (image, stats) = adaptive(default_world_camera(21)).render_with_stats(&w);
+
+        assert_eq!((21 * 21, 0), (stats.samples, stats.refined_pixels));
+        assert_eq!(w.color_at(default_world_camera(21).ray_for_pixel(3, 4)), image.pixel_at(3, 4));
+    }
+
+    #[test]
+    fn adaptive_pass_refines_silhouette_but_not_background() {
+        let w = World::default();
+        let single = default_world_camera(21).render(&w);
+        let (image, stats) = adaptive(default_world_camera(21)).render_with_stats(&w);
+
+        assert!(stats.refined_pixels > 0);
+        assert_eq!(21 * 21 + stats.refined_pixels * 16, stats.samples);
+        // The corners are far from the sphere, and the edge of it has pixels that changed
+        assert_eq!(single.pixel_at(0, 0), image.pixel_at(0, 0));
+        assert_eq!(single.pixel_at(20, 20), image.pixel_at(20, 20));
+        let changed = (0..21).cartesian_product(0..21).filter(|&(x, y)| single.pixel_at(x, y) != image.pixel_at(x, y)).count();
+        assert!(changed > 0 && changed <= stats.refined_pixels);
+    }
+
+    #[test]
+    fn every_stratum_gets_one_sample() {
+        for samples in 1..=20 {
+            let cells: Vec<_> = (0..samples).map(|i| stratum(i, samples)).collect();
+            let area: f64 = cells.iter().map(|&(_, columns, _, rows)| 1.0 / (columns * rows) as f64).sum();
+            assert_fuzzy_eq!(1.0, area);
+
+            // Cells that cover the pixel between them with their areas adding up to all of it
+            // can't overlap, or leave a gap
+            for (x, y) in (0..16).cartesian_product(0..16) {
+                let (px, py) = ((x as f64 + 0.5) / 16.0, (y as f64 + 0.5) / 16.0);
+                let covering = cells
+                    .iter()
+                    .filter(|&&(column, columns, row, rows)| {
+                        (px * columns as f64) as usize == column && (py * rows as f64) as usize == row
+                    })
+                    .count();
+                assert_eq!(1, covering, "{} samples, point ({}, {})", samples, px, py);
+            }
+        }
+    }
+
+    #[test]
+    fn slices_are_dealt_from_a_stream_of_their_own() {
+        let c = Camera { seed: 3, samples_per_pixel: 4, ..default_world_camera(5) };
+        let sample_draws: Vec<f64> = (0..20).map(|i| PixelSampler::new(c.seed, 2, 2, i).next_f64()).collect();
+
+        // The first pass of 4 and the adaptive pass after it
+        for first in [0, 4] {
+            let draw = c.slice_sampler(2, 2, first).next_f64();
+            assert!(!sample_draws.contains(&draw));
+        }
+        assert_ne!(c.slice_sampler(2, 2, 0).next_f64(), c.slice_sampler(2, 2, 4).next_f64());
+    }
+
+    #[test]
+    fn refined_pixel_averages_its_first_and_extra_samples() {
+        let w = World::default();
+        let c = adaptive(Camera { samples_per_pixel: 4, ..default_world_camera(21) });
+        let image = c.render(&w);
+        let (x, y) = c.high_contrast_pixels(&c.render_region(&w, 0, 0, 21, 21).unwrap(), 0.1)[0];
+
+        let (first, _) = c.pixel_samples(&w, x, y, 0, 4, c.sampling);
+        let (extra, _) = c.pixel_samples(&w, x, y, 4, 16, Sampling::Jittered);
+        assert_fuzzy_eq!((first + extra) * (1.0 / 20.0), image.pixel_at(x, y));
+    }
+
+    #[test]
+    fn stats_count_only_the_samples_traced() {
+        let w = World::default();
+        let fisheye = Camera { projection: Projection::Fisheye { fov: PI }, samples_per_pixel: 4, ..Camera::new(21, 21, PI / 2.0) };
+        let (_, stats) = fisheye.render_with_stats(&w);
+        let traced: usize = (0..21).cartesian_product(0..21).map(|(x, y)| fisheye.pixel_samples(&w, x, y, 0, 4, fisheye.sampling).1).sum();
+
+        assert_eq!(traced, stats.samples);
+        assert!(stats.samples < 21 * 21 * 4);
+    }
+
+    #[test]
+    fn zero_aperture_ignores_focal_distance() {
+        let w = World::default();
+        let pinhole = default_world_camera(11);
+        let focused = Camera { focal_distance: 3.0, seed: 7, ..pinhole };
+
+        for (x, y) in (0..11).cartesian_product(0..11) {
+            assert_eq!(pinhole.ray_for_pixel(x, y), focused.ray_for_pixel(x, y));
+        }
+        assert_eq!(pinhole.render(&w).pixel_at(5, 5), focused.render(&w).pixel_at(5, 5));
+    }
+
+    #[test]
+    fn lens_rays_meet_on_focal_plane() {
+        let c = Camera { aperture: 0.5, focal_distance: 4.0, ..default_world_camera(11) };
+        let a = c.ray_through_lens(5, 5, 0.5, 0.5, 0.0, 0.0);
+        let b = c.ray_through_lens(5, 5, 0.5, 0.5, 1.0, 0.0);
+
+        // The camera sits at z = -5, so the focal plane is at z = -1. Looking down -z from
+        // behind turns the lens around, so its x runs along -x in the world
+        assert_fuzzy_eq!(Tuple::point(-0.5, 0.0, -5.0), b.origin);
+        assert_fuzzy_eq!(a.position(4.0), b.position((b.origin - a.position(4.0)).magnitude()));
+    }
+
+    #[test]
+    fn sphere_at_focal_distance_stays_sharp() {
+        let w = World::default();
+        // Small pixels, so the sphere curving away inside one doesn't blur it either
+        let pinhole = default_world_camera(201);
+        // The front of the sphere is 4 units from the camera
+        let lens = Camera { aperture: 0.05, focal_distance: 4.0, ..pinhole };
+
+        assert_fuzzy_eq!(
+            pinhole.pixel_color(&w, 100, 100, 16, Sampling::Grid),
+            lens.pixel_color(&w, 100, 100, 16, Sampling::Grid)
+        );
+    }
+
+    #[test]
+    fn sphere_off_focus_blurs_its_silhouette() {
+        let w = World::default();
+        let pinhole = default_world_camera(21);
+        let lens = Camera { aperture: 0.3, focal_distance: 10.0, samples_per_pixel: 16, ..pinhole };
+        let (sharp, blurred) = (pinhole.render(&w), lens.render(&w));
+
+        let edge = (1..20).find(|&x| sharp.pixel_at(x, 10).fuzzy_ne(Color::black())).unwrap();
+        assert!(sharp.pixel_at(edge, 10).fuzzy_ne(blurred.pixel_at(edge, 10)));
+    }
+
+    #[test]
+    fn parallel_render_matches_sequential() {
+        let w = World::default();
+        let plain = default_world_camera(31);
+        let adaptive = Camera {
+            samples_per_pixel: 2,
+            sampling: Sampling::Jittered,
+            adaptive: Some(AdaptiveAa { threshold: 0.1, extra_samples: 4 }),
+            ..facing_default_world(Camera::new(17, 9, PI / 3.0))
+        };
+
+        assert_eq!(plain.render_sequential(&w), plain.render(&w));
+        assert_eq!(adaptive.render_sequential(&w), adaptive.render(&w));
+    }
+
+    #[test]
+    fn progress_counts_up_to_every_pixel() {
+        let w = World::default();
+        let c = Camera { rows_per_progress: 3, ..default_world_camera(11) };
+        let reports = Mutex::new(vec![]);
+
+        let image = c.render_with_progress(&w, |p| reports.lock().unwrap().push(p));
+        let reports = reports.into_inner().unwrap();
+
+        assert_eq!(c.render(&w), image);
+        // Every third row and then the last one, which is 11 rows in 4 reports
+        assert_eq!(4, reports.len());
+        assert!(reports.windows(2).all(|p| p[0].pixels_done <= p[1].pixels_done));
+        assert!(reports.iter().all(|p| p.pixels_total == 121));
+        assert_eq!(121, reports.last().unwrap().pixels_done);
+    }
+
+    #[test]
+    fn region_matches_full_render() {
+        let w = World::default();
+        let c = Camera { samples_per_pixel: 4, ..default_world_camera(11) };
+        let full = c.render(&w);
+        let region = c.render_region(&w, 3, 3, 4, 4).unwrap();
+
+        assert_eq!((4, 4), (region.width, region.height));
+        for (x, y) in (0..4).cartesian_product(0..4) {
+            assert_eq!(full.pixel_at(x + 3, y + 3), region.pixel_at(x, y));
+        }
+    }
+
+    #[test]
+    fn region_past_the_image_is_rejected() {
+        let w = World::default();
+        let c = default_world_camera(11);
+
+        assert!(c.render_region(&w, 0, 0, 11, 11).is_ok());
+        assert_eq!(
+            Err(RegionError::OutOfBounds { x0: 8, y0: 3, width: 4, height: 4, hsize: 11, vsize: 11 }),
+            c.render_region(&w, 8, 3, 4, 4)
+        );
+        assert!(c.render_region(&w, 3, 9, 4, 4).is_err());
+        assert!(c.render_region(&w, usize::MAX, 0, 2, 1).is_err());
+    }
+
+    fn stitch(c: &Camera, tiles: impl Iterator<Item = Tile>) -> Canvas {
+        let mut canvas = Canvas::new(c.hsize, c.vsize);
+        for tile in tiles {
+            for (x, y) in (0..tile.canvas.width).cartesian_product(0..tile.canvas.height) {
+                canvas.write_pixel(tile.x0 + x, tile.y0 + y, tile.canvas.pixel_at(x, y));
+            }
+        }
+        canvas
+    }
+
+    #[test]
+    fn stitched_tiles_match_full_render() {
+        let w = World::default();
+        let c = facing_default_world(Camera::new(37, 23, PI / 2.0));
+        let tiles: Vec<_> = c.render_tiles(&w, 16).collect();
+
+        // 3 across and 2 down, with the last column 5 wide and the last row 7 high
+        assert_eq!(6, tiles.len());
+        assert!(tiles.iter().all(|t| t.canvas.width == if t.x0 == 32 { 5 } else { 16 }));
+        assert!(tiles.iter().all(|t| t.canvas.height == if t.y0 == 16 { 7 } else { 16 }));
+        assert_eq!(c.render(&w), stitch(&c, tiles.into_iter()));
+    }
+
+    #[test]
+    fn streamed_tiles_match_full_render() {
+        let w = World::default();
+        let c = facing_default_world(Camera::new(37, 23, PI / 2.0));
+        let tiles = Mutex::new(vec![]);
+
+        c.render_tiles_with(&w, 16, |t| tiles.lock().unwrap().push(t));
+        assert_eq!(c.render(&w), stitch(&c, tiles.into_inner().unwrap().into_iter()));
+    }
+
+    #[test]
+    fn progressive_passes_refine_down_to_full_render() {
+        let w = World::default();
+        let c = facing_default_world(Camera::new(21, 13, PI / 2.0));
+        let mut progressive = c.render_progressive(&w);
+
+        for block in [8, 4, 2] {
+            let canvas = progressive.next_pass().unwrap();
+            for (x, y) in (0..21).cartesian_product(0..13) {
+                assert_eq!(canvas.pixel_at(x / block * block, y / block * block), canvas.pixel_at(x, y));
+            }
+            assert!(progressive.pixels_traced() <= 21 * 13);
+        }
+
+        assert_eq!(&c.render(&w), progressive.next_pass().unwrap());
+        assert_eq!(21 * 13, progressive.pixels_traced());
+        assert!(progressive.next_pass().is_none());
+    }
+
+    #[test]
+    fn coarse_pass_traces_one_pixel_per_block() {
+        let w = World::default();
+        let c = facing_default_world(Camera::new(21, 13, PI / 2.0));
+        let mut progressive = c.render_progressive(&w);
+
+        progressive.next_pass();
+        assert_eq!(3 * 2, progressive.pixels_traced());
+        progressive.next_pass();
+        assert_eq!(6 * 4, progressive.pixels_traced());
+    }
+
+    fn grey_world() -> World {
+        let mut w = World::new(vec![], Light::default());
+        w.background = Background::Solid(Color::grey(0.5));
+        w
+    }
+
+    #[test]
+    fn default_exposure_and_gamma_change_nothing() {
+        let w = World::default();
+        let c = default_world_camera(11);
+        let developed = Camera { exposure: 1.0, gamma: 1.0, ..c };
+
+        assert_eq!(w.color_at(c.ray_for_pixel(5, 5)), developed.render(&w).pixel_at(5, 5));
+    }
+
+    #[test]
+    fn gamma_brightens_mid_grey() {
+        let c = Camera { gamma: 2.2, ..default_world_camera(3) };
+
+        assert_fuzzy_eq!(Color::grey(0.72974), c.render(&grey_world()).pixel_at(1, 1));
+    }
+
+    #[test]
+    fn gamma_of_zero_or_below_is_rejected() {
+        let camera = |gamma: f64| CameraBuilder::default().hsize(3).vsize(3).fov(PI / 2.0).gamma(gamma).build();
+
+        assert!(camera(0.0).unwrap_err().to_string().contains("gamma must be above 0"));
+        assert!(camera(-2.2).is_err());
+        assert!(camera(f64::NAN).is_err());
+        assert!(camera(2.2).is_ok());
+    }
+
+    #[test]
+    fn exposure_applies_before_gamma() {
+        let c = Camera { exposure: 0.5, gamma: 2.0, ..default_world_camera(3) };
+
+        // (0.5 * 0.5)^(1 / 2) is 0.5, where gamma first would give 0.5^(1 / 2) * 0.5
+        assert_fuzzy_eq!(Color::grey(0.5), c.render(&grey_world()).pixel_at(1, 1));
+    }
+
+    #[test]
+    fn equirectangular_looks_every_way() {
+        let c = Camera { projection: Projection::Equirectangular, ..Camera::new(40, 20, PI / 2.0) };
+
+        assert_fuzzy_eq!(Tuple::vector(0.0, 0.0, -1.0), c.ray_for_pixel_offset(20, 10, 0.0, 0.0).direction);
+        // A quarter of the way round on each side, then straight behind at the edges
+        assert_fuzzy_eq!(Tuple::vector(1.0, 0.0, 0.0), c.ray_for_pixel_offset(10, 10, 0.0, 0.0).direction);
+        assert_fuzzy_eq!(Tuple::vector(-1.0, 0.0, 0.0), c.ray_for_pixel_offset(30, 10, 0.0, 0.0).direction);
+        assert_fuzzy_eq!(Tuple::vector(0.0, 0.0, 1.0), c.ray_for_pixel_offset(0, 10, 0.0, 0.0).direction);
+        for x in [0, 13, 39] {
+            assert_fuzzy_eq!(Tuple::vector(0.0, 1.0, 0.0), c.ray_for_pixel_offset(x, 0, 0.5, 0.0).direction);
+        }
+    }
+
+    #[test]
+    fn equirectangular_follows_camera_transform() {
+        let mut c = Camera { projection: Projection::Equirectangular, ..Camera::new(40, 20, PI / 2.0) };
+        c.set_transform(Matrix::view_transform(
+            Tuple::point(1.0, 2.0, 3.0),
+            Tuple::point(1.0, 2.0, 4.0),
+            Tuple::vector(0.0, 1.0, 0.0),
+        )).unwrap();
+        let r = c.ray_for_pixel_offset(20, 10, 0.0, 0.0);
+
+        assert_fuzzy_eq!(Tuple::point(1.0, 2.0, 3.0), r.origin);
+        assert_fuzzy_eq!(Tuple::vector(0.0, 0.0, 1.0), r.direction);
+    }
+
+    #[test]
+    fn fisheye_spaces_angles_evenly() {
+        let c = Camera { projection: Projection::Fisheye { fov: PI }, ..Camera::new(21, 21, PI / 2.0) };
+
+        assert_fuzzy_eq!(Tuple::vector(0.0, 0.0, -1.0), c.ray_for_pixel(10, 10).direction);
+        // The edge of the circle is 90 degrees out, halfway there is 45
+        assert_fuzzy_eq!(Tuple::vector(0.0, 1.0, 0.0), c.ray_for_pixel_offset(10, 0, 0.5, 0.0).direction);
+        let halfway = c.ray_for_pixel_offset(0, 10, 5.25, 0.5).direction;
+        assert_fuzzy_eq!(Tuple::vector(2.0_f64.sqrt() / 2.0, 0.0, -(2.0_f64.sqrt() / 2.0)), halfway);
+    }
+
+    #[test]
+    fn fisheye_leaves_outside_of_circle_untouched() {
+        let mut w = World::new(vec![], Light::default());
+        w.background = Background::Solid(Color::white());
+        let c = Camera { projection: Projection::Fisheye { fov: PI }, ..Camera::new(31, 21, PI / 2.0) };
+        let image = c.render(&w);
+
+        for (x, y) in [(0, 0), (30, 0), (0, 20), (30, 20), (3, 10), (27, 10)] {
+            assert_eq!(Color::black(), image.pixel_at(x, y));
+        }
+        for (x, y) in [(15, 10), (15, 1), (7, 10), (22, 10)] {
+            assert_fuzzy_eq!(Color::white(), image.pixel_at(x, y));
+        }
+    }
+
+    #[test]
+    fn builder_defaults_match_new() {
+        assert_eq!(Camera::new(160, 120, PI / 2.0), CameraBuilder::default().build().unwrap());
+    }
+
+    #[test]
+    fn builder_sizes_pixels() {
+        let horizontal = CameraBuilder::default().hsize(200).vsize(125).build().unwrap();
+        let vertical = CameraBuilder::default().hsize(125).vsize(200).build().unwrap();
+
+        assert_fuzzy_eq!(0.01, horizontal.pixel_width);
+        assert_fuzzy_eq!(0.01, vertical.pixel_height);
+    }
+
+    #[test]
+    fn builder_sets_render_options() {
+        let c = CameraBuilder::default()
+            .samples_per_pixel(4)
+            .projection(Projection::Equirectangular)
+            .adaptive(AdaptiveAa { threshold: 0.1, extra_samples: 8 })
+            .build()
+            .unwrap();
+
+        assert_eq!(4, c.samples_per_pixel);
+        assert_eq!(Projection::Equirectangular, c.projection);
+        assert_eq!(Some(AdaptiveAa { threshold: 0.1, extra_samples: 8 }), c.adaptive);
+    }
+
+    #[test]
+    fn builder_rejects_bad_size_or_fov() {
+        for builder in [
+            CameraBuilder::default().hsize(0).clone(),
+            CameraBuilder::default().vsize(0).clone(),
+            CameraBuilder::default().fov(0.0).clone(),
+            CameraBuilder::default().fov(PI).clone(),
+            CameraBuilder::default().fov(-1.0).clone(),
+            CameraBuilder::default().fov(f64::NAN).clone(),
+        ] {
+            assert!(matches!(builder.build(), Err(CameraBuilderError::ValidationError(_))));
+        }
+    }
+
+    #[test]
+    fn thread_count_does_not_change_the_render() {
+        let w = World::default();
+        let c = Camera { samples_per_pixel: 2, ..default_world_camera(15) };
+
+        let one = Camera { threads: Some(1), ..c };
+        let four = Camera { threads: Some(4), ..c };
+        assert_eq!(one.render(&w), four.render(&w));
+        assert_eq!(c.render(&w), one.render(&w));
+        assert_eq!(c.render_tiles(&w, 4).count(), four.render_tiles(&w, 4).count());
+    }
+
+    #[test]
+    fn single_thread_renders_on_one_worker() {
+        let w = World::default();
+        let c = Camera { threads: Some(1), ..default_world_camera(9) };
+        let threads = Mutex::new(std::collections::HashSet::new());
+
+        // Later renders, and other cameras with as many threads, reuse the worker
+        for c in [c, c, Camera { seed: 1, ..c }] {
+            c.render_with_progress(&w, |_| {
+                threads.lock().unwrap().insert(std::thread::current().id());
+            });
+        }
+        let threads = threads.into_inner().unwrap();
+
+        assert_eq!(1, threads.len());
+        assert!(!threads.contains(&std::thread::current().id()));
+    }
+
+    #[test]
+    fn builder_rejects_zero_threads() {
+        assert!(CameraBuilder::default().threads(0).build().is_err());
+        assert_eq!(Some(2), CameraBuilder::default().threads(2).build().unwrap().threads);
+    }
+
+    #[test]
+    fn set_transform_moves_the_rays() {
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        let before = c.ray_for_pixel(5, 5);
+        c.set_transform(Matrix::translation(0.0, 0.0, 3.0)).unwrap();
+        let after = c.ray_for_pixel(5, 5);
+
+        assert_fuzzy_eq!(Tuple::point(0.0, 0.0, 0.0), before.origin);
+        assert_fuzzy_eq!(Tuple::point(0.0, 0.0, -3.0), after.origin);
+        assert_fuzzy_eq!(c.transform().inverse(), c.inverse_transform);
+    }
+
+    #[test]
+    fn inverse_transform_is_worked_out_once_per_set_transform() {
+        let inversions = || crate::matrix::INVERSIONS.with(|c| c.get());
+        let mut c = Camera::new(11, 11, PI / 2.0);
+
+        let before = inversions();
+        c.set_transform(Matrix::rotation_y(PI / 4.0) * Matrix::translation(0.0, -2.0, 5.0)).unwrap();
+        assert_eq!(before + 1, inversions());
+
+        for (x, y) in (0..11).cartesian_product(0..11) {
+            c.ray_for_pixel(x, y);
+        }
+        assert_eq!(before + 1, inversions());
+    }
+
+    #[test]
+    fn builder_caches_inverse_transform() {
+        let t = Matrix::rotation_y(PI / 4.0) * Matrix::translation(0.0, -2.0, 5.0);
+        let c = CameraBuilder::default().transform(t).build().unwrap();
+
+        assert_fuzzy_eq!(t.inverse(), c.inverse_transform);
+    }
+
+    #[test]
+    fn non_invertible_transform_is_rejected() {
+        let flat = Matrix::scaling(1.0, 0.0, 1.0);
+
+        let message = CameraBuilder::default().transform(flat).build().unwrap_err().to_string();
+        assert!(message.contains(&format!("{:?}", flat)), "{}", message);
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        assert_eq!(Err(Error::NonInvertibleTransform { matrix: Box::new(flat) }), c.set_transform(flat));
+        assert_eq!(Matrix::identity(), c.transform());
+    }
+
+    #[test]
+    fn motion_blur_leaves_static_scene_alone() {
+        let w = World::default();
+        let c = Camera { samples_per_pixel: 4, sampling: Sampling::Jittered, ..default_world_camera(15) };
+
+        assert_eq!(c.render(&w), Camera { motion_blur: true, ..c }.render(&w));
+    }
+
+    #[test]
+    fn motion_blur_smears_moving_sphere() {
+        let sphere = crate::sphere::SphereBuilder::default()
+            .transform(Matrix::translation(-2.0, 0.0, 0.0))
+            .transform_at_t1(Matrix::translation(2.0, 0.0, 0.0))
+            .build()
+            .unwrap();
+        let w = World::new(vec![sphere.into()], Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white()));
+        let lit = |canvas: &Canvas, x: usize| canvas.pixel_at(x, 10).max_difference(Color::black()) > 0.0;
+
+        // Without blur only the start of the path is covered, with it the whole path is some of
+        // the time. Every slice of the frame gets a sample, whatever the seed. The sphere covers
+        // columns 3 to 8 at the start and 12 to 17 at the end, but only for an instant, so the
+        // columns checked are the ones it covers for all of the first and last slice
+        for seed in 0..8 {
+            let c = Camera { samples_per_pixel: 16, seed, ..default_world_camera(21) };
+            let still = c.render(&w);
+            let blurred = Camera { motion_blur: true, ..c }.render(&w);
+
+            assert_ne!(lit(&still, 5), lit(&still, 15));
+            assert!(!lit(&still, 10));
+            assert!(lit(&blurred, 5) && lit(&blurred, 10) && lit(&blurred, 15), "seed {}", seed);
+        }
+    }
+
+    fn stochastic_camera(seed: u64) -> Camera {
+        Camera {
+            samples_per_pixel: 4,
+            sampling: Sampling::Jittered,
+            aperture: 0.3,
+            focal_distance: 4.0,
+            motion_blur: true,
+            seed,
+            ..default_world_camera(11)
+        }
+    }
+
+    #[test]
+    fn same_seed_renders_byte_identical_images() {
+        use crate::ppm::ToPPM;
+
+        let w = World::default();
+
+        assert_eq!(stochastic_camera(3).render(&w).to_ppm(), stochastic_camera(3).render(&w).to_ppm());
+    }
+
+    #[test]
+    fn different_seeds_render_differently() {
+        let w = World::default();
+
+        assert_ne!(stochastic_camera(3).render(&w), stochastic_camera(4).render(&w));
+    }
+
+    #[test]
+    fn seeded_region_matches_full_render() {
+        let w = World::default();
+        let c = stochastic_camera(9);
+        let full = c.render(&w);
+        let region = c.render_region(&w, 2, 5, 5, 3).unwrap();
+
+        for (x, y) in (0..5).cartesian_product(0..3) {
+            assert_eq!(full.pixel_at(x + 2, y + 5), region.pixel_at(x, y));
+        }
+    }
+
+    #[test]
+    fn depth_of_default_world_center_is_distance_to_sphere() {
+        let depth = default_world_camera(11).render_depth(&World::default());
+
+        assert_eq!((11, 11), (depth.width, depth.height));
+        assert_fuzzy_eq!(4.0, depth.depth_at(5, 5));
+    }
+
+    #[test]
+    fn depth_of_missed_pixels_is_infinite() {
+        let depth = default_world_camera(11).render_depth(&World::default());
+
+        assert_eq!(f64::INFINITY, depth.depth_at(0, 0));
+        assert_eq!(f64::INFINITY, depth.depth_at(10, 10));
+    }
+
+    #[test]
+    fn depth_canvas_shows_nearest_hit_white() {
+        let depth = default_world_camera(11).render_depth(&World::default());
+        let canvas = depth.to_canvas();
+
+        assert_eq!(Color::white(), canvas.pixel_at(5, 5));
+        assert_eq!(Color::black(), canvas.pixel_at(0, 0));
+    }
+
+    #[test]
+    fn normal_pass_encodes_normal_facing_the_camera() {
+        let w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(Matrix::view_transform(
+            Tuple::point(0.0, 0.0, 5.0),
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0),
+        )).unwrap();
+        let normals = c.render_aov(&w, Aov::Normal);
+
+        assert_fuzzy_eq!(Color::new(0.5, 0.5, 1.0), normals.pixel_at(5, 5));
+        assert_eq!(Color::black(), normals.pixel_at(0, 0));
+    }
+
+    #[test]
+    fn albedo_pass_is_material_color_whatever_the_light() {
+        let mut w = World::default();
+        let c = default_world_camera(11);
+        let albedo = c.render_aov(&w, Aov::Albedo);
+
+        assert_eq!(Color::new(0.8, 1.0, 0.6), albedo.pixel_at(5, 5));
+        assert_eq!(Color::black(), albedo.pixel_at(0, 0));
+
+        w.lights = vec![Light::point(Tuple::point(10.0, -10.0, 10.0), Color::white())];
+        assert_eq!(albedo, c.render_aov(&w, Aov::Albedo));
+    }
+
+    // The default world with its small sphere moved out from inside the big one, to the left of
+    // it in the image
+    fn side_by_side_world() -> World {
+        let mut w = World::default();
+        w.object_mut(1).unwrap().set_transform(Matrix::translation(2.5, 0.0, 0.0) * Matrix::scaling(0.5, 0.5, 0.5));
+        w
+    }
+
+    #[test]
+    fn object_ids_split_the_image_by_object() {
+        let w = side_by_side_world();
+        let c = default_world_camera(21);
+        let ids = c.render_object_ids(&w);
+        let id_at = |x: usize, y: usize| ids[y * c.hsize + x];
+
+        assert_eq!(21 * 21, ids.len());
+        assert_eq!(Some(0), id_at(10, 10));
+        assert!(ids.contains(&Some(1)));
+        assert_eq!(None, id_at(0, 0));
+        assert_eq!(None, id_at(20, 20));
+    }
+
+    #[test]
+    fn object_id_canvas_colors_each_object_differently() {
+        let w = side_by_side_world();
+        let c = default_world_camera(21);
+        let ids = c.render_object_ids(&w);
+        let canvas = c.object_id_canvas(&ids);
+        let color_of = |id: Option<usize>| canvas.pixels[ids.iter().position(|&i| i == id).unwrap()];
+
+        assert_eq!(Color::black(), color_of(None));
+        assert_ne!(color_of(Some(0)), color_of(Some(1)));
+        assert_ne!(Color::black(), color_of(Some(0)));
+        assert_ne!(Color::black(), color_of(Some(1)));
+    }
+
+    #[test]
+    fn debugging_center_pixel_of_default_world() {
+        let w = World::default();
+        let c = default_world_camera(11);
+        let trace = c.debug_pixel(&w, 5, 5);
+
+        assert_eq!(4, trace.shading.intersections.intersections.len());
+        assert_fuzzy_eq!(4.0, trace.shading.hit.as_ref().unwrap().t);
+        assert_eq!(vec![Some(1.0)], trace.shading.light_intensities);
+        assert_fuzzy_eq!(Color::new(0.38066, 0.47583, 0.2855), trace.color);
+        assert_eq!(c.render(&w).pixel_at(5, 5), trace.color);
+    }
+
+    #[test]
+    fn pixel_trace_reads_as_a_report() {
+        let report = default_world_camera(11).debug_pixel(&World::default(), 5, 5).to_string();
+
+        assert!(report.starts_with("pixel (5, 5)"));
+        assert!(report.contains("4 intersections"));
+        assert!(report.contains("t = 4.00000 on sphere"));
+        assert!(report.contains("<- hit"));
+        assert!(report.contains("light 0: intensity 1.00000"));
+    }
+
+    #[test]
+    fn stereo_without_separation_is_the_mono_render() {
+        let w = World::default();
+        let c = default_world_camera(11);
+        let (left, right) = c.render_stereo(&w, 0.0, 5.0).unwrap();
+
+        assert_eq!(c.render(&w), left);
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn stereo_shifts_the_foreground_more_than_the_background() {
+        let flat = |color: Color, transform: Matrix<4>| -> crate::shape::Shape {
+            crate::sphere::SphereBuilder::default()
+                .transform(transform)
+                .material(crate::material::Material::new(color, 1.0, 0.0, 0.0, 200.0))
+                .build()
+                .unwrap()
+                .into()
+        };
+        let near = flat(Color::red(), Matrix::translation(0.6, 0.0, -3.0) * Matrix::scaling(0.3, 0.3, 0.3));
+        let far = flat(Color::blue(), Matrix::translation(-5.0, 0.0, 20.0) * Matrix::scaling(4.0, 4.0, 4.0));
+        let w = World::new(vec![near, far], Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white()));
+        let c = facing_default_world(Camera::new(41, 41, PI / 3.0));
+        let (left, right) = c.render_stereo(&w, 0.5, 5.0).unwrap();
+
+        // Average column of the pixels that are mostly the given color
+        let column_of = |canvas: &Canvas, color: Color| {
+            let xs: Vec<f64> = (0..canvas.width)
+                .cartesian_product(0..canvas.height)
+                .filter(|&(x, y)| canvas.pixel_at(x, y).max_difference(color) < 0.5)
+                .map(|(x, _)| x as f64)
+                .collect();
+            xs.iter().sum::<f64>() / xs.len() as f64
+        };
+        let near_shift = (column_of(&left, Color::red()) - column_of(&right, Color::red())).abs();
+        let far_shift = (column_of(&left, Color::blue()) - column_of(&right, Color::blue())).abs();
+
+        assert!(near_shift > 1.0);
+        assert!(far_shift < near_shift);
+    }
+
+    #[test]
+    fn stereo_converges_independently_of_the_focal_distance() {
+        let w = World::default();
+        let c = default_world_camera(11);
+        let lens = Camera { focal_distance: 2.0, ..c };
+
+        assert_eq!(c.render_stereo(&w, 0.5, 5.0), lens.render_stereo(&w, 0.5, 5.0));
+        assert_ne!(c.render_stereo(&w, 0.5, 5.0), c.render_stereo(&w, 0.5, 2.0));
+    }
+
+    #[test]
+    fn stereo_without_convergence_is_an_error() {
+        let (c, w) = (default_world_camera(11), World::default());
+
+        assert_eq!(Err(Error::InvalidConvergence { distance: 0.0 }), c.render_stereo(&w, 0.5, 0.0));
+        assert!(c.render_stereo(&w, 0.5, f64::INFINITY).is_err());
+        assert!(matches!(c.render_stereo(&w, f64::NAN, 5.0), Err(Error::InvalidEyeSeparation { .. })));
+    }
+
+    #[test]
+    fn equal_fovs_match_single_fov_camera() {
+        let single = Camera::new(21, 21, PI / 3.0);
+        let both = Camera::with_fovs(21, 21, PI / 3.0, PI / 3.0);
+
+        for (x, y) in [(0, 0), (10, 10), (20, 0), (3, 17)] {
+            assert_fuzzy_eq!(single.ray_for_pixel(x, y), both.ray_for_pixel(x, y));
+        }
+    }
+
+    #[test]
+    fn wider_hfov_stretches_rays_sideways() {
+        let c = Camera::with_fovs(20, 20, PI / 2.0, 2.0 * 0.5f64.atan());
+        let corner = c.ray_for_pixel_offset(0, 0, 0.0, 0.0).direction;
+
+        assert_fuzzy_eq!(2.0, corner.x / corner.y);
+        assert_fuzzy_eq!(0.1, c.pixel_width);
+        assert_fuzzy_eq!(0.05, c.pixel_height);
+    }
+
+    #[test]
+    fn square_pixels_cover_both_fovs() {
+        let c = CameraBuilder::default()
+            .hsize(20)
+            .vsize(20)
+            .fov(PI / 2.0)
+            .vfov(2.0 * 0.5f64.atan())
+            .square_pixels(true)
+            .build()
+            .unwrap();
+        let corner = c.ray_for_pixel_offset(0, 0, 0.0, 0.0).direction;
+
+        assert_fuzzy_eq!(c.pixel_width, c.pixel_height);
+        assert_fuzzy_eq!(1.0, corner.x / corner.y);
+        assert_fuzzy_eq!(1.0, -corner.x / corner.z);
+    }
+
+    #[test]
+    fn builder_rejects_bad_vfov() {
+        assert!(CameraBuilder::default().vfov(0.0).build().is_err());
+        assert!(CameraBuilder::default().vfov(PI).build().is_err());
+    }
+
+    #[test]
+    fn preview_at_full_size_is_the_render() {
+        let w = World::default();
+        let c = default_world_camera(11);
+
+        assert_eq!(c.render(&w), c.render_preview(&w, 11));
+        assert_eq!(c.render(&w), c.render_preview(&w, 100));
+    }
+
+    #[test]
+    fn preview_shrinks_the_image() {
+        let w = World::default();
+        let c = default_world_camera(11);
+        let preview = c.render_preview(&w, 5);
+
+        assert_eq!((5, 5), (preview.width, preview.height));
+        assert!(preview.pixel_at(2, 2).max_difference(c.render(&w).pixel_at(5, 5)) < 0.01);
+    }
+
+    #[test]
+    fn preview_keeps_the_aspect_ratio() {
+        let w = World::default();
+        let c = facing_default_world(Camera::new(160, 90, PI / 2.0));
+        let preview = c.render_preview(&w, 32);
+
+        assert_eq!((32, 18), (preview.width, preview.height));
+    }
+
+    #[cfg(feature = "ray-counters")]
+    #[test]
+    fn stats_count_every_ray_of_default_world_render() {
+        let w = World::default();
+        let c = default_world_camera(11);
+        let (image, stats) = c.render_with_stats(&w);
+        let hit_pixels = c.render_object_ids(&w).iter().filter(|id| id.is_some()).count();
+
+        assert_eq!(c.render(&w), image);
+        assert!(hit_pixels > 0 && hit_pixels < 11 * 11);
+        assert_eq!(11 * 11, stats.rays.primary_rays);
+        assert_eq!(hit_pixels, stats.rays.shadow_rays);
+        assert_eq!(0, stats.rays.secondary_rays);
+        // Both spheres share a box, so a ray tests both of them or, when it misses the box, neither.
+        // Every camera ray that hits gets through it
+        let tests = stats.rays.intersection_tests;
+        assert_eq!(0, tests % 2);
+        assert!(tests >= 2 * hit_pixels && tests <= 2 * (11 * 11 + hit_pixels));
+    }
+
+    #[cfg(feature = "ray-counters")]
+    #[test]
+    fn stats_count_reflected_rays_and_refined_samples() {
+        let mut w = World::default();
+        let Some(crate::shape::Shape::Sphere(outer)) = w.object_mut(0) else { unreachable!() };
+        outer.material = outer.material.clone().with_reflective(0.5);
+        let (_, stats) = adaptive(default_world_camera(11)).render_with_stats(&w);
+
+        assert!(stats.rays.secondary_rays > 0);
+        assert_eq!(stats.samples, stats.rays.primary_rays);
+    }
 }
@@ -1,4 +1,21 @@
-use crate::{color::Color, png::ToPNG, ppm::ToPPM, rgb::ToRgbA32, two_dimensional::TwoDimensional};
+use std::io::BufRead;
+
+use rayon::prelude::*;
+
+use crate::{
+    bmp::{self, ToBMP},
+    color::Color,
+    error::Error,
+    hdr::ToHdr,
+    pgm::ToPGM,
+    png::{self, PngError, ToPNG},
+    ppm::{self, PpmError, ToPPM},
+    rgb::{ExportOptions, ToRgbA32, ToRgbA64, ToRgbF32},
+    two_dimensional::TwoDimensional,
+};
+
+// Below this many pixels, handing rows out to threads costs more than mapping them does
+const PARALLEL_MAP_PIXELS: usize = 64 * 64;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Canvas {
@@ -16,21 +33,122 @@ impl Canvas {
         }
     }
 
+    pub fn from_ppm(reader: impl BufRead) -> Result<Self, PpmError> {
+        ppm::read(reader)
+    }
+
+    pub fn from_png(bytes: &[u8]) -> Result<Self, PngError> {
+        png::read(bytes)
+    }
+
     pub fn pixel_at(&self, x: usize, y: usize) -> Color {
-        self.pixels[self.get_pixel_index(x, y)]
+        self.try_pixel_at(x, y).unwrap_or_else(|e| panic!("{}", e))
     }
 
     pub fn write_pixel(&mut self, x: usize, y: usize, c: Color) {
-        let index = self.get_pixel_index(x, y);
+        self.try_write_pixel(x, y, c).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_pixel_at(&self, x: usize, y: usize) -> Result<Color, Error> {
+        Ok(self.pixels[self.checked_index(x, y)?])
+    }
+
+    pub fn try_write_pixel(&mut self, x: usize, y: usize, c: Color) -> Result<(), Error> {
+        let index = self.checked_index(x, y)?;
         self.pixels[index] = c;
+        Ok(())
+    }
+
+    pub fn fill(&mut self, color: Color) {
+        self.pixels.fill(color);
+    }
+
+    pub fn clear(&mut self) {
+        self.fill(Color::black());
+    }
+
+    // Replaces every pixel with what f makes of it and its position, which is how exposure, gamma
+    // and tone mapping passes get applied
+    pub fn map_pixels(&mut self, f: impl Fn(usize, usize, Color) -> Color + Sync) {
+        self.map_pixels_with(self.pixels.len() >= PARALLEL_MAP_PIXELS, f);
+    }
+
+    fn map_pixels_with(&mut self, parallel: bool, f: impl Fn(usize, usize, Color) -> Color + Sync) {
+        // Rows of no pixels can't be chunked, and there's nothing to map anyway
+        if self.width == 0 {
+            return;
+        }
+
+        let map_row = |(y, row): (usize, &mut [Color])| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = f(x, y, *pixel);
+            }
+        };
+
+        if parallel {
+            self.pixels.par_chunks_mut(self.width).enumerate().for_each(map_row);
+        } else {
+            self.pixels.chunks_mut(self.width).enumerate().for_each(map_row);
+        }
+    }
+
+    // Copies src onto this canvas with its top left corner at (dst_x, dst_y). Whatever falls
+    // outside this canvas, on any side, is left out
+    pub fn blit(&mut self, src: &Canvas, dst_x: isize, dst_y: isize) {
+        self.paste(src, dst_x, dst_y, |_, s| s);
+    }
+
+    // Like blit, but each pixel only moves alpha of the way from what was there to src's
+    pub fn composite_over(&mut self, src: &Canvas, dst_x: isize, dst_y: isize, alpha: f64) {
+        self.paste(src, dst_x, dst_y, |d, s| d * (1.0 - alpha) + s * alpha);
+    }
+
+    fn paste(&mut self, src: &Canvas, dst_x: isize, dst_y: isize, blend: impl Fn(Color, Color) -> Color) {
+        for y in 0..src.height {
+            for x in 0..src.width {
+                let (to_x, to_y) = (dst_x + x as isize, dst_y + y as isize);
+                if (0..self.width as isize).contains(&to_x) && (0..self.height as isize).contains(&to_y) {
+                    let index = self.get_pixel_index(to_x as usize, to_y as usize);
+                    self.pixels[index] = blend(self.pixels[index], src.pixels[src.get_pixel_index(x, y)]);
+                }
+            }
+        }
+    }
+
+    // The two canvases next to each other, left one first. They have to be equally tall
+    pub fn side_by_side(left: &Canvas, right: &Canvas) -> Canvas {
+        assert_eq!(left.height, right.height, "side by side canvases need the same height");
+
+        let pixels = left
+            .pixels
+            .chunks(left.width)
+            .zip(right.pixels.chunks(right.width))
+            .flat_map(|(l, r)| l.iter().chain(r))
+            .copied()
+            .collect();
+
+        Canvas {
+            width: left.width + right.width,
+            height: left.height,
+            pixels,
+        }
     }
 
     fn get_pixel_index(&self, x: usize, y: usize) -> usize {
         y * self.width + x
     }
 
-    fn pixels_as_rgba32(&self) -> Vec<u8> {
-        self.pixels.iter().flat_map(|c| c.to_rgba32()).collect()
+    fn pixels_as_rgba32(&self, options: ExportOptions) -> Vec<u8> {
+        self.pixels.iter().flat_map(|c| c.to_rgba32_with(options.encoding)).collect()
+    }
+
+    // Big endian, as PNG wants its samples
+    fn pixels_as_rgba64(&self, options: ExportOptions) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|c| c.to_rgba64_with(options.encoding))
+            .flat_map(u16::to_be_bytes)
+            .collect()
     }
 }
 
@@ -45,78 +163,94 @@ impl TwoDimensional for Canvas {
 }
 
 impl ToPPM for Canvas {
-    fn to_ppm(&self) -> Vec<u8> {
-        let mut last_image_row: usize = 0;
-        let mut column_count: usize = 0;
+    fn to_ppm_with(&self, options: ExportOptions) -> Vec<u8> {
+        let header = self.header();
+        // At most three digits and a space or line break for every sample
+        let mut data = Vec::with_capacity(header.len() + self.pixels.len() * 3 * 4 + 1);
+        data.extend(header);
+
+        let rows = self.pixels.chunks(self.width.max(1)).map(|row| {
+            row.iter().flat_map(|pixel| {
+                let [r, g, b, _] = pixel.to_rgba32_with(options.encoding);
+                [r, g, b]
+            })
+        });
+        ppm::write_plain_rows(&mut data, rows);
 
-        let rgb_colors: Vec<u8> = self.pixels_as_rgba32();
+        data
+    }
 
-        let pixel_data = rgb_colors
+    fn to_ppm_p6_with(&self, options: ExportOptions) -> Vec<u8> {
+        let rgb_colors = self
+            .pixels_as_rgba32(options)
             .into_iter()
-            .map(|byte| format!("{}", byte))
             .enumerate()
             .filter(|(i, _)| (i + 1) % 4 != 0)
-            .enumerate()
-            .flat_map(|(i, (_, pixel_string))| {
-                let mut data: Vec<u8> = Vec::new();
-
-                let current_image_row = i / (self.width() * 3);
-                if current_image_row != last_image_row {
-                    last_image_row = current_image_row;
-                    data.extend(String::from("\n").into_bytes());
-                    column_count = 0;
-                }
-
-                let mut needed_space: usize = 0;
+            .map(|(_, byte)| byte);
 
-                if column_count != 0 {
-                    needed_space += 1;
-                }
+        self.header_with_magic("P6").into_iter().chain(rgb_colors).collect()
+    }
+}
 
-                needed_space += pixel_string.len();
+impl ToBMP for Canvas {
+    fn to_bmp_with(&self, options: ExportOptions) -> Vec<u8> {
+        let padding = bmp::row_size(self.width) - self.width * 3;
+        let mut data = bmp::header(self.width, self.height);
+        data.reserve(bmp::row_size(self.width) * self.height);
 
-                if column_count + needed_space > 70 {
-                    data.extend(String::from("\n").into_bytes());
-                    column_count = 0;
-                }
+        for row in self.pixels.chunks(self.width.max(1)).rev() {
+            for pixel in row {
+                let [r, g, b, _] = pixel.to_rgba32_with(options.encoding);
+                data.extend([b, g, r]);
+            }
+            data.extend(std::iter::repeat_n(0, padding));
+        }
 
-                if column_count != 0 {
-                    data.extend(String::from(" ").into_bytes());
-                    column_count += 1;
-                }
+        data
+    }
+}
 
-                data.extend(pixel_string.clone().into_bytes());
-                column_count += pixel_string.len();
+impl ToPGM for Canvas {
+    fn gray_values(&self) -> Vec<f64> {
+        self.pixels.iter().map(Color::luminance).collect()
+    }
+}
 
-                data
-            });
+impl ToHdr for Canvas {
+    fn to_pfm(&self) -> Vec<u8> {
+        let rows = self.pixels.chunks(self.width.max(1)).rev();
+        let pixel_data = rows.flatten().flat_map(|c| c.to_rgbf32()).flat_map(f32::to_le_bytes);
 
-        self.header()
-            .into_iter()
-            .chain(pixel_data)
-            .chain(String::from("\n").into_bytes())
-            .collect()
+        self.pfm_header().into_iter().chain(pixel_data).collect()
     }
 }
 
 impl ToPNG for Canvas {
-    fn to_png(self) -> Vec<u8> {
-        let mut data = Vec::new();
-        let mut encoder = png::Encoder::new(&mut data, self.width() as u32, self.height() as u32);
-        encoder.set_color(png::ColorType::Rgba);
-        encoder.set_depth(png::BitDepth::Eight);
-        let mut writer = encoder.write_header().unwrap();
-        writer.write_image_data(&self.pixels_as_rgba32()).unwrap();
-
-        drop(writer);
+    fn to_png_with(self, options: ExportOptions) -> Vec<u8> {
+        encode_png(self.width, self.height, ::png::BitDepth::Eight, &self.pixels_as_rgba32(options))
+    }
 
-        data
+    fn to_png16_with(&self, options: ExportOptions) -> Vec<u8> {
+        encode_png(self.width, self.height, ::png::BitDepth::Sixteen, &self.pixels_as_rgba64(options))
     }
 }
 
+fn encode_png(width: usize, height: usize, depth: ::png::BitDepth, pixels: &[u8]) -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut encoder = ::png::Encoder::new(&mut data, width as u32, height as u32);
+    encoder.set_color(::png::ColorType::Rgba);
+    encoder.set_depth(depth);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(pixels).unwrap();
+
+    drop(writer);
+
+    data
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::util::FuzzyEq;
+    use crate::{rgb::Encoding, util::FuzzyEq};
 
     use super::*;
 
@@ -200,4 +334,470 @@ mod tests {
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn placing_canvases_side_by_side() {
+        let mut left = Canvas::new(2, 2);
+        let mut right = Canvas::new(3, 2);
+        left.write_pixel(1, 1, Color::red());
+        right.write_pixel(0, 0, Color::blue());
+
+        let both = Canvas::side_by_side(&left, &right);
+        assert_eq!((5, 2), (both.width, both.height));
+        assert_eq!(Color::red(), both.pixel_at(1, 1));
+        assert_eq!(Color::blue(), both.pixel_at(2, 0));
+        assert_eq!(Color::black(), both.pixel_at(4, 1));
+    }
+
+    #[test]
+    fn pixel_access_outside_the_canvas_is_an_error() {
+        let mut c = Canvas::new(10, 20);
+        let err = c.try_pixel_at(10, 0).unwrap_err();
+
+        assert_eq!(Error::PixelOutOfBounds { x: 10, y: 0, width: 10, height: 20 }, err);
+        assert_eq!("pixel (10, 0) is outside the 10x20 canvas", err.to_string());
+        assert!(c.try_write_pixel(3, 20, Color::white()).is_err());
+        assert!(c.try_write_pixel(9, 19, Color::white()).is_ok());
+        assert_eq!(Color::white(), c.pixel_at(9, 19));
+    }
+
+    #[test]
+    fn constructing_p6_header() {
+        let c = Canvas::new(5, 3);
+        let ppm = c.to_ppm_p6();
+
+        assert!(ppm.starts_with(b"P6\n5 3\n255\n"));
+    }
+
+    #[test]
+    fn p6_pixel_data_is_three_raw_bytes_per_pixel() {
+        let mut canvas = Canvas::new(5, 3);
+        canvas.write_pixel(0, 0, Color::new(1.5, 0.0, 0.0));
+        canvas.write_pixel(2, 1, Color::new(0.0, 0.5, 0.0));
+        canvas.write_pixel(4, 2, Color::new(-0.5, 0.0, 1.0));
+
+        let ppm = canvas.to_ppm_p6();
+        let data = &ppm[canvas.header_with_magic("P6").len()..];
+
+        assert_eq!(5 * 3 * 3, data.len());
+        assert_eq!([255, 0, 0], data[0..3]);
+        assert_eq!([0, 128, 0], data[(5 + 2) * 3..(5 + 2) * 3 + 3]);
+        assert_eq!([0, 0, 255], data[(2 * 5 + 4) * 3..]);
+    }
+
+    #[test]
+    fn reading_a_file_with_the_wrong_magic_number() {
+        let ppm = "P32\n1 1\n255\n0 0 0\n";
+
+        assert!(matches!(Canvas::from_ppm(ppm.as_bytes()), Err(PpmError::BadMagic { found }) if found == "P32"));
+    }
+
+    #[test]
+    fn reading_a_ppm_returns_a_canvas_of_the_right_size() {
+        let ppm = "P3\n10 2\n255\n0 0 0  0 0 0  0 0 0  0 0 0  0 0 0\n0 0 0  0 0 0  0 0 0  0 0 0  0 0 0\n\
+                   0 0 0  0 0 0  0 0 0  0 0 0  0 0 0\n0 0 0  0 0 0  0 0 0  0 0 0  0 0 0\n";
+        let canvas = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+
+        assert_eq!(10, canvas.width);
+        assert_eq!(2, canvas.height);
+    }
+
+    #[test]
+    fn reading_pixel_data_from_a_ppm_file() {
+        let ppm = "P3\n4 3\n255\n\
+                   255 127 0  0 127 255  127 255 0  255 255 255\n\
+                   0 0 0  255 0 0  0 255 0  0 0 255\n\
+                   255 255 0  0 255 255  255 0 255  127 127 127\n";
+        let canvas = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+        let half = 127.0 / 255.0;
+
+        let expected = [
+            (0, 0, Color::new(1.0, half, 0.0)),
+            (1, 0, Color::new(0.0, half, 1.0)),
+            (2, 0, Color::new(half, 1.0, 0.0)),
+            (3, 0, Color::new(1.0, 1.0, 1.0)),
+            (0, 1, Color::new(0.0, 0.0, 0.0)),
+            (1, 1, Color::new(1.0, 0.0, 0.0)),
+            (2, 1, Color::new(0.0, 1.0, 0.0)),
+            (3, 1, Color::new(0.0, 0.0, 1.0)),
+            (0, 2, Color::new(1.0, 1.0, 0.0)),
+            (1, 2, Color::new(0.0, 1.0, 1.0)),
+            (2, 2, Color::new(1.0, 0.0, 1.0)),
+            (3, 2, Color::new(half, half, half)),
+        ];
+        for (x, y, color) in expected {
+            assert!(canvas.pixel_at(x, y).fuzzy_eq(color), "pixel ({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn ppm_parsing_ignores_comment_lines() {
+        let ppm = "P3\n# this is a comment\n2 1\n# this, too\n255\n# another comment\n255 255 255\n\
+                   # oh, no, comments in the pixel data!\n255 0 255\n";
+        let canvas = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+
+        assert!(canvas.pixel_at(0, 0).fuzzy_eq(Color::new(1.0, 1.0, 1.0)));
+        assert!(canvas.pixel_at(1, 0).fuzzy_eq(Color::new(1.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn ppm_allows_an_rgb_triple_to_span_lines() {
+        let ppm = "P3\n1 1\n255\n51\n153\n\n204\n";
+        let canvas = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+
+        assert!(canvas.pixel_at(0, 0).fuzzy_eq(Color::new(0.2, 0.6, 0.8)));
+    }
+
+    #[test]
+    fn ppm_respects_the_scale_setting() {
+        let ppm = "P3\n2 2\n100\n100 100 100  50 50 50\n75 50 25  0 0 0\n";
+        let canvas = Canvas::from_ppm(ppm.as_bytes()).unwrap();
+
+        assert!(canvas.pixel_at(0, 1).fuzzy_eq(Color::new(0.75, 0.5, 0.25)));
+    }
+
+    #[test]
+    fn reading_a_ppm_with_a_maxval_of_zero() {
+        let ppm = "P3\n1 1\n0\n0 0 0\n";
+
+        assert!(matches!(Canvas::from_ppm(ppm.as_bytes()), Err(PpmError::ZeroMaxval)));
+    }
+
+    #[test]
+    fn reading_a_truncated_ppm() {
+        let err = Canvas::from_ppm("P3\n2 1\n255\n0 0 0 255\n".as_bytes()).unwrap_err();
+
+        assert!(matches!(err, PpmError::Truncated { expected: 6, found: 4 }));
+        assert_eq!("the PPM data ends after 4 of its 6 samples", err.to_string());
+        assert!(matches!(
+            Canvas::from_ppm("P3\n2".as_bytes()),
+            Err(PpmError::MissingHeader { field: "height" })
+        ));
+        assert!(matches!(
+            Canvas::from_ppm("P3\n2 x\n255\n".as_bytes()),
+            Err(PpmError::InvalidHeader { field: "height", .. })
+        ));
+    }
+
+    #[test]
+    fn reading_a_ppm_with_an_impossible_header() {
+        let huge = format!("P3\n{} {}\n255\n", usize::MAX / 2, 2);
+        let err = Canvas::from_ppm(huge.as_bytes()).unwrap_err();
+        assert!(matches!(err, PpmError::InvalidHeader { field: "size", .. }), "{}", err);
+        // The samples fit in a usize, but not once they take two bytes each
+        let wide = format!("P6\n{} 1\n65535\n\0\0", usize::MAX / 4);
+        let err = Canvas::from_ppm(wide.as_bytes()).unwrap_err();
+        assert!(matches!(err, PpmError::InvalidHeader { field: "size", .. }), "{}", err);
+
+        let err = Canvas::from_ppm("P6\n1 1\n65536\n\0\0\0\0\0\0".as_bytes()).unwrap_err();
+        assert_eq!("the PPM header has an invalid maximum value: \"65536\"", err.to_string());
+        let err = Canvas::from_ppm("P3\n1 1\n4294967297\n1 1 1\n".as_bytes()).unwrap_err();
+        assert!(matches!(err, PpmError::InvalidHeader { field: "maximum value", .. }));
+        assert!(Canvas::from_ppm("P3\n1 1\n65535\n65535 0 1\n".as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn p6_output_reads_back_in() {
+        let mut canvas = Canvas::new(5, 3);
+        canvas.write_pixel(0, 0, Color::new(1.5, 0.0, 0.0));
+        canvas.write_pixel(2, 1, Color::new(0.0, 0.2, 0.0));
+        canvas.write_pixel(4, 2, Color::new(-0.5, 0.0, 1.0));
+
+        let read = Canvas::from_ppm(canvas.to_ppm_p6().as_slice()).unwrap();
+
+        assert_eq!((5, 3), (read.width, read.height));
+        assert!(read.pixel_at(0, 0).fuzzy_eq(Color::red()));
+        assert!(read.pixel_at(2, 1).fuzzy_eq(Color::new(0.0, 0.2, 0.0)));
+        assert!(read.pixel_at(4, 2).fuzzy_eq(Color::blue()));
+        assert!(Canvas::from_ppm(&canvas.to_ppm_p6()[..20]).is_err());
+    }
+
+    #[test]
+    fn png_output_reads_back_in() {
+        let mut canvas = Canvas::new(7, 4);
+        for (i, pixel) in canvas.pixels.iter_mut().enumerate() {
+            *pixel = Color::new(i as f64 / 28.0, 1.0 - i as f64 / 28.0, (i % 3) as f64 * 0.4);
+        }
+
+        let read = Canvas::from_png(&canvas.clone().to_png()).unwrap();
+
+        assert_eq!((7, 4), (read.width, read.height));
+        for (written, read) in canvas.pixels.iter().zip(&read.pixels) {
+            assert!(written.clamp(0.0, 1.0).max_difference(*read) <= 1.0 / 255.0);
+        }
+    }
+
+    #[test]
+    fn reading_a_grayscale_png() {
+        let mut data = Vec::new();
+        let mut encoder = ::png::Encoder::new(&mut data, 2, 2);
+        encoder.set_color(::png::ColorType::Grayscale);
+        encoder.set_depth(::png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(&[0, 64, 128, 255]).unwrap();
+
+        let err = Canvas::from_png(&data).unwrap_err();
+
+        assert!(matches!(err, PngError::Unsupported { color_type: ::png::ColorType::Grayscale, .. }));
+        assert_eq!("only 8-bit RGB and RGBA PNG files can be read, got 8-bit Grayscale", err.to_string());
+    }
+
+    #[test]
+    fn fill_sets_every_pixel() {
+        let mut c = Canvas::new(4, 3);
+        c.fill(Color::new(0.2, 0.4, 0.6));
+
+        assert!(c.pixels.iter().all(|&p| p == Color::new(0.2, 0.4, 0.6)));
+
+        c.clear();
+        assert_eq!(Canvas::new(4, 3), c);
+    }
+
+    #[test]
+    fn mapping_with_identity_changes_nothing() {
+        let mut c = Canvas::new(4, 3);
+        c.write_pixel(1, 2, Color::red());
+        let before = c.clone();
+
+        c.map_pixels(|_, _, p| p);
+
+        assert_eq!(before, c);
+    }
+
+    #[test]
+    fn mapping_gets_each_pixels_coordinates() {
+        let mut c = Canvas::new(4, 3);
+        c.map_pixels(|x, y, _| Color::new(x as f64, y as f64, 0.0));
+
+        for y in 0..3 {
+            for x in 0..4 {
+                assert_eq!(Color::new(x as f64, y as f64, 0.0), c.pixel_at(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_and_sequential_mapping_agree() {
+        let mut parallel = Canvas::new(90, 70);
+        parallel.map_pixels(|x, y, _| Color::new(x as f64 / 90.0, y as f64 / 70.0, 0.5));
+        let mut sequential = parallel.clone();
+
+        let gamma = |x: usize, y: usize, p: Color| p.powf(1.0 / 2.2) * ((x + y) % 3) as f64;
+        parallel.map_pixels_with(true, gamma);
+        sequential.map_pixels_with(false, gamma);
+
+        assert_eq!(sequential, parallel);
+        assert_ne!(Canvas::new(90, 70), parallel);
+    }
+
+    #[test]
+    fn blitting_past_the_corner_writes_what_overlaps() {
+        let mut c = Canvas::new(5, 5);
+        let mut src = Canvas::new(2, 2);
+        src.fill(Color::red());
+
+        c.blit(&src, 4, 4);
+
+        assert_eq!(1, c.pixels.iter().filter(|&&p| p == Color::red()).count());
+        assert_eq!(Color::red(), c.pixel_at(4, 4));
+    }
+
+    #[test]
+    fn blitting_at_negative_positions_clips() {
+        let mut c = Canvas::new(5, 5);
+        let mut src = Canvas::new(3, 3);
+        src.map_pixels(|x, y, _| Color::new(x as f64, y as f64, 1.0));
+
+        c.blit(&src, -2, -1);
+
+        assert_eq!(Color::new(2.0, 1.0, 1.0), c.pixel_at(0, 0));
+        assert_eq!(Color::new(2.0, 2.0, 1.0), c.pixel_at(0, 1));
+        assert_eq!(2, c.pixels.iter().filter(|&&p| p != Color::black()).count());
+    }
+
+    #[test]
+    fn compositing_at_full_alpha_is_blitting() {
+        let mut src = Canvas::new(3, 2);
+        src.map_pixels(|x, y, _| Color::new(0.1 * x as f64, 0.3, 0.2 * y as f64));
+        let mut blitted = Canvas::new(4, 4);
+        blitted.fill(Color::new(0.5, 0.7, 0.9));
+        let mut composited = blitted.clone();
+
+        blitted.blit(&src, 2, 1);
+        composited.composite_over(&src, 2, 1, 1.0);
+
+        assert_eq!(blitted, composited);
+    }
+
+    #[test]
+    fn compositing_at_half_alpha_averages() {
+        let mut c = Canvas::new(2, 2);
+        c.fill(Color::new(0.2, 0.4, 1.0));
+        let mut src = Canvas::new(2, 2);
+        src.fill(Color::new(0.6, 0.0, 0.5));
+
+        c.composite_over(&src, 0, 0, 0.5);
+
+        assert!(c.pixels.iter().all(|p| p.fuzzy_eq(Color::new(0.4, 0.2, 0.75))));
+    }
+
+    #[test]
+    fn writers_can_encode_as_srgb() {
+        let mut canvas = Canvas::new(2, 1);
+        canvas.fill(Color::grey(0.5));
+        let srgb = ExportOptions { encoding: Encoding::Srgb };
+
+        assert_eq!(canvas.to_ppm(), canvas.to_ppm_with(ExportOptions::default()));
+        assert_eq!(b"P3\n2 1\n255\n188 188 188 188 188 188\n".to_vec(), canvas.to_ppm_with(srgb));
+        assert!(canvas.to_ppm_p6_with(srgb).ends_with(&[188; 6]));
+
+        let read = Canvas::from_png(&canvas.clone().to_png_with(srgb)).unwrap();
+        assert!(read.pixel_at(1, 0).fuzzy_eq(Color::grey(188.0 / 255.0)));
+    }
+
+    #[test]
+    fn png16_output_has_sixteen_bit_samples() {
+        let mut canvas = Canvas::new(3, 2);
+        canvas.map_pixels(|x, y, _| Color::new(x as f64 / 3.0, 0.5, 0.1 * y as f64 + 0.0001));
+
+        let png = canvas.to_png16();
+        let mut reader = ::png::Decoder::new(png.as_slice()).read_info().unwrap();
+        assert_eq!(::png::BitDepth::Sixteen, reader.info().bit_depth);
+        let mut data = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut data).unwrap();
+
+        let samples: Vec<u16> = data.chunks(2).map(|b| u16::from_be_bytes([b[0], b[1]])).collect();
+        assert!(samples[1].abs_diff(32768) <= 1);
+        for (pixel, rgba) in canvas.pixels.iter().zip(samples.chunks(4)) {
+            let read = Color::new(rgba[0] as f64, rgba[1] as f64, rgba[2] as f64) * (1.0 / 65535.0);
+            assert!(pixel.max_difference(read) <= 1.0 / 65535.0);
+            assert_eq!(u16::MAX, rgba[3]);
+        }
+    }
+
+    // Just enough of a PFM reader for the tests: the three header lines, then floats
+    fn read_pfm(pfm: &[u8]) -> Canvas {
+        let mut lines = pfm.splitn(4, |&b| b == b'\n');
+        assert_eq!(b"PF", lines.next().unwrap());
+        let size = std::str::from_utf8(lines.next().unwrap()).unwrap();
+        let (width, height) = size.split_once(' ').unwrap();
+        let (width, height): (usize, usize) = (width.parse().unwrap(), height.parse().unwrap());
+        assert_eq!(b"-1.0", lines.next().unwrap());
+
+        let floats: Vec<f64> =
+            lines.next().unwrap().chunks(4).map(|b| f32::from_le_bytes(b.try_into().unwrap()) as f64).collect();
+        let mut canvas = Canvas::new(width, height);
+        for (i, rgb) in floats.chunks(3).enumerate() {
+            canvas.write_pixel(i % width, height - 1 - i / width, Color::new(rgb[0], rgb[1], rgb[2]));
+        }
+
+        canvas
+    }
+
+    #[test]
+    fn pfm_output_keeps_colors_brighter_than_white() {
+        let mut canvas = Canvas::new(2, 2);
+        canvas.write_pixel(0, 0, Color::new(1.9, 0.2, 0.0));
+        canvas.write_pixel(1, 1, Color::new(1e-3, 5.0, 1.0));
+        canvas.write_pixel(1, 0, Color::new(-0.5, 0.25, 0.125));
+
+        let pfm = canvas.to_pfm();
+        assert!(pfm.starts_with(b"PF\n2 2\n-1.0\n"));
+        assert_eq!(b"PF\n2 2\n-1.0\n".len() + 2 * 2 * 3 * 4, pfm.len());
+
+        let read = read_pfm(&pfm);
+        for (written, read) in canvas.pixels.iter().zip(&read.pixels) {
+            let [r, g, b] = written.to_rgbf32();
+            assert_eq!(Color::new(r as f64, g as f64, b as f64), *read);
+        }
+        assert!(read.pixel_at(0, 0).fuzzy_eq(Color::new(1.9, 0.2, 0.0)));
+        assert!(read.pixel_at(1, 1).fuzzy_eq(Color::new(1e-3, 5.0, 1.0)));
+    }
+
+    #[test]
+    fn pfm_rows_go_bottom_to_top() {
+        let mut canvas = Canvas::new(1, 2);
+        canvas.write_pixel(0, 0, Color::new(1.5, 0.0, 0.0));
+
+        let pfm = canvas.to_pfm();
+        let data = &pfm[canvas.pfm_header().len()..];
+
+        assert_eq!([0; 12], data[..12]);
+        assert_eq!(1.5_f32.to_le_bytes(), data[12..16]);
+    }
+
+    #[test]
+    fn ppm_lines_never_go_over_70_characters() {
+        use crate::sampling::{PixelSampler, Sampler};
+
+        for width in 1..40 {
+            let mut sampler = PixelSampler::new(7, width, 0, 0);
+            let mut canvas = Canvas::new(width, 1 + width % 4);
+            for pixel in canvas.pixels.iter_mut() {
+                // Some samples get one digit, some three, and some are clamped
+                *pixel = Color::new(sampler.next_f64() * 1.2, sampler.next_f64() * 0.04, sampler.next_f64() - 0.1);
+            }
+
+            let ppm = canvas.to_ppm();
+
+            assert!(ppm.split(|&b| b == b'\n').all(|line| line.len() <= 70), "width {}", width);
+            let read = Canvas::from_ppm(ppm.as_slice()).unwrap();
+            for (written, read) in canvas.pixels.iter().zip(&read.pixels) {
+                assert!(written.clamp(0.0, 1.0).max_difference(*read) <= 0.5 / 255.0);
+            }
+        }
+    }
+
+    #[test]
+    fn constructing_pgm_headers() {
+        let c = Canvas::new(5, 3);
+
+        assert!(c.to_pgm().starts_with(b"P2\n5 3\n255\n"));
+        assert!(c.to_pgm_p5().starts_with(b"P5\n5 3\n255\n"));
+    }
+
+    #[test]
+    fn pgm_samples_are_luminance() {
+        let mut c = Canvas::new(3, 1);
+        c.write_pixel(0, 0, Color::red());
+        c.write_pixel(1, 0, Color::white());
+
+        assert_eq!(b"P2\n3 1\n255\n54 255 0\n".to_vec(), c.to_pgm());
+        assert_eq!([54, 255, 0], c.to_pgm_p5()[c.pgm_header("P5").len()..]);
+    }
+
+    #[test]
+    fn p5_pixel_data_is_one_byte_per_pixel() {
+        let c = Canvas::new(7, 4);
+
+        assert_eq!(7 * 4, c.to_pgm_p5().len() - c.pgm_header("P5").len());
+    }
+
+    #[test]
+    fn bmp_rows_are_padded_to_four_bytes() {
+        let c = Canvas::new(3, 2);
+        let data = c.to_bmp();
+
+        // Rows of 9 bytes padded to 12
+        assert_eq!(54 + 12 * 2, data.len());
+        assert_eq!(b"BM", &data[0..2]);
+        assert_eq!((54_u32 + 24).to_le_bytes(), data[2..6]);
+        assert_eq!(54_u32.to_le_bytes(), data[10..14]);
+        assert_eq!(3_i32.to_le_bytes(), data[18..22]);
+        assert_eq!(2_i32.to_le_bytes(), data[22..26]);
+        assert_eq!(24_u16.to_le_bytes(), data[28..30]);
+    }
+
+    #[test]
+    fn bmp_rows_go_bottom_up_in_bgr_order() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(0, 0, Color::new(1.0, 0.5, 0.0));
+        c.write_pixel(2, 1, Color::blue());
+
+        let data = c.to_bmp();
+        let (bottom_row, top_row) = (&data[54..66], &data[66..78]);
+
+        assert_eq!([0, 128, 255], top_row[0..3]);
+        assert_eq!([0, 0, 0], top_row[9..12]);
+        assert_eq!([255, 0, 0], bottom_row[6..9]);
+    }
 }
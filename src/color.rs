@@ -1,8 +1,12 @@
 use std::ops::{Add, Mul, Sub};
 
-use crate::{rgb::ToRgbA32, util::FuzzyEq};
+use crate::{
+    rgb::{Encoding, ToRgbA32, ToRgbA64, ToRgbF32},
+    util::FuzzyEq,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Color {
     red: f64,
     green: f64,
@@ -22,6 +26,31 @@ impl Color {
         )
     }
 
+    // e raised to each channel
+    pub fn exp(&self) -> Self {
+        Color::new(self.red.exp(), self.green.exp(), self.blue.exp())
+    }
+
+    // The biggest gap between matching channels of the two colors
+    pub fn max_difference(&self, other: Color) -> f64 {
+        (self.red - other.red).abs().max((self.green - other.green).abs()).max((self.blue - other.blue).abs())
+    }
+
+    // Each channel raised to the power
+    pub fn powf(&self, exponent: f64) -> Self {
+        Color::new(self.red.powf(exponent), self.green.powf(exponent), self.blue.powf(exponent))
+    }
+
+    // The same value in every channel
+    // How bright the color looks, by the Rec. 709 weights
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.red + 0.7152 * self.green + 0.0722 * self.blue
+    }
+
+    pub fn grey(value: f64) -> Self {
+        Self::new(value, value, value)
+    }
+
     pub fn white() -> Self {
         Self {
             red: 1.0,
@@ -70,6 +99,12 @@ impl Default for Color {
 }
 
 
+impl From<f64> for Color {
+    fn from(value: f64) -> Self {
+        Self::grey(value)
+    }
+}
+
 impl FuzzyEq<Self> for Color {
     fn fuzzy_eq(&self, other: Self) -> bool {
         self.red.fuzzy_eq(other.red)
@@ -83,21 +118,30 @@ impl FuzzyEq<Self> for Color {
 }
 
 impl ToRgbA32 for Color {
-    fn to_rgba32(&self) -> Vec<u8> {
+    fn to_rgba32_with(&self, encoding: Encoding) -> [u8; 4] {
         let clamped = self.clamp(0.0, 1.0);
-        let mut data: Vec<u8> = Vec::new();
 
-        let r = (clamped.red * 255.0).round() as u8;
-        let g = (clamped.green * 255.0).round() as u8;
-        let b = (clamped.blue * 255.0).round() as u8;
+        let r = (encoding.encode(clamped.red) * 255.0).round() as u8;
+        let g = (encoding.encode(clamped.green) * 255.0).round() as u8;
+        let b = (encoding.encode(clamped.blue) * 255.0).round() as u8;
         let a: u8 = 255;
 
-        data.push(r);
-        data.push(g);
-        data.push(b);
-        data.push(a);
+        [r, g, b, a]
+    }
+}
+
+impl ToRgbA64 for Color {
+    fn to_rgba64_with(&self, encoding: Encoding) -> [u16; 4] {
+        let clamped = self.clamp(0.0, 1.0);
+        let quantize = |value: f64| (encoding.encode(value) * 65535.0).round() as u16;
+
+        [quantize(clamped.red), quantize(clamped.green), quantize(clamped.blue), u16::MAX]
+    }
+}
 
-        data
+impl ToRgbF32 for Color {
+    fn to_rgbf32(&self) -> [f32; 3] {
+        [self.red as f32, self.green as f32, self.blue as f32]
     }
 }
 
@@ -191,6 +235,15 @@ mod tests {
         assert!(actual.fuzzy_eq(expected));
     }
 
+    #[test]
+    fn max_difference_is_largest_channel_gap() {
+        let a = Color::new(0.9, 0.6, 0.75);
+        let b = Color::new(0.8, 0.9, 0.7);
+
+        assert!(a.max_difference(b).fuzzy_eq(0.3));
+        assert!(b.max_difference(a).fuzzy_eq(0.3));
+    }
+
     #[test]
     fn multiply_two_colors() {
         let a = Color::new(1.0, 0.2, 0.4);
@@ -220,4 +273,21 @@ mod tests {
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn color_to_rgba32_with_an_encoding() {
+        let grey = Color::grey(0.5);
+
+        assert_eq!([128, 128, 128, 255], grey.to_rgba32_with(Encoding::Linear));
+        assert_eq!([188, 188, 188, 255], grey.to_rgba32_with(Encoding::Srgb));
+        assert_eq!(Color::black().to_rgba32(), Color::black().to_rgba32_with(Encoding::Srgb));
+        assert_eq!(Color::white().to_rgba32(), Color::white().to_rgba32_with(Encoding::Srgb));
+    }
+
+    #[test]
+    fn luminance_weighs_green_the_most() {
+        assert!(Color::red().luminance().fuzzy_eq(0.2126));
+        assert!(Color::green().luminance().fuzzy_eq(0.7152));
+        assert!(Color::white().luminance().fuzzy_eq(1.0));
+    }
 }
@@ -0,0 +1,345 @@
+use crate::{
+    bounding_box::BoundingBox,
+    intersection::{Intersection, Intersections},
+    light::LightId,
+    material::Material,
+    matrix::Matrix,
+    ray::Ray,
+    shape::{next_shape_id, validate_transforms, Shape, ShapeFuncs},
+    tuple::Tuple,
+    util::{FuzzyEq, EPSILON},
+};
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Builder)]
+#[builder(build_fn(validate = "Self::validate"))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cone {
+    #[cfg_attr(feature = "serde", serde(skip, default = "next_shape_id"))]
+    #[builder(setter(skip), default = "next_shape_id()")]
+    pub(crate) id: u64,
+    #[builder(default)]
+    pub transform: Matrix<4>,
+    #[builder(default, setter(strip_option))]
+    pub transform_at_t1: Option<Matrix<4>>,
+    #[builder(default)]
+    pub material: Material,
+    #[builder(default = "f64::NEG_INFINITY")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde_float"))]
+    pub minimum: f64,
+    #[builder(default = "f64::INFINITY")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde_float"))]
+    pub maximum: f64,
+    #[builder(default)]
+    pub closed: bool,
+    #[builder(default)]
+    pub excluded_lights: Vec<LightId>,
+    #[builder(default, setter(into, strip_option))]
+    pub name: Option<String>,
+}
+
+impl Default for Cone {
+    fn default() -> Self {
+        Self {
+            id: next_shape_id(),
+            transform: Matrix::identity(),
+            transform_at_t1: None,
+            material: Material::default(),
+            minimum: f64::NEG_INFINITY,
+            maximum: f64::INFINITY,
+            closed: false,
+            excluded_lights: vec![],
+            name: None,
+        }
+    }
+}
+
+impl Cone {
+    // Checks whether the intersection at t is within the radius of the cap at height y,
+    // which for a cone equals |y|
+    fn check_cap(ray: Ray, t: f64, y: f64) -> bool {
+        let x = ray.origin.x + t * ray.direction.x;
+        let z = ray.origin.z + t * ray.direction.z;
+
+        (x.powi(2) + z.powi(2)) <= y.powi(2)
+    }
+
+    fn intersect_caps(&self, ray: Ray, xs: &mut Vec<Intersection>) {
+        if !self.closed || ray.direction.y.abs() < EPSILON {
+            return;
+        }
+
+        let t = (self.minimum - ray.origin.y) / ray.direction.y;
+        if Self::check_cap(ray, t, self.minimum) {
+            xs.push(Intersection::new(t, Shape::from(self.clone()).at_time(ray.time)));
+        }
+
+        let t = (self.maximum - ray.origin.y) / ray.direction.y;
+        if Self::check_cap(ray, t, self.maximum) {
+            xs.push(Intersection::new(t, Shape::from(self.clone()).at_time(ray.time)));
+        }
+    }
+
+    fn push_if_within_bounds(&self, ray: Ray, t: f64, xs: &mut Vec<Intersection>) {
+        let y = ray.origin.y + t * ray.direction.y;
+        if self.minimum < y && y < self.maximum {
+            xs.push(Intersection::new(t, Shape::from(self.clone()).at_time(ray.time)));
+        }
+    }
+}
+
+impl ConeBuilder {
+    fn validate(&self) -> Result<(), String> {
+        validate_transforms(self.transform, self.transform_at_t1.flatten())
+    }
+}
+
+impl ShapeFuncs for Cone {
+    fn local_intersect(&self, object_space_ray: Ray) -> Intersections {
+        let origin = object_space_ray.origin;
+        let direction = object_space_ray.direction;
+
+        let mut xs = vec![];
+        let a = direction.x.powi(2) - direction.y.powi(2) + direction.z.powi(2);
+        let b = 2.0 * origin.x * direction.x - 2.0 * origin.y * direction.y
+            + 2.0 * origin.z * direction.z;
+        let c = origin.x.powi(2) - origin.y.powi(2) + origin.z.powi(2);
+
+        if a.abs() < EPSILON {
+            // The ray is parallel to one of the cone's halves, so it can hit the other
+            // half at most once
+            if b.abs() >= EPSILON {
+                self.push_if_within_bounds(object_space_ray, -c / (2.0 * b), &mut xs);
+            }
+        } else {
+            let discriminant = b.powi(2) - 4.0 * a * c;
+
+            if discriminant < 0.0 {
+                return Intersections::new(vec![]);
+            }
+
+            let mut t0 = (-b - discriminant.sqrt()) / (2.0 * a);
+            let mut t1 = (-b + discriminant.sqrt()) / (2.0 * a);
+            if t0 > t1 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            self.push_if_within_bounds(object_space_ray, t0, &mut xs);
+            self.push_if_within_bounds(object_space_ray, t1, &mut xs);
+        }
+
+        self.intersect_caps(object_space_ray, &mut xs);
+
+        Intersections::new(xs)
+    }
+
+    fn local_normal_at(&self, object_point: Tuple) -> Tuple {
+        let dist = object_point.x.powi(2) + object_point.z.powi(2);
+
+        if dist < object_point.y.powi(2) && object_point.y >= self.maximum - EPSILON {
+            Tuple::vector(0.0, 1.0, 0.0)
+        } else if dist < object_point.y.powi(2) && object_point.y <= self.minimum + EPSILON {
+            Tuple::vector(0.0, -1.0, 0.0)
+        } else if dist.sqrt() < EPSILON && object_point.y.abs() < EPSILON {
+            // The normal at the apex is ambiguous, so point it along the cone's axis
+            // instead of normalizing a zero vector
+            Tuple::vector(0.0, 1.0, 0.0)
+        } else {
+            let mut y = dist.sqrt();
+            if object_point.y > 0.0 {
+                y = -y;
+            }
+
+            Tuple::vector(object_point.x, y, object_point.z)
+        }
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn transform_at_t1(&self) -> Option<Matrix<4>> {
+        self.transform_at_t1
+    }
+
+    fn excluded_lights(&self) -> &[LightId] {
+        &self.excluded_lights
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let limit = self.minimum.abs().max(self.maximum.abs());
+
+        BoundingBox::new(
+            Tuple::point(-limit, self.minimum, -limit),
+            Tuple::point(limit, self.maximum, limit),
+        )
+    }
+}
+
+impl FuzzyEq<Self> for Cone {
+    fn fuzzy_eq(&self, other: Self) -> bool {
+        self.transform.fuzzy_eq(other.transform)
+            && self.material.fuzzy_eq(other.material)
+            && self.minimum == other.minimum
+            && self.maximum == other.maximum
+            && self.closed == other.closed
+    }
+
+    fn fuzzy_ne(&self, other: Self) -> bool {
+        !self.fuzzy_eq(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_fuzzy_eq;
+
+    use super::*;
+
+    #[test]
+    fn intersecting_cone_with_ray() {
+        let shape = Cone::default();
+        let cases = vec![
+            (
+                Tuple::point(0.0, 0.0, -5.0),
+                Tuple::vector(0.0, 0.0, 1.0),
+                5.0,
+                5.0,
+            ),
+            (
+                Tuple::point(0.0, 0.0, -5.0),
+                Tuple::vector(1.0, 1.0, 1.0),
+                8.66025,
+                8.66025,
+            ),
+            (
+                Tuple::point(1.0, 1.0, -5.0),
+                Tuple::vector(-0.5, -1.0, 1.0),
+                4.55006,
+                49.44994,
+            ),
+        ];
+
+        for (origin, direction, t0, t1) in cases {
+            let r = Ray::new(origin, direction.normalize());
+            let xs = shape.intersect(r);
+            assert_eq!(2, xs.intersections.len());
+            assert_fuzzy_eq!(t0, xs.intersections[0].t);
+            assert_fuzzy_eq!(t1, xs.intersections[1].t);
+        }
+    }
+
+    #[test]
+    fn intersecting_cone_with_ray_parallel_to_one_half() {
+        let shape = Cone::default();
+        let r = Ray::new(
+            Tuple::point(0.0, 0.0, -1.0),
+            Tuple::vector(0.0, 1.0, 1.0).normalize(),
+        );
+
+        let xs = shape.intersect(r);
+        assert_eq!(1, xs.intersections.len());
+        assert_fuzzy_eq!(0.35355, xs.intersections[0].t);
+    }
+
+    #[test]
+    fn intersecting_cone_end_caps() {
+        let shape = ConeBuilder::default()
+            .minimum(-0.5)
+            .maximum(0.5)
+            .closed(true)
+            .build()
+            .unwrap();
+        let cases = vec![
+            (
+                Tuple::point(0.0, 0.0, -5.0),
+                Tuple::vector(0.0, 1.0, 0.0),
+                0,
+            ),
+            (
+                Tuple::point(0.0, 0.0, -0.25),
+                Tuple::vector(0.0, 1.0, 1.0),
+                2,
+            ),
+            (
+                Tuple::point(0.0, 0.0, -0.25),
+                Tuple::vector(0.0, 1.0, 0.0),
+                4,
+            ),
+        ];
+
+        for (origin, direction, count) in cases {
+            let r = Ray::new(origin, direction.normalize());
+            let xs = shape.intersect(r);
+            assert_eq!(count, xs.intersections.len());
+        }
+    }
+
+    #[test]
+    fn computing_normal_vector_on_cone() {
+        let shape = Cone::default();
+        let sqrt_2 = 2.0_f64.sqrt();
+        let cases = vec![
+            (
+                Tuple::point(1.0, 1.0, 1.0),
+                Tuple::vector(1.0, -sqrt_2, 1.0).normalize(),
+            ),
+            (
+                Tuple::point(-1.0, -1.0, 0.0),
+                Tuple::vector(-1.0, 1.0, 0.0).normalize(),
+            ),
+        ];
+
+        for (point, expected) in cases {
+            assert_fuzzy_eq!(expected, shape.normal_at(point));
+        }
+    }
+
+    #[test]
+    fn normal_at_apex_is_not_nan() {
+        let shape = Cone::default();
+        let n = shape.normal_at(Tuple::point(0.0, 0.0, 0.0));
+
+        assert!(!n.x.is_nan() && !n.y.is_nan() && !n.z.is_nan());
+        assert_fuzzy_eq!(1.0, n.magnitude());
+    }
+
+    #[test]
+    fn normal_vector_on_cone_end_caps() {
+        let shape = ConeBuilder::default()
+            .minimum(-1.0)
+            .maximum(2.0)
+            .closed(true)
+            .build()
+            .unwrap();
+
+        assert_fuzzy_eq!(
+            Tuple::vector(0.0, 1.0, 0.0),
+            shape.normal_at(Tuple::point(0.5, 2.0, 0.5))
+        );
+        assert_fuzzy_eq!(
+            Tuple::vector(0.0, -1.0, 0.0),
+            shape.normal_at(Tuple::point(0.0, -1.0, 0.5))
+        );
+    }
+
+    #[test]
+    fn bounded_cone_has_bounding_box() {
+        let shape = ConeBuilder::default()
+            .minimum(-5.0)
+            .maximum(3.0)
+            .build()
+            .unwrap();
+        let b = shape.bounds();
+
+        assert_fuzzy_eq!(Tuple::point(-5.0, -5.0, -5.0), b.min);
+        assert_fuzzy_eq!(Tuple::point(5.0, 3.0, 5.0), b.max);
+    }
+}
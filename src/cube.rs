@@ -0,0 +1,307 @@
+use crate::{
+    bounding_box::BoundingBox,
+    intersection::{Intersection, Intersections},
+    light::LightId,
+    material::Material,
+    matrix::Matrix,
+    ray::Ray,
+    shape::{next_shape_id, validate_transforms, Shape, ShapeFuncs},
+    tuple::Tuple,
+    util::FuzzyEq,
+};
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Builder)]
+#[builder(build_fn(validate = "Self::validate"))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cube {
+    #[cfg_attr(feature = "serde", serde(skip, default = "next_shape_id"))]
+    #[builder(setter(skip), default = "next_shape_id()")]
+    pub(crate) id: u64,
+    #[builder(default)]
+    pub transform: Matrix<4>,
+    #[builder(default, setter(strip_option))]
+    pub transform_at_t1: Option<Matrix<4>>,
+    #[builder(default)]
+    pub material: Material,
+    #[builder(default)]
+    pub excluded_lights: Vec<LightId>,
+    #[builder(default, setter(into, strip_option))]
+    pub name: Option<String>,
+}
+
+impl Default for Cube {
+    fn default() -> Self {
+        Self {
+            id: next_shape_id(),
+            transform: Matrix::identity(),
+            transform_at_t1: None,
+            material: Material::default(),
+            excluded_lights: vec![],
+            name: None,
+        }
+    }
+}
+
+impl CubeBuilder {
+    fn validate(&self) -> Result<(), String> {
+        validate_transforms(self.transform, self.transform_at_t1.flatten())
+    }
+}
+
+impl ShapeFuncs for Cube {
+    fn local_intersect(&self, object_space_ray: Ray) -> Intersections {
+        let bounds = self.bounds();
+        let Some((tmin, tmax)) = object_space_ray.aabb_interval(bounds.min, bounds.max) else {
+            return Intersections::new(vec![]);
+        };
+
+        Intersections::new(vec![
+            Intersection::new(tmin, Shape::from(self.clone()).at_time(object_space_ray.time)),
+            Intersection::new(tmax, Shape::from(self.clone()).at_time(object_space_ray.time)),
+        ])
+    }
+
+    fn local_normal_at(&self, object_point: Tuple) -> Tuple {
+        let abs_x = object_point.x.abs();
+        let abs_y = object_point.y.abs();
+        let abs_z = object_point.z.abs();
+        let maxc = abs_x.max(abs_y).max(abs_z);
+
+        if maxc == abs_x {
+            Tuple::vector(object_point.x, 0.0, 0.0)
+        } else if maxc == abs_y {
+            Tuple::vector(0.0, object_point.y, 0.0)
+        } else {
+            Tuple::vector(0.0, 0.0, object_point.z)
+        }
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn transform_at_t1(&self) -> Option<Matrix<4>> {
+        self.transform_at_t1
+    }
+
+    fn excluded_lights(&self) -> &[LightId] {
+        &self.excluded_lights
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Tuple::point(-1.0, -1.0, -1.0), Tuple::point(1.0, 1.0, 1.0))
+    }
+}
+
+impl FuzzyEq<Self> for Cube {
+    fn fuzzy_eq(&self, other: Self) -> bool {
+        self.transform.fuzzy_eq(other.transform) && self.material.fuzzy_eq(other.material)
+    }
+
+    fn fuzzy_ne(&self, other: Self) -> bool {
+        !self.fuzzy_eq(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_fuzzy_eq;
+
+    use super::*;
+
+    fn assert_intersects(origin: Tuple, direction: Tuple, t1: f64, t2: f64) {
+        let c = Cube::default();
+        let r = Ray::new(origin, direction);
+
+        let xs = c.intersect(r);
+        assert_eq!(2, xs.intersections.len());
+        assert_fuzzy_eq!(t1, xs.intersections[0].t);
+        assert_fuzzy_eq!(t2, xs.intersections[1].t);
+    }
+
+    #[test]
+    fn ray_intersects_cube_at_positive_x() {
+        assert_intersects(
+            Tuple::point(5.0, 0.5, 0.0),
+            Tuple::vector(-1.0, 0.0, 0.0),
+            4.0,
+            6.0,
+        );
+    }
+
+    #[test]
+    fn ray_intersects_cube_at_negative_x() {
+        assert_intersects(
+            Tuple::point(-5.0, 0.5, 0.0),
+            Tuple::vector(1.0, 0.0, 0.0),
+            4.0,
+            6.0,
+        );
+    }
+
+    #[test]
+    fn ray_intersects_cube_at_positive_y() {
+        assert_intersects(
+            Tuple::point(0.5, 5.0, 0.0),
+            Tuple::vector(0.0, -1.0, 0.0),
+            4.0,
+            6.0,
+        );
+    }
+
+    #[test]
+    fn ray_intersects_cube_at_negative_y() {
+        assert_intersects(
+            Tuple::point(0.5, -5.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0),
+            4.0,
+            6.0,
+        );
+    }
+
+    #[test]
+    fn ray_intersects_cube_at_positive_z() {
+        assert_intersects(
+            Tuple::point(0.5, 0.0, 5.0),
+            Tuple::vector(0.0, 0.0, -1.0),
+            4.0,
+            6.0,
+        );
+    }
+
+    #[test]
+    fn ray_intersects_cube_at_negative_z() {
+        assert_intersects(
+            Tuple::point(0.5, 0.0, -5.0),
+            Tuple::vector(0.0, 0.0, 1.0),
+            4.0,
+            6.0,
+        );
+    }
+
+    #[test]
+    fn ray_originates_inside_cube() {
+        assert_intersects(
+            Tuple::point(0.0, 0.5, 0.0),
+            Tuple::vector(0.0, 0.0, 1.0),
+            -1.0,
+            1.0,
+        );
+    }
+
+    #[test]
+    fn ray_hits_cube_on_edge_and_corner() {
+        let sqrt_2 = 2.0_f64.sqrt();
+        assert_intersects(
+            Tuple::point(-2.0, 0.0, 0.0),
+            Tuple::vector(1.0 / sqrt_2, 0.0, 1.0 / sqrt_2),
+            sqrt_2,
+            sqrt_2,
+        );
+
+        let sqrt_3 = 3.0_f64.sqrt();
+        assert_intersects(
+            Tuple::point(-2.0, -2.0, -2.0),
+            Tuple::vector(1.0 / sqrt_3, 1.0 / sqrt_3, 1.0 / sqrt_3),
+            sqrt_3,
+            3.0 * sqrt_3,
+        );
+    }
+
+    #[test]
+    fn ray_misses_cube() {
+        let c = Cube::default();
+        let rays = vec![
+            Ray::new(
+                Tuple::point(-2.0, 0.0, 0.0),
+                Tuple::vector(0.2673, 0.5345, 0.8018),
+            ),
+            Ray::new(
+                Tuple::point(0.0, -2.0, 0.0),
+                Tuple::vector(0.8018, 0.2673, 0.5345),
+            ),
+            Ray::new(
+                Tuple::point(0.0, 0.0, -2.0),
+                Tuple::vector(0.5345, 0.8018, 0.2673),
+            ),
+            Ray::new(Tuple::point(2.0, 0.0, 2.0), Tuple::vector(0.0, 0.0, -1.0)),
+            Ray::new(Tuple::point(0.0, 2.0, 2.0), Tuple::vector(0.0, -1.0, 0.0)),
+            Ray::new(Tuple::point(2.0, 2.0, 0.0), Tuple::vector(-1.0, 0.0, 0.0)),
+        ];
+
+        for r in rays {
+            let xs = c.intersect(r);
+            assert_eq!(0, xs.intersections.len());
+        }
+    }
+
+    #[test]
+    fn ray_parallel_to_face_outside_cube_misses() {
+        let c = Cube::default();
+        let r = Ray::new(Tuple::point(0.0, 2.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        let xs = c.intersect(r);
+        assert_eq!(0, xs.intersections.len());
+    }
+
+    #[test]
+    fn normal_on_surface_of_cube() {
+        let c = Cube::default();
+        let cases = vec![
+            (Tuple::point(1.0, 0.5, -0.8), Tuple::vector(1.0, 0.0, 0.0)),
+            (Tuple::point(-1.0, -0.2, 0.9), Tuple::vector(-1.0, 0.0, 0.0)),
+            (Tuple::point(-0.4, 1.0, -0.1), Tuple::vector(0.0, 1.0, 0.0)),
+            (Tuple::point(0.3, -1.0, -0.7), Tuple::vector(0.0, -1.0, 0.0)),
+            (Tuple::point(-0.6, 0.3, 1.0), Tuple::vector(0.0, 0.0, 1.0)),
+            (Tuple::point(0.4, 0.4, -1.0), Tuple::vector(0.0, 0.0, -1.0)),
+            (Tuple::point(1.0, 1.0, 1.0), Tuple::vector(1.0, 0.0, 0.0)),
+            (
+                Tuple::point(-1.0, -1.0, -1.0),
+                Tuple::vector(-1.0, 0.0, 0.0),
+            ),
+        ];
+
+        for (point, expected) in cases {
+            assert_fuzzy_eq!(expected, c.normal_at(point));
+        }
+    }
+
+    #[test]
+    fn normal_on_transformed_cube() {
+        let c = CubeBuilder::default()
+            .transform(Matrix::translation(0.0, 1.0, 0.0))
+            .build()
+            .unwrap();
+
+        let n = c.normal_at(Tuple::point(0.0, 2.0, 0.0));
+        assert_fuzzy_eq!(Tuple::vector(0.0, 1.0, 0.0), n);
+    }
+
+    #[test]
+    fn moving_cube_is_hit_and_handed_out_where_the_ray_time_puts_it() {
+        let c: Shape = CubeBuilder::default()
+            .transform_at_t1(Matrix::translation(4.0, 0.0, 0.0))
+            .build()
+            .unwrap()
+            .into();
+        let r = Ray::new(Tuple::point(4.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        assert!(c.intersect(r).intersections.is_empty());
+        let xs = c.intersect(r.at_time(1.0));
+        assert_eq!(2, xs.intersections.len());
+        assert_eq!(None, xs.intersections[0].object.transform_at_t1());
+        assert_fuzzy_eq!(Matrix::translation(4.0, 0.0, 0.0), xs.intersections[0].object.transform());
+        assert_fuzzy_eq!(
+            Tuple::vector(0.0, 0.0, -1.0),
+            xs.intersections[0].object.normal_at(Tuple::point(4.0, 0.0, -1.0))
+        );
+    }
+}
@@ -0,0 +1,462 @@
+use crate::{
+    bounding_box::BoundingBox,
+    intersection::{Intersection, Intersections},
+    light::LightId,
+    material::Material,
+    matrix::Matrix,
+    ray::Ray,
+    shape::{next_shape_id, validate_transforms, Shape, ShapeFuncs},
+    tuple::Tuple,
+    util::{FuzzyEq, EPSILON},
+};
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Builder)]
+#[builder(build_fn(validate = "Self::validate"))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cylinder {
+    #[cfg_attr(feature = "serde", serde(skip, default = "next_shape_id"))]
+    #[builder(setter(skip), default = "next_shape_id()")]
+    pub(crate) id: u64,
+    #[builder(default)]
+    pub transform: Matrix<4>,
+    #[builder(default, setter(strip_option))]
+    pub transform_at_t1: Option<Matrix<4>>,
+    #[builder(default)]
+    pub material: Material,
+    #[builder(default = "f64::NEG_INFINITY")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde_float"))]
+    pub minimum: f64,
+    #[builder(default = "f64::INFINITY")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde_float"))]
+    pub maximum: f64,
+    #[builder(default)]
+    pub closed: bool,
+    #[builder(default)]
+    pub excluded_lights: Vec<LightId>,
+    #[builder(default, setter(into, strip_option))]
+    pub name: Option<String>,
+}
+
+impl Default for Cylinder {
+    fn default() -> Self {
+        Self {
+            id: next_shape_id(),
+            transform: Matrix::identity(),
+            transform_at_t1: None,
+            material: Material::default(),
+            minimum: f64::NEG_INFINITY,
+            maximum: f64::INFINITY,
+            closed: false,
+            excluded_lights: vec![],
+            name: None,
+        }
+    }
+}
+
+impl Cylinder {
+    // Checks whether the intersection at t is within the unit radius of the caps
+    fn check_cap(ray: Ray, t: f64) -> bool {
+        let x = ray.origin.x + t * ray.direction.x;
+        let z = ray.origin.z + t * ray.direction.z;
+
+        (x.powi(2) + z.powi(2)) <= 1.0
+    }
+
+    fn intersect_caps(&self, ray: Ray, xs: &mut Vec<Intersection>) {
+        if !self.closed || ray.direction.y.abs() < EPSILON {
+            return;
+        }
+
+        let t = (self.minimum - ray.origin.y) / ray.direction.y;
+        if Self::check_cap(ray, t) {
+            xs.push(Intersection::new(t, Shape::from(self.clone()).at_time(ray.time)));
+        }
+
+        let t = (self.maximum - ray.origin.y) / ray.direction.y;
+        if Self::check_cap(ray, t) {
+            xs.push(Intersection::new(t, Shape::from(self.clone()).at_time(ray.time)));
+        }
+    }
+}
+
+impl CylinderBuilder {
+    fn validate(&self) -> Result<(), String> {
+        validate_transforms(self.transform, self.transform_at_t1.flatten())
+    }
+}
+
+impl ShapeFuncs for Cylinder {
+    fn local_intersect(&self, object_space_ray: Ray) -> Intersections {
+        let origin = object_space_ray.origin;
+        let direction = object_space_ray.direction;
+
+        let mut xs = vec![];
+        let a = direction.x.powi(2) + direction.z.powi(2);
+
+        // A ray parallel to the y axis can only hit the caps
+        if a.abs() >= EPSILON {
+            let b = 2.0 * origin.x * direction.x + 2.0 * origin.z * direction.z;
+            let c = origin.x.powi(2) + origin.z.powi(2) - 1.0;
+            let discriminant = b.powi(2) - 4.0 * a * c;
+
+            if discriminant < 0.0 {
+                return Intersections::new(vec![]);
+            }
+
+            let mut t0 = (-b - discriminant.sqrt()) / (2.0 * a);
+            let mut t1 = (-b + discriminant.sqrt()) / (2.0 * a);
+            if t0 > t1 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            for t in [t0, t1] {
+                let y = origin.y + t * direction.y;
+                if self.minimum < y && y < self.maximum {
+                    xs.push(Intersection::new(t, Shape::from(self.clone()).at_time(object_space_ray.time)));
+                }
+            }
+        }
+
+        self.intersect_caps(object_space_ray, &mut xs);
+
+        Intersections::new(xs)
+    }
+
+    fn local_normal_at(&self, object_point: Tuple) -> Tuple {
+        let dist = object_point.x.powi(2) + object_point.z.powi(2);
+
+        if dist < 1.0 && object_point.y >= self.maximum - EPSILON {
+            Tuple::vector(0.0, 1.0, 0.0)
+        } else if dist < 1.0 && object_point.y <= self.minimum + EPSILON {
+            Tuple::vector(0.0, -1.0, 0.0)
+        } else {
+            Tuple::vector(object_point.x, 0.0, object_point.z)
+        }
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn transform_at_t1(&self) -> Option<Matrix<4>> {
+        self.transform_at_t1
+    }
+
+    fn excluded_lights(&self) -> &[LightId] {
+        &self.excluded_lights
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Tuple::point(-1.0, self.minimum, -1.0),
+            Tuple::point(1.0, self.maximum, 1.0),
+        )
+    }
+}
+
+impl FuzzyEq<Self> for Cylinder {
+    fn fuzzy_eq(&self, other: Self) -> bool {
+        self.transform.fuzzy_eq(other.transform)
+            && self.material.fuzzy_eq(other.material)
+            && self.minimum == other.minimum
+            && self.maximum == other.maximum
+            && self.closed == other.closed
+    }
+
+    fn fuzzy_ne(&self, other: Self) -> bool {
+        !self.fuzzy_eq(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_fuzzy_eq;
+
+    use super::*;
+
+    #[test]
+    fn ray_misses_cylinder() {
+        let cyl = Cylinder::default();
+        let cases = vec![
+            (Tuple::point(1.0, 0.0, 0.0), Tuple::vector(0.0, 1.0, 0.0)),
+            (Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 1.0, 0.0)),
+            (Tuple::point(0.0, 0.0, -5.0), Tuple::vector(1.0, 1.0, 1.0)),
+        ];
+
+        for (origin, direction) in cases {
+            let r = Ray::new(origin, direction.normalize());
+            let xs = cyl.intersect(r);
+            assert_eq!(0, xs.intersections.len());
+        }
+    }
+
+    #[test]
+    fn ray_parallel_to_y_axis_outside_cylinder_misses() {
+        let cyl = Cylinder::default();
+        let r = Ray::new(Tuple::point(2.0, -5.0, 0.0), Tuple::vector(0.0, 1.0, 0.0));
+
+        let xs = cyl.intersect(r);
+        assert_eq!(0, xs.intersections.len());
+    }
+
+    #[test]
+    fn ray_strikes_cylinder() {
+        let cyl = Cylinder::default();
+        let cases = vec![
+            (
+                Tuple::point(1.0, 0.0, -5.0),
+                Tuple::vector(0.0, 0.0, 1.0),
+                5.0,
+                5.0,
+            ),
+            (
+                Tuple::point(0.0, 0.0, -5.0),
+                Tuple::vector(0.0, 0.0, 1.0),
+                4.0,
+                6.0,
+            ),
+            (
+                Tuple::point(0.5, 0.0, -5.0),
+                Tuple::vector(0.1, 1.0, 1.0),
+                6.80798,
+                7.08872,
+            ),
+        ];
+
+        for (origin, direction, t0, t1) in cases {
+            let r = Ray::new(origin, direction.normalize());
+            let xs = cyl.intersect(r);
+            assert_eq!(2, xs.intersections.len());
+            assert_fuzzy_eq!(t0, xs.intersections[0].t);
+            assert_fuzzy_eq!(t1, xs.intersections[1].t);
+        }
+    }
+
+    #[test]
+    fn normal_vector_on_cylinder() {
+        let cyl = Cylinder::default();
+        let cases = vec![
+            (Tuple::point(1.0, 0.0, 0.0), Tuple::vector(1.0, 0.0, 0.0)),
+            (Tuple::point(0.0, 5.0, -1.0), Tuple::vector(0.0, 0.0, -1.0)),
+            (Tuple::point(0.0, -2.0, 1.0), Tuple::vector(0.0, 0.0, 1.0)),
+            (Tuple::point(-1.0, 1.0, 0.0), Tuple::vector(-1.0, 0.0, 0.0)),
+        ];
+
+        for (point, expected) in cases {
+            assert_fuzzy_eq!(expected, cyl.normal_at(point));
+        }
+    }
+
+    #[test]
+    fn default_minimum_and_maximum_for_cylinder() {
+        let cyl = Cylinder::default();
+        assert_eq!(f64::NEG_INFINITY, cyl.minimum);
+        assert_eq!(f64::INFINITY, cyl.maximum);
+        assert!(!cyl.closed);
+
+        let built = CylinderBuilder::default().build().unwrap();
+        assert_eq!(
+            Cylinder {
+                id: built.id,
+                ..cyl
+            },
+            built
+        );
+    }
+
+    #[test]
+    fn intersecting_constrained_cylinder() {
+        let cyl = CylinderBuilder::default()
+            .minimum(1.0)
+            .maximum(2.0)
+            .build()
+            .unwrap();
+        let cases = vec![
+            (Tuple::point(0.0, 1.5, 0.0), Tuple::vector(0.1, 1.0, 0.0), 0),
+            (
+                Tuple::point(0.0, 3.0, -5.0),
+                Tuple::vector(0.0, 0.0, 1.0),
+                0,
+            ),
+            (
+                Tuple::point(0.0, 0.0, -5.0),
+                Tuple::vector(0.0, 0.0, 1.0),
+                0,
+            ),
+            (
+                Tuple::point(0.0, 2.0, -5.0),
+                Tuple::vector(0.0, 0.0, 1.0),
+                0,
+            ),
+            (
+                Tuple::point(0.0, 1.0, -5.0),
+                Tuple::vector(0.0, 0.0, 1.0),
+                0,
+            ),
+            (
+                Tuple::point(0.0, 1.5, -2.0),
+                Tuple::vector(0.0, 0.0, 1.0),
+                2,
+            ),
+        ];
+
+        for (origin, direction, count) in cases {
+            let r = Ray::new(origin, direction.normalize());
+            let xs = cyl.intersect(r);
+            assert_eq!(count, xs.intersections.len());
+        }
+    }
+
+    #[test]
+    fn truncation_cuts_off_both_ends() {
+        let cyl = CylinderBuilder::default()
+            .minimum(-1.0)
+            .maximum(1.0)
+            .build()
+            .unwrap();
+
+        // Would hit the infinite cylinder both below and above the truncation
+        let r = Ray::new(
+            Tuple::point(0.0, -10.0, -5.0),
+            Tuple::vector(0.0, 2.0, 1.0).normalize(),
+        );
+        let xs = cyl.intersect(r);
+        assert_eq!(0, xs.intersections.len());
+
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = cyl.intersect(r);
+        assert_eq!(2, xs.intersections.len());
+    }
+
+    #[test]
+    fn intersecting_caps_of_closed_cylinder() {
+        let cyl = CylinderBuilder::default()
+            .minimum(1.0)
+            .maximum(2.0)
+            .closed(true)
+            .build()
+            .unwrap();
+        let cases = vec![
+            (
+                Tuple::point(0.0, 3.0, 0.0),
+                Tuple::vector(0.0, -1.0, 0.0),
+                2,
+            ),
+            (
+                Tuple::point(0.0, 3.0, -2.0),
+                Tuple::vector(0.0, -1.0, 2.0),
+                2,
+            ),
+            (
+                Tuple::point(0.0, 4.0, -2.0),
+                Tuple::vector(0.0, -1.0, 1.0),
+                2,
+            ),
+            (
+                Tuple::point(0.0, 0.0, -2.0),
+                Tuple::vector(0.0, 1.0, 2.0),
+                2,
+            ),
+            (
+                Tuple::point(0.0, -1.0, -2.0),
+                Tuple::vector(0.0, 1.0, 1.0),
+                2,
+            ),
+        ];
+
+        for (origin, direction, count) in cases {
+            let r = Ray::new(origin, direction.normalize());
+            let xs = cyl.intersect(r);
+            assert_eq!(count, xs.intersections.len());
+        }
+    }
+
+    #[test]
+    fn ray_enters_through_cap_and_exits_through_wall() {
+        let cyl = CylinderBuilder::default()
+            .minimum(1.0)
+            .maximum(2.0)
+            .closed(true)
+            .build()
+            .unwrap();
+        let r = Ray::new(
+            Tuple::point(0.0, 3.0, -2.0),
+            Tuple::vector(0.0, -1.0, 2.0).normalize(),
+        );
+
+        let xs = cyl.intersect(r);
+        assert_eq!(2, xs.intersections.len());
+
+        let entry = r.position(xs.intersections[0].t);
+        let exit = r.position(xs.intersections[1].t);
+        assert_fuzzy_eq!(2.0, entry.y);
+        assert_fuzzy_eq!(Tuple::vector(0.0, 1.0, 0.0), cyl.normal_at(entry));
+        assert_fuzzy_eq!(1.0, exit.z);
+        assert_fuzzy_eq!(Tuple::vector(0.0, 0.0, 1.0), cyl.normal_at(exit));
+    }
+
+    #[test]
+    fn normal_vector_on_cylinder_end_caps() {
+        let cyl = CylinderBuilder::default()
+            .minimum(1.0)
+            .maximum(2.0)
+            .closed(true)
+            .build()
+            .unwrap();
+        let cases = vec![
+            (Tuple::point(0.0, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0)),
+            (Tuple::point(0.5, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0)),
+            (Tuple::point(0.0, 1.0, 0.5), Tuple::vector(0.0, -1.0, 0.0)),
+            (Tuple::point(0.0, 2.0, 0.0), Tuple::vector(0.0, 1.0, 0.0)),
+            (Tuple::point(0.5, 2.0, 0.0), Tuple::vector(0.0, 1.0, 0.0)),
+            (Tuple::point(0.0, 2.0, 0.5), Tuple::vector(0.0, 1.0, 0.0)),
+        ];
+
+        for (point, expected) in cases {
+            assert_fuzzy_eq!(expected, cyl.normal_at(point));
+        }
+    }
+
+    #[test]
+    fn cylinder_builder_sets_transform() {
+        let t = Matrix::scaling(2.0, 1.0, 2.0);
+        let cyl = CylinderBuilder::default().transform(t).build().unwrap();
+
+        assert_fuzzy_eq!(t, cyl.transform);
+        assert_fuzzy_eq!(
+            Tuple::vector(1.0, 0.0, 0.0),
+            cyl.normal_at(Tuple::point(2.0, 0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn bounded_cylinder_has_bounding_box() {
+        let cyl = CylinderBuilder::default()
+            .minimum(-5.0)
+            .maximum(3.0)
+            .build()
+            .unwrap();
+        let b = cyl.bounds();
+
+        assert_fuzzy_eq!(Tuple::point(-1.0, -5.0, -1.0), b.min);
+        assert_fuzzy_eq!(Tuple::point(1.0, 3.0, 1.0), b.max);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn infinite_cylinder_survives_a_json_round_trip() {
+        let c = Cylinder::default();
+        let read: Cylinder = serde_json::from_str(&serde_json::to_string(&c).unwrap()).unwrap();
+
+        assert_eq!(f64::NEG_INFINITY, read.minimum);
+        assert_eq!(f64::INFINITY, read.maximum);
+    }
+}
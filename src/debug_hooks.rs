@@ -0,0 +1,114 @@
+use std::{cmp::Ordering, fmt, sync::Arc};
+
+use crate::{color::Color, intersection::ComputedIntersection, ray::Ray};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RayKind {
+    // Straight into the world through World::color_at, which is where the camera's rays go
+    Primary,
+    Shadow,
+    Reflected,
+    Refracted,
+}
+
+type RayHook = Box<dyn Fn(&Ray, RayKind) + Send + Sync>;
+type HitHook = Box<dyn Fn(&ComputedIntersection) + Send + Sync>;
+type ShadeHook = Box<dyn Fn(&Color) + Send + Sync>;
+
+// Callbacks for following what a world does with the rays sent into it. They're called from the
+// render threads, so anything they record has to be shared safely. on_hit sees the hit of every
+// traced ray and on_shade the color that ray came back with, background and fog included
+#[derive(Default)]
+pub struct DebugHooks {
+    pub on_ray: Option<RayHook>,
+    pub on_hit: Option<HitHook>,
+    pub on_shade: Option<ShadeHook>,
+}
+
+impl DebugHooks {
+    pub fn on_ray(self, hook: impl Fn(&Ray, RayKind) + Send + Sync + 'static) -> Self {
+        Self {
+            on_ray: Some(Box::new(hook)),
+            ..self
+        }
+    }
+
+    pub fn on_hit(self, hook: impl Fn(&ComputedIntersection) + Send + Sync + 'static) -> Self {
+        Self {
+            on_hit: Some(Box::new(hook)),
+            ..self
+        }
+    }
+
+    pub fn on_shade(self, hook: impl Fn(&Color) + Send + Sync + 'static) -> Self {
+        Self {
+            on_shade: Some(Box::new(hook)),
+            ..self
+        }
+    }
+}
+
+impl fmt::Debug for DebugHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DebugHooks")
+            .field("on_ray", &self.on_ray.is_some())
+            .field("on_hit", &self.on_hit.is_some())
+            .field("on_shade", &self.on_shade.is_some())
+            .finish()
+    }
+}
+
+// Where a world keeps its hooks, shared by its copies. It takes no part in comparing worlds
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HookSlot(Option<Arc<DebugHooks>>);
+
+impl HookSlot {
+    pub(crate) fn new(hooks: DebugHooks) -> Self {
+        Self(Some(Arc::new(hooks)))
+    }
+
+    pub(crate) fn ray(&self, ray: &Ray, kind: RayKind) {
+        if let Some(hook) = self.0.as_ref().and_then(|h| h.on_ray.as_ref()) {
+            hook(ray, kind);
+        }
+    }
+
+    pub(crate) fn hit(&self, comp: &ComputedIntersection) {
+        if let Some(hook) = self.0.as_ref().and_then(|h| h.on_hit.as_ref()) {
+            hook(comp);
+        }
+    }
+
+    pub(crate) fn shade(&self, color: &Color) {
+        if let Some(hook) = self.0.as_ref().and_then(|h| h.on_shade.as_ref()) {
+            hook(color);
+        }
+    }
+}
+
+impl PartialEq for HookSlot {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl PartialOrd for HookSlot {
+    fn partial_cmp(&self, _other: &Self) -> Option<Ordering> {
+        Some(Ordering::Equal)
+    }
+}
+
+// Hooks that count the rays of a kind, for tests of which rays get skipped
+#[cfg(test)]
+pub(crate) fn counting_rays(kind: RayKind) -> (DebugHooks, Arc<std::sync::atomic::AtomicUsize>) {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let counter = count.clone();
+    let hooks = DebugHooks::default().on_ray(move |_, k| {
+        if k == kind {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+    (hooks, count)
+}
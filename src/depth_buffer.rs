@@ -0,0 +1,166 @@
+use crate::{canvas::Canvas, color::Color, error::Error, pgm::ToPGM, two_dimensional::TwoDimensional};
+
+// Distance to the first hit of every pixel, infinite where the ray hit nothing
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepthBuffer {
+    pub width: usize,
+    pub height: usize,
+    pub depths: Vec<f64>,
+}
+
+impl DepthBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            depths: vec![f64::INFINITY; width * height],
+        }
+    }
+
+    pub fn depth_at(&self, x: usize, y: usize) -> f64 {
+        self.try_depth_at(x, y).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn write_depth(&mut self, x: usize, y: usize, depth: f64) {
+        self.try_write_depth(x, y, depth).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_depth_at(&self, x: usize, y: usize) -> Result<f64, Error> {
+        Ok(self.depths[self.checked_index(x, y)?])
+    }
+
+    pub fn try_write_depth(&mut self, x: usize, y: usize, depth: f64) -> Result<(), Error> {
+        let index = self.checked_index(x, y)?;
+        self.depths[index] = depth;
+        Ok(())
+    }
+
+    // The nearest and farthest hit, or None when every pixel missed
+    pub fn range(&self) -> Option<(f64, f64)> {
+        self.depths
+            .iter()
+            .filter(|d| d.is_finite())
+            .fold(None, |range, &d| match range {
+                None => Some((d, d)),
+                Some((min, max)) => Some((min.min(d), max.max(d))),
+            })
+    }
+
+    // Hits scaled from 0 at the nearest to 1 at the farthest, misses stay infinite. When every
+    // hit is as far away they all become 0
+    pub fn normalized(&self) -> Self {
+        let (min, max) = self.range().unwrap_or((0.0, 0.0));
+        let span = if max > min { max - min } else { 1.0 };
+
+        Self {
+            depths: self
+                .depths
+                .iter()
+                .map(|&d| if d.is_finite() { (d - min) / span } else { d })
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    // Grayscale with the nearest hit white, fading to black at the farthest. Misses are black
+    pub fn to_canvas(&self) -> Canvas {
+        Canvas {
+            width: self.width,
+            height: self.height,
+            pixels: self.gray_values().into_iter().map(Color::grey).collect(),
+        }
+    }
+}
+
+// The same grays as to_canvas
+impl ToPGM for DepthBuffer {
+    fn gray_values(&self) -> Vec<f64> {
+        self.normalized().depths.iter().map(|&d| if d.is_finite() { 1.0 - d } else { 0.0 }).collect()
+    }
+}
+
+impl TwoDimensional for DepthBuffer {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(depths: &[f64]) -> DepthBuffer {
+        DepthBuffer {
+            width: depths.len(),
+            height: 1,
+            depths: depths.to_vec(),
+        }
+    }
+
+    #[test]
+    fn new_buffer_has_no_hits() {
+        let b = DepthBuffer::new(3, 2);
+
+        assert!(b.depths.iter().all(|d| d.is_infinite()));
+        assert_eq!(None, b.range());
+    }
+
+    #[test]
+    fn range_skips_misses() {
+        let b = buffer(&[4.0, f64::INFINITY, 2.0, 6.0]);
+
+        assert_eq!(Some((2.0, 6.0)), b.range());
+    }
+
+    #[test]
+    fn normalizing_scales_hits_between_nearest_and_farthest() {
+        let b = buffer(&[4.0, f64::INFINITY, 2.0, 6.0]).normalized();
+
+        assert_eq!(vec![0.5, f64::INFINITY, 0.0, 1.0], b.depths);
+    }
+
+    #[test]
+    fn equally_far_hits_normalize_to_zero() {
+        let b = buffer(&[3.0, 3.0]).normalized();
+
+        assert_eq!(vec![0.0, 0.0], b.depths);
+    }
+
+    #[test]
+    fn canvas_shows_near_hits_bright_and_misses_black() {
+        let c = buffer(&[4.0, f64::INFINITY, 2.0, 6.0]).to_canvas();
+
+        assert_eq!(Color::grey(0.5), c.pixel_at(0, 0));
+        assert_eq!(Color::black(), c.pixel_at(1, 0));
+        assert_eq!(Color::white(), c.pixel_at(2, 0));
+        assert_eq!(Color::grey(0.0), c.pixel_at(3, 0));
+    }
+
+    #[test]
+    fn pgm_shows_depths_like_the_canvas() {
+        let b = buffer(&[4.0, f64::INFINITY, 2.0, 6.0]);
+
+        assert_eq!(b"P2\n4 1\n255\n128 0 255 0\n".to_vec(), b.to_pgm());
+    }
+
+    #[test]
+    fn depth_past_the_edge_is_an_error() {
+        let mut b = DepthBuffer::new(3, 2);
+        let outside = Error::PixelOutOfBounds { x: 3, y: 0, width: 3, height: 2 };
+
+        assert_eq!(Err(outside.clone()), b.try_depth_at(3, 0));
+        assert_eq!(Err(outside), b.try_write_depth(3, 0, 1.0));
+        assert!(b.try_write_depth(0, 1, 1.0).is_ok());
+        assert_eq!(vec![f64::INFINITY; 3], b.depths[..3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "pixel (0, 2) is outside the 3x2 canvas")]
+    fn reading_a_depth_past_the_bottom_panics() {
+        DepthBuffer::new(3, 2).depth_at(0, 2);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{
+    bounding_box::BoundingBox,
+    intersection::{Intersection, Intersections},
+    light::LightId,
+    material::Material,
+    matrix::Matrix,
+    ray::Ray,
+    shape::{next_shape_id, validate_transforms, Shape, ShapeFuncs},
+    tuple::Tuple,
+    util::{FuzzyEq, EPSILON},
+};
+
+// A flat disk in the xz plane, optionally with a hole to form an annulus
+#[derive(Debug, Clone, PartialEq, PartialOrd, Builder)]
+#[builder(build_fn(validate = "Self::validate"))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Disk {
+    #[cfg_attr(feature = "serde", serde(skip, default = "next_shape_id"))]
+    #[builder(setter(skip), default = "next_shape_id()")]
+    pub(crate) id: u64,
+    #[builder(default)]
+    pub transform: Matrix<4>,
+    #[builder(default, setter(strip_option))]
+    pub transform_at_t1: Option<Matrix<4>>,
+    #[builder(default)]
+    pub material: Material,
+    #[builder(default = "1.0")]
+    pub radius: f64,
+    #[builder(default = "0.0")]
+    pub inner_radius: f64,
+    #[builder(default)]
+    pub excluded_lights: Vec<LightId>,
+    #[builder(default, setter(into, strip_option))]
+    pub name: Option<String>,
+}
+
+impl Default for Disk {
+    fn default() -> Self {
+        Self {
+            id: next_shape_id(),
+            transform: Matrix::identity(),
+            transform_at_t1: None,
+            material: Material::default(),
+            radius: 1.0,
+            inner_radius: 0.0,
+            excluded_lights: vec![],
+            name: None,
+        }
+    }
+}
+
+impl DiskBuilder {
+    fn validate(&self) -> Result<(), String> {
+        validate_transforms(self.transform, self.transform_at_t1.flatten())
+    }
+}
+
+impl ShapeFuncs for Disk {
+    fn local_intersect(&self, object_space_ray: Ray) -> Intersections {
+        if object_space_ray.direction.y.abs() < EPSILON {
+            return Intersections::new(vec![]);
+        }
+
+        let t = -object_space_ray.origin.y / object_space_ray.direction.y;
+        let point = object_space_ray.position(t);
+        let dist_sq = point.x.powi(2) + point.z.powi(2);
+
+        if dist_sq < self.inner_radius.powi(2) || dist_sq > self.radius.powi(2) {
+            return Intersections::new(vec![]);
+        }
+
+        Intersections::new(vec![Intersection::new(t, Shape::from(self.clone()).at_time(object_space_ray.time))])
+    }
+
+    fn local_normal_at(&self, _object_point: Tuple) -> Tuple {
+        Tuple::vector(0.0, 1.0, 0.0)
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn transform_at_t1(&self) -> Option<Matrix<4>> {
+        self.transform_at_t1
+    }
+
+    fn excluded_lights(&self) -> &[LightId] {
+        &self.excluded_lights
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Tuple::point(-self.radius, 0.0, -self.radius),
+            Tuple::point(self.radius, 0.0, self.radius),
+        )
+    }
+}
+
+impl FuzzyEq<Self> for Disk {
+    fn fuzzy_eq(&self, other: Self) -> bool {
+        self.transform.fuzzy_eq(other.transform)
+            && self.material.fuzzy_eq(other.material)
+            && self.radius.fuzzy_eq(other.radius)
+            && self.inner_radius.fuzzy_eq(other.inner_radius)
+    }
+
+    fn fuzzy_ne(&self, other: Self) -> bool {
+        !self.fuzzy_eq(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_fuzzy_eq;
+
+    use super::*;
+
+    #[test]
+    fn disk_builder_defaults() {
+        let d = DiskBuilder::default().build().unwrap();
+
+        assert_fuzzy_eq!(Disk::default(), d.clone());
+        assert_fuzzy_eq!(1.0, d.radius);
+        assert_fuzzy_eq!(0.0, d.inner_radius);
+    }
+
+    #[test]
+    fn ray_hits_disk_inside_radius() {
+        let d = Disk::default();
+        let r = Ray::new(Tuple::point(0.5, 1.0, 0.5), Tuple::vector(0.0, -1.0, 0.0));
+
+        let xs = d.intersect(r);
+        assert_eq!(1, xs.intersections.len());
+        assert_fuzzy_eq!(1.0, xs.intersections[0].t);
+    }
+
+    #[test]
+    fn ray_misses_disk_outside_radius() {
+        let d = Disk::default();
+        let r = Ray::new(Tuple::point(1.5, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+
+        let xs = d.intersect(r);
+        assert_eq!(0, xs.intersections.len());
+    }
+
+    #[test]
+    fn ray_passes_through_hole_of_annulus() {
+        let d = DiskBuilder::default()
+            .radius(2.0)
+            .inner_radius(1.0)
+            .build()
+            .unwrap();
+
+        let r = Ray::new(Tuple::point(0.5, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        let xs = d.intersect(r);
+        assert_eq!(0, xs.intersections.len());
+
+        let r = Ray::new(Tuple::point(1.5, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        let xs = d.intersect(r);
+        assert_eq!(1, xs.intersections.len());
+    }
+
+    #[test]
+    fn ray_hits_transformed_disk() {
+        let d = DiskBuilder::default()
+            .transform(Matrix::translation(0.0, 2.0, 0.0) * Matrix::scaling(3.0, 3.0, 3.0))
+            .build()
+            .unwrap();
+        let r = Ray::new(Tuple::point(2.5, 5.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+
+        let xs = d.intersect(r);
+        assert_eq!(1, xs.intersections.len());
+        assert_fuzzy_eq!(3.0, xs.intersections[0].t);
+        assert_fuzzy_eq!(
+            Tuple::vector(0.0, 1.0, 0.0),
+            d.normal_at(r.position(xs.intersections[0].t))
+        );
+    }
+}
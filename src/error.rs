@@ -0,0 +1,81 @@
+use std::fmt;
+
+use crate::{matrix::Matrix, tuple::Tuple};
+
+// What can go wrong putting a scene together, carrying the values that caused it. The panicking
+// constructors report these same messages. The matrix is boxed to keep results on the pixel
+// paths small
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    NonInvertibleTransform {
+        matrix: Box<Matrix<4>>,
+    },
+    InvalidRay {
+        origin: Tuple,
+        direction: Tuple,
+    },
+    PixelOutOfBounds {
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    },
+    // A mesh face pointing past the end of the vertices (or the normals, when there are fewer of
+    // those), or past the end of the materials
+    FaceIndexOutOfBounds {
+        face: usize,
+        index: usize,
+        len: usize,
+    },
+    FaceMaterialOutOfBounds {
+        face: usize,
+        material: usize,
+        len: usize,
+    },
+    InvalidConvergence {
+        distance: f64,
+    },
+    InvalidEyeSeparation {
+        separation: f64,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonInvertibleTransform { matrix } => {
+                write!(f, "transform is not invertible: {:?}", matrix)
+            }
+            Self::InvalidRay { origin, direction } => write!(
+                f,
+                "a ray needs a point origin and a vector direction, got {:?} and {:?}",
+                origin, direction
+            ),
+            Self::PixelOutOfBounds { x, y, width, height } => write!(
+                f,
+                "pixel ({}, {}) is outside the {}x{} canvas",
+                x, y, width, height
+            ),
+            Self::FaceIndexOutOfBounds { face, index, len } => write!(
+                f,
+                "face {} uses vertex {}, but the mesh only has {}",
+                face, index, len
+            ),
+            Self::FaceMaterialOutOfBounds { face, material, len } => write!(
+                f,
+                "face {} uses material {}, but the mesh only has {}",
+                face, material, len
+            ),
+            Self::InvalidConvergence { distance } => write!(
+                f,
+                "the eyes need to converge a positive distance ahead, got {}",
+                distance
+            ),
+            Self::InvalidEyeSeparation { separation } => {
+                write!(f, "the eyes need a finite distance between them, got {}", separation)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
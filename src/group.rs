@@ -0,0 +1,471 @@
+use std::{cmp::Ordering, sync::OnceLock};
+
+use crate::{
+    bounding_box::BoundingBox,
+    intersection::Intersections,
+    light::LightId,
+    material::Material,
+    matrix::Matrix,
+    ray::Ray,
+    shape::{next_shape_id, validate_transforms, Shape, ShapeFuncs},
+    tuple::Tuple,
+    util::FuzzyEq,
+};
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Builder)]
+#[builder(build_fn(validate = "Self::validate"))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Group {
+    #[cfg_attr(feature = "serde", serde(skip, default = "next_shape_id"))]
+    #[builder(setter(skip), default = "next_shape_id()")]
+    pub(crate) id: u64,
+    #[builder(default)]
+    pub transform: Matrix<4>,
+    #[builder(default, setter(strip_option))]
+    pub transform_at_t1: Option<Matrix<4>>,
+    // Set through add_child and the other methods, which keep the cached bounds up to date
+    #[builder(default)]
+    children: Vec<Shape>,
+    #[builder(setter(skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    bounds_cache: BoundsCache,
+    #[builder(default)]
+    pub excluded_lights: Vec<LightId>,
+    #[builder(default, setter(into, strip_option))]
+    pub name: Option<String>,
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Self {
+            id: next_shape_id(),
+            transform: Matrix::identity(),
+            transform_at_t1: None,
+            children: vec![],
+            bounds_cache: BoundsCache::default(),
+            excluded_lights: vec![],
+            name: None,
+        }
+    }
+}
+
+impl Group {
+    pub fn add_child(&mut self, child: impl Into<Shape>) {
+        self.children_changed();
+        self.children.push(child.into());
+    }
+
+    pub fn children(&self) -> &[Shape] {
+        &self.children
+    }
+
+    pub fn child_mut(&mut self, idx: usize) -> Option<&mut Shape> {
+        self.children_changed();
+        self.children.get_mut(idx)
+    }
+
+    // Every child after the removed one moves down an index
+    pub fn remove_child(&mut self, idx: usize) -> Option<Shape> {
+        if idx >= self.children.len() {
+            return None;
+        }
+
+        self.children_changed();
+        Some(self.children.remove(idx))
+    }
+
+    pub fn into_children(self) -> Vec<Shape> {
+        self.children
+    }
+
+    // Has the next call to bounds work them out again
+    fn children_changed(&mut self) {
+        self.bounds_cache = BoundsCache::default();
+    }
+}
+
+// Lazily computed bounds of the children, it takes no part in comparing groups
+#[derive(Debug, Clone, Default)]
+struct BoundsCache(OnceLock<BoundingBox>);
+
+impl PartialEq for BoundsCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl PartialOrd for BoundsCache {
+    fn partial_cmp(&self, _other: &Self) -> Option<Ordering> {
+        Some(Ordering::Equal)
+    }
+}
+
+impl GroupBuilder {
+    fn validate(&self) -> Result<(), String> {
+        validate_transforms(self.transform, self.transform_at_t1.flatten())
+    }
+}
+
+impl ShapeFuncs for Group {
+    fn local_intersect(&self, group_space_ray: Ray) -> Intersections {
+        if !self.bounds().intersects(group_space_ray) {
+            return Intersections::new(vec![]);
+        }
+
+        // The intersected children are handed out in world space by baking this group's
+        // transform into theirs, so normals are resolved through the whole parent chain. The
+        // lights the group excludes are left out for everything in it the same way
+        let xs = self
+            .children
+            .iter()
+            .flat_map(|c| c.intersect(group_space_ray))
+            .map(|mut i| {
+                i.object
+                    .set_transform(self.transform_at(group_space_ray.time) * i.object.transform());
+                i.object.exclude_lights(&self.excluded_lights);
+                i
+            })
+            .collect();
+
+        Intersections::new(xs)
+    }
+
+    fn local_normal_at(&self, _object_point: Tuple) -> Tuple {
+        panic!("Groups have no surface, but normal_at was called on a group!")
+    }
+
+    fn material(&self) -> Material {
+        Material::default()
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn transform_at_t1(&self) -> Option<Matrix<4>> {
+        self.transform_at_t1
+    }
+
+    fn excluded_lights(&self) -> &[LightId] {
+        &self.excluded_lights
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        *self.bounds_cache.0.get_or_init(|| {
+            let mut bounds = BoundingBox::default();
+            for child in &self.children {
+                bounds.merge(child.parent_space_bounds());
+            }
+            bounds
+        })
+    }
+}
+
+impl FuzzyEq<Self> for Group {
+    fn fuzzy_eq(&self, other: Self) -> bool {
+        self.transform.fuzzy_eq(other.transform)
+            && self.children.len() == other.children.len()
+            && self
+                .children
+                .iter()
+                .zip(other.children)
+                .all(|(a, b)| a.fuzzy_eq(b))
+    }
+
+    fn fuzzy_ne(&self, other: Self) -> bool {
+        !self.fuzzy_eq(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::{
+        assert_fuzzy_eq, camera::Camera, color::Color, light::Light, sphere::SphereBuilder,
+        test_shape::TestShape, triangle::Triangle, world::World,
+    };
+
+    use super::*;
+
+    #[test]
+    fn creating_a_new_group() {
+        let g = Group::default();
+
+        assert_fuzzy_eq!(Matrix::identity(), g.transform);
+        assert!(g.children().is_empty());
+    }
+
+    #[test]
+    fn adding_a_child_to_a_group() {
+        let mut g = Group::default();
+        let s: Shape = SphereBuilder::default().build().unwrap().into();
+        g.add_child(s.clone());
+
+        assert_eq!(1, g.children().len());
+        assert_eq!(s, g.children()[0]);
+    }
+
+    #[test]
+    fn intersecting_ray_with_empty_group() {
+        let g = Group::default();
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        let xs = g.intersect(r);
+        assert!(xs.intersections.is_empty());
+    }
+
+    #[test]
+    fn intersecting_ray_with_nonempty_group() {
+        let s1: Shape = SphereBuilder::default().build().unwrap().into();
+        let s2: Shape = SphereBuilder::default()
+            .transform(Matrix::translation(0.0, 0.0, -3.0))
+            .build()
+            .unwrap()
+            .into();
+        let s3: Shape = SphereBuilder::default()
+            .transform(Matrix::translation(5.0, 0.0, 0.0))
+            .build()
+            .unwrap()
+            .into();
+        let g = GroupBuilder::default()
+            .children(vec![s1.clone(), s2.clone(), s3])
+            .build()
+            .unwrap();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        let xs = g.intersect(r);
+        assert_eq!(4, xs.intersections.len());
+        assert_eq!(s2, xs.intersections[0].object);
+        assert_eq!(s2, xs.intersections[1].object);
+        assert_eq!(s1, xs.intersections[2].object);
+        assert_eq!(s1, xs.intersections[3].object);
+    }
+
+    #[test]
+    fn intersecting_transformed_group() {
+        let s = SphereBuilder::default()
+            .transform(Matrix::translation(5.0, 0.0, 0.0))
+            .build()
+            .unwrap();
+        let g = GroupBuilder::default()
+            .transform(Matrix::scaling(2.0, 2.0, 2.0))
+            .children(vec![s.into()])
+            .build()
+            .unwrap();
+        let r = Ray::new(Tuple::point(10.0, 0.0, -10.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        let xs = g.intersect(r);
+        assert_eq!(2, xs.intersections.len());
+    }
+
+    #[test]
+    fn converting_point_from_world_to_object_space() {
+        let s = SphereBuilder::default()
+            .transform(Matrix::translation(5.0, 0.0, 0.0))
+            .build()
+            .unwrap();
+        let g2 = GroupBuilder::default()
+            .transform(Matrix::scaling(2.0, 2.0, 2.0))
+            .children(vec![s.into()])
+            .build()
+            .unwrap();
+        let g1 = GroupBuilder::default()
+            .transform(Matrix::rotation_y(PI / 2.0))
+            .children(vec![g2.into()])
+            .build()
+            .unwrap();
+        let r = Ray::new(Tuple::point(-2.0, 0.0, -20.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        let xs = g1.intersect(r);
+        let object = &xs.intersections[0].object;
+        let p = object.world_point_to_object_point(Tuple::point(-2.0, 0.0, -10.0));
+
+        assert_fuzzy_eq!(Tuple::point(0.0, 0.0, -1.0), p);
+    }
+
+    #[test]
+    fn finding_normal_on_child_object() {
+        let s = SphereBuilder::default()
+            .transform(Matrix::translation(5.0, 0.0, 0.0))
+            .build()
+            .unwrap();
+        let g2 = GroupBuilder::default()
+            .transform(Matrix::scaling(1.0, 2.0, 3.0))
+            .children(vec![s.into()])
+            .build()
+            .unwrap();
+        let g1 = GroupBuilder::default()
+            .transform(Matrix::rotation_y(PI / 2.0))
+            .children(vec![g2.into()])
+            .build()
+            .unwrap();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -20.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        let xs = g1.intersect(r);
+        let object = &xs.intersections[0].object;
+        let n = object.normal_at(Tuple::point(1.7321, 1.1547, -5.5774));
+
+        assert_fuzzy_eq!(Tuple::vector(0.28570, 0.42854, -0.85716), n);
+    }
+
+    #[test]
+    fn group_has_bounding_box_containing_its_children() {
+        let s = SphereBuilder::default()
+            .transform(Matrix::translation(2.0, 5.0, -3.0) * Matrix::scaling(2.0, 2.0, 2.0))
+            .build()
+            .unwrap();
+        let c = crate::cylinder::CylinderBuilder::default()
+            .minimum(-2.0)
+            .maximum(2.0)
+            .transform(Matrix::translation(-4.0, -1.0, 4.0) * Matrix::scaling(0.5, 1.0, 0.5))
+            .build()
+            .unwrap();
+        let g = GroupBuilder::default()
+            .children(vec![s.into(), c.into()])
+            .build()
+            .unwrap();
+        let b = g.bounds();
+
+        assert_fuzzy_eq!(Tuple::point(-4.5, -3.0, -5.0), b.min);
+        assert_fuzzy_eq!(Tuple::point(4.0, 7.0, 4.5), b.max);
+    }
+
+    #[test]
+    fn changing_children_updates_the_bounds() {
+        let mut g = Group::default();
+        g.add_child(SphereBuilder::default().build().unwrap());
+        g.add_child(SphereBuilder::default().build().unwrap());
+        assert_fuzzy_eq!(Tuple::point(1.0, 1.0, 1.0), g.bounds().max);
+
+        g.child_mut(1).unwrap().set_transform(Matrix::translation(5.0, 0.0, 0.0));
+        assert_fuzzy_eq!(Tuple::point(6.0, 1.0, 1.0), g.bounds().max);
+
+        assert!(g.remove_child(1).is_some());
+        assert_eq!(None, g.remove_child(1));
+        assert_fuzzy_eq!(Tuple::point(1.0, 1.0, 1.0), g.bounds().max);
+    }
+
+    #[test]
+    fn intersecting_ray_and_group_does_not_test_children_if_box_is_missed() {
+        let child = TestShape::default();
+        let mut g = Group::default();
+        g.add_child(child.clone());
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0));
+
+        g.intersect(r);
+        assert!(child.saved_ray().is_none());
+    }
+
+    #[test]
+    fn intersecting_ray_and_group_tests_children_if_box_is_hit() {
+        let child = TestShape::default();
+        let mut g = Group::default();
+        g.add_child(child.clone());
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        g.intersect(r);
+        assert!(child.saved_ray().is_some());
+    }
+
+    #[test]
+    fn adding_a_child_invalidates_cached_bounds() {
+        let mut g = Group::default();
+        g.add_child(SphereBuilder::default().build().unwrap());
+        assert_fuzzy_eq!(Tuple::point(1.0, 1.0, 1.0), g.bounds().max);
+
+        g.add_child(
+            SphereBuilder::default()
+                .transform(Matrix::translation(5.0, 0.0, 0.0))
+                .build()
+                .unwrap(),
+        );
+        assert_fuzzy_eq!(Tuple::point(6.0, 1.0, 1.0), g.bounds().max);
+    }
+
+    fn sphere_mesh(stacks: usize, slices: usize) -> Vec<Triangle> {
+        let vertex = |i: usize, j: usize| {
+            let theta = PI * i as f64 / stacks as f64;
+            let phi = 2.0 * PI * j as f64 / slices as f64;
+            Tuple::point(
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            )
+        };
+
+        let mut triangles = vec![];
+        for i in 0..stacks {
+            for j in 0..slices {
+                let (a, b, c, d) = (
+                    vertex(i, j),
+                    vertex(i + 1, j),
+                    vertex(i + 1, j + 1),
+                    vertex(i, j + 1),
+                );
+                // The quads touching the poles collapse into a single triangle
+                if i + 1 < stacks {
+                    triangles.push(Triangle::new(a, b, c));
+                }
+                if i > 0 {
+                    triangles.push(Triangle::new(a, c, d));
+                }
+            }
+        }
+
+        triangles
+    }
+
+    #[test]
+    fn bounded_mesh_renders_same_pixels_as_naive_mesh() {
+        let triangles = sphere_mesh(12, 24);
+        let light = Light::point(Tuple::point(-10.0, 10.0, -10.0), Color::white());
+
+        let mut mesh = Group::default();
+        for t in &triangles {
+            mesh.add_child(t.clone());
+        }
+        let bounded = World::new(vec![mesh.into()], light);
+        let naive = World::new(triangles.into_iter().map(Shape::from).collect(), light);
+
+        let mut camera = Camera::new(16, 16, PI / 3.0);
+        camera.set_transform(Matrix::view_transform(
+            Tuple::point(0.0, 0.0, -5.0),
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0),
+        )).unwrap();
+
+        assert_eq!(camera.render(&naive), camera.render(&bounded));
+    }
+
+    #[test]
+    fn moving_group_hands_out_children_where_the_ray_time_puts_it() {
+        let g = GroupBuilder::default()
+            .children(vec![SphereBuilder::default().build().unwrap().into()])
+            .transform_at_t1(Matrix::translation(4.0, 0.0, 0.0))
+            .build()
+            .unwrap();
+        let r = Ray::new(Tuple::point(2.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0)).at_time(0.5);
+
+        let xs = g.intersect(r);
+        assert_eq!(2, xs.intersections.len());
+        assert_fuzzy_eq!(Matrix::translation(2.0, 0.0, 0.0), xs.intersections[0].object.transform());
+        assert_fuzzy_eq!(
+            Tuple::vector(0.0, 0.0, -1.0),
+            xs.intersections[0].object.normal_at(Tuple::point(2.0, 0.0, -1.0))
+        );
+        assert!(g.intersect(r.at_time(0.0)).intersections.is_empty());
+    }
+
+    #[test]
+    fn group_with_a_flat_transform_is_rejected() {
+        let flat = Matrix::scaling(0.0, 1.0, 1.0);
+
+        assert!(GroupBuilder::default().transform(flat).build().is_err());
+        assert!(GroupBuilder::default().transform_at_t1(flat).build().is_err());
+    }
+}
@@ -0,0 +1,17 @@
+use crate::two_dimensional::TwoDimensional;
+
+// Floating point images that keep colors as rendered, brighter than white included, for exposure
+// and tone mapping to be done afterwards
+pub trait ToHdr {
+    // PF says three channels, and the negative scale that the floats after it are little endian
+    fn pfm_header(&self) -> Vec<u8>
+    where
+        Self: TwoDimensional,
+    {
+        format!("PF\n{} {}\n-1.0\n", self.width(), self.height()).into_bytes()
+    }
+
+    // Portable float map: the header, then three 32 bit floats a pixel with nothing clamped.
+    // Rows go from the bottom of the image to the top
+    fn to_pfm(&self) -> Vec<u8>;
+}
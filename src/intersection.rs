@@ -2,23 +2,30 @@ use crate::{
     ray::Ray,
     shape::{Shape, ShapeFuncs},
     tuple::Tuple,
-    util::EPSILON,
+    util::{FuzzyEq, EPSILON},
 };
 
-#[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
 pub struct Intersection {
     pub t: f64,
     pub object: Shape,
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd, Copy)]
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub struct ComputedIntersection {
     pub intersection: Intersection,
     pub point: Tuple,
     pub over_point: Tuple,
+    pub under_point: Tuple,
     pub eyev: Tuple,
     pub normalv: Tuple,
+    pub reflectv: Tuple,
     pub inside: bool,
+    // Refractive indices on the side the ray comes from and the side it goes into
+    pub n1: f64,
+    pub n2: f64,
+    // Time of the ray that made the hit, which the reflected and refracted rays carry on
+    pub time: f64,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -27,16 +34,21 @@ pub struct Intersections {
 }
 
 impl Intersections {
-    pub fn new(mut xs: Vec<Intersection>) -> Self {
-        xs.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+    pub fn new(xs: Vec<Intersection>) -> Self {
+        let mut xs = Self { intersections: xs };
+        xs.sort();
+        xs
+    }
 
-        Self { intersections: xs }
+    // Puts the intersections back in order of t, after more were pushed on
+    pub fn sort(&mut self) {
+        self.intersections.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
     }
 
     pub fn hit(&self) -> Option<Intersection> {
         for i in self.intersections.iter() {
             if i.t > 0.0 {
-                return Some(*i);
+                return Some(i.clone());
             }
         }
 
@@ -58,7 +70,8 @@ impl Intersection {
         Self { t, object }
     }
 
-    pub fn as_computed(&self, ray: Ray) -> ComputedIntersection {
+    // Takes every intersection along the ray, so it can tell which objects the hit sits inside
+    pub fn as_computed(&self, ray: Ray, xs: &Intersections) -> ComputedIntersection {
         let point = ray.position(self.t);
         let eyev = -ray.direction;
         let mut normalv = self.object.normal_at(point);
@@ -71,16 +84,55 @@ impl Intersection {
         }
 
         let over_point = point + normalv * EPSILON;
+        let under_point = point - normalv * EPSILON;
+        let reflectv = ray.direction.reflect(normalv);
+        let (n1, n2) = self.refractive_indices(xs);
 
         ComputedIntersection {
-            intersection: *self,
+            intersection: self.clone(),
             point,
             over_point,
+            under_point,
             eyev,
             normalv,
+            reflectv,
             inside,
+            n1,
+            n2,
+            time: ray.time,
         }
     }
+
+    // Walks the intersections keeping a list of the objects the ray is inside. Intersections
+    // within EPSILON of each other are one boundary, so where two surfaces touch the ray goes
+    // straight from one medium into the next instead of briefly through air
+    fn refractive_indices(&self, xs: &Intersections) -> (f64, f64) {
+        let index_of = |containers: &Vec<&Shape>| {
+            containers
+                .last()
+                .map_or(1.0, |o| o.material().refractive_index())
+        };
+
+        let mut containers: Vec<&Shape> = vec![];
+        for boundary in xs.intersections.chunk_by(|a, b| a.t.fuzzy_eq(b.t)) {
+            let n1 = index_of(&containers);
+
+            for i in boundary {
+                match containers.iter().position(|o| **o == i.object) {
+                    Some(index) => {
+                        containers.remove(index);
+                    }
+                    None => containers.push(&i.object),
+                }
+            }
+
+            if boundary.contains(self) {
+                return (n1, index_of(&containers));
+            }
+        }
+
+        (1.0, 1.0)
+    }
 }
 
 #[cfg(test)]
@@ -88,6 +140,7 @@ mod tests {
     use crate::{
         assert_fuzzy_eq,
         matrix::Matrix,
+        plane::Plane,
         ray::Ray,
         sphere::{Sphere, SphereBuilder},
         tuple::Tuple,
@@ -99,7 +152,7 @@ mod tests {
     #[test]
     fn intersection_encapsulates_t_and_object() {
         let s = Shape::from(Sphere::default());
-        let i = Intersection::new(3.5, s);
+        let i = Intersection::new(3.5, s.clone());
 
         assert_eq!(3.5, i.t);
         assert_eq!(s, i.object)
@@ -108,8 +161,8 @@ mod tests {
     #[test]
     fn aggregating_intersections() {
         let s = Shape::from(Sphere::default());
-        let a = Intersection::new(1.0, s);
-        let b = Intersection::new(2.0, s);
+        let a = Intersection::new(1.0, s.clone());
+        let b = Intersection::new(2.0, s.clone());
 
         let xs = Intersections::new(vec![a, b]);
         assert_eq!(2, xs.intersections.len());
@@ -131,9 +184,9 @@ mod tests {
     #[test]
     fn hit_when_all_intersections_have_positive_t() {
         let s = Shape::from(Sphere::default());
-        let a = Intersection::new(1.0, s);
-        let b = Intersection::new(2.0, s);
-        let xs = Intersections::new(vec![b, a]);
+        let a = Intersection::new(1.0, s.clone());
+        let b = Intersection::new(2.0, s.clone());
+        let xs = Intersections::new(vec![b.clone(), a.clone()]);
 
         let i = xs.hit();
 
@@ -144,9 +197,9 @@ mod tests {
     #[test]
     fn hit_when_some_intersections_have_negative_t() {
         let s = Shape::from(Sphere::default());
-        let a = Intersection::new(-1.0, s);
-        let b = Intersection::new(1.0, s);
-        let xs = Intersections::new(vec![b, a]);
+        let a = Intersection::new(-1.0, s.clone());
+        let b = Intersection::new(1.0, s.clone());
+        let xs = Intersections::new(vec![b.clone(), a.clone()]);
 
         let i = xs.hit();
 
@@ -157,9 +210,9 @@ mod tests {
     #[test]
     fn hit_when_all_intersections_have_negative_t() {
         let s = Shape::from(Sphere::default());
-        let a = Intersection::new(-2.0, s);
-        let b = Intersection::new(-1.0, s);
-        let xs = Intersections::new(vec![b, a]);
+        let a = Intersection::new(-2.0, s.clone());
+        let b = Intersection::new(-1.0, s.clone());
+        let xs = Intersections::new(vec![b.clone(), a.clone()]);
 
         let i = xs.hit();
 
@@ -169,11 +222,11 @@ mod tests {
     #[test]
     fn hit_is_always_lowest_nognegative_intersection() {
         let s = Shape::from(Sphere::default());
-        let a = Intersection::new(5.0, s);
-        let b = Intersection::new(7.0, s);
-        let c = Intersection::new(-3.0, s);
-        let d = Intersection::new(2.0, s);
-        let xs = Intersections::new(vec![a, b, c, d]);
+        let a = Intersection::new(5.0, s.clone());
+        let b = Intersection::new(7.0, s.clone());
+        let c = Intersection::new(-3.0, s.clone());
+        let d = Intersection::new(2.0, s.clone());
+        let xs = Intersections::new(vec![a, b, c, d.clone()]);
 
         let i = xs.hit();
 
@@ -185,8 +238,8 @@ mod tests {
     fn precomputing_state_of_intersection() {
         let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
         let s = Shape::from(Sphere::default());
-        let i = Intersection::new(4.0, s);
-        let comp = i.as_computed(r);
+        let i = Intersection::new(4.0, s.clone());
+        let comp = i.as_computed(r, &Intersections::new(vec![i.clone()]));
 
         assert!(comp.intersection.t.fuzzy_eq(i.t));
         assert_fuzzy_eq!(i.object, comp.intersection.object);
@@ -199,8 +252,8 @@ mod tests {
     fn hit_when_intersection_occurs_on_outside() {
         let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
         let s = Shape::from(Sphere::default());
-        let i = Intersection::new(4.0, s);
-        let comp = i.as_computed(r);
+        let i = Intersection::new(4.0, s.clone());
+        let comp = i.as_computed(r, &Intersections::new(vec![i.clone()]));
 
         assert!(!comp.inside);
     }
@@ -209,8 +262,8 @@ mod tests {
     fn hit_when_intersection_is_inside() {
         let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
         let s = Shape::from(Sphere::default());
-        let i = Intersection::new(1.0, s);
-        let comp = i.as_computed(r);
+        let i = Intersection::new(1.0, s.clone());
+        let comp = i.as_computed(r, &Intersections::new(vec![i.clone()]));
 
         assert!(comp.inside);
         assert_fuzzy_eq!(Tuple::point(0.0, 0.0, 1.0), comp.point);
@@ -227,10 +280,133 @@ mod tests {
                 .build()
                 .unwrap(),
         );
-        let i = Intersection::new(5.0, s);
-        let comp = i.as_computed(r);
+        let i = Intersection::new(5.0, s.clone());
+        let comp = i.as_computed(r, &Intersections::new(vec![i.clone()]));
 
         assert!(comp.over_point.z < -EPSILON / 2.0);
         assert!(comp.point.z > comp.over_point.z);
     }
+
+    #[test]
+    fn precomputing_reflection_vector() {
+        let shape = Shape::from(Plane::default());
+        let sqrt_2_2 = 2.0_f64.sqrt() / 2.0;
+        let r = Ray::new(Tuple::point(0.0, 1.0, -1.0), Tuple::vector(0.0, -sqrt_2_2, sqrt_2_2));
+        let i = Intersection::new(2.0_f64.sqrt(), shape);
+
+        let comp = i.as_computed(r, &Intersections::new(vec![i.clone()]));
+        assert_fuzzy_eq!(Tuple::vector(0.0, sqrt_2_2, sqrt_2_2), comp.reflectv);
+    }
+
+    fn glass_sphere(transform: Matrix<4>, refractive_index: f64) -> Shape {
+        let glass = Sphere::glass_sphere();
+        Sphere {
+            transform,
+            material: glass.material.with_refractive_index(refractive_index),
+            ..glass
+        }
+        .into()
+    }
+
+    #[test]
+    fn finding_n1_and_n2_at_various_intersections() {
+        let a = glass_sphere(Matrix::scaling(2.0, 2.0, 2.0), 1.5);
+        let b = glass_sphere(Matrix::translation(0.0, 0.0, -0.25), 2.0);
+        let c = glass_sphere(Matrix::translation(0.0, 0.0, 0.25), 2.5);
+        let r = Ray::new(Tuple::point(0.0, 0.0, -4.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = Intersections::new(vec![
+            Intersection::new(2.0, a.clone()),
+            Intersection::new(2.75, b.clone()),
+            Intersection::new(3.25, c.clone()),
+            Intersection::new(4.75, b),
+            Intersection::new(5.25, c),
+            Intersection::new(6.0, a),
+        ]);
+        let expected = [
+            (1.0, 1.5),
+            (1.5, 2.0),
+            (2.0, 2.5),
+            (2.5, 2.5),
+            (2.5, 1.5),
+            (1.5, 1.0),
+        ];
+
+        for (i, (n1, n2)) in xs.intersections.iter().zip(expected) {
+            let comp = i.as_computed(r, &xs);
+            assert_fuzzy_eq!(n1, comp.n1);
+            assert_fuzzy_eq!(n2, comp.n2);
+        }
+    }
+
+    #[test]
+    fn finding_n1_and_n2_in_concentric_spheres() {
+        // An air bubble inside a marble inside a ball of water
+        let water = glass_sphere(Matrix::scaling(3.0, 3.0, 3.0), 1.33);
+        let marble = glass_sphere(Matrix::scaling(2.0, 2.0, 2.0), 1.5);
+        let bubble = glass_sphere(Matrix::identity(), 1.0);
+        let r = Ray::new(Tuple::point(0.0, 0.0, -4.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = Intersections::new(vec![
+            Intersection::new(1.0, water.clone()),
+            Intersection::new(2.0, marble.clone()),
+            Intersection::new(3.0, bubble.clone()),
+            Intersection::new(5.0, bubble),
+            Intersection::new(6.0, marble),
+            Intersection::new(7.0, water),
+        ]);
+        let expected = [
+            (1.0, 1.33),
+            (1.33, 1.5),
+            (1.5, 1.0),
+            (1.0, 1.5),
+            (1.5, 1.33),
+            (1.33, 1.0),
+        ];
+
+        for (i, (n1, n2)) in xs.intersections.iter().zip(expected) {
+            let comp = i.as_computed(r, &xs);
+            assert_fuzzy_eq!(n1, comp.n1);
+            assert_fuzzy_eq!(n2, comp.n2);
+        }
+    }
+
+    #[test]
+    fn touching_surfaces_pass_straight_between_media() {
+        let glass = glass_sphere(Matrix::translation(0.0, 0.0, -1.0), 1.5);
+        let water = glass_sphere(Matrix::translation(0.0, 0.0, 1.0), 1.33);
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let exit = Intersection::new(5.0, glass.clone());
+
+        // The order of the two hits where the spheres touch, or a rounding error between them,
+        // mustn't make the ray pass through air
+        for enter in [
+            Intersection::new(5.0, water.clone()),
+            Intersection::new(5.0 + EPSILON / 10.0, water.clone()),
+            Intersection::new(5.0 - EPSILON / 10.0, water.clone()),
+        ] {
+            for boundary in [vec![exit.clone(), enter.clone()], vec![enter.clone(), exit.clone()]] {
+                let mut xs = vec![Intersection::new(3.0, glass.clone())];
+                xs.extend(boundary);
+                xs.push(Intersection::new(7.0, water.clone()));
+                let xs = Intersections::new(xs);
+
+                for i in [&exit, &enter] {
+                    let comp = i.as_computed(r, &xs);
+                    assert_fuzzy_eq!(1.5, comp.n1);
+                    assert_fuzzy_eq!(1.33, comp.n2);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn under_point_is_offset_below_surface() {
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let shape = glass_sphere(Matrix::translation(0.0, 0.0, 1.0), 1.5);
+        let i = Intersection::new(5.0, shape);
+        let xs = Intersections::new(vec![i.clone()]);
+
+        let comp = i.as_computed(r, &xs);
+        assert!(comp.under_point.z > EPSILON / 2.0);
+        assert!(comp.point.z < comp.under_point.z);
+    }
 }
@@ -3,22 +3,49 @@
 #[macro_use]
 extern crate derive_builder;
 
+pub mod animation;
+pub mod bmp;
+pub mod bounding_box;
+mod bvh;
 pub mod camera;
 pub mod canvas;
 pub mod color;
+pub mod cone;
+pub mod cube;
+pub mod cylinder;
+pub mod debug_hooks;
+pub mod depth_buffer;
+pub mod disk;
+pub mod error;
+pub mod group;
+pub mod hdr;
 pub mod intersection;
 pub mod light;
 pub mod material;
 pub mod matrix;
+pub mod mesh;
+pub mod noise;
+pub mod normal_map;
+pub mod obj;
 pub mod plane;
 pub mod png;
+pub mod prefabs;
 pub mod ppm;
 pub mod ray;
+pub mod ray_counters;
 pub mod rgb;
+pub mod sampling;
 pub mod shape;
 pub mod sphere;
+pub mod stl;
+#[cfg(test)]
+pub mod test_shape;
+pub mod texture_map;
+pub mod torus;
+pub mod triangle;
 pub mod tuple;
 pub mod two_dimensional;
 pub mod util;
 pub mod world;
-pub mod pattern;
\ No newline at end of file
+pub mod pattern;
+pub mod pgm;
\ No newline at end of file
@@ -1,15 +1,50 @@
-use crate::{color::Color, tuple::Tuple};
+use std::sync::atomic::{AtomicU64, Ordering};
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq)]
+use crate::{color::Color, tuple::Tuple, world::World};
+
+pub type LightId = u64;
+
+static NEXT_LIGHT_ID: AtomicU64 = AtomicU64::new(0);
+
+pub fn next_light_id() -> LightId {
+    NEXT_LIGHT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+// Lights read back keep the ids shapes exclude them by, so lights made afterwards have to be
+// numbered past them
+#[cfg(feature = "serde")]
+fn deserialize_id<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<LightId, D::Error> {
+    let id = <LightId as serde::Deserialize>::deserialize(deserializer)?;
+    NEXT_LIGHT_ID.fetch_max(id.saturating_add(1), Ordering::Relaxed);
+    Ok(id)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(tag = "type"))]
 pub enum LightType {
     Point,
+    // Angles are in radians, measured from the direction to the edge of the cone
+    Spot {
+        direction: Tuple,
+        inner_angle: f64,
+        outer_angle: f64,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Light {
+    // Copies of a light keep its id, so shapes can exclude it by id
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_id"))]
+    pub(crate) id: LightId,
     typ: LightType,
     pub position: Tuple,
-    pub intensity: Color,
+    pub color: Color,
+    // Brightness as a multiple of the color, so the hue stays put
+    pub intensity: f64,
+    pub casts_shadows: bool,
+    // Points further away than this get nothing from the light, not even a shadow ray
+    pub max_range: Option<f64>,
 }
 
 impl Default for Light {
@@ -19,36 +54,285 @@ impl Default for Light {
 }
 
 impl Light {
-    pub fn new(typ: LightType, position: Tuple, intensity: Color) -> Self {
+    pub fn new(typ: LightType, position: Tuple, color: Color) -> Self {
         Self {
+            id: next_light_id(),
             typ,
             position,
-            intensity,
+            color,
+            intensity: 1.0,
+            casts_shadows: true,
+            max_range: None,
         }
     }
 
-    pub fn point(position: Tuple, intensity: Color) -> Self {
+    pub fn point(position: Tuple, color: Color) -> Self {
+        Self::point_with_intensity(position, color, 1.0)
+    }
+
+    pub fn point_with_intensity(position: Tuple, color: Color, intensity: f64) -> Self {
         Self {
+            id: next_light_id(),
             typ: LightType::Point,
             position,
+            color,
             intensity,
+            casts_shadows: true,
+            max_range: None,
+        }
+    }
+
+    pub fn spot(
+        position: Tuple,
+        direction: Tuple,
+        inner_angle: f64,
+        outer_angle: f64,
+        color: Color,
+    ) -> Self {
+        Self {
+            id: next_light_id(),
+            typ: LightType::Spot {
+                direction: direction.normalize(),
+                inner_angle,
+                outer_angle,
+            },
+            position,
+            color,
+            intensity: 1.0,
+            casts_shadows: true,
+            max_range: None,
+        }
+    }
+
+    // Fill lights usually shouldn't add shadows of their own
+    pub fn with_shadows(self, casts_shadows: bool) -> Self {
+        Self {
+            casts_shadows,
+            ..self
+        }
+    }
+
+    // Cuts the light off past max_range, for scenes with many lights that each only matter nearby
+    pub fn with_range(self, max_range: f64) -> Self {
+        Self {
+            max_range: Some(max_range),
+            ..self
+        }
+    }
+
+    pub fn reaches(&self, point: Tuple) -> bool {
+        self.max_range.is_none_or(|range| (self.position - point).magnitude() <= range)
+    }
+
+    // The fraction of the light that reaches the point at the time. Point and spot lights are
+    // blocked by opaque shapes and dimmed by transparent ones
+    pub fn intensity_at(&self, point: Tuple, time: f64, world: &World) -> f64 {
+        if !self.casts_shadows {
+            return 1.0;
+        }
+
+        match self.typ {
+            LightType::Point | LightType::Spot { .. } => world.transmittance(self.position, point, time),
+        }
+    }
+
+    pub fn id(&self) -> LightId {
+        self.id
+    }
+
+    pub fn typ(&self) -> LightType {
+        self.typ
+    }
+
+    // How much of the light's intensity reaches the point: 1 inside the inner cone, 0 outside
+    // the outer one and a smoothstep between the two
+    pub fn falloff(&self, point: Tuple) -> f64 {
+        match self.typ {
+            LightType::Point => 1.0,
+            LightType::Spot {
+                direction,
+                inner_angle,
+                outer_angle,
+            } => {
+                let cos_angle = (point - self.position).normalize().dot(direction);
+                let cos_inner = inner_angle.cos();
+                let cos_outer = outer_angle.cos();
+
+                if cos_angle >= cos_inner {
+                    1.0
+                } else if cos_angle <= cos_outer {
+                    0.0
+                } else {
+                    let t = (cos_angle - cos_outer) / (cos_inner - cos_outer);
+                    t * t * (3.0 - 2.0 * t)
+                }
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{f64::consts::PI, sync::atomic::Ordering};
+
     use super::*;
-    use crate::assert_fuzzy_eq;
+    use crate::{assert_fuzzy_eq, debug_hooks::{counting_rays, RayKind}};
     use crate::util::FuzzyEq;
 
     #[test]
     fn point_light_has_position_and_intensity() {
-        let intensity = Color::white();
+        let color = Color::white();
         let position = Tuple::point(0.0, 0.0, 0.0);
-        let light = Light::new(LightType::Point, position, intensity);
+        let light = Light::new(LightType::Point, position, color);
 
         assert_fuzzy_eq!(position, light.position);
-        assert_fuzzy_eq!(intensity, light.intensity);
+        assert_fuzzy_eq!(color, light.color);
+        assert_fuzzy_eq!(1.0, light.intensity);
+    }
+
+    #[test]
+    fn point_light_with_intensity_keeps_color() {
+        let color = Color::new(1.0, 0.5, 0.25);
+        let light = Light::point_with_intensity(Tuple::point(0.0, 0.0, 0.0), color, 2.0);
+
+        assert_fuzzy_eq!(color, light.color);
+        assert_fuzzy_eq!(2.0, light.intensity);
+    }
+
+    #[test]
+    fn point_light_intensity_at_is_binary() {
+        let w = World::default();
+        let light = w.lights[0];
+        let cases = [
+            (Tuple::point(0.0, 1.0001, 0.0), 1.0),
+            (Tuple::point(-1.0001, 0.0, 0.0), 1.0),
+            (Tuple::point(0.0, 0.0, -1.0001), 1.0),
+            (Tuple::point(0.0, 0.0, 1.0001), 0.0),
+            (Tuple::point(1.0001, 0.0, 0.0), 0.0),
+            (Tuple::point(0.0, -1.0001, 0.0), 0.0),
+            (Tuple::point(0.0, 0.0, 0.0), 0.0),
+        ];
+
+        for (point, expected) in cases {
+            assert_fuzzy_eq!(expected, light.intensity_at(point, 0.0, &w));
+        }
+    }
+
+    #[test]
+    fn lights_get_unique_ids_that_copies_keep() {
+        let a = Light::default();
+        let b = Light::default();
+
+        assert_ne!(a.id(), b.id());
+        assert_eq!(a.id(), a.with_shadows(false).id());
+    }
+
+    #[test]
+    fn lights_cast_shadows_by_default() {
+        assert!(Light::default().casts_shadows);
+        assert!(!Light::default().with_shadows(false).casts_shadows);
+    }
+
+    #[test]
+    fn lights_reach_everywhere_unless_given_a_range() {
+        let light = Light::point(Tuple::point(0.0, 0.0, 0.0), Color::white());
+        let far = Tuple::point(0.0, 1000.0, 0.0);
+
+        assert!(light.reaches(far));
+        assert!(light.with_range(5.0).reaches(Tuple::point(0.0, 4.0, 0.0)));
+        assert!(!light.with_range(5.0).reaches(Tuple::point(0.0, 6.0, 0.0)));
+        assert_eq!(light.id(), light.with_range(5.0).id());
+    }
+
+    #[test]
+    fn light_without_shadows_lights_occluded_point() {
+        let w = World::default();
+        let light = w.lights[0].with_shadows(false);
+        let p = Tuple::point(10.0, -10.0, 10.0);
+
+        assert_fuzzy_eq!(0.0, w.lights[0].intensity_at(p, 0.0, &w));
+        assert_fuzzy_eq!(1.0, light.intensity_at(p, 0.0, &w));
+    }
+
+    #[test]
+    fn light_without_shadows_casts_no_shadow_rays() {
+        let mut w = World::default();
+        let (hooks, shadow_rays) = counting_rays(RayKind::Shadow);
+        w.set_debug_hooks(hooks);
+        let p = Tuple::point(10.0, -10.0, 10.0);
+
+        w.lights[0].with_shadows(false).intensity_at(p, 0.0, &w);
+        assert_eq!(0, shadow_rays.load(Ordering::Relaxed));
+
+        w.lights[0].intensity_at(p, 0.0, &w);
+        assert_eq!(1, shadow_rays.load(Ordering::Relaxed));
+    }
+
+    fn downward_spot() -> Light {
+        Light::spot(
+            Tuple::point(0.0, 10.0, 0.0),
+            Tuple::vector(0.0, -1.0, 0.0),
+            PI / 12.0,
+            PI / 6.0,
+            Color::white(),
+        )
+    }
+
+    #[test]
+    fn point_light_has_no_falloff() {
+        let light = Light::point(Tuple::point(0.0, 0.0, 0.0), Color::white());
+
+        assert_fuzzy_eq!(1.0, light.falloff(Tuple::point(5.0, -3.0, 2.0)));
+    }
+
+    #[test]
+    fn spot_light_normalizes_direction() {
+        let light = Light::spot(
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(0.0, 0.0, 4.0),
+            0.1,
+            0.2,
+            Color::white(),
+        );
+
+        match light.typ() {
+            LightType::Spot { direction, .. } => {
+                assert_fuzzy_eq!(Tuple::vector(0.0, 0.0, 1.0), direction)
+            }
+            t => panic!("expected a spot light, got {:?}", t),
+        }
+    }
+
+    #[test]
+    fn spot_light_is_full_along_its_axis() {
+        assert_fuzzy_eq!(1.0, downward_spot().falloff(Tuple::point(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn spot_light_is_zero_outside_outer_cone() {
+        // 31 degrees off the axis
+        let x = 10.0 * (31.0_f64.to_radians()).tan();
+        assert_fuzzy_eq!(0.0, downward_spot().falloff(Tuple::point(x, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn spot_light_falls_off_in_penumbra() {
+        // 22.5 degrees off the axis, halfway between the cones
+        let x = 10.0 * (PI / 8.0).tan();
+        let f = downward_spot().falloff(Tuple::point(x, 0.0, 0.0));
+
+        assert!(f > 0.0 && f < 1.0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn lights_made_after_reading_one_back_get_new_ids() {
+        let mut light = Light::default();
+        light.id += 1000;
+
+        let read: Light = serde_json::from_str(&serde_json::to_string(&light).unwrap()).unwrap();
+
+        assert_eq!(light.id(), read.id());
+        assert!(Light::default().id() > read.id());
     }
 }
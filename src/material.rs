@@ -1,24 +1,253 @@
-use crate::{color::Color, light::Light, tuple::Tuple, util::FuzzyEq, pattern::{Pattern, PatternFuncs}};
+use std::f64::consts::PI;
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Builder)]
+use crate::{color::Color, light::Light, normal_map::NormalMap, shape::Shape, tuple::Tuple, util::{FuzzyEq, EPSILON}, pattern::Pattern};
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(tag = "type"))]
+pub enum SpecularModel {
+    #[default]
+    Phong,
+    // Uses the half vector between the light and the eye, giving softer highlights at grazing
+    // angles
+    BlinnPhong,
+    // Microfacet model with a GGX distribution, Smith shadowing and Schlick's Fresnel. Roughness
+    // goes from 0 for a polished surface to 1, and metallic tints the reflection with the color
+    CookTorrance { roughness: f64, metallic: f64 },
+}
+
+// How much a perfectly polished metal reflects
+const METAL_REFLECTANCE: f64 = 0.8;
+
+const DEFAULT_SHININESS: f64 = 200.0;
+
+// The kind of surface a material describes, which decides the rays World::shade_hit follows
+// from a hit. Each kind only holds what it uses, so combinations like a transparent metal
+// can't be asked for
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(tag = "type"))]
+pub enum Surface {
+    // Diffuse only, without a highlight
+    Lambertian,
+    Phong { specular: Color, shininess: f64 },
+    // A highlight, with the scene reflected in it as strongly as reflective says
+    Metal { specular: Color, shininess: f64, reflective: f64 },
+    // Lets light through, bending it by the index of refraction. Glass reflects the scene too
+    Dielectric { specular: Color, shininess: f64, reflective: f64, transparency: f64, ior: f64 },
+}
+
+impl Default for Surface {
+    fn default() -> Self {
+        Self::Phong { specular: Color::grey(0.9), shininess: DEFAULT_SHININESS }
+    }
+}
+
+impl Surface {
+    pub fn specular(&self) -> Color {
+        match self {
+            Self::Lambertian => Color::black(),
+            Self::Phong { specular, .. } | Self::Metal { specular, .. } | Self::Dielectric { specular, .. } => *specular,
+        }
+    }
+
+    pub fn shininess(&self) -> f64 {
+        match self {
+            Self::Lambertian => DEFAULT_SHININESS,
+            Self::Phong { shininess, .. } | Self::Metal { shininess, .. } | Self::Dielectric { shininess, .. } => {
+                *shininess
+            }
+        }
+    }
+
+    pub fn reflective(&self) -> f64 {
+        match self {
+            Self::Lambertian | Self::Phong { .. } => 0.0,
+            Self::Metal { reflective, .. } | Self::Dielectric { reflective, .. } => *reflective,
+        }
+    }
+
+    pub fn transparency(&self) -> f64 {
+        match self {
+            Self::Dielectric { transparency, .. } => *transparency,
+            _ => 0.0,
+        }
+    }
+
+    pub fn refractive_index(&self) -> f64 {
+        match self {
+            Self::Dielectric { ior, .. } => *ior,
+            _ => 1.0,
+        }
+    }
+
+    // The setters only ever move a surface on to a kind that can hold everything it had, so
+    // nothing set earlier is lost whatever order they're called in
+    pub fn with_specular(mut self, value: Color) -> Self {
+        match &mut self {
+            Self::Lambertian => return Self::Phong { specular: value, shininess: DEFAULT_SHININESS },
+            Self::Phong { specular, .. } | Self::Metal { specular, .. } | Self::Dielectric { specular, .. } => {
+                *specular = value
+            }
+        }
+        self
+    }
+
+    pub fn with_shininess(mut self, value: f64) -> Self {
+        match &mut self {
+            Self::Lambertian => return Self::Phong { specular: Color::black(), shininess: value },
+            Self::Phong { shininess, .. } | Self::Metal { shininess, .. } | Self::Dielectric { shininess, .. } => {
+                *shininess = value
+            }
+        }
+        self
+    }
+
+    pub fn with_reflective(mut self, value: f64) -> Self {
+        match &mut self {
+            Self::Lambertian | Self::Phong { .. } => {
+                return Self::Metal { specular: self.specular(), shininess: self.shininess(), reflective: value }
+            }
+            Self::Metal { reflective, .. } | Self::Dielectric { reflective, .. } => *reflective = value,
+        }
+        self
+    }
+
+    pub fn with_transparency(self, value: f64) -> Self {
+        match self {
+            Self::Dielectric { specular, shininess, reflective, ior, .. } => {
+                Self::Dielectric { specular, shininess, reflective, transparency: value, ior }
+            }
+            _ => self.into_dielectric(value, 1.0),
+        }
+    }
+
+    pub fn with_refractive_index(self, value: f64) -> Self {
+        match self {
+            Self::Dielectric { specular, shininess, reflective, transparency, .. } => {
+                Self::Dielectric { specular, shininess, reflective, transparency, ior: value }
+            }
+            _ => self.into_dielectric(0.0, value),
+        }
+    }
+
+    fn into_dielectric(self, transparency: f64, ior: f64) -> Self {
+        Self::Dielectric {
+            specular: self.specular(),
+            shininess: self.shininess(),
+            reflective: self.reflective(),
+            transparency,
+            ior,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Builder)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[builder(build_fn(validate = "Self::validate"))]
 pub struct Material {
+    #[builder(default = "Color::white()")]
     pub color: Color,
-    pub ambient: f64,
-    pub diffuse: f64,
-    pub specular: f64,
-    pub shininess: f64,
+    // These scale each channel separately, so a highlight can have its own tint. Builders and
+    // the with_* setters also take a plain f64, which means the same value for every channel
+    #[builder(default = "Color::grey(0.1)", setter(into))]
+    pub ambient: Color,
+    #[builder(default = "Color::grey(0.9)", setter(into))]
+    pub diffuse: Color,
+    #[builder(default)]
     pub pattern: Option<Pattern>,
+    // Everything else about how the material reacts to light. The builder also has a setter for
+    // each of the surface's fields, like the with_* setters
+    #[builder(default)]
+    pub surface: Surface,
+    // How much of each channel is absorbed per unit travelled through the inside, following
+    // Beer's law. Black absorbs nothing
+    #[builder(default = "Color::black()")]
+    pub attenuation: Color,
+    #[builder(default)]
+    pub specular_model: SpecularModel,
+    #[builder(default)]
+    pub normal_perturbation: Option<NormalMap>,
+}
+
+impl MaterialBuilder {
+    pub fn specular<V: Into<Color>>(&mut self, value: V) -> &mut Self {
+        self.surface = Some(self.surface.unwrap_or_default().with_specular(value.into()));
+        self
+    }
+
+    pub fn shininess(&mut self, value: f64) -> &mut Self {
+        self.surface = Some(self.surface.unwrap_or_default().with_shininess(value));
+        self
+    }
+
+    pub fn reflective(&mut self, value: f64) -> &mut Self {
+        self.surface = Some(self.surface.unwrap_or_default().with_reflective(value));
+        self
+    }
+
+    pub fn transparency(&mut self, value: f64) -> &mut Self {
+        self.surface = Some(self.surface.unwrap_or_default().with_transparency(value));
+        self
+    }
+
+    pub fn refractive_index(&mut self, value: f64) -> &mut Self {
+        self.surface = Some(self.surface.unwrap_or_default().with_refractive_index(value));
+        self
+    }
+
+    // Catches values that would only show up as NaNs or odd highlights deep inside a render
+    fn validate(&self) -> Result<(), String> {
+        let surface = self.surface.unwrap_or_default();
+        for (name, value) in [
+            ("ambient", self.ambient),
+            ("diffuse", self.diffuse),
+            ("specular", Some(surface.specular())),
+        ] {
+            if let Some(color) = value {
+                if color != color.clamp(0.0, 1.0) {
+                    return Err(format!("{} must be between 0 and 1, got {:?}", name, color));
+                }
+            }
+        }
+        for (name, value) in [
+            ("reflective", surface.reflective()),
+            ("transparency", surface.transparency()),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(format!("{} must be between 0 and 1, got {}", name, value));
+            }
+        }
+        let shininess = surface.shininess();
+        if shininess.is_nan() || shininess <= 0.0 {
+            return Err(format!("shininess must be above 0, got {}", shininess));
+        }
+        let refractive_index = surface.refractive_index();
+        if refractive_index.is_nan() || refractive_index < 1.0 {
+            return Err(format!(
+                "refractive_index must be at least 1, got {}",
+                refractive_index
+            ));
+        }
+
+        Ok(())
+    }
 }
 
 impl Material {
+    // Out of range values are clamped rather than rejected: ambient, diffuse and specular into
+    // [0, 1], and shininess to just above 0. Use MaterialBuilder to get an error instead
     pub fn new(color: Color, ambient: f64, diffuse: f64, specular: f64, shininess: f64) -> Self {
         Self {
             color,
-            ambient,
-            diffuse,
-            specular,
-            shininess,
-            pattern: None
+            ambient: Color::grey(ambient.clamp(0.0, 1.0)),
+            diffuse: Color::grey(diffuse.clamp(0.0, 1.0)),
+            pattern: None,
+            surface: Surface::Phong {
+                specular: Color::grey(specular.clamp(0.0, 1.0)),
+                shininess: shininess.max(EPSILON),
+            },
+            attenuation: Color::black(),
+            specular_model: SpecularModel::Phong,
+            normal_perturbation: None,
         }
     }
 
@@ -29,62 +258,285 @@ impl Material {
         }
     }
 
+    pub fn with_ambient(self, ambient: impl Into<Color>) -> Self {
+        Self {
+            ambient: ambient.into(),
+            ..self
+        }
+    }
+
+    pub fn with_diffuse(self, diffuse: impl Into<Color>) -> Self {
+        Self {
+            diffuse: diffuse.into(),
+            ..self
+        }
+    }
+
+    pub fn with_specular(self, specular: impl Into<Color>) -> Self {
+        Self {
+            surface: self.surface.with_specular(specular.into()),
+            ..self
+        }
+    }
+
+    pub fn with_shininess(self, shininess: f64) -> Self {
+        Self {
+            surface: self.surface.with_shininess(shininess),
+            ..self
+        }
+    }
+
+    pub fn with_pattern(self, pattern: Pattern) -> Self {
+        Self {
+            pattern: Some(pattern),
+            ..self
+        }
+    }
+
+    pub fn with_reflective(self, reflective: f64) -> Self {
+        Self {
+            surface: self.surface.with_reflective(reflective),
+            ..self
+        }
+    }
+
+    pub fn with_transparency(self, transparency: f64) -> Self {
+        Self {
+            surface: self.surface.with_transparency(transparency),
+            ..self
+        }
+    }
+
+    pub fn with_refractive_index(self, refractive_index: f64) -> Self {
+        Self {
+            surface: self.surface.with_refractive_index(refractive_index),
+            ..self
+        }
+    }
+
+    pub fn with_surface(self, surface: Surface) -> Self {
+        Self { surface, ..self }
+    }
+
+    pub fn specular(&self) -> Color {
+        self.surface.specular()
+    }
+
+    pub fn shininess(&self) -> f64 {
+        self.surface.shininess()
+    }
+
+    pub fn reflective(&self) -> f64 {
+        self.surface.reflective()
+    }
+
+    pub fn transparency(&self) -> f64 {
+        self.surface.transparency()
+    }
+
+    pub fn refractive_index(&self) -> f64 {
+        self.surface.refractive_index()
+    }
+
+    // Clear glass that mostly shows what's behind and around it
+    pub fn glass() -> Self {
+        Self {
+            ambient: Color::grey(0.0),
+            diffuse: Color::grey(0.1),
+            surface: Surface::Dielectric {
+                specular: Color::grey(1.0),
+                shininess: 300.0,
+                reflective: 0.9,
+                transparency: 1.0,
+                ior: 1.5,
+            },
+            ..Default::default()
+        }
+    }
+
+    pub fn mirror() -> Self {
+        Self {
+            color: Color::black(),
+            ambient: Color::grey(0.0),
+            diffuse: Color::grey(0.0),
+            surface: Surface::Metal {
+                specular: Color::grey(1.0),
+                shininess: 300.0,
+                reflective: 1.0,
+            },
+            ..Default::default()
+        }
+    }
+
+    pub fn matte(color: Color) -> Self {
+        Self {
+            color,
+            diffuse: Color::grey(0.9),
+            surface: Surface::Lambertian,
+            ..Default::default()
+        }
+    }
+
+    // Fuzz goes from 0 for polished to 1 for brushed metal, which dulls the reflection and
+    // spreads out the highlight
+    pub fn metal(color: Color, fuzz: f64) -> Self {
+        let fuzz = fuzz.clamp(0.0, 1.0);
+        Self {
+            color,
+            ambient: Color::grey(0.1),
+            diffuse: Color::grey(0.3),
+            surface: Surface::Metal {
+                specular: Color::grey(0.9),
+                shininess: 10.0 + 290.0 * (1.0 - fuzz),
+                reflective: METAL_REFLECTANCE * (1.0 - fuzz),
+            },
+            ..Default::default()
+        }
+    }
+
+    // The unlit color of the surface, from the pattern when there is one
+    pub fn color_at(&self, object: &Shape, point: Tuple) -> Color {
+        match &self.pattern {
+            Some(p) => p.color_at_object(object, point),
+            None => self.color,
+        }
+    }
+
+    // The object is needed to find where on it the point is, for the pattern
     pub fn lighting(
         &self,
+        object: &Shape,
         point: Tuple,
         light: Light,
         eyev: Tuple,
         normalv: Tuple,
-        in_shadow: bool,
+        intensity: f64,
     ) -> Color {
-        let color  = match self.pattern {
-            Some(p) => p.color_at(point),
-            None => self.color
-        };
-        let effective_color = color * light.intensity;
+        let color = self.color_at(object, point);
+        let light_color = light.color * light.intensity;
+        let effective_color = color * light_color;
+        let falloff = light.falloff(point);
         let lightv = (light.position - point).normalize();
         let ambient = effective_color * self.ambient;
-        let diffuse;
-        let specular;
         let light_dot_normal = lightv.dot(normalv);
-
-        if light_dot_normal < 0.0 {
-            diffuse = Color::black();
-            specular = Color::black();
-        } else {
-            diffuse = effective_color * self.diffuse * light_dot_normal;
-            let reflectv = -lightv.reflect(normalv);
-            let reflect_dot_eye = reflectv.dot(eyev);
-
-            if reflect_dot_eye <= 0.0 {
-                specular = Color::black();
+        let highlight = |factor: f64| {
+            if factor <= 0.0 {
+                Color::black()
             } else {
-                let factor = reflect_dot_eye.powf(self.shininess);
-                specular = light.intensity * self.specular * factor;
+                light_color * self.specular() * factor.powf(self.shininess())
             }
-        }
+        };
 
-        if in_shadow {
-            ambient
+        let (diffuse, specular) = if light_dot_normal < 0.0 {
+            (Color::black(), Color::black())
         } else {
-            ambient + diffuse + specular
-        }
+            match self.specular_model {
+                SpecularModel::Phong => (
+                    effective_color * self.diffuse * light_dot_normal,
+                    highlight(-lightv.reflect(normalv).dot(eyev)),
+                ),
+                SpecularModel::BlinnPhong => (
+                    effective_color * self.diffuse * light_dot_normal,
+                    highlight(normalv.dot((lightv + eyev).normalize())),
+                ),
+                SpecularModel::CookTorrance { roughness, metallic } => {
+                    let (reflectance, reflected) =
+                        cook_torrance(color, lightv, eyev, normalv, roughness, metallic);
+                    // Whatever the surface reflects, or a metal absorbs, is not scattered diffusely
+                    (
+                        effective_color
+                            * self.diffuse
+                            * (Color::white() - reflected)
+                            * (light_dot_normal * (1.0 - metallic)),
+                        light_color * reflectance * self.specular() * light_dot_normal,
+                    )
+                }
+            }
+        };
+
+        ambient + (diffuse + specular) * (falloff * intensity)
     }
 }
 
+// Returns the specular BRDF and the share of the light arriving that the surface reflects, going
+// by the Fresnel term at the angle it arrives at. Dielectrics reflect about 4% head on, metals
+// reflect their own color
+fn cook_torrance(
+    color: Color,
+    lightv: Tuple,
+    eyev: Tuple,
+    normalv: Tuple,
+    roughness: f64,
+    metallic: f64,
+) -> (Color, Color) {
+    let halfv = (lightv + eyev).normalize();
+    let n_dot_l = normalv.dot(lightv).max(0.0);
+    let n_dot_v = normalv.dot(eyev).max(0.0);
+    let n_dot_h = normalv.dot(halfv).max(0.0);
+    let v_dot_h = eyev.dot(halfv).max(0.0);
+
+    let f0 = Color::new(0.04, 0.04, 0.04) * (1.0 - metallic) + color * metallic;
+    let schlick = |cos: f64| f0 + (Color::white() - f0) * (1.0 - cos).powi(5);
+    let (fresnel, reflected) = (schlick(v_dot_h), schlick(n_dot_l));
+
+    if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+        return (Color::black(), reflected);
+    }
+
+    // A roughness of exactly 0 would make the distribution a spike of zero width
+    let alpha = roughness.clamp(1e-3, 1.0).powi(2);
+    let alpha2 = alpha * alpha;
+    let denom = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    let distribution = alpha2 / (PI * denom * denom);
+
+    let k = alpha / 2.0;
+    let g1 = |x: f64| x / (x * (1.0 - k) + k);
+    let geometry = g1(n_dot_l) * g1(n_dot_v);
+
+    (fresnel * (distribution * geometry / (4.0 * n_dot_l * n_dot_v)), reflected)
+}
+
 impl Default for Material {
     fn default() -> Self {
         Self::new(Color::white(), 0.1, 0.9, 0.9, 200.0)
     }
 }
 
+// The default material with the given kind of surface
+impl From<Surface> for Material {
+    fn from(surface: Surface) -> Self {
+        Self {
+            surface,
+            ..Default::default()
+        }
+    }
+}
+
+// Surfaces of different kinds are never equal, even when their fields would make them look alike
+impl FuzzyEq<Self> for Surface {
+    fn fuzzy_eq(&self, other: Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(&other)
+            && self.specular().fuzzy_eq(other.specular())
+            && self.shininess().fuzzy_eq(other.shininess())
+            && self.reflective().fuzzy_eq(other.reflective())
+            && self.transparency().fuzzy_eq(other.transparency())
+            && self.refractive_index().fuzzy_eq(other.refractive_index())
+    }
+
+    fn fuzzy_ne(&self, other: Self) -> bool {
+        !self.fuzzy_eq(other)
+    }
+}
+
 impl FuzzyEq<Self> for Material {
     fn fuzzy_eq(&self, other: Self) -> bool {
         self.color.fuzzy_eq(other.color)
             && self.ambient.fuzzy_eq(other.ambient)
             && self.diffuse.fuzzy_eq(other.diffuse)
-            && self.specular.fuzzy_eq(other.specular)
-            && self.shininess.fuzzy_eq(other.shininess)
+            && self.surface.fuzzy_eq(other.surface)
+            && self.attenuation.fuzzy_eq(other.attenuation)
+            && self.specular_model == other.specular_model
+            && self.normal_perturbation == other.normal_perturbation
     }
 
     fn fuzzy_ne(&self, other: Self) -> bool {
@@ -94,19 +546,179 @@ impl FuzzyEq<Self> for Material {
 
 #[cfg(test)]
 mod tests {
-    use crate::{assert_fuzzy_eq, pattern::StripePattern};
+    use crate::{
+        assert_fuzzy_eq,
+        matrix::Matrix,
+        pattern::StripePattern,
+        rgb::ToRgbA32,
+        sphere::{Sphere, SphereBuilder},
+    };
 
     use super::*;
 
+    fn object() -> Shape {
+        Sphere::default().into()
+    }
+
     #[test]
     fn default_material() {
         let m = Material::default();
         assert_fuzzy_eq!(Color::white(), m.color);
-        assert_fuzzy_eq!(0.1, m.ambient);
-        assert_fuzzy_eq!(0.9, m.diffuse);
-        assert_fuzzy_eq!(0.9, m.specular);
-        assert_fuzzy_eq!(200.0, m.shininess);
-        assert_eq!(None, m.pattern)
+        assert_fuzzy_eq!(Color::grey(0.1), m.ambient);
+        assert_fuzzy_eq!(Color::grey(0.9), m.diffuse);
+        assert_fuzzy_eq!(Color::grey(0.9), m.specular());
+        assert_fuzzy_eq!(200.0, m.shininess());
+        assert_eq!(None, m.pattern);
+        assert_fuzzy_eq!(0.0, m.reflective());
+        assert_fuzzy_eq!(0.0, m.transparency());
+        assert_fuzzy_eq!(1.0, m.refractive_index());
+        assert_fuzzy_eq!(Color::black(), m.attenuation);
+    }
+
+    #[test]
+    fn builder_defaults_match_default_material() {
+        let built = MaterialBuilder::default().build().unwrap();
+
+        assert_fuzzy_eq!(Material::default(), built);
+    }
+
+    #[test]
+    fn builder_with_some_fields_set() {
+        let built = MaterialBuilder::default()
+            .color(Color::red())
+            .specular(0.3)
+            .build()
+            .unwrap();
+
+        assert_fuzzy_eq!(Color::red(), built.color);
+        assert_fuzzy_eq!(Color::grey(0.3), built.specular());
+        assert_fuzzy_eq!(Color::grey(0.1), built.ambient);
+        assert_fuzzy_eq!(Color::grey(0.9), built.diffuse);
+        assert_fuzzy_eq!(200.0, built.shininess());
+        assert_eq!(None, built.pattern);
+    }
+
+    #[test]
+    fn fluent_setters_change_one_field_each() {
+        let m = Material::with_color(Color::green())
+            .with_ambient(0.2)
+            .with_diffuse(0.5)
+            .with_specular(0.3)
+            .with_shininess(50.0)
+            .with_reflective(0.4)
+            .with_transparency(0.6)
+            .with_refractive_index(1.3)
+            .with_pattern(StripePattern::default().into());
+
+        let expected = Material {
+            color: Color::green(),
+            ambient: Color::grey(0.2),
+            diffuse: Color::grey(0.5),
+            pattern: Some(StripePattern::default().into()),
+            surface: Surface::Dielectric {
+                specular: Color::grey(0.3),
+                shininess: 50.0,
+                reflective: 0.4,
+                transparency: 0.6,
+                ior: 1.3,
+            },
+            ..Default::default()
+        };
+        assert_fuzzy_eq!(expected, m.clone());
+        assert_eq!(expected.pattern, m.pattern);
+    }
+
+    #[test]
+    fn builder_rejects_out_of_range_fields() {
+        let cases = [
+            ("ambient", MaterialBuilder::default().ambient(-0.1).build()),
+            ("diffuse", MaterialBuilder::default().diffuse(-3.0).build()),
+            (
+                "specular",
+                MaterialBuilder::default()
+                    .specular(Color::new(0.5, 1.5, 0.5))
+                    .build(),
+            ),
+            ("reflective", MaterialBuilder::default().reflective(1.1).build()),
+            ("transparency", MaterialBuilder::default().transparency(-1.0).build()),
+            ("shininess", MaterialBuilder::default().shininess(0.0).build()),
+            ("shininess", MaterialBuilder::default().shininess(f64::NAN).build()),
+            ("refractive_index", MaterialBuilder::default().refractive_index(0.5).build()),
+        ];
+
+        for (field, result) in cases {
+            match result {
+                Err(MaterialBuilderError::ValidationError(message)) => {
+                    assert!(message.starts_with(field), "{}", message)
+                }
+                other => panic!("expected {} to be rejected, got {:?}", field, other),
+            }
+        }
+    }
+
+    #[test]
+    fn builder_accepts_values_on_the_edges_of_their_range() {
+        let m = MaterialBuilder::default()
+            .ambient(0.0)
+            .diffuse(1.0)
+            .specular(Color::new(0.0, 0.5, 1.0))
+            .reflective(1.0)
+            .transparency(0.0)
+            .shininess(0.5)
+            .refractive_index(1.0)
+            .build();
+
+        assert!(m.is_ok());
+    }
+
+    #[test]
+    fn new_clamps_out_of_range_values() {
+        let m = Material::new(Color::white(), -1.0, 3.0, 0.5, -10.0);
+
+        assert_fuzzy_eq!(Color::grey(0.0), m.ambient);
+        assert_fuzzy_eq!(Color::grey(1.0), m.diffuse);
+        assert_fuzzy_eq!(Color::grey(0.5), m.specular());
+        assert!(m.shininess() > 0.0);
+    }
+
+    #[test]
+    fn scalar_and_color_coefficients_can_be_mixed() {
+        let built = MaterialBuilder::default()
+            .ambient(0.2)
+            .specular(Color::new(1.0, 0.5, 0.0))
+            .build()
+            .unwrap();
+
+        assert_fuzzy_eq!(Color::new(0.2, 0.2, 0.2), built.ambient);
+        assert_fuzzy_eq!(Color::new(1.0, 0.5, 0.0), built.specular());
+        assert_fuzzy_eq!(
+            Color::new(0.0, 0.5, 1.0),
+            Material::default().with_diffuse(Color::new(0.0, 0.5, 1.0)).diffuse
+        );
+    }
+
+    #[test]
+    fn gold_has_a_tinted_highlight_over_its_base_color() {
+        let base = Color::new(0.8, 0.6, 0.2);
+        let highlight = Color::new(1.0, 0.85, 0.4);
+        let gold = Material::with_color(base)
+            .with_ambient(0.0)
+            .with_specular(highlight);
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let sqrt_2_2 = (2.0_f64.sqrt()) / 2.0;
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let light = Light::point(Tuple::point(0.0, 10.0, -10.0), Color::white());
+        let diffuse = base * (0.9 * sqrt_2_2);
+
+        // Looking straight on there is no highlight, only the base color
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let c = gold.lighting(&object(), position, light, eyev, normalv, 1.0);
+        assert_fuzzy_eq!(diffuse, c);
+
+        // In the path of the reflection the highlight adds its own yellow on top
+        let eyev = Tuple::vector(0.0, -sqrt_2_2, -sqrt_2_2);
+        let c = gold.lighting(&object(), position, light, eyev, normalv, 1.0);
+        assert_fuzzy_eq!(diffuse + highlight, c);
     }
 
     #[test]
@@ -119,7 +731,7 @@ mod tests {
         let light = Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white());
 
         let expected = Color::new(1.9, 1.9, 1.9);
-        let actual = material.lighting(position, light, eyev, normalv, false);
+        let actual = material.lighting(&object(), position, light, eyev, normalv, 1.0);
 
         assert_fuzzy_eq!(expected, actual);
     }
@@ -135,7 +747,7 @@ mod tests {
         let light = Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white());
 
         let expected = Color::new(1.0, 1.0, 1.0);
-        let actual = material.lighting(position, light, eyev, normalv, false);
+        let actual = material.lighting(&object(), position, light, eyev, normalv, 1.0);
 
         assert_fuzzy_eq!(expected, actual);
     }
@@ -150,7 +762,7 @@ mod tests {
         let light = Light::point(Tuple::point(0.0, 10.0, -10.0), Color::white());
 
         let expected = Color::new(0.7364, 0.7364, 0.7364);
-        let actual = material.lighting(position, light, eyev, normalv, false);
+        let actual = material.lighting(&object(), position, light, eyev, normalv, 1.0);
 
         assert_fuzzy_eq!(expected, actual);
     }
@@ -166,7 +778,7 @@ mod tests {
         let light = Light::point(Tuple::point(0.0, 10.0, -10.0), Color::white());
 
         let expected = Color::new(1.6364, 1.6364, 1.6364);
-        let actual = material.lighting(position, light, eyev, normalv, false);
+        let actual = material.lighting(&object(), position, light, eyev, normalv, 1.0);
 
         assert_fuzzy_eq!(expected, actual);
     }
@@ -181,7 +793,7 @@ mod tests {
         let light = Light::point(Tuple::point(0.0, 0.0, 10.0), Color::white());
 
         let expected = Color::new(0.1, 0.1, 0.1);
-        let actual = material.lighting(position, light, eyev, normalv, false);
+        let actual = material.lighting(&object(), position, light, eyev, normalv, 1.0);
 
         assert_fuzzy_eq!(expected, actual);
     }
@@ -194,10 +806,10 @@ mod tests {
         let eyev = Tuple::vector(0.0, 0.0, -1.0);
         let normalv = Tuple::vector(0.0, 0.0, -1.0);
         let light = Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white());
-        let in_shadow = true;
+        let intensity = 0.0;
 
         let expected = Color::new(0.1, 0.1, 0.1);
-        let actual = material.lighting(position, light, eyev, normalv, in_shadow);
+        let actual = material.lighting(&object(), position, light, eyev, normalv, intensity);
 
         assert_fuzzy_eq!(expected, actual);
     }
@@ -218,9 +830,497 @@ mod tests {
         let normalv = Tuple::vector(0.0, 0.0, -1.0);
         let light = Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white());
 
-        let c1 = material.lighting(Tuple::point(0.9, 0.0, 0.0), light, eyev, normalv, false);
+        let object = object();
+        let c1 = material.lighting(&object, Tuple::point(0.9, 0.0, 0.0), light, eyev, normalv, 1.0);
         assert_fuzzy_eq!(Color::white(), c1);
-        let c2 = material.lighting(Tuple::point(1.1, 0.0, 0.0), light, eyev, normalv, false);
+        let c2 = material.lighting(&object, Tuple::point(1.1, 0.0, 0.0), light, eyev, normalv, 1.0);
         assert_fuzzy_eq!(Color::black(), c2);
     }
+
+    #[test]
+    fn lighting_with_pattern_uses_object_transform() {
+        let material = Material {
+            ambient: Color::grey(1.0),
+            diffuse: Color::grey(0.0),
+            pattern: Some(StripePattern::default().into()),
+            surface: Surface::Lambertian,
+            ..Default::default()
+        };
+        let object: Shape = SphereBuilder::default()
+            .transform(Matrix::scaling(2.0, 2.0, 2.0))
+            .build()
+            .unwrap()
+            .into();
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let light = Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white());
+
+        // x = 1.5 in the world is 0.75 on the scaled sphere
+        let c = material.lighting(&object, Tuple::point(1.5, 0.0, 0.0), light, eyev, normalv, 1.0);
+        assert_fuzzy_eq!(Color::white(), c);
+    }
+
+    fn spot_light() -> Light {
+        Light::spot(
+            Tuple::point(0.0, 0.0, -10.0),
+            Tuple::vector(0.0, 0.0, 1.0),
+            std::f64::consts::PI / 12.0,
+            std::f64::consts::PI / 6.0,
+            Color::white(),
+        )
+    }
+
+    #[test]
+    fn lighting_down_spot_light_axis_is_full_intensity() {
+        let material = Material::default();
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+
+        let position = Tuple::point(0.0, 0.0, 0.0);
+
+        let actual = material.lighting(&object(), position, spot_light(), eyev, normalv, 1.0);
+        assert_fuzzy_eq!(Color::new(1.9, 1.9, 1.9), actual);
+    }
+
+    #[test]
+    fn lighting_outside_spot_light_cone_is_only_ambient() {
+        let material = Material::default();
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        // Just past the 30 degree outer angle
+        let position = Tuple::point(10.0 * (31.0_f64.to_radians()).tan(), 0.0, 0.0);
+
+        let actual = material.lighting(&object(), position, spot_light(), eyev, normalv, 1.0);
+        assert_fuzzy_eq!(Color::new(0.1, 0.1, 0.1), actual);
+    }
+
+    #[test]
+    fn lighting_in_spot_light_penumbra_is_partial() {
+        let material = Material::default();
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let position = Tuple::point(10.0 * (std::f64::consts::PI / 8.0).tan(), 0.0, 0.0);
+
+        let lit = material.lighting(
+            &object(),
+            position,
+            Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white()),
+            eyev,
+            normalv,
+            1.0,
+        );
+        let falloff = spot_light().falloff(position);
+        assert!(falloff > 0.0 && falloff < 1.0);
+
+        let ambient = Color::new(0.1, 0.1, 0.1);
+        let actual = material.lighting(&object(), position, spot_light(), eyev, normalv, 1.0);
+        assert_fuzzy_eq!(ambient + (lit - ambient) * falloff, actual);
+        assert!(actual.fuzzy_ne(ambient) && actual.fuzzy_ne(lit));
+    }
+
+    #[test]
+    fn lighting_uses_light_intensity_to_attenuate_color() {
+        let material = Material {
+            ambient: Color::grey(0.1),
+            diffuse: Color::grey(0.9),
+            surface: Surface::Lambertian,
+            ..Default::default()
+        };
+        let position = Tuple::point(0.0, 0.0, -1.0);
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let light = Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white());
+
+        for (intensity, expected) in [(1.0, 1.0), (0.5, 0.55), (0.0, 0.1)] {
+            let actual = material.lighting(&object(), position, light, eyev, normalv, intensity);
+            assert_fuzzy_eq!(Color::new(expected, expected, expected), actual);
+        }
+    }
+
+    #[test]
+    fn default_specular_model_is_phong() {
+        assert_eq!(SpecularModel::Phong, Material::default().specular_model);
+        assert_eq!(
+            SpecularModel::Phong,
+            MaterialBuilder::default()
+                .color(Color::white())
+                .ambient(0.1)
+                .diffuse(0.9)
+                .specular(0.9)
+                .shininess(200.0)
+                .pattern(None)
+                .build()
+                .unwrap()
+                .specular_model
+        );
+    }
+
+    #[test]
+    fn specular_models_with_light_offset_45_deg() {
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let light = Light::point(Tuple::point(0.0, 10.0, -10.0), Color::white());
+        let phong = Material::default().with_shininess(10.0);
+        let blinn = Material {
+            specular_model: SpecularModel::BlinnPhong,
+            ..phong.clone()
+        };
+
+        // Phong raises cos(45°) to the shininess, Blinn-Phong the cosine of the half angle
+        let expected = Color::new(0.76452, 0.76452, 0.76452);
+        assert_fuzzy_eq!(expected, phong.lighting(&object(), position, light, eyev, normalv, 1.0));
+        let expected = Color::new(1.14415, 1.14415, 1.14415);
+        assert_fuzzy_eq!(expected, blinn.lighting(&object(), position, light, eyev, normalv, 1.0));
+    }
+
+    #[test]
+    fn specular_models_agree_with_eye_in_path_of_reflection() {
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let sqrt_2_2 = (2.0_f64.sqrt()) / 2.0;
+        let eyev = Tuple::vector(0.0, -sqrt_2_2, -sqrt_2_2);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let light = Light::point(Tuple::point(0.0, 10.0, -10.0), Color::white());
+        let blinn = Material {
+            specular_model: SpecularModel::BlinnPhong,
+            ..Default::default()
+        };
+
+        let expected = Color::new(1.6364, 1.6364, 1.6364);
+        assert_fuzzy_eq!(expected, blinn.lighting(&object(), position, light, eyev, normalv, 1.0));
+    }
+
+    #[test]
+    fn blinn_phong_specular_is_never_negative() {
+        let material = Material {
+            ambient: Color::grey(0.0),
+            diffuse: Color::grey(0.0),
+            specular_model: SpecularModel::BlinnPhong,
+            surface: Surface::Phong {
+                specular: Color::grey(0.9),
+                shininess: 3.0,
+            },
+            ..Default::default()
+        };
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let light = Light::point(Tuple::point(0.0, 10.0, -10.0), Color::white());
+
+        for step in 0..36 {
+            let angle = step as f64 * std::f64::consts::PI / 18.0;
+            let eyev = Tuple::vector(0.0, angle.sin(), -angle.cos());
+
+            let c = material.lighting(&object(), position, light, eyev, normalv, 1.0);
+            assert_fuzzy_eq!(c.clamp(0.0, f64::INFINITY), c);
+        }
+    }
+
+    #[test]
+    fn cook_torrance_with_low_roughness_is_a_mirror_highlight() {
+        let material = Material {
+            ambient: Color::grey(0.0),
+            diffuse: Color::grey(0.0),
+            specular_model: SpecularModel::CookTorrance {
+                roughness: 0.01,
+                metallic: 1.0,
+            },
+            surface: Surface::Phong {
+                specular: Color::grey(1.0),
+                shininess: 200.0,
+            },
+            ..Default::default()
+        };
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let sqrt_2_2 = (2.0_f64.sqrt()) / 2.0;
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let light = Light::point(Tuple::point(0.0, 10.0, -10.0), Color::white());
+
+        let in_path = Tuple::vector(0.0, -sqrt_2_2, -sqrt_2_2);
+        let c = material.lighting(&object(), position, light, in_path, normalv, 1.0);
+        assert_fuzzy_eq!(Color::white(), c.clamp(0.0, 1.0));
+
+        let off_path = Tuple::vector(0.0, 0.0, -1.0);
+        let c = material.lighting(&object(), position, light, off_path, normalv, 1.0);
+        assert_fuzzy_eq!(Color::black(), c);
+    }
+
+    // What the material sends back over the whole hemisphere above the surface, as a fraction of
+    // the light arriving at the given angle to the normal, weighing the channels by luminance. The
+    // diffuse term of lighting is its radiance times pi, so that's divided back out
+    fn reflected_energy(material: &Material, light_angle: f64) -> f64 {
+        use std::f64::consts::PI;
+
+        use itertools::Itertools;
+
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let light = Light::point(Tuple::point(10.0 * light_angle.sin(), 0.0, -10.0 * light_angle.cos()), Color::white());
+        let diffuse_only = material.clone().with_specular(Color::black());
+        let specular_only = material.clone().with_diffuse(0.0);
+
+        let (steps_theta, steps_phi) = (64, 128);
+        let (d_theta, d_phi) = (PI / 2.0 / steps_theta as f64, 2.0 * PI / steps_phi as f64);
+        let reflected = (0..steps_theta).cartesian_product(0..steps_phi).fold(Color::black(), |total, (i, j)| {
+            let (theta, phi) = ((i as f64 + 0.5) * d_theta, (j as f64 + 0.5) * d_phi);
+            let eyev = Tuple::vector(theta.sin() * phi.cos(), theta.sin() * phi.sin(), -theta.cos());
+            let radiance = diffuse_only.lighting(&object(), position, light, eyev, normalv, 1.0) * (1.0 / PI)
+                + specular_only.lighting(&object(), position, light, eyev, normalv, 1.0);
+            total + radiance * (theta.cos() * theta.sin() * d_theta * d_phi)
+        });
+        let arriving = light.color * light_angle.cos();
+
+        reflected.luminance() / arriving.luminance()
+    }
+
+    #[test]
+    fn cook_torrance_never_reflects_more_energy_than_arrives() {
+        let gold = Color::new(1.0, 0.8, 0.3);
+        for (color, metallic) in [(Color::white(), 0.0), (gold, 0.5), (gold, 1.0)] {
+            for roughness in [0.3, 0.6, 1.0] {
+                let material = Material {
+                    color,
+                    ambient: Color::grey(0.0),
+                    diffuse: Color::grey(1.0),
+                    specular_model: SpecularModel::CookTorrance { roughness, metallic },
+                    ..Default::default()
+                }
+                .with_specular(1.0);
+
+                for light_angle in [0.0, 0.5, 1.0, 1.4] {
+                    let energy = reflected_energy(&material, light_angle);
+                    assert!(
+                        energy > 0.0 && energy <= 1.0,
+                        "metallic {}, roughness {}, light at {}: {}",
+                        metallic,
+                        roughness,
+                        light_angle,
+                        energy
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cook_torrance_metal_reflects_its_own_color() {
+        let gold = Color::new(1.0, 0.8, 0.3);
+        let material = Material {
+            color: gold,
+            ambient: Color::grey(0.0),
+            specular_model: SpecularModel::CookTorrance {
+                roughness: 0.3,
+                metallic: 1.0,
+            },
+            ..Default::default()
+        };
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let light = Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white());
+
+        // Metals have no diffuse part, and head on the Fresnel term is exactly the color
+        let c = material.lighting(&object(), position, light, eyev, normalv, 1.0);
+        let white = Material {
+            color: Color::white(),
+            ..material
+        }
+        .lighting(&object(), position, light, eyev, normalv, 1.0);
+        assert_fuzzy_eq!(white * gold, c);
+    }
+
+    #[test]
+    fn doubling_light_intensity_doubles_diffuse_and_specular() {
+        let material = Material {
+            ambient: Color::grey(0.0),
+            ..Default::default()
+        };
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let sqrt_2_2 = (2.0_f64.sqrt()) / 2.0;
+        let eyev = Tuple::vector(0.0, -sqrt_2_2, -sqrt_2_2);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let light_position = Tuple::point(0.0, 10.0, -10.0);
+        let single = Light::point(light_position, Color::white());
+        let double = Light::point_with_intensity(light_position, Color::white(), 2.0);
+
+        let c1 = material.lighting(&object(), position, single, eyev, normalv, 1.0);
+        let c2 = material.lighting(&object(), position, double, eyev, normalv, 1.0);
+        assert_fuzzy_eq!(Color::new(1.5364, 1.5364, 1.5364), c1);
+        assert_fuzzy_eq!(c1 * 2.0, c2);
+    }
+
+    #[test]
+    fn bright_light_clamps_to_white_on_canvas() {
+        let material = Material::default();
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let light = Light::point_with_intensity(Tuple::point(0.0, 0.0, -10.0), Color::white(), 2.0);
+
+        let c = material.lighting(&object(), position, light, eyev, normalv, 1.0);
+        assert_fuzzy_eq!(Color::new(3.8, 3.8, 3.8), c);
+        assert_eq!(vec![255, 255, 255, 255], c.to_rgba32());
+    }
+
+    #[test]
+    fn glass_preset() {
+        let m = Material::glass();
+
+        assert_fuzzy_eq!(Color::white(), m.color);
+        assert_fuzzy_eq!(Color::grey(0.0), m.ambient);
+        assert_fuzzy_eq!(Color::grey(0.1), m.diffuse);
+        assert_fuzzy_eq!(Color::grey(1.0), m.specular());
+        assert_fuzzy_eq!(300.0, m.shininess());
+        assert_fuzzy_eq!(0.9, m.reflective());
+        assert_fuzzy_eq!(1.0, m.transparency());
+        assert_fuzzy_eq!(1.5, m.refractive_index());
+    }
+
+    #[test]
+    fn mirror_preset() {
+        let m = Material::mirror();
+
+        assert_fuzzy_eq!(Color::black(), m.color);
+        assert_fuzzy_eq!(Color::grey(0.0), m.ambient);
+        assert_fuzzy_eq!(Color::grey(0.0), m.diffuse);
+        assert_fuzzy_eq!(Color::grey(1.0), m.specular());
+        assert_fuzzy_eq!(300.0, m.shininess());
+        assert_fuzzy_eq!(1.0, m.reflective());
+        assert_fuzzy_eq!(0.0, m.transparency());
+    }
+
+    #[test]
+    fn matte_preset() {
+        let m = Material::matte(Color::red());
+
+        assert_fuzzy_eq!(Color::red(), m.color);
+        assert_fuzzy_eq!(Color::grey(0.1), m.ambient);
+        assert_fuzzy_eq!(Color::grey(0.9), m.diffuse);
+        assert_fuzzy_eq!(Color::grey(0.0), m.specular());
+        assert_eq!(Surface::Lambertian, m.surface);
+        assert_fuzzy_eq!(0.0, m.reflective());
+        assert_fuzzy_eq!(0.0, m.transparency());
+    }
+
+    #[test]
+    fn metal_preset() {
+        let polished = Material::metal(Color::blue(), 0.0);
+        assert_fuzzy_eq!(Color::blue(), polished.color);
+        assert_fuzzy_eq!(Color::grey(0.1), polished.ambient);
+        assert_fuzzy_eq!(Color::grey(0.3), polished.diffuse);
+        assert_fuzzy_eq!(Color::grey(0.9), polished.specular());
+        assert_fuzzy_eq!(300.0, polished.shininess());
+        assert_fuzzy_eq!(0.8, polished.reflective());
+
+        let brushed = Material::metal(Color::blue(), 0.5);
+        assert_fuzzy_eq!(155.0, brushed.shininess());
+        assert_fuzzy_eq!(0.4, brushed.reflective());
+
+        // Fuzz past 1 is treated as 1
+        let rough = Material::metal(Color::blue(), 3.0);
+        assert_fuzzy_eq!(10.0, rough.shininess());
+        assert_fuzzy_eq!(0.0, rough.reflective());
+    }
+
+    #[test]
+    fn default_material_is_a_phong_surface() {
+        assert_eq!(
+            Surface::Phong {
+                specular: Color::grey(0.9),
+                shininess: 200.0
+            },
+            Material::default().surface
+        );
+    }
+
+    #[test]
+    fn presets_hold_their_kind_of_surface() {
+        assert_eq!(Surface::Lambertian, Material::matte(Color::red()).surface);
+        assert_eq!(
+            Surface::Metal {
+                specular: Color::grey(1.0),
+                shininess: 300.0,
+                reflective: 1.0
+            },
+            Material::mirror().surface
+        );
+        assert_eq!(
+            Surface::Dielectric {
+                specular: Color::grey(1.0),
+                shininess: 300.0,
+                reflective: 0.9,
+                transparency: 1.0,
+                ior: 1.5
+            },
+            Material::glass().surface
+        );
+        match Material::metal(Color::blue(), 0.3).surface {
+            Surface::Metal { reflective, .. } => assert_fuzzy_eq!(0.56, reflective),
+            s => panic!("expected a metal, got {:?}", s),
+        }
+    }
+
+    #[test]
+    fn materials_round_trip_through_their_surface() {
+        let materials = [
+            Material::mirror(),
+            Material::glass(),
+            Material::matte(Color::red()),
+            Material::metal(Color::new(0.8, 0.6, 0.2), 0.25),
+            Material::with_color(Color::blue()).with_specular(0.3).with_reflective(0.4),
+        ];
+
+        for m in materials {
+            let rebuilt = Material {
+                color: m.color,
+                ..Material::from(m.surface)
+            };
+            assert_eq!(m.surface, rebuilt.surface);
+            assert_eq!(m.color, rebuilt.color);
+            assert_eq!(m.reflective(), rebuilt.reflective());
+        }
+        assert_eq!(1.0, Material::mirror().reflective());
+    }
+
+    #[test]
+    fn setters_never_lose_earlier_surface_fields() {
+        let glass_first = Material::matte(Color::red())
+            .with_refractive_index(1.3)
+            .with_shininess(50.0)
+            .with_transparency(0.6)
+            .with_reflective(0.4)
+            .with_specular(0.3);
+        let glass_last = Material::matte(Color::red())
+            .with_specular(0.3)
+            .with_reflective(0.4)
+            .with_shininess(50.0)
+            .with_transparency(0.6)
+            .with_refractive_index(1.3);
+
+        let expected = Surface::Dielectric {
+            specular: Color::grey(0.3),
+            shininess: 50.0,
+            reflective: 0.4,
+            transparency: 0.6,
+            ior: 1.3,
+        };
+        assert_fuzzy_eq!(expected, glass_first.surface);
+        assert_fuzzy_eq!(expected, glass_last.surface);
+        assert_eq!(Color::red(), glass_first.color);
+    }
+
+    #[test]
+    fn metal_surface_is_opaque_and_dielectric_is_transparent() {
+        let metal = Material::from(Surface::Metal {
+            specular: Color::grey(0.9),
+            shininess: 300.0,
+            reflective: 0.8,
+        });
+        assert_fuzzy_eq!(0.0, metal.transparency());
+        assert_fuzzy_eq!(0.8, metal.reflective());
+
+        let water = Material::default().with_transparency(0.9).with_refractive_index(1.33);
+        assert!(matches!(water.surface, Surface::Dielectric { .. }));
+        assert_fuzzy_eq!(0.9, water.transparency());
+        assert_fuzzy_eq!(1.33, water.refractive_index());
+    }
 }
@@ -1,6 +1,12 @@
-use crate::{tuple::Tuple, util::FuzzyEq};
+use crate::{error::Error, tuple::Tuple, util::FuzzyEq};
 use std::ops::{Index, IndexMut, Mul};
 
+#[cfg(test)]
+thread_local! {
+    // How many 4x4 inverses were worked out on this thread, for tests of what gets cached
+    pub(crate) static INVERSIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
 pub struct Matrix<const D: usize>
 where
@@ -36,6 +42,17 @@ impl<const D: usize> Matrix<D> {
         matrix
     }
 
+    // Element by element blend towards other, which is exact for translations and scalings
+    pub fn lerp(&self, other: Self, t: f64) -> Self {
+        let mut m = *self;
+        for row in 0..D {
+            for column in 0..D {
+                m[row][column] += (other[row][column] - self[row][column]) * t;
+            }
+        }
+        m
+    }
+
     pub fn tranpose(&self) -> Self {
         let mut res: Matrix<D> = Matrix::default();
 
@@ -80,6 +97,21 @@ impl<const D: usize> Matrix<D> {
     }
 }
 
+// Written out as its rows, each a list of four numbers
+#[cfg(feature = "serde")]
+impl serde::Serialize for Matrix<4> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.data.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Matrix<4> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <[[f64; 4]; 4]>::deserialize(deserializer).map(|data| Self { data })
+    }
+}
+
 impl<const D: usize> Index<usize> for Matrix<D> {
     type Output = [f64; D];
 
@@ -144,7 +176,7 @@ impl Matrix<3> {
 
     pub fn cofactor(&self, row: usize, column: usize) -> f64 {
         let minor = self.minor(row, column);
-        if (row + column) % 2 == 0 {
+        if (row + column).is_multiple_of(2) {
             minor
         } else {
             -minor
@@ -191,7 +223,7 @@ impl Matrix<4> {
 
     pub fn cofactor(&self, row: usize, column: usize) -> f64 {
         let minor = self.minor(row, column);
-        if (row + column) % 2 == 0 {
+        if (row + column).is_multiple_of(2) {
             minor
         } else {
             -minor
@@ -212,8 +244,14 @@ impl Matrix<4> {
     }
 
     pub fn inverse(&self) -> Matrix<4> {
+        self.try_inverse().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_inverse(&self) -> Result<Matrix<4>, Error> {
+        #[cfg(test)]
+        INVERSIONS.with(|c| c.set(c.get() + 1));
         if !self.is_invertible() {
-            panic!("Matrix is not invertible, but inverse was called!");
+            return Err(Error::NonInvertibleTransform { matrix: Box::new(*self) });
         }
 
         let mut matrix = Matrix::new();
@@ -227,7 +265,7 @@ impl Matrix<4> {
             }
         }
 
-        matrix
+        Ok(matrix)
     }
 
     pub fn translation(x: f64, y: f64, z: f64) -> Matrix<4> {
@@ -1087,4 +1125,24 @@ mod tests {
         let t = Matrix::view_transform(from, to, up);
         assert_fuzzy_eq!(expected, t);
     }
+
+    #[test]
+    fn lerp_blends_between_translations() {
+        let a = Matrix::translation(0.0, 0.0, 0.0);
+        let b = Matrix::translation(4.0, -2.0, 0.0);
+
+        assert_fuzzy_eq!(a, a.lerp(b, 0.0));
+        assert_fuzzy_eq!(Matrix::translation(2.0, -1.0, 0.0), a.lerp(b, 0.5));
+        assert_fuzzy_eq!(b, a.lerp(b, 1.0));
+    }
+
+    #[test]
+    fn inverting_a_singular_matrix_is_an_error_holding_it() {
+        let flat = Matrix::scaling(1.0, 0.0, 1.0);
+        let err = flat.try_inverse().unwrap_err();
+
+        assert_eq!(Error::NonInvertibleTransform { matrix: Box::new(flat) }, err);
+        assert!(err.to_string().contains(&format!("{:?}", flat)));
+        assert_fuzzy_eq!(Matrix::identity(), Matrix::<4>::identity().try_inverse().unwrap());
+    }
 }
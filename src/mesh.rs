@@ -0,0 +1,411 @@
+use std::sync::Arc;
+
+use crate::{
+    bounding_box::BoundingBox,
+    error::Error,
+    intersection::{Intersection, Intersections},
+    light::LightId,
+    material::Material,
+    matrix::Matrix,
+    ray::Ray,
+    shape::{next_shape_id, Shape, ShapeFuncs},
+    triangle::intersect_triangle,
+    tuple::Tuple,
+    util::{FuzzyEq, EPSILON},
+};
+
+// An indexed triangle mesh. The buffers are shared, so the copies handed out in intersections
+// only differ in the face that was hit. Faces with a material of their own, like the ones an OBJ
+// file picks with usemtl, index into materials, the rest use material
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mesh {
+    #[cfg_attr(feature = "serde", serde(skip, default = "next_shape_id"))]
+    pub(crate) id: u64,
+    pub transform: Matrix<4>,
+    pub transform_at_t1: Option<Matrix<4>>,
+    pub material: Material,
+    pub excluded_lights: Vec<LightId>,
+    pub vertices: Arc<Vec<Tuple>>,
+    pub normals: Option<Arc<Vec<Tuple>>>,
+    pub indices: Arc<Vec<[u32; 3]>>,
+    pub materials: Arc<Vec<Material>>,
+    pub face_materials: Arc<Vec<Option<u32>>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hit_face: Option<usize>,
+}
+
+impl Mesh {
+    // When normals are given there is one per vertex, and they are interpolated across faces
+    pub fn new(vertices: Vec<Tuple>, normals: Option<Vec<Tuple>>, indices: Vec<[u32; 3]>) -> Self {
+        Self::try_new(vertices, normals, indices).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(vertices: Vec<Tuple>, normals: Option<Vec<Tuple>>, indices: Vec<[u32; 3]>) -> Result<Self, Error> {
+        let len = normals.as_ref().map_or(vertices.len(), |n| n.len().min(vertices.len()));
+        for (face, corners) in indices.iter().enumerate() {
+            if let Some(&index) = corners.iter().find(|&&i| i as usize >= len) {
+                return Err(Error::FaceIndexOutOfBounds { face, index: index as usize, len });
+            }
+        }
+
+        Ok(Self {
+            id: next_shape_id(),
+            transform: Matrix::identity(),
+            transform_at_t1: None,
+            material: Material::default(),
+            excluded_lights: vec![],
+            vertices: Arc::new(vertices),
+            normals: normals.map(Arc::new),
+            indices: Arc::new(indices),
+            materials: Arc::new(vec![]),
+            face_materials: Arc::new(vec![]),
+            hit_face: None,
+        })
+    }
+
+    // One entry per face, faces past the end of face_materials use the mesh's material
+    pub fn with_face_materials(self, materials: Vec<Material>, face_materials: Vec<Option<u32>>) -> Self {
+        self.try_with_face_materials(materials, face_materials)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_with_face_materials(
+        mut self,
+        materials: Vec<Material>,
+        face_materials: Vec<Option<u32>>,
+    ) -> Result<Self, Error> {
+        let len = materials.len();
+        for (face, material) in face_materials.iter().enumerate() {
+            if let Some(material) = material.map(|m| m as usize).filter(|&m| m >= len) {
+                return Err(Error::FaceMaterialOutOfBounds { face, material, len });
+            }
+        }
+
+        self.materials = Arc::new(materials);
+        self.face_materials = Arc::new(face_materials);
+        Ok(self)
+    }
+
+    pub fn hit_face(&self) -> Option<usize> {
+        self.hit_face
+    }
+
+    fn face(&self, face: usize) -> [Tuple; 3] {
+        self.indices[face].map(|i| self.vertices[i as usize])
+    }
+
+    // Barycentric coordinates of the point on the face's plane, u weighs p2 and v weighs p3
+    fn barycentric(&self, face: usize, point: Tuple) -> (f64, f64) {
+        let [p1, p2, p3] = self.face(face);
+        let (e1, e2, p) = (p2 - p1, p3 - p1, point - p1);
+        let d00 = e1.dot(e1);
+        let d01 = e1.dot(e2);
+        let d11 = e2.dot(e2);
+        let d20 = p.dot(e1);
+        let d21 = p.dot(e2);
+        let denom = d00 * d11 - d01 * d01;
+
+        ((d11 * d20 - d01 * d21) / denom, (d00 * d21 - d01 * d20) / denom)
+    }
+
+    // For a mesh that wasn't handed out by an intersection. The faces the point lies over come
+    // first, and of those the one whose plane is nearest
+    fn face_at(&self, point: Tuple) -> Option<usize> {
+        let key = |face: usize| {
+            let [p1, p2, p3] = self.face(face);
+            let distance = (point - p1).dot((p3 - p1).cross(p2 - p1).normalize()).abs();
+            let (u, v) = self.barycentric(face, point);
+            let over = u >= -EPSILON && v >= -EPSILON && u + v <= 1.0 + EPSILON;
+            (!over, distance)
+        };
+
+        (0..self.indices.len()).min_by(|&a, &b| key(a).partial_cmp(&key(b)).unwrap_or(std::cmp::Ordering::Equal))
+    }
+}
+
+impl ShapeFuncs for Mesh {
+    fn local_intersect(&self, object_space_ray: Ray) -> Intersections {
+        let xs = (0..self.indices.len())
+            .filter_map(|face| {
+                let [p1, p2, p3] = self.face(face);
+                intersect_triangle(object_space_ray, p1, p2 - p1, p3 - p1).map(|t| {
+                    let hit = Mesh {
+                        hit_face: Some(face),
+                        ..self.clone()
+                    };
+                    Intersection::new(t, Shape::from(hit).at_time(object_space_ray.time))
+                })
+            })
+            .collect();
+
+        Intersections::new(xs)
+    }
+
+    fn local_normal_at(&self, object_point: Tuple) -> Tuple {
+        // A mesh without faces has no surface to be normal to, so any direction will do
+        let Some(face) = self.hit_face.or_else(|| self.face_at(object_point)) else {
+            return Tuple::vector(0.0, 1.0, 0.0);
+        };
+        let [p1, p2, p3] = self.face(face);
+
+        let normals = match &self.normals {
+            None => return (p3 - p1).cross(p2 - p1).normalize(),
+            Some(normals) => self.indices[face].map(|i| normals[i as usize]),
+        };
+        let (u, v) = self.barycentric(face, object_point);
+
+        normals[1] * u + normals[2] * v + normals[0] * (1.0 - u - v)
+    }
+
+    fn material(&self) -> Material {
+        match self.hit_face.and_then(|face| self.face_materials.get(face).copied().flatten()) {
+            Some(m) => self.materials[m as usize].clone(),
+            None => self.material.clone(),
+        }
+    }
+
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn transform_at_t1(&self) -> Option<Matrix<4>> {
+        self.transform_at_t1
+    }
+
+    fn excluded_lights(&self) -> &[LightId] {
+        &self.excluded_lights
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut bounds = BoundingBox::default();
+        for vertex in self.vertices.iter() {
+            bounds.add_point(*vertex);
+        }
+        bounds
+    }
+}
+
+impl FuzzyEq<Self> for Mesh {
+    fn fuzzy_eq(&self, other: Self) -> bool {
+        self.transform.fuzzy_eq(other.transform)
+            && self.material.fuzzy_eq(other.material)
+            && self.indices == other.indices
+            && self.face_materials == other.face_materials
+            && self.materials.len() == other.materials.len()
+            && self
+                .materials
+                .iter()
+                .zip(other.materials.iter())
+                .all(|(a, b)| a.fuzzy_eq(b.clone()))
+            && self.vertices.len() == other.vertices.len()
+            && self
+                .vertices
+                .iter()
+                .zip(other.vertices.iter())
+                .all(|(a, b)| a.fuzzy_eq(*b))
+    }
+
+    fn fuzzy_ne(&self, other: Self) -> bool {
+        !self.fuzzy_eq(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_fuzzy_eq, obj::parse_obj};
+
+    use super::*;
+
+    fn smooth_mesh() -> Mesh {
+        Mesh::new(
+            vec![
+                Tuple::point(0.0, 1.0, 0.0),
+                Tuple::point(-1.0, 0.0, 0.0),
+                Tuple::point(1.0, 0.0, 0.0),
+            ],
+            Some(vec![
+                Tuple::vector(0.0, 1.0, 0.0),
+                Tuple::vector(-1.0, 0.0, 0.0),
+                Tuple::vector(1.0, 0.0, 0.0),
+            ]),
+            vec![[0, 1, 2]],
+        )
+    }
+
+    #[test]
+    fn intersection_remembers_face_that_was_hit() {
+        let m = Mesh::new(
+            vec![
+                Tuple::point(0.0, 1.0, 0.0),
+                Tuple::point(-1.0, 0.0, 0.0),
+                Tuple::point(1.0, 0.0, 0.0),
+                Tuple::point(0.0, -1.0, 0.0),
+            ],
+            None,
+            vec![[0, 1, 2], [1, 3, 2]],
+        );
+        let r = Ray::new(Tuple::point(0.0, -0.5, -2.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        let xs = m.intersect(r);
+        assert_eq!(1, xs.intersections.len());
+        assert_fuzzy_eq!(2.0, xs.intersections[0].t);
+        match &xs.intersections[0].object {
+            Shape::Mesh(hit) => assert_eq!(Some(1), hit.hit_face()),
+            s => panic!("expected a mesh, got {:?}", s),
+        }
+    }
+
+    #[test]
+    fn mesh_interpolates_vertex_normals() {
+        let m = smooth_mesh();
+        let r = Ray::new(Tuple::point(-0.2, 0.3, -2.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        let xs = m.intersect(r);
+        let hit = &xs.intersections[0];
+        let n = hit.object.normal_at(r.position(hit.t));
+
+        assert_fuzzy_eq!(Tuple::vector(-0.55470, 0.83205, 0.0), n);
+    }
+
+    const MODEL: &str = "v -1 1 0\n\
+                         v -1 0 0\n\
+                         v 1 0 0\n\
+                         v 1 1 0\n\
+                         v 0 2 1\n\
+                         v 0 -1 -1\n\
+                         f 1 2 3 4\n\
+                         f 1 4 5\n\
+                         f 2 6 3\n";
+
+    #[test]
+    fn mesh_and_triangle_group_intersect_identically() {
+        let group = parse_obj(MODEL.as_bytes()).unwrap().into_group();
+        let mesh = parse_obj(MODEL.as_bytes()).unwrap().into_mesh();
+        let rays = vec![
+            Ray::new(Tuple::point(0.0, 0.5, -5.0), Tuple::vector(0.0, 0.0, 1.0)),
+            Ray::new(Tuple::point(0.2, 1.2, -5.0), Tuple::vector(0.0, 0.0, 1.0)),
+            Ray::new(Tuple::point(0.0, -0.4, -5.0), Tuple::vector(0.0, 0.1, 1.0)),
+            Ray::new(Tuple::point(5.0, 0.5, 0.5), Tuple::vector(-1.0, 0.0, 0.0)),
+            Ray::new(Tuple::point(3.0, 3.0, -5.0), Tuple::vector(0.0, 0.0, 1.0)),
+        ];
+
+        for r in rays {
+            let expected = group.intersect(r);
+            let actual = mesh.intersect(r);
+
+            assert_eq!(expected.intersections.len(), actual.intersections.len());
+            for (e, a) in expected.into_iter().zip(actual) {
+                assert_fuzzy_eq!(e.t, a.t);
+                let p = r.position(e.t);
+                assert_fuzzy_eq!(e.object.normal_at(p), a.object.normal_at(p));
+            }
+        }
+    }
+
+    #[test]
+    fn mesh_stores_fewer_tuples_than_triangles() {
+        let group = parse_obj(MODEL.as_bytes()).unwrap().into_group();
+        let triangles = match &group {
+            Shape::Group(g) => g.children().iter().filter(|c| matches!(c, Shape::Triangle(_))).count(),
+            s => panic!("expected a group, got {:?}", s),
+        };
+        let mesh = match parse_obj(MODEL.as_bytes()).unwrap().into_mesh() {
+            Shape::Mesh(m) => m,
+            s => panic!("expected a mesh, got {:?}", s),
+        };
+
+        // Every Triangle keeps at least its three points, the mesh each vertex once
+        let mesh_tuples = mesh.vertices.len() + mesh.normals.as_ref().map_or(0, |n| n.len());
+        assert_eq!(4, triangles);
+        assert_eq!(6, mesh_tuples);
+        assert!(mesh_tuples * 2 <= triangles * 3);
+    }
+
+    #[test]
+    fn face_past_the_vertices_is_an_error() {
+        let vertices = vec![
+            Tuple::point(0.0, 1.0, 0.0),
+            Tuple::point(-1.0, 0.0, 0.0),
+            Tuple::point(1.0, 0.0, 0.0),
+        ];
+
+        assert_eq!(
+            Some(Error::FaceIndexOutOfBounds { face: 1, index: 3, len: 3 }),
+            Mesh::try_new(vertices.clone(), None, vec![[0, 1, 2], [0, 2, 3]]).err()
+        );
+        assert_eq!(
+            Some(Error::FaceIndexOutOfBounds { face: 0, index: 2, len: 2 }),
+            Mesh::try_new(vertices.clone(), Some(vertices[..2].to_vec()), vec![[0, 1, 2]]).err()
+        );
+        assert_eq!(
+            Some(Error::FaceMaterialOutOfBounds { face: 0, material: 1, len: 1 }),
+            Mesh::new(vertices, None, vec![[0, 1, 2]])
+                .try_with_face_materials(vec![Material::default()], vec![Some(1)])
+                .err()
+        );
+    }
+
+    #[test]
+    fn intersections_share_the_mesh_buffers() {
+        let mesh = match parse_obj(MODEL.as_bytes()).unwrap().into_mesh() {
+            Shape::Mesh(m) => m,
+            s => panic!("expected a mesh, got {:?}", s),
+        };
+        let r = Ray::new(Tuple::point(0.0, 0.5, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        let xs = mesh.intersect(r);
+        assert!(!xs.intersections.is_empty());
+        for x in &xs.intersections {
+            let Shape::Mesh(hit) = &x.object else { panic!("expected a mesh, got {:?}", x.object) };
+            assert!(Arc::ptr_eq(&mesh.vertices, &hit.vertices));
+            assert!(Arc::ptr_eq(&mesh.indices, &hit.indices));
+            assert!(Arc::ptr_eq(&mesh.face_materials, &hit.face_materials));
+        }
+        // The mesh and each hit point at the one copy of the vertices
+        assert_eq!(1 + xs.intersections.len(), Arc::strong_count(&mesh.vertices));
+    }
+
+    #[test]
+    fn faces_keep_their_own_materials() {
+        let red = Material::with_color(crate::color::Color::red());
+        let m = Mesh::new(
+            vec![
+                Tuple::point(0.0, 1.0, 0.0),
+                Tuple::point(-1.0, 0.0, 0.0),
+                Tuple::point(1.0, 0.0, 0.0),
+                Tuple::point(0.0, -1.0, 0.0),
+            ],
+            None,
+            vec![[0, 1, 2], [1, 3, 2]],
+        )
+        .with_face_materials(vec![red.clone()], vec![Some(0)]);
+        let material_hit = |y: f64| {
+            let r = Ray::new(Tuple::point(0.0, y, -2.0), Tuple::vector(0.0, 0.0, 1.0));
+            m.intersect(r).intersections[0].object.material()
+        };
+
+        assert_fuzzy_eq!(red, material_hit(0.5));
+        assert_fuzzy_eq!(Material::default(), material_hit(-0.5));
+    }
+
+    #[test]
+    fn normal_without_a_hit_face_comes_from_the_face_under_the_point() {
+        let m = smooth_mesh();
+        let r = Ray::new(Tuple::point(-0.2, 0.3, -2.0), Tuple::vector(0.0, 0.0, 1.0));
+        let hit = &m.intersect(r).intersections[0];
+        let point = r.position(hit.t);
+
+        assert_fuzzy_eq!(hit.object.normal_at(point), Shape::from(m).normal_at(point));
+        let empty = Mesh::new(vec![], None, vec![]);
+        assert_fuzzy_eq!(Tuple::vector(0.0, 1.0, 0.0), empty.local_normal_at(Tuple::point(0.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn mesh_bounds_contain_all_vertices() {
+        let b = smooth_mesh().bounds();
+
+        assert_fuzzy_eq!(Tuple::point(-1.0, 0.0, 0.0), b.min);
+        assert_fuzzy_eq!(Tuple::point(1.0, 1.0, 0.0), b.max);
+    }
+}
@@ -0,0 +1,174 @@
+// Ken Perlin's improved noise. The permutation decides which gradient each lattice point gets,
+// so the same seed always gives the same noise
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Perlin {
+    permutation: Vec<usize>,
+}
+
+// The table from Perlin's reference implementation
+const REFERENCE_PERMUTATION: [usize; 256] = [
+    151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225, 140, 36, 103, 30, 69,
+    142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148, 247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219,
+    203, 117, 35, 11, 32, 57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+    74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122, 60, 211, 133, 230,
+    220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54, 65, 25, 63, 161, 1, 216, 80, 73, 209, 76,
+    132, 187, 208, 89, 18, 169, 200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173,
+    186, 3, 64, 52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212, 207, 206,
+    59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213, 119, 248, 152, 2, 44, 154, 163,
+    70, 221, 153, 101, 155, 167, 43, 172, 9, 129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232,
+    178, 185, 112, 104, 218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162,
+    241, 81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157, 184, 84, 204,
+    176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93, 222, 114, 67, 29, 24, 72, 243,
+    141, 128, 195, 78, 66, 215, 61, 156, 180,
+];
+
+impl Perlin {
+    // Shuffles the lattice with a splitmix64 generator started from the seed
+    pub fn new(seed: u64) -> Self {
+        let mut state = seed;
+        let mut next = move || {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+
+        let mut permutation: Vec<usize> = (0..256).collect();
+        for i in (1..256).rev() {
+            let j = (next() % (i as u64 + 1)) as usize;
+            permutation.swap(i, j);
+        }
+
+        Self::from_permutation(&permutation)
+    }
+
+    pub fn reference() -> Self {
+        Self::from_permutation(&REFERENCE_PERMUTATION)
+    }
+
+    // Repeating the table saves wrapping the index when neighbouring corners are looked up
+    fn from_permutation(permutation: &[usize]) -> Self {
+        Self {
+            permutation: permutation.iter().chain(permutation).copied().collect(),
+        }
+    }
+
+    // Between -1 and 1, and exactly 0 on every lattice point
+    pub fn noise(&self, x: f64, y: f64, z: f64) -> f64 {
+        let p = &self.permutation;
+        let (xi, yi, zi) = (
+            x.floor() as i64 as usize & 255,
+            y.floor() as i64 as usize & 255,
+            z.floor() as i64 as usize & 255,
+        );
+        let (x, y, z) = (x - x.floor(), y - y.floor(), z - z.floor());
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let a = p[xi] + yi;
+        let aa = p[a] + zi;
+        let ab = p[a + 1] + zi;
+        let b = p[xi + 1] + yi;
+        let ba = p[b] + zi;
+        let bb = p[b + 1] + zi;
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, grad(p[aa], x, y, z), grad(p[ba], x - 1.0, y, z)),
+                lerp(u, grad(p[ab], x, y - 1.0, z), grad(p[bb], x - 1.0, y - 1.0, z)),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    grad(p[aa + 1], x, y, z - 1.0),
+                    grad(p[ba + 1], x - 1.0, y, z - 1.0),
+                ),
+                lerp(
+                    u,
+                    grad(p[ab + 1], x, y - 1.0, z - 1.0),
+                    grad(p[bb + 1], x - 1.0, y - 1.0, z - 1.0),
+                ),
+            ),
+        )
+    }
+}
+
+impl Default for Perlin {
+    fn default() -> Self {
+        Self::reference()
+    }
+}
+
+// 6t^5 - 15t^4 + 10t^3, which eases in and out of each cell
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+// Dot product of the offset with one of 12 edge gradients, picked by the low bits of the hash
+fn grad(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = match h {
+        0..=3 => y,
+        12 | 14 => x,
+        _ => z,
+    };
+
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_fuzzy_eq, util::FuzzyEq};
+
+    use super::*;
+
+    #[test]
+    fn noise_is_zero_on_lattice_points() {
+        for perlin in [Perlin::reference(), Perlin::new(7)] {
+            for (x, y, z) in [(0.0, 0.0, 0.0), (3.0, 4.0, 5.0), (-2.0, 17.0, -300.0)] {
+                assert_fuzzy_eq!(0.0, perlin.noise(x, y, z));
+            }
+        }
+    }
+
+    #[test]
+    fn reference_noise_matches_known_values() {
+        let perlin = Perlin::reference();
+
+        assert_fuzzy_eq!(0.22412109375, perlin.noise(3.25, 42.0, 7.0));
+        assert_fuzzy_eq!(-0.27359523416, perlin.noise(-0.3, 0.6, 12.9));
+        assert_fuzzy_eq!(-0.25, perlin.noise(0.5, 0.5, 0.5));
+        assert_fuzzy_eq!(0.14238739013671875, perlin.noise(1.25, -2.75, 0.5));
+    }
+
+    #[test]
+    fn seeded_noise_is_deterministic() {
+        let a = Perlin::new(42);
+        let b = Perlin::new(42);
+        let c = Perlin::new(43);
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(a.noise(1.3, 2.7, -0.4), b.noise(1.3, 2.7, -0.4));
+    }
+
+    #[test]
+    fn noise_stays_within_unit_range() {
+        let perlin = Perlin::new(1);
+
+        for i in 0..1000 {
+            let t = i as f64 * 0.137;
+            let n = perlin.noise(t, t * 0.7 - 3.0, 11.0 - t * 1.3);
+            assert!((-1.0..=1.0).contains(&n));
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::tuple::Tuple;
+
+// Procedural bumps that tilt a shape's normal without changing its geometry. The offset is
+// worked out in object space and added to the object space normal before it is normalized
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(tag = "type"))]
+pub enum NormalMap {
+    // Waves running along x and z, like a heightfield of sines
+    Ripple { amplitude: f64, frequency: f64 },
+}
+
+impl NormalMap {
+    pub fn perturbation_at(&self, object_point: Tuple) -> Tuple {
+        match *self {
+            Self::Ripple {
+                amplitude,
+                frequency,
+            } => Tuple::vector(
+                amplitude * (frequency * object_point.x).sin(),
+                0.0,
+                amplitude * (frequency * object_point.z).sin(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::{
+        assert_fuzzy_eq,
+        color::Color,
+        light::Light,
+        material::Material,
+        matrix::Matrix,
+        plane::{Plane, PlaneBuilder},
+        ray::Ray,
+        shape::{Shape, ShapeFuncs},
+        util::FuzzyEq,
+        world::World,
+    };
+
+    use super::*;
+
+    fn rippled_plane() -> Shape {
+        PlaneBuilder::default()
+            .material(Material {
+                normal_perturbation: Some(NormalMap::Ripple {
+                    amplitude: 0.5,
+                    frequency: PI,
+                }),
+                ..Default::default()
+            })
+            .build()
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn no_perturbation_leaves_normal_alone() {
+        let p: Shape = Plane::default().into();
+
+        assert_fuzzy_eq!(
+            Tuple::vector(0.0, 1.0, 0.0),
+            p.normal_at(Tuple::point(0.3, 0.0, 0.7))
+        );
+    }
+
+    #[test]
+    fn ripple_tilts_plane_normal_along_x() {
+        let p = rippled_plane();
+
+        assert_fuzzy_eq!(
+            Tuple::vector(0.0, 1.0, 0.0),
+            p.normal_at(Tuple::point(0.0, 0.0, 0.0))
+        );
+        assert_fuzzy_eq!(
+            Tuple::vector(0.5, 1.0, 0.0).normalize(),
+            p.normal_at(Tuple::point(0.5, 0.0, 0.0))
+        );
+        assert_fuzzy_eq!(
+            Tuple::vector(-0.5, 1.0, 0.0).normalize(),
+            p.normal_at(Tuple::point(1.5, 0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn perturbed_normals_are_unit_length() {
+        let p = rippled_plane();
+
+        for i in 0..20 {
+            let x = i as f64 * 0.37;
+            let n = p.normal_at(Tuple::point(x, 0.0, 1.0 - x));
+            assert_fuzzy_eq!(1.0, n.magnitude());
+        }
+    }
+
+    #[test]
+    fn ripple_shading_repeats_along_x() {
+        // Far enough away that the light arrives at the same angle everywhere
+        let light = Light::point(Tuple::point(-1e8, 1e8, 0.0), Color::white());
+        let w = World::new(vec![rippled_plane()], light);
+        let shade = |x: f64| {
+            let r = Ray::new(Tuple::point(x, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+            w.color_at(r)
+        };
+
+        // The period is 2 along x, and the two slopes face towards and away from the light
+        assert_fuzzy_eq!(shade(0.5), shade(2.5));
+        assert_fuzzy_eq!(shade(1.5), shade(3.5));
+        assert_fuzzy_eq!(shade(0.0), shade(1.0));
+        assert!(shade(0.5).fuzzy_ne(shade(1.5)));
+    }
+
+    #[test]
+    fn perturbation_is_applied_in_object_space() {
+        let p: Shape = PlaneBuilder::default()
+            .transform(Matrix::translation(1.0, 0.0, 0.0))
+            .material(rippled_plane().material())
+            .build()
+            .unwrap()
+            .into();
+
+        // World x = 1.5 is object x = 0.5
+        assert_fuzzy_eq!(
+            Tuple::vector(0.5, 1.0, 0.0).normalize(),
+            p.normal_at(Tuple::point(1.5, 0.0, 0.0))
+        );
+    }
+}
@@ -0,0 +1,568 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+use crate::{
+    color::Color, group::Group, material::Material, mesh::Mesh, shape::Shape, triangle::Triangle,
+    tuple::Tuple,
+};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(io::Error),
+    InvalidVertex { line: usize },
+    InvalidFace { line: usize },
+    InvalidMaterial { line: usize },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read OBJ data: {}", e),
+            Self::InvalidVertex { line } => write!(f, "invalid vertex record on line {}", line),
+            Self::InvalidFace { line } => write!(f, "invalid face record on line {}", line),
+            Self::InvalidMaterial { line } => {
+                write!(f, "invalid material statement on line {}", line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ObjError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ObjParse {
+    pub vertices: Vec<Tuple>,
+    pub faces: Vec<[u32; 3]>,
+    // The name of the material each face was given with usemtl, if it was in a library
+    pub face_materials: Vec<Option<String>>,
+    pub default_group: Group,
+    pub named_groups: Vec<(String, Group)>,
+    pub materials: HashMap<String, Material>,
+    pub ignored_lines: usize,
+    pub warnings: Vec<String>,
+}
+
+impl ObjParse {
+    pub fn group(&self, name: &str) -> Option<&Group> {
+        self.named_groups
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, g)| g)
+    }
+
+    // Triangles outside any `g` statement end up directly in the returned group, every named
+    // group becomes a sub-group of it
+    pub fn into_group(self) -> Shape {
+        let mut group = self.default_group;
+        for (_, g) in self.named_groups {
+            group.add_child(g);
+        }
+
+        group.into()
+    }
+
+    // A single Mesh sharing the vertex buffer, instead of a group of independent triangles.
+    // Groups are not kept, but every face keeps its material, and each material is only stored
+    // once however many faces use it
+    pub fn into_mesh(self) -> Shape {
+        let mut materials = vec![];
+        let mut used: HashMap<&str, u32> = HashMap::new();
+        let face_materials = self
+            .face_materials
+            .iter()
+            .map(|name| {
+                let name = name.as_deref()?;
+                Some(*used.entry(name).or_insert_with(|| {
+                    materials.push(self.materials[name].clone());
+                    (materials.len() - 1) as u32
+                }))
+            })
+            .collect();
+
+        Mesh::new(self.vertices, None, self.faces).with_face_materials(materials, face_materials).into()
+    }
+
+    fn current_group(&mut self, name: &Option<String>) -> &mut Group {
+        match name {
+            None => &mut self.default_group,
+            Some(name) => {
+                let index = match self.named_groups.iter().position(|(n, _)| n == name) {
+                    Some(index) => index,
+                    None => {
+                        self.named_groups.push((name.clone(), Group::default()));
+                        self.named_groups.len() - 1
+                    }
+                };
+                &mut self.named_groups[index].1
+            }
+        }
+    }
+}
+
+pub fn parse_obj_file(path: impl AsRef<Path>) -> Result<ObjParse, ObjError> {
+    let path = path.as_ref();
+    let file = File::open(path)?;
+    parse_obj_in_dir(BufReader::new(file), path.parent().unwrap_or(Path::new("")))
+}
+
+pub fn parse_obj(reader: impl BufRead) -> Result<ObjParse, ObjError> {
+    parse_obj_in_dir(reader, Path::new(""))
+}
+
+// Material libraries referenced by `mtllib` are resolved relative to `base_dir`
+pub fn parse_obj_in_dir(reader: impl BufRead, base_dir: &Path) -> Result<ObjParse, ObjError> {
+    let mut parse = ObjParse::default();
+    let mut group_name: Option<String> = None;
+    let mut active_material = Material::default();
+    let mut active_material_name: Option<String> = None;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_number = index + 1;
+        let mut tokens = line.split_whitespace();
+
+        match tokens.next() {
+            Some("v") => {
+                let coords = tokens
+                    .map(|t| t.parse::<f64>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| ObjError::InvalidVertex { line: line_number })?;
+                if coords.len() < 3 {
+                    return Err(ObjError::InvalidVertex { line: line_number });
+                }
+
+                parse
+                    .vertices
+                    .push(Tuple::point(coords[0], coords[1], coords[2]));
+            }
+            Some("f") => {
+                let indices = tokens
+                    .map(|t| vertex_index(&parse.vertices, t))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or(ObjError::InvalidFace { line: line_number })?;
+                if indices.len() < 3 {
+                    return Err(ObjError::InvalidFace { line: line_number });
+                }
+
+                for i in 1..indices.len() - 1 {
+                    let face = [indices[0], indices[i], indices[i + 1]];
+                    let [p1, p2, p3] = face.map(|index| parse.vertices[index as usize]);
+                    let mut triangle = Triangle::new(p1, p2, p3);
+                    triangle.material = active_material.clone();
+
+                    parse.faces.push(face);
+                    parse.face_materials.push(active_material_name.clone());
+                    parse.current_group(&group_name).add_child(triangle);
+                }
+            }
+            Some("g") => group_name = tokens.next().map(String::from),
+            Some("mtllib") => {
+                for name in tokens {
+                    let path = base_dir.join(name);
+                    match File::open(&path) {
+                        Ok(file) => parse.materials.extend(parse_mtl(BufReader::new(file))?),
+                        Err(e) => parse.warnings.push(format!(
+                            "could not open material library {}: {}",
+                            path.display(),
+                            e
+                        )),
+                    }
+                }
+            }
+            Some("usemtl") => {
+                let name = tokens.next().unwrap_or_default();
+                active_material_name = parse.materials.contains_key(name).then(|| name.to_string());
+                active_material = match parse.materials.get(name) {
+                    Some(material) => material.clone(),
+                    None => {
+                        parse
+                            .warnings
+                            .push(format!("unknown material {} on line {}", name, line_number));
+                        Material::default()
+                    }
+                };
+            }
+            _ => parse.ignored_lines += 1,
+        }
+    }
+
+    Ok(parse)
+}
+
+// Only the statements that map onto Material are read, texture maps and illumination models
+// are skipped
+pub fn parse_mtl(reader: impl BufRead) -> Result<HashMap<String, Material>, ObjError> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, Material)> = None;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_number = index + 1;
+        let mut tokens = line.split_whitespace();
+        let statement = tokens.next();
+
+        if statement == Some("newmtl") {
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material);
+            }
+            let name = tokens
+                .next()
+                .ok_or(ObjError::InvalidMaterial { line: line_number })?;
+            current = Some((name.to_string(), Material::default()));
+            continue;
+        }
+
+        let (statement, material) = match (statement, current.as_mut()) {
+            (Some(statement), Some((_, material))) => (statement, material),
+            _ => continue,
+        };
+        let values = tokens
+            .map(|t| t.parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ObjError::InvalidMaterial { line: line_number });
+
+        match statement {
+            "Ka" => material.ambient = color(&values?, line_number)?,
+            "Kd" => material.color = color(&values?, line_number)?,
+            "Ks" => material.surface = material.surface.with_specular(color(&values?, line_number)?),
+            "Ns" => material.surface = material.surface.with_shininess(first(&values?, line_number)?),
+            "d" => material.surface = material.surface.with_transparency(1.0 - first(&values?, line_number)?),
+            "Tr" => material.surface = material.surface.with_transparency(first(&values?, line_number)?),
+            "Ni" => material.surface = material.surface.with_refractive_index(first(&values?, line_number)?),
+            _ => {}
+        }
+    }
+
+    if let Some((name, material)) = current {
+        materials.insert(name, material);
+    }
+
+    Ok(materials)
+}
+
+fn first(values: &[f64], line: usize) -> Result<f64, ObjError> {
+    values
+        .first()
+        .copied()
+        .ok_or(ObjError::InvalidMaterial { line })
+}
+
+fn color(values: &[f64], line: usize) -> Result<Color, ObjError> {
+    match values {
+        [r, g, b, ..] => Ok(Color::new(*r, *g, *b)),
+        [v] => Ok(Color::new(*v, *v, *v)),
+        _ => Err(ObjError::InvalidMaterial { line }),
+    }
+}
+
+// Face references look like `v`, `v/vt`, `v//vn` or `v/vt/vn`, only the vertex index is used.
+// Indices are 1-based in the file and 0-based in the result
+fn vertex_index(vertices: &[Tuple], token: &str) -> Option<u32> {
+    let index = token
+        .split('/')
+        .next()?
+        .parse::<usize>()
+        .ok()?
+        .checked_sub(1)?;
+    if index < vertices.len() {
+        u32::try_from(index).ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_fuzzy_eq, util::FuzzyEq};
+
+    use super::*;
+
+    fn triangle(shape: &Shape) -> Triangle {
+        match shape {
+            Shape::Triangle(t) => t.clone(),
+            _ => panic!("expected a triangle, got {:?}", shape),
+        }
+    }
+
+    #[test]
+    fn ignoring_unrecognized_lines() {
+        let gibberish = "There was a young lady named Bright\n\
+                         who traveled much faster than light.\n\
+                         She set out one day\n\
+                         in a relative way,\n\
+                         and came back the previous night.\n";
+
+        let parse = parse_obj(gibberish.as_bytes()).unwrap();
+        assert_eq!(5, parse.ignored_lines);
+    }
+
+    #[test]
+    fn vertex_records() {
+        let file = "v -1 1 0\n\
+                    v -1.0000 0.5000 0.0000\n\
+                    v 1 0 0\n\
+                    v 1 1 0\n";
+
+        let parse = parse_obj(file.as_bytes()).unwrap();
+        assert_eq!(4, parse.vertices.len());
+        assert_fuzzy_eq!(Tuple::point(-1.0, 1.0, 0.0), parse.vertices[0]);
+        assert_fuzzy_eq!(Tuple::point(-1.0, 0.5, 0.0), parse.vertices[1]);
+        assert_fuzzy_eq!(Tuple::point(1.0, 0.0, 0.0), parse.vertices[2]);
+        assert_fuzzy_eq!(Tuple::point(1.0, 1.0, 0.0), parse.vertices[3]);
+    }
+
+    #[test]
+    fn parsing_triangle_faces() {
+        let file = "v -1 1 0\n\
+                    v -1 0 0\n\
+                    v 1 0 0\n\
+                    v 1 1 0\n\
+                    \n\
+                    f 1 2 3\n\
+                    f 1 3 4\n";
+
+        let parse = parse_obj(file.as_bytes()).unwrap();
+        let g = &parse.default_group;
+        let t1 = triangle(&g.children()[0]);
+        let t2 = triangle(&g.children()[1]);
+
+        assert_fuzzy_eq!(parse.vertices[0], t1.p1);
+        assert_fuzzy_eq!(parse.vertices[1], t1.p2);
+        assert_fuzzy_eq!(parse.vertices[2], t1.p3);
+        assert_fuzzy_eq!(parse.vertices[0], t2.p1);
+        assert_fuzzy_eq!(parse.vertices[2], t2.p2);
+        assert_fuzzy_eq!(parse.vertices[3], t2.p3);
+    }
+
+    #[test]
+    fn triangulating_polygons() {
+        let file = "v -1 1 0\n\
+                    v -1 0 0\n\
+                    v 1 0 0\n\
+                    v 1 1 0\n\
+                    v 0 2 0\n\
+                    \n\
+                    f 1 2 3 4 5\n";
+
+        let parse = parse_obj(file.as_bytes()).unwrap();
+        let g = &parse.default_group;
+        assert_eq!(3, g.children().len());
+
+        let t1 = triangle(&g.children()[0]);
+        let t2 = triangle(&g.children()[1]);
+        let t3 = triangle(&g.children()[2]);
+
+        assert_fuzzy_eq!(parse.vertices[0], t1.p1);
+        assert_fuzzy_eq!(parse.vertices[1], t1.p2);
+        assert_fuzzy_eq!(parse.vertices[2], t1.p3);
+        assert_fuzzy_eq!(parse.vertices[0], t2.p1);
+        assert_fuzzy_eq!(parse.vertices[2], t2.p2);
+        assert_fuzzy_eq!(parse.vertices[3], t2.p3);
+        assert_fuzzy_eq!(parse.vertices[0], t3.p1);
+        assert_fuzzy_eq!(parse.vertices[3], t3.p2);
+        assert_fuzzy_eq!(parse.vertices[4], t3.p3);
+    }
+
+    #[test]
+    fn triangles_in_groups() {
+        let file = "v -1 1 0\n\
+                    v -1 0 0\n\
+                    v 1 0 0\n\
+                    v 1 1 0\n\
+                    \n\
+                    g FirstGroup\n\
+                    f 1 2 3\n\
+                    g SecondGroup\n\
+                    f 1 3 4\n";
+
+        let parse = parse_obj(file.as_bytes()).unwrap();
+        let t1 = triangle(&parse.group("FirstGroup").unwrap().children()[0]);
+        let t2 = triangle(&parse.group("SecondGroup").unwrap().children()[0]);
+
+        assert_fuzzy_eq!(parse.vertices[0], t1.p1);
+        assert_fuzzy_eq!(parse.vertices[1], t1.p2);
+        assert_fuzzy_eq!(parse.vertices[2], t1.p3);
+        assert_fuzzy_eq!(parse.vertices[0], t2.p1);
+        assert_fuzzy_eq!(parse.vertices[2], t2.p2);
+        assert_fuzzy_eq!(parse.vertices[3], t2.p3);
+    }
+
+    #[test]
+    fn converting_obj_file_to_group() {
+        let file = "v -1 1 0\n\
+                    v -1 0 0\n\
+                    v 1 0 0\n\
+                    v 1 1 0\n\
+                    f 1 2 4\n\
+                    g FirstGroup\n\
+                    f 1 2 3\n\
+                    g SecondGroup\n\
+                    f 1 3 4\n";
+
+        let parse = parse_obj(file.as_bytes()).unwrap();
+        let first = parse.group("FirstGroup").unwrap().clone();
+        let second = parse.group("SecondGroup").unwrap().clone();
+
+        let g = match parse.into_group() {
+            Shape::Group(g) => g,
+            s => panic!("expected a group, got {:?}", s),
+        };
+        assert_eq!(3, g.children().len());
+        assert!(matches!(g.children()[0], Shape::Triangle(_)));
+        assert_eq!(Shape::from(first), g.children()[1]);
+        assert_eq!(Shape::from(second), g.children()[2]);
+    }
+
+    #[test]
+    fn face_with_texture_and_normal_indices() {
+        let file = "v 0 1 0\n\
+                    v -1 0 0\n\
+                    v 1 0 0\n\
+                    f 1/1/1 2//2 3/3\n";
+
+        let parse = parse_obj(file.as_bytes()).unwrap();
+        let t = triangle(&parse.default_group.children()[0]);
+
+        assert_fuzzy_eq!(parse.vertices[1], t.p2);
+    }
+
+    #[test]
+    fn face_referencing_unknown_vertex_fails() {
+        let file = "v 0 1 0\n\
+                    f 1 2 3\n";
+
+        let result = parse_obj(file.as_bytes());
+        assert!(matches!(result, Err(ObjError::InvalidFace { line: 2 })));
+    }
+
+    #[test]
+    fn parsing_material_library() {
+        let mtl = "# two materials\n\
+                   newmtl red\n\
+                   Ka 0.2 0.2 0.2\n\
+                   Kd 1.0 0.0 0.0\n\
+                   Ks 0.5 0.5 0.5\n\
+                   Ns 50\n\
+                   illum 2\n\
+                   newmtl glass\n\
+                   d 0.1\n\
+                   Ni 1.5\n";
+
+        let materials = parse_mtl(mtl.as_bytes()).unwrap();
+        let red = &materials["red"];
+        let glass = &materials["glass"];
+
+        assert_fuzzy_eq!(Color::grey(0.2), red.ambient);
+        assert_fuzzy_eq!(Color::new(1.0, 0.0, 0.0), red.color);
+        assert_fuzzy_eq!(Color::grey(0.5), red.specular());
+        assert_fuzzy_eq!(50.0, red.shininess());
+        assert_fuzzy_eq!(0.9, glass.transparency());
+        assert_fuzzy_eq!(1.5, glass.refractive_index());
+    }
+
+    #[test]
+    fn groups_get_materials_from_library() {
+        let dir = std::env::temp_dir().join("obj_groups_get_materials_from_library");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("scene.mtl"),
+            "newmtl red\nKd 1 0 0\nnewmtl blue\nKd 0 0 1\nTr 0.25\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("scene.obj"),
+            "mtllib scene.mtl\n\
+             v -1 1 0\n\
+             v -1 0 0\n\
+             v 1 0 0\n\
+             v 1 1 0\n\
+             g First\n\
+             usemtl red\n\
+             f 1 2 3\n\
+             g Second\n\
+             usemtl blue\n\
+             f 1 3 4\n",
+        )
+        .unwrap();
+
+        let parse = parse_obj_file(dir.join("scene.obj")).unwrap();
+        let first = triangle(&parse.group("First").unwrap().children()[0]).material;
+        let second = triangle(&parse.group("Second").unwrap().children()[0]).material;
+
+        assert!(parse.warnings.is_empty());
+        assert_fuzzy_eq!(Color::new(1.0, 0.0, 0.0), first.color);
+        assert_fuzzy_eq!(Color::new(0.0, 0.0, 1.0), second.color);
+        assert_fuzzy_eq!(0.25, second.transparency());
+        assert!(first.fuzzy_ne(second));
+    }
+
+    #[test]
+    fn mesh_faces_get_materials_from_library() {
+        let dir = std::env::temp_dir().join("obj_mesh_faces_get_materials_from_library");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("scene.mtl"), "newmtl red\nKd 1 0 0\n").unwrap();
+        std::fs::write(
+            dir.join("scene.obj"),
+            "mtllib scene.mtl\n\
+             v -1 1 0\n\
+             v -1 0 0\n\
+             v 1 0 0\n\
+             v 1 1 0\n\
+             v 0 2 0\n\
+             f 1 2 3\n\
+             usemtl red\n\
+             f 1 3 4\n\
+             f 1 4 5\n",
+        )
+        .unwrap();
+
+        let mesh = match parse_obj_file(dir.join("scene.obj")).unwrap().into_mesh() {
+            Shape::Mesh(m) => m,
+            s => panic!("expected a mesh, got {:?}", s),
+        };
+
+        assert_eq!(vec![None, Some(0), Some(0)], *mesh.face_materials);
+        assert_eq!(1, mesh.materials.len());
+        assert_fuzzy_eq!(Color::new(1.0, 0.0, 0.0), mesh.materials[0].color);
+    }
+
+    #[test]
+    fn missing_material_library_is_a_warning() {
+        let file = "mtllib does_not_exist.mtl\n\
+                    usemtl red\n\
+                    v 0 1 0\n\
+                    v -1 0 0\n\
+                    v 1 0 0\n\
+                    f 1 2 3\n";
+
+        let parse = parse_obj(file.as_bytes()).unwrap();
+        let t = triangle(&parse.default_group.children()[0]);
+
+        assert_eq!(2, parse.warnings.len());
+        assert_fuzzy_eq!(Material::default(), t.material);
+    }
+
+    #[test]
+    fn missing_file_is_an_io_error() {
+        let result = parse_obj_file("does/not/exist.obj");
+        assert!(matches!(result, Err(ObjError::Io(_))));
+    }
+}
@@ -1,24 +1,45 @@
-use crate::{matrix::Matrix, color::Color, shape::{Shape, ShapeFuncs}, tuple::Tuple};
+use std::{cmp::Ordering, fmt, sync::Arc};
+
+use crate::{matrix::Matrix, color::Color, noise::Perlin, texture_map::{TextureMapPattern, UvCubeMap}, shape::{Shape, ShapeFuncs}, tuple::Tuple, util::EPSILON};
 
 pub trait PatternFuncs {
     fn color_at(&self, point: Tuple) -> Color;
     fn transform(&self) -> Matrix<4>;
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(tag = "type"))]
 pub enum Pattern {
     Stripe(StripePattern),
     Gradient(GradientPattern),
+    LinearGradient(LinearGradientPattern),
     Ring(RingPattern),
     Checker3D(CheckerPattern3D),
+    Blended(BlendedPattern),
+    Perturbed(PerturbedPattern),
+    Solid(SolidPattern),
+    TextureMap(TextureMapPattern),
+    // Boxed, as six faces make it much bigger than the other patterns
+    CubeMap(Box<UvCubeMap>),
+    // Can't be written out, as there's no telling what the pattern is
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(CustomPattern),
+    #[cfg(test)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Test(TestPattern),
 }
 
 impl Pattern {
-    fn color_at_object(&self, object: Shape, point: Tuple) -> Color {
+    pub fn color_at_object(&self, object: &Shape, point: Tuple) -> Color {
         let object_point = object.world_point_to_object_point(point);
-        let pattern_point = self.transform().inverse() * object_point;
 
-        self.color_at(pattern_point)
+        self.color_at_transformed(object_point)
+    }
+
+    // Moves a point from the space the pattern sits in, an object or a parent pattern, into the
+    // pattern's own space before sampling it
+    pub fn color_at_transformed(&self, point: Tuple) -> Color {
+        self.color_at(self.transform().inverse() * point)
     }
 }
 
@@ -27,8 +48,17 @@ impl PatternFuncs for Pattern {
         match self {
             Self::Stripe(s) => s.color_at(point),
             Self::Gradient(g) => g.color_at(point),
+            Self::LinearGradient(g) => g.color_at(point),
             Self::Ring(r) => r.color_at(point),
-            Self::Checker3D(c) => c.color_at(point)
+            Self::Checker3D(c) => c.color_at(point),
+            Self::Blended(b) => b.color_at(point),
+            Self::Perturbed(p) => p.color_at(point),
+            Self::Solid(s) => s.color_at(point),
+            Self::TextureMap(t) => t.color_at(point),
+            Self::CubeMap(c) => c.color_at(point),
+            Self::Custom(c) => c.color_at(point),
+            #[cfg(test)]
+            Self::Test(t) => t.color_at(point),
         }
     }
 
@@ -36,8 +66,17 @@ impl PatternFuncs for Pattern {
         match self {
             Self::Stripe(s) => s.transform(),
             Self::Gradient(g) => g.transform(),
+            Self::LinearGradient(g) => g.transform(),
             Self::Ring(r) => r.transform(),
-            Self::Checker3D(c) => c.transform()
+            Self::Checker3D(c) => c.transform(),
+            Self::Blended(b) => b.transform(),
+            Self::Perturbed(p) => p.transform(),
+            Self::Solid(s) => s.transform(),
+            Self::TextureMap(t) => t.transform(),
+            Self::CubeMap(c) => c.transform(),
+            Self::Custom(c) => c.transform(),
+            #[cfg(test)]
+            Self::Test(t) => t.transform(),
         }
     }
 }
@@ -54,6 +93,12 @@ impl From<GradientPattern> for Pattern {
     }
 }
 
+impl From<LinearGradientPattern> for Pattern {
+    fn from(g: LinearGradientPattern) -> Self {
+        Self::LinearGradient(g)
+    }
+}
+
 impl From<RingPattern> for Pattern {
     fn from(r: RingPattern) -> Self {
         Self::Ring(r)
@@ -66,29 +111,145 @@ impl From<CheckerPattern3D> for Pattern {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Builder)]
+impl From<BlendedPattern> for Pattern {
+    fn from(b: BlendedPattern) -> Self {
+        Self::Blended(b)
+    }
+}
+
+impl From<PerturbedPattern> for Pattern {
+    fn from(p: PerturbedPattern) -> Self {
+        Self::Perturbed(p)
+    }
+}
+
+impl From<SolidPattern> for Pattern {
+    fn from(s: SolidPattern) -> Self {
+        Self::Solid(s)
+    }
+}
+
+impl From<TextureMapPattern> for Pattern {
+    fn from(t: TextureMapPattern) -> Self {
+        Self::TextureMap(t)
+    }
+}
+
+impl From<UvCubeMap> for Pattern {
+    fn from(c: UvCubeMap) -> Self {
+        Self::CubeMap(Box::new(c))
+    }
+}
+
+impl From<CustomPattern> for Pattern {
+    fn from(c: CustomPattern) -> Self {
+        Self::Custom(c)
+    }
+}
+
+impl From<Color> for Pattern {
+    fn from(c: Color) -> Self {
+        Self::Solid(SolidPattern(c))
+    }
+}
+
+#[cfg(test)]
+impl From<TestPattern> for Pattern {
+    fn from(t: TestPattern) -> Self {
+        Self::Test(t)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Axis {
+    #[default]
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    fn of(&self, point: Tuple) -> f64 {
+        match self {
+            Self::X => point.x,
+            Self::Y => point.y,
+            Self::Z => point.z,
+        }
+    }
+}
+
+// What each band or cell of a pattern is filled with. A nested pattern is sampled at the same
+// point as its parent, after its own transform
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(tag = "fill"))]
+pub enum PatternOrColor {
+    Color(Color),
+    Pattern(Box<Pattern>),
+}
+
+impl PatternOrColor {
+    pub fn color_at(&self, point: Tuple) -> Color {
+        match self {
+            Self::Color(c) => *c,
+            Self::Pattern(p) => p.color_at_transformed(point),
+        }
+    }
+}
+
+impl Default for PatternOrColor {
+    fn default() -> Self {
+        Self::Color(Color::default())
+    }
+}
+
+impl From<Color> for PatternOrColor {
+    fn from(c: Color) -> Self {
+        Self::Color(c)
+    }
+}
+
+impl From<Pattern> for PatternOrColor {
+    fn from(p: Pattern) -> Self {
+        Self::Pattern(Box::new(p))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Builder)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StripePattern {
     #[builder(default)]
     pub transform: Matrix<4>,
+    #[builder(default, setter(into))]
+    pub color_a: PatternOrColor,
+    #[builder(default, setter(into))]
+    pub color_b: PatternOrColor,
+    // The stripes change color along this axis, and stay the same along the other two
     #[builder(default)]
-    pub color_a: Color,
-    #[builder(default)]
-    pub color_b: Color,
+    pub axis: Axis,
+    #[builder(default = "1.0")]
+    pub width: f64,
 }
 
 impl Default for StripePattern {
     fn default() -> Self {
-        Self { transform: Matrix::identity(), color_a: Color::white(), color_b: Color::black() }
+        Self {
+            transform: Matrix::identity(),
+            color_a: Color::white().into(),
+            color_b: Color::black().into(),
+            axis: Axis::X,
+            width: 1.0,
+        }
     }
 }
 
 impl PatternFuncs for StripePattern {
     fn color_at(&self, point: Tuple) -> Color {
-        if point.x.floor() as i64 % 2 == 0 {
-            return self.color_a
+        if ((self.axis.of(point) / self.width).floor() as i64).rem_euclid(2) == 0 {
+            return self.color_a.color_at(point)
         }
 
-        self.color_b
+        self.color_b.color_at(point)
     }
 
     fn transform(&self) -> Matrix<4> {
@@ -96,19 +257,20 @@ impl PatternFuncs for StripePattern {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Builder)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Builder)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GradientPattern {
     #[builder(default)]
     pub transform: Matrix<4>,
-    #[builder(default)]
-    pub color_a: Color,
-    #[builder(default)]
-    pub color_b: Color, 
+    #[builder(default, setter(into))]
+    pub color_a: PatternOrColor,
+    #[builder(default, setter(into))]
+    pub color_b: PatternOrColor,
 }
 
 impl Default for GradientPattern {
     fn default() -> Self {
-        Self { transform: Matrix::identity(), color_a: Color::white(), color_b: Color::black() }
+        Self { transform: Matrix::identity(), color_a: Color::white().into(), color_b: Color::black().into() }
     }
 }
 
@@ -118,23 +280,63 @@ impl PatternFuncs for GradientPattern {
     }
 
     fn color_at(&self, point: Tuple) -> Color {
-        self.color_a + (self.color_b - self.color_a) * (point.x - point.x.floor())  
+        let (a, b) = (self.color_a.color_at(point), self.color_b.color_at(point));
+        a + (b - a) * (point.x - point.x.floor())
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Builder)]
+// Runs from color_a at one point to color_b at another, and stays flat past either end
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LinearGradientPattern {
+    pub transform: Matrix<4>,
+    pub from: Tuple,
+    pub to: Tuple,
+    pub color_a: PatternOrColor,
+    pub color_b: PatternOrColor,
+}
+
+impl LinearGradientPattern {
+    pub fn between(from: Tuple, to: Tuple, color_a: impl Into<PatternOrColor>, color_b: impl Into<PatternOrColor>) -> Self {
+        Self { transform: Matrix::identity(), from, to, color_a: color_a.into(), color_b: color_b.into() }
+    }
+}
+
+impl PatternFuncs for LinearGradientPattern {
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn color_at(&self, point: Tuple) -> Color {
+        // How far along the segment the point projects, with both ends at the same spot counting
+        // as the start
+        let segment = self.to - self.from;
+        let length_squared = segment.dot(segment);
+        let t = if length_squared < EPSILON {
+            0.0
+        } else {
+            ((point - self.from).dot(segment) / length_squared).clamp(0.0, 1.0)
+        };
+
+        let (a, b) = (self.color_a.color_at(point), self.color_b.color_at(point));
+        a + (b - a) * t
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Builder)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RingPattern {
     #[builder(default)]
     pub transform: Matrix<4>,
-    #[builder(default)]
-    pub color_a: Color,
-    #[builder(default)]
-    pub color_b: Color, 
+    #[builder(default, setter(into))]
+    pub color_a: PatternOrColor,
+    #[builder(default, setter(into))]
+    pub color_b: PatternOrColor,
 }
 
 impl Default for RingPattern {
     fn default() -> Self {
-        Self { transform: Matrix::identity(), color_a: Color::white(), color_b: Color::black() }
+        Self { transform: Matrix::identity(), color_a: Color::white().into(), color_b: Color::black().into() }
     }
 }
 
@@ -144,27 +346,28 @@ impl PatternFuncs for RingPattern {
     }
 
     fn color_at(&self, point: Tuple) -> Color {
-        if (point.x.powi(2) + point.z.powi(2)).sqrt() as i64 % 2 == 0 {
-            return self.color_a
+        if ((point.x.powi(2) + point.z.powi(2)).sqrt().floor() as i64).rem_euclid(2) == 0 {
+            return self.color_a.color_at(point)
         }
 
-        self.color_b
+        self.color_b.color_at(point)
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Builder)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Builder)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CheckerPattern3D {
     #[builder(default)]
     pub transform: Matrix<4>,
-    #[builder(default)]
-    pub color_a: Color,
-    #[builder(default)]
-    pub color_b: Color, 
+    #[builder(default, setter(into))]
+    pub color_a: PatternOrColor,
+    #[builder(default, setter(into))]
+    pub color_b: PatternOrColor,
 }
 
 impl Default for CheckerPattern3D {
     fn default() -> Self {
-        Self { transform: Matrix::identity(), color_a: Color::white(), color_b: Color::black() }
+        Self { transform: Matrix::identity(), color_a: Color::white().into(), color_b: Color::black().into() }
     }
 }
 
@@ -174,11 +377,198 @@ impl PatternFuncs for CheckerPattern3D {
     }
 
     fn color_at(&self, point: Tuple) -> Color {
-        if (point.x.floor() + point.y.floor() + point.z.floor()) as i64 % 2 == 0 {
-            return self.color_a
+        let parity = snapped_floor(point.x) + snapped_floor(point.y) + snapped_floor(point.z);
+        if (parity as i64).rem_euclid(2) == 0 {
+            return self.color_a.color_at(point)
+        }
+
+        self.color_b.color_at(point)
+    }
+}
+
+// Hit points on a boundary come back a hair either side of it, so they're rounded to the EPSILON
+// grid first to make them all land on the same side
+fn snapped_floor(v: f64) -> f64 {
+    ((v / EPSILON).round() * EPSILON).floor()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BlendMode {
+    #[default]
+    Average,
+    Multiply,
+}
+
+impl BlendMode {
+    fn blend(&self, a: Color, b: Color) -> Color {
+        match self {
+            Self::Average => (a + b) * 0.5,
+            Self::Multiply => a * b,
+        }
+    }
+}
+
+// Mixes two patterns, each sampled through its own transform inside the blended pattern's space
+#[derive(Debug, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlendedPattern {
+    pub transform: Matrix<4>,
+    pub a: Box<Pattern>,
+    pub b: Box<Pattern>,
+    pub mode: BlendMode,
+}
+
+impl BlendedPattern {
+    pub fn new(a: Pattern, b: Pattern, mode: BlendMode) -> Self {
+        Self { transform: Matrix::identity(), a: Box::new(a), b: Box::new(b), mode }
+    }
+}
+
+impl Clone for BlendedPattern {
+    fn clone(&self) -> Self {
+        Self {
+            transform: self.transform,
+            a: Box::new((*self.a).clone()),
+            b: Box::new((*self.b).clone()),
+            mode: self.mode,
+        }
+    }
+}
+
+impl PatternFuncs for BlendedPattern {
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn color_at(&self, point: Tuple) -> Color {
+        self.mode.blend(self.a.color_at_transformed(point), self.b.color_at_transformed(point))
+    }
+}
+
+// The same color everywhere, so a plain color can go wherever a pattern is expected
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SolidPattern(pub Color);
+
+impl PatternFuncs for SolidPattern {
+    fn transform(&self) -> Matrix<4> {
+        Matrix::identity()
+    }
+
+    fn color_at(&self, _point: Tuple) -> Color {
+        self.0
+    }
+}
+
+// Jitters the point another pattern is sampled at by Perlin noise, so its edges wobble. Scale
+// sets how quickly the noise changes across the pattern, amplitude how far points move
+#[derive(Debug, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PerturbedPattern {
+    pub transform: Matrix<4>,
+    pub pattern: Box<Pattern>,
+    pub noise: Perlin,
+    pub scale: f64,
+    pub amplitude: f64,
+}
+
+impl PerturbedPattern {
+    pub fn new(pattern: Pattern, seed: u64) -> Self {
+        Self {
+            transform: Matrix::identity(),
+            pattern: Box::new(pattern),
+            noise: Perlin::new(seed),
+            scale: 1.0,
+            amplitude: 0.2,
+        }
+    }
+}
+
+impl Clone for PerturbedPattern {
+    fn clone(&self) -> Self {
+        Self {
+            transform: self.transform,
+            pattern: Box::new((*self.pattern).clone()),
+            noise: self.noise.clone(),
+            scale: self.scale,
+            amplitude: self.amplitude,
         }
+    }
+}
+
+impl PatternFuncs for PerturbedPattern {
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn color_at(&self, point: Tuple) -> Color {
+        // Sampling the noise at shifted positions keeps the three offsets independent
+        let p = point * self.scale;
+        let offset = Tuple::vector(
+            self.noise.noise(p.x, p.y, p.z),
+            self.noise.noise(p.x + 31.4, p.y + 17.9, p.z + 5.3),
+            self.noise.noise(p.x - 12.7, p.y + 41.1, p.z - 23.5),
+        );
+
+        self.pattern.color_at_transformed(point + offset * self.amplitude)
+    }
+}
+
+// Wraps a pattern defined outside this crate. Clones share the same pattern, and two custom
+// patterns are only equal when they are the same one
+#[derive(Clone)]
+pub struct CustomPattern(pub Arc<dyn PatternFuncs + Send + Sync>);
+
+impl CustomPattern {
+    pub fn new(pattern: impl PatternFuncs + Send + Sync + 'static) -> Self {
+        Self(Arc::new(pattern))
+    }
+}
+
+impl fmt::Debug for CustomPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomPattern").field("transform", &self.0.transform()).finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for CustomPattern {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl PartialOrd for CustomPattern {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self == other).then_some(Ordering::Equal)
+    }
+}
+
+impl PatternFuncs for CustomPattern {
+    fn color_at(&self, point: Tuple) -> Color {
+        self.0.color_at(point)
+    }
+
+    fn transform(&self) -> Matrix<4> {
+        self.0.transform()
+    }
+}
+
+// Shows the point it's sampled at as a color, for checking the transforms that lead there
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct TestPattern {
+    pub transform: Matrix<4>,
+}
+
+#[cfg(test)]
+impl PatternFuncs for TestPattern {
+    fn color_at(&self, point: Tuple) -> Color {
+        Color::new(point.x, point.y, point.z)
+    }
 
-        self.color_b
+    fn transform(&self) -> Matrix<4> {
+        self.transform
     }
 }
 
@@ -186,6 +576,7 @@ impl PatternFuncs for CheckerPattern3D {
 mod tests {
     use crate::assert_fuzzy_eq;
     use crate::sphere::{SphereBuilder};
+    use crate::{light::Light, material::Material};
     use crate::util::FuzzyEq;
     use super::*;
 
@@ -193,8 +584,8 @@ mod tests {
     fn creating_stripe_pattern() {
         let stripe = StripePatternBuilder::default().color_b(Color::white() ).build().unwrap();
 
-        assert_fuzzy_eq!(Color::black(), stripe.color_a);
-        assert_fuzzy_eq!(Color::white(), stripe.color_b);
+        assert_eq!(PatternOrColor::Color(Color::black()), stripe.color_a);
+        assert_eq!(PatternOrColor::Color(Color::white()), stripe.color_b);
     }
 
     #[test]
@@ -224,12 +615,39 @@ mod tests {
         assert_fuzzy_eq!(Color::white(), p.color_at(Tuple::point(-1.1, 0.0, 0.0)));
     }
 
+    #[test]
+    fn z_axis_stripes_are_constant_in_x_and_y() {
+        let p: Pattern = StripePatternBuilder::default()
+            .color_a(Color::white())
+            .axis(Axis::Z)
+            .build()
+            .unwrap()
+            .into();
+
+        assert_fuzzy_eq!(Color::white(), p.color_at(Tuple::point(0.0, 0.0, 0.0)));
+        assert_fuzzy_eq!(Color::white(), p.color_at(Tuple::point(3.5, 0.0, 0.0)));
+        assert_fuzzy_eq!(Color::white(), p.color_at(Tuple::point(0.0, -2.5, 0.5)));
+        assert_fuzzy_eq!(Color::black(), p.color_at(Tuple::point(0.0, 0.0, 1.0)));
+        assert_fuzzy_eq!(Color::black(), p.color_at(Tuple::point(7.0, 3.0, -0.1)));
+        assert_fuzzy_eq!(Color::white(), p.color_at(Tuple::point(0.0, 0.0, 2.0)));
+    }
+
+    #[test]
+    fn half_width_stripes_alternate_twice_as_often() {
+        let p: Pattern = StripePattern { width: 0.5, ..Default::default() }.into();
+
+        assert_fuzzy_eq!(Color::white(), p.color_at(Tuple::point(0.25, 0.0, 0.0)));
+        assert_fuzzy_eq!(Color::black(), p.color_at(Tuple::point(0.75, 0.0, 0.0)));
+        assert_fuzzy_eq!(Color::white(), p.color_at(Tuple::point(1.25, 0.0, 0.0)));
+        assert_fuzzy_eq!(Color::black(), p.color_at(Tuple::point(-0.25, 0.0, 0.0)));
+    }
+
     #[test]
     fn stripe_with_object_transformation() {
         let object: Shape = SphereBuilder::default().transform(Matrix::scaling(2.0, 2.0, 2.0)).build().unwrap().into();
         let pattern: Pattern = StripePattern::default().into();
 
-        let c = pattern.color_at_object(object, Tuple::point(1.5, 0.0, 0.0));
+        let c = pattern.color_at_object(&object, Tuple::point(1.5, 0.0, 0.0));
         assert_fuzzy_eq!(Color::white(), c);
     }
 
@@ -241,7 +659,7 @@ mod tests {
             .unwrap()
             .into();
      
-        let c = pattern.color_at_object(object, Tuple::point(1.5, 0.0, 0.0));
+        let c = pattern.color_at_object(&object, Tuple::point(1.5, 0.0, 0.0));
         assert_fuzzy_eq!(Color::white(), c);
     }
 
@@ -255,7 +673,7 @@ mod tests {
             .unwrap()
             .into();
 
-        let c = pattern.color_at_object(object, Tuple::point(2.5, 0.0, 0.0));
+        let c = pattern.color_at_object(&object, Tuple::point(2.5, 0.0, 0.0));
         assert_fuzzy_eq!(Color::white(), c);
     }
 
@@ -268,6 +686,44 @@ mod tests {
         assert_fuzzy_eq!(Color::new(0.25, 0.25, 0.25), p.color_at(Tuple::point(0.75, 0.0, 0.0)));
     }
 
+    fn linear_gradient() -> LinearGradientPattern {
+        LinearGradientPattern::between(Tuple::point(-1.0, 0.0, 0.0), Tuple::point(1.0, 2.0, 0.0), Color::red(), Color::blue())
+    }
+
+    #[test]
+    fn linear_gradient_hits_both_colors_at_its_ends() {
+        let p = linear_gradient();
+
+        assert_fuzzy_eq!(Color::red(), p.color_at(Tuple::point(-1.0, 0.0, 0.0)));
+        assert_fuzzy_eq!(Color::blue(), p.color_at(Tuple::point(1.0, 2.0, 0.0)));
+    }
+
+    #[test]
+    fn linear_gradient_averages_at_the_midpoint() {
+        let p = linear_gradient();
+
+        assert_fuzzy_eq!(Color::new(0.5, 0.0, 0.5), p.color_at(Tuple::point(0.0, 1.0, 0.0)));
+        // Moving across the segment doesn't change the color
+        assert_fuzzy_eq!(Color::new(0.5, 0.0, 0.5), p.color_at(Tuple::point(1.0, 0.0, 3.0)));
+    }
+
+    #[test]
+    fn linear_gradient_clamps_beyond_its_ends() {
+        let p = linear_gradient();
+
+        assert_fuzzy_eq!(Color::red(), p.color_at(Tuple::point(-3.0, -2.0, 0.0)));
+        assert_fuzzy_eq!(Color::blue(), p.color_at(Tuple::point(4.0, 5.0, 1.0)));
+    }
+
+    #[test]
+    fn linear_gradient_follows_object_and_pattern_transforms() {
+        let p: Pattern = LinearGradientPattern { transform: Matrix::translation(1.0, 0.0, 0.0), ..linear_gradient() }.into();
+        let object: Shape = SphereBuilder::default().transform(Matrix::scaling(2.0, 2.0, 2.0)).build().unwrap().into();
+
+        // (2, 2, 0) is (1, 1, 0) in the object and the midpoint (0, 1, 0) in the pattern
+        assert_fuzzy_eq!(Color::new(0.5, 0.0, 0.5), p.color_at_object(&object, Tuple::point(2.0, 2.0, 0.0)));
+    }
+
     #[test]
     fn ring_pattern_should_extend_both_x_and_z_direction() {
         let p: Pattern = RingPattern::default().into();
@@ -277,6 +733,34 @@ mod tests {
         assert_fuzzy_eq!(Color::black(), p.color_at(Tuple::point(0.708, 0.0, 0.708)));
     }
 
+    #[test]
+    fn rings_are_one_unit_wide() {
+        let p: Pattern = RingPattern::default().into();
+        assert_fuzzy_eq!(Color::white(), p.color_at(Tuple::point(0.5, 0.0, 0.0)));
+        assert_fuzzy_eq!(Color::black(), p.color_at(Tuple::point(1.5, 0.0, 0.0)));
+        assert_fuzzy_eq!(Color::white(), p.color_at(Tuple::point(2.5, 0.0, 0.0)));
+        assert_fuzzy_eq!(Color::black(), p.color_at(Tuple::point(0.0, 0.0, -1.5)));
+        assert_fuzzy_eq!(Color::white(), p.color_at(Tuple::point(-1.5, 0.0, -1.5)));
+    }
+
+    #[test]
+    fn stripes_keep_their_width_on_negative_x() {
+        let p: Pattern = StripePattern::default().into();
+        assert_fuzzy_eq!(Color::black(), p.color_at(Tuple::point(-0.5, 0.0, 0.0)));
+        assert_fuzzy_eq!(Color::white(), p.color_at(Tuple::point(-1.5, 0.0, 0.0)));
+        assert_fuzzy_eq!(Color::black(), p.color_at(Tuple::point(-2.5, 0.0, 0.0)));
+        assert_fuzzy_eq!(Color::white(), p.color_at(Tuple::point(-3.5, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn checkers_alternate_across_zero() {
+        let p: Pattern = CheckerPattern3D::default().into();
+        assert_fuzzy_eq!(Color::black(), p.color_at(Tuple::point(-0.5, 0.5, 0.5)));
+        assert_fuzzy_eq!(Color::white(), p.color_at(Tuple::point(-1.5, 0.5, 0.5)));
+        assert_fuzzy_eq!(Color::white(), p.color_at(Tuple::point(-0.5, -0.5, 0.5)));
+        assert_fuzzy_eq!(Color::black(), p.color_at(Tuple::point(-0.5, -0.5, -0.5)));
+    }
+
     #[test]
     fn checkers_should_repeat_in_x() {
         let p: Pattern = CheckerPattern3D::default().into();
@@ -300,4 +784,257 @@ mod tests {
         assert_fuzzy_eq!(Color::white(), p.color_at(Tuple::point(0.0, 0.0, 0.99)));
         assert_fuzzy_eq!(Color::black(), p.color_at(Tuple::point(0.0, 0.0, 1.01)));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn checker_of_perpendicular_stripes() {
+        // Half unit stripes, one set shrunk by its transform and the other by its width
+        let a: Pattern = StripePatternBuilder::default()
+            .transform(Matrix::scaling(0.5, 1.0, 1.0))
+            .color_a(Color::red())
+            .color_b(Color::green())
+            .build()
+            .unwrap()
+            .into();
+        let b: Pattern = StripePatternBuilder::default()
+            .axis(Axis::Z)
+            .width(0.5)
+            .color_a(Color::blue())
+            .color_b(Color::white())
+            .build()
+            .unwrap()
+            .into();
+        let p = CheckerPattern3DBuilder::default().color_a(a).color_b(b).build().unwrap();
+
+        assert_fuzzy_eq!(Color::red(), p.color_at(Tuple::point(0.25, 0.5, 0.25)));
+        assert_fuzzy_eq!(Color::green(), p.color_at(Tuple::point(0.75, 0.5, 0.25)));
+        assert_fuzzy_eq!(Color::blue(), p.color_at(Tuple::point(1.25, 0.5, 0.25)));
+        assert_fuzzy_eq!(Color::white(), p.color_at(Tuple::point(1.25, 0.5, 0.75)));
+    }
+
+    #[test]
+    fn checker_of_solid_patterns_matches_plain_checker() {
+        let plain = CheckerPattern3D::default();
+        let nested = CheckerPattern3D {
+            color_a: Pattern::from(Color::white()).into(),
+            color_b: Pattern::from(Color::black()).into(),
+            ..Default::default()
+        };
+
+        for point in [Tuple::point(0.5, 0.5, 0.5), Tuple::point(1.5, 0.5, 0.5), Tuple::point(-0.5, 2.5, 0.1)] {
+            assert_fuzzy_eq!(plain.color_at(point), nested.color_at(point));
+        }
+    }
+
+    #[test]
+    fn averaging_white_and_black_is_grey_everywhere() {
+        let white = StripePattern { color_b: Color::white().into(), ..Default::default() };
+        let black = StripePattern { color_a: Color::black().into(), ..Default::default() };
+        let p: Pattern = BlendedPattern::new(white.into(), black.into(), BlendMode::Average).into();
+
+        for point in [
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::point(1.5, -2.0, 0.3),
+            Tuple::point(-7.2, 4.0, 9.9),
+        ] {
+            assert_fuzzy_eq!(Color::new(0.5, 0.5, 0.5), p.color_at(point));
+        }
+    }
+
+    #[test]
+    fn multiplying_keeps_only_what_both_patterns_share() {
+        let a = StripePattern { color_a: Color::new(1.0, 1.0, 0.0).into(), ..Default::default() };
+        let b = StripePattern { color_a: Color::new(0.0, 1.0, 1.0).into(), ..Default::default() };
+        let p: Pattern = BlendedPattern::new(a.into(), b.into(), BlendMode::Multiply).into();
+
+        assert_fuzzy_eq!(Color::green(), p.color_at(Tuple::point(0.5, 0.0, 0.0)));
+        assert_fuzzy_eq!(Color::black(), p.color_at(Tuple::point(1.5, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn blended_children_keep_their_own_transforms() {
+        let a = TestPattern { transform: Matrix::translation(1.0, 0.0, 0.0) };
+        let b = TestPattern { transform: Matrix::scaling(2.0, 2.0, 2.0) };
+        let p = BlendedPattern {
+            transform: Matrix::translation(0.0, 1.0, 0.0),
+            ..BlendedPattern::new(a.into(), b.into(), BlendMode::Average)
+        };
+        let object: Shape = SphereBuilder::default().build().unwrap().into();
+
+        // (3, 3, 3) is (3, 2, 3) in the blend, (2, 2, 3) in a and (1.5, 1, 1.5) in b
+        let c = Pattern::from(p).color_at_object(&object, Tuple::point(3.0, 3.0, 3.0));
+        assert_fuzzy_eq!(Color::new(1.75, 1.5, 2.25), c);
+    }
+
+    #[test]
+    fn blended_checkers_rotated_from_each_other() {
+        let a = CheckerPattern3D::default();
+        let b = CheckerPattern3D { transform: Matrix::rotation_y(std::f64::consts::PI / 4.0), ..a.clone() };
+        let p = BlendedPattern::new(a.into(), b.into(), BlendMode::Average);
+
+        // Past x = 1 the first checker has turned black while the rotated one is still white
+        assert_fuzzy_eq!(Color::white(), p.color_at(Tuple::point(0.5, 0.0, 0.1)));
+        assert_fuzzy_eq!(Color::new(0.5, 0.5, 0.5), p.color_at(Tuple::point(1.2, 0.0, 0.1)));
+    }
+
+    #[test]
+    fn perturbing_by_zero_amplitude_changes_nothing() {
+        let stripes = StripePattern { transform: Matrix::scaling(0.3, 1.0, 1.0), ..Default::default() };
+        let inner: Pattern = stripes.into();
+        let p = PerturbedPattern { amplitude: 0.0, ..PerturbedPattern::new(inner.clone(), 5) };
+
+        for i in 0..50 {
+            let point = Tuple::point(i as f64 * 0.173, 0.5, i as f64 * -0.31);
+            assert_fuzzy_eq!(inner.color_at_transformed(point), p.color_at(point));
+        }
+    }
+
+    #[test]
+    fn perturbed_pattern_is_deterministic_for_a_seed() {
+        let a = PerturbedPattern::new(TestPattern::default().into(), 9);
+        let b = PerturbedPattern::new(TestPattern::default().into(), 9);
+        let point = Tuple::point(0.4, 1.7, -2.2);
+
+        assert_fuzzy_eq!(a.color_at(point), b.color_at(point));
+        assert_fuzzy_eq!(a.color_at(point), a.clone().color_at(point));
+    }
+
+    #[test]
+    fn perturbation_moves_the_lookup_point() {
+        let inner = TestPattern::default().into();
+        let p = PerturbedPattern { amplitude: 0.5, ..PerturbedPattern::new(inner, 3) };
+        let point = Tuple::point(0.4, 1.7, -2.2);
+        let moved = p.color_at(point);
+
+        // The test pattern returns the point it was sampled at, which is at most
+        // amplitude away on each axis
+        assert!(moved.fuzzy_ne(Color::new(0.4, 1.7, -2.2)));
+        let offset = moved - Color::new(0.4, 1.7, -2.2);
+        assert_fuzzy_eq!(offset, offset.clamp(-0.5, 0.5));
+    }
+
+    #[test]
+    fn solid_pattern_ignores_the_point() {
+        let p: Pattern = Color::new(0.2, 0.4, 0.6).into();
+
+        for point in [Tuple::point(0.0, 0.0, 0.0), Tuple::point(-3.5, 12.0, 0.25)] {
+            assert_fuzzy_eq!(Color::new(0.2, 0.4, 0.6), p.color_at(point));
+        }
+    }
+
+    #[test]
+    fn solid_pattern_transform_is_identity() {
+        let p: Pattern = SolidPattern(Color::red()).into();
+        let object: Shape = SphereBuilder::default()
+            .transform(Matrix::translation(5.0, 0.0, 0.0) * Matrix::scaling(3.0, 3.0, 3.0))
+            .build()
+            .unwrap()
+            .into();
+
+        assert_fuzzy_eq!(Matrix::identity(), p.transform());
+        assert_fuzzy_eq!(Color::red(), p.color_at_object(&object, Tuple::point(7.0, 1.0, -2.0)));
+    }
+
+    #[test]
+    fn blending_with_solid_white_keeps_a_checker() {
+        let checker = CheckerPattern3D::default();
+        let p = BlendedPattern::new(checker.clone().into(), Color::white().into(), BlendMode::Multiply);
+
+        let points = [Tuple::point(0.5, 0.5, 0.5), Tuple::point(1.5, 0.5, 0.5), Tuple::point(-0.5, 2.5, 0.1)];
+        for point in points {
+            assert_fuzzy_eq!(checker.color_at(point), p.color_at(point));
+        }
+    }
+
+    // Dots of a quarter unit around every lattice point, the way a user of the crate would
+    // write their own pattern
+    struct PolkaDots {
+        transform: Matrix<4>,
+    }
+
+    impl PatternFuncs for PolkaDots {
+        fn color_at(&self, point: Tuple) -> Color {
+            let nearest = Tuple::point(point.x.round(), point.y.round(), point.z.round());
+            if (point - nearest).magnitude() < 0.25 {
+                return Color::red()
+            }
+
+            Color::white()
+        }
+
+        fn transform(&self) -> Matrix<4> {
+            self.transform
+        }
+    }
+
+    #[test]
+    fn custom_pattern_on_transformed_sphere() {
+        let p: Pattern = CustomPattern::new(PolkaDots { transform: Matrix::translation(0.6, 0.8, 0.0) }).into();
+        let object: Shape = SphereBuilder::default()
+            .transform(Matrix::scaling(2.0, 2.0, 2.0))
+            .material(Material { pattern: Some(p.clone()), ..Default::default() })
+            .build()
+            .unwrap()
+            .into();
+
+        // (1.2, 1.6, 0) is (0.6, 0.8, 0) on the unit sphere, which the pattern moves onto a dot
+        assert_fuzzy_eq!(Color::red(), p.color_at_object(&object, Tuple::point(1.2, 1.6, 0.0)));
+        assert_fuzzy_eq!(Color::white(), p.color_at_object(&object, Tuple::point(2.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn custom_pattern_lights_like_built_in_patterns() {
+        let dots = CustomPattern::new(PolkaDots { transform: Matrix::identity() });
+        let solid = Color::red();
+        let object: Shape = SphereBuilder::default().build().unwrap().into();
+        let light = Light::default();
+        let (eyev, normalv) = (Tuple::vector(0.0, 0.0, -1.0), Tuple::vector(0.0, 0.0, -1.0));
+        let point = Tuple::point(0.0, 0.0, -1.0);
+
+        let lit = |pattern: Pattern| {
+            let m = Material { pattern: Some(pattern), ..Default::default() };
+            m.lighting(&object, point, light, eyev, normalv, 1.0)
+        };
+        assert_fuzzy_eq!(lit(solid.into()), lit(dots.clone().into()));
+        assert_eq!(dots, dots.clone());
+        assert_ne!(dots, CustomPattern::new(PolkaDots { transform: Matrix::identity() }));
+    }
+
+    #[test]
+    fn default_pattern_transformation() {
+        let p: Pattern = TestPattern::default().into();
+        assert_fuzzy_eq!(Matrix::identity(), p.transform());
+    }
+
+    #[test]
+    fn assigning_a_transformation() {
+        let p: Pattern = TestPattern { transform: Matrix::translation(1.0, 2.0, 3.0) }.into();
+        assert_fuzzy_eq!(Matrix::translation(1.0, 2.0, 3.0), p.transform());
+    }
+
+    #[test]
+    fn pattern_with_an_object_transformation() {
+        let object: Shape = SphereBuilder::default().transform(Matrix::scaling(2.0, 2.0, 2.0)).build().unwrap().into();
+        let p: Pattern = TestPattern::default().into();
+
+        let c = p.color_at_object(&object, Tuple::point(2.0, 3.0, 4.0));
+        assert_fuzzy_eq!(Color::new(1.0, 1.5, 2.0), c);
+    }
+
+    #[test]
+    fn pattern_with_a_pattern_transformation() {
+        let object: Shape = SphereBuilder::default().build().unwrap().into();
+        let p: Pattern = TestPattern { transform: Matrix::scaling(2.0, 2.0, 2.0) }.into();
+
+        let c = p.color_at_object(&object, Tuple::point(2.0, 3.0, 4.0));
+        assert_fuzzy_eq!(Color::new(1.0, 1.5, 2.0), c);
+    }
+
+    #[test]
+    fn pattern_with_both_an_object_and_a_pattern_transformation() {
+        let object: Shape = SphereBuilder::default().transform(Matrix::scaling(2.0, 2.0, 2.0)).build().unwrap().into();
+        let p: Pattern = TestPattern { transform: Matrix::translation(0.5, 1.0, 1.5) }.into();
+
+        let c = p.color_at_object(&object, Tuple::point(2.5, 3.0, 3.5));
+        assert_fuzzy_eq!(Color::new(0.75, 0.5, 0.25), c);
+    }
+}
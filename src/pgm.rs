@@ -0,0 +1,42 @@
+use crate::{ppm, two_dimensional::TwoDimensional};
+
+// Grayscale images, for depth passes and masks. Every pixel is one value from 0 for black to 1
+// for white, anything outside that is clamped
+pub trait ToPGM {
+    fn gray_values(&self) -> Vec<f64>;
+
+    // P2 and P5 headers only differ in the magic number on the first line
+    fn pgm_header(&self, magic: &str) -> Vec<u8>
+    where
+        Self: TwoDimensional,
+    {
+        format!("{}\n{} {}\n255\n", magic, self.width(), self.height()).into_bytes()
+    }
+
+    // Text samples, laid out like those of a P3 file
+    fn to_pgm(&self) -> Vec<u8>
+    where
+        Self: TwoDimensional,
+    {
+        let samples: Vec<u8> = self.gray_values().into_iter().map(quantize).collect();
+        let mut data = self.pgm_header("P2");
+        ppm::write_plain_rows(&mut data, samples.chunks(self.width().max(1)).map(|row| row.iter().copied()));
+
+        data
+    }
+
+    // One raw byte a pixel after the header
+    fn to_pgm_p5(&self) -> Vec<u8>
+    where
+        Self: TwoDimensional,
+    {
+        let mut data = self.pgm_header("P5");
+        data.extend(self.gray_values().into_iter().map(quantize));
+
+        data
+    }
+}
+
+fn quantize(value: f64) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
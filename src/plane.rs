@@ -1,16 +1,46 @@
-use crate::{material::Material, matrix::Matrix, shape::{ShapeFuncs, Shape}, tuple::Tuple, util::{FuzzyEq, EPSILON}, ray::Ray, intersection::{Intersections, Intersection}};
+use crate::{bounding_box::BoundingBox, light::LightId, material::Material, matrix::Matrix, shape::{next_shape_id, validate_transforms, ShapeFuncs, Shape}, tuple::Tuple, util::{FuzzyEq, EPSILON}, ray::Ray, intersection::{Intersections, Intersection}};
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default, Builder)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Builder)]
+#[builder(build_fn(validate = "Self::validate"))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Plane {
+    #[cfg_attr(feature = "serde", serde(skip, default = "next_shape_id"))]
+    #[builder(setter(skip), default = "next_shape_id()")]
+    pub(crate) id: u64,
     #[builder(default)]
     pub transform: Matrix<4>,
+    #[builder(default, setter(strip_option))]
+    pub transform_at_t1: Option<Matrix<4>>,
     #[builder(default)]
     pub material: Material,
+    #[builder(default = "true")]
+    pub cast_shadows: bool,
+    #[builder(default)]
+    pub excluded_lights: Vec<LightId>,
+    #[builder(default, setter(into, strip_option))]
+    pub name: Option<String>,
+}
+
+impl Default for Plane {
+    fn default() -> Self {
+        Self {
+            id: next_shape_id(),
+            transform: Matrix::identity(),
+            transform_at_t1: None,
+            material: Material::default(),
+            cast_shadows: true,
+            excluded_lights: vec![],
+            name: None,
+        }
+    }
 }
 
 impl FuzzyEq<Self> for Plane {
     fn fuzzy_eq(&self, other: Self) -> bool {
-        self.transform.fuzzy_eq(other.transform) && self.material.fuzzy_eq(other.material)
+        self.transform.fuzzy_eq(other.transform)
+            && self.material.fuzzy_eq(other.material)
+            && self.cast_shadows == other.cast_shadows
+            && self.excluded_lights == other.excluded_lights
     }
 
     fn fuzzy_ne(&self, other: Self) -> bool {
@@ -18,31 +48,56 @@ impl FuzzyEq<Self> for Plane {
     }
 }
 
+impl PlaneBuilder {
+    fn validate(&self) -> Result<(), String> {
+        validate_transforms(self.transform, self.transform_at_t1.flatten())
+    }
+}
+
 impl ShapeFuncs for Plane {
-    fn intersect(&self, ray: Ray) -> crate::intersection::Intersections {
-        if ray.direction.y.abs() < EPSILON {
+    fn local_intersect(&self, object_space_ray: Ray) -> Intersections {
+        if object_space_ray.direction.y.abs() < EPSILON {
             return Intersections::new(vec![])
         }
 
-        let t = -ray.origin.y / ray.direction.y;
-        Intersections::new(vec![Intersection::new(t, Shape::from(*self))])
+        let t = -object_space_ray.origin.y / object_space_ray.direction.y;
+        Intersections::new(vec![Intersection::new(t, Shape::from(self.clone()).at_time(object_space_ray.time))])
     }
 
-    fn normal_at(&self, _object_point: crate::tuple::Tuple) -> crate::tuple::Tuple {
+    fn local_normal_at(&self, _object_point: Tuple) -> Tuple {
         Tuple::vector(0.0, 1.0, 0.0)
     }
 
-    fn world_point_to_object_point(&self, world_point: Tuple) -> Tuple {
-        self.transform.inverse() * world_point
+    fn material(&self) -> Material {
+        self.material.clone()
     }
 
-    fn material(&self) -> Material {
-        self.material
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
     }
 
     fn transform(&self) -> Matrix<4> {
         self.transform
     }
+
+    fn casts_shadows(&self) -> bool {
+        self.cast_shadows
+    }
+
+    fn transform_at_t1(&self) -> Option<Matrix<4>> {
+        self.transform_at_t1
+    }
+
+    fn excluded_lights(&self) -> &[LightId] {
+        &self.excluded_lights
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Tuple::point(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY),
+            Tuple::point(f64::INFINITY, 0.0, f64::INFINITY),
+        )
+    }
 }
 
 #[cfg(test)]
@@ -89,7 +144,7 @@ mod tests {
         
         let xs = p.intersect(r);
         assert_fuzzy_eq!(1.0, xs.intersections[0].t);
-        assert_fuzzy_eq!(p, xs.intersections[0].object);
+        assert_fuzzy_eq!(p, xs.intersections[0].object.clone());
     }
 
     #[test]
@@ -99,7 +154,52 @@ mod tests {
         
         let xs = p.intersect(r);
         assert_fuzzy_eq!(1.0, xs.intersections[0].t);
-        assert_fuzzy_eq!(p, xs.intersections[0].object);
+        assert_fuzzy_eq!(p, xs.intersections[0].object.clone());
+    }
+
+    #[test]
+    fn intersect_translated_plane() {
+        let p: Shape = PlaneBuilder::default()
+            .transform(Matrix::translation(0.0, 2.0, 0.0))
+            .build()
+            .unwrap()
+            .into();
+        let r = Ray::new(Tuple::point(0.0, 5.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+
+        let xs = p.intersect(r);
+        assert_fuzzy_eq!(3.0, xs.intersections[0].t);
+    }
+
+    #[test]
+    fn normal_of_rotated_plane_is_in_world_space() {
+        let p = PlaneBuilder::default()
+            .transform(Matrix::rotation_x(std::f64::consts::PI / 2.0))
+            .build()
+            .unwrap();
+
+        assert_fuzzy_eq!(
+            Tuple::vector(0.0, 0.0, 1.0),
+            p.normal_at(Tuple::point(1.0, 0.0, 3.0))
+        );
+    }
+
+    #[test]
+    fn normal_of_sheared_plane_is_normalized() {
+        let p = PlaneBuilder::default()
+            .transform(Matrix::shearing(0.0, 0.0, 1.0, 0.0, 0.0, 0.0))
+            .build()
+            .unwrap();
+        let n = p.normal_at(Tuple::point(2.0, 0.0, 0.0));
+
+        assert_fuzzy_eq!(1.0, n.magnitude());
+        assert_fuzzy_eq!(Tuple::vector(-1.0, 1.0, 0.0).normalize(), n);
     }
 
+    #[test]
+    fn plane_has_bounding_box() {
+        let b = Plane::default().bounds();
+
+        assert_eq!(Tuple::point(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY), b.min);
+        assert_eq!(Tuple::point(f64::INFINITY, 0.0, f64::INFINITY), b.max);
+    }
 }
@@ -1,3 +1,76 @@
+use std::fmt;
+
+use ::png::{BitDepth, ColorType, Decoder, DecodingError};
+
+use crate::{canvas::Canvas, color::Color, rgb::ExportOptions};
+
 pub trait ToPNG {
-    fn to_png(self) -> Vec<u8>;
+    fn to_png(self) -> Vec<u8>
+    where
+        Self: Sized,
+    {
+        self.to_png_with(ExportOptions::default())
+    }
+
+    fn to_png_with(self, options: ExportOptions) -> Vec<u8>;
+
+    // Sixteen bits a channel instead of eight, so smooth gradients don't band. Readers that only
+    // take eight, from_png included, can't open these
+    fn to_png16(&self) -> Vec<u8> {
+        self.to_png16_with(ExportOptions::default())
+    }
+
+    fn to_png16_with(&self, options: ExportOptions) -> Vec<u8>;
+}
+
+#[derive(Debug)]
+pub enum PngError {
+    Decoding(DecodingError),
+    Unsupported { color_type: ColorType, bit_depth: BitDepth },
+}
+
+impl fmt::Display for PngError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decoding(e) => write!(f, "could not decode the PNG file: {}", e),
+            Self::Unsupported { color_type, bit_depth } => write!(
+                f,
+                "only 8-bit RGB and RGBA PNG files can be read, got {}-bit {:?}",
+                *bit_depth as u8, color_type
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PngError {}
+
+impl From<DecodingError> for PngError {
+    fn from(e: DecodingError) -> Self {
+        Self::Decoding(e)
+    }
+}
+
+// Samples are divided by 255 with no gamma undone, the inverse of what to_png writes. Alpha is
+// dropped, canvases have no use for it
+pub(crate) fn read(bytes: &[u8]) -> Result<Canvas, PngError> {
+    let mut reader = Decoder::new(bytes).read_info()?;
+    let info = reader.info();
+    let channels = match (info.color_type, info.bit_depth) {
+        (ColorType::Rgb, BitDepth::Eight) => 3,
+        (ColorType::Rgba, BitDepth::Eight) => 4,
+        (color_type, bit_depth) => return Err(PngError::Unsupported { color_type, bit_depth }),
+    };
+
+    let mut data = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut data)?;
+    let (width, height) = (frame.width as usize, frame.height as usize);
+
+    let pixels = data
+        .chunks(frame.line_size)
+        .take(height)
+        .flat_map(|line| line[..width * channels].chunks(channels))
+        .map(|p| Color::new(p[0] as f64 / 255.0, p[1] as f64 / 255.0, p[2] as f64 / 255.0))
+        .collect();
+
+    Ok(Canvas { width, height, pixels })
 }
@@ -0,0 +1,277 @@
+use std::f64::consts::PI;
+
+use crate::{
+    camera::Camera,
+    color::Color,
+    cylinder::CylinderBuilder,
+    group::{Group, GroupBuilder},
+    light::Light,
+    material::Material,
+    matrix::Matrix,
+    plane::PlaneBuilder,
+    shape::Shape,
+    sphere::SphereBuilder,
+    tuple::Tuple,
+    world::World,
+};
+
+fn hexagon_corner() -> Shape {
+    SphereBuilder::default()
+        .transform(Matrix::translation(0.0, 0.0, -1.0) * Matrix::scaling(0.25, 0.25, 0.25))
+        .build()
+        .unwrap()
+        .into()
+}
+
+fn hexagon_edge() -> Shape {
+    CylinderBuilder::default()
+        .minimum(0.0)
+        .maximum(1.0)
+        .transform(
+            Matrix::translation(0.0, 0.0, -1.0)
+                * Matrix::rotation_y(-PI / 6.0)
+                * Matrix::rotation_z(-PI / 2.0)
+                * Matrix::scaling(0.25, 1.0, 0.25),
+        )
+        .build()
+        .unwrap()
+        .into()
+}
+
+fn hexagon_side(transform: Matrix<4>) -> Shape {
+    GroupBuilder::default()
+        .transform(transform)
+        .children(vec![hexagon_corner(), hexagon_edge()])
+        .build()
+        .unwrap()
+        .into()
+}
+
+// The hexagon from the book's groups chapter: six sides, each a corner sphere and an edge
+// cylinder, rotated into place around the y axis
+pub fn hexagon() -> Shape {
+    let mut hex = Group::default();
+    for n in 0..6 {
+        hex.add_child(hexagon_side(Matrix::rotation_y(n as f64 * PI / 3.0)));
+    }
+
+    hex.into()
+}
+
+fn matte(color: Color) -> Material {
+    Material::with_color(color).with_specular(0.0)
+}
+
+fn sphere(name: &str, color: Color, transform: Matrix<4>) -> Shape {
+    SphereBuilder::default()
+        .name(name)
+        .material(Material::with_color(color).with_diffuse(0.7).with_specular(0.3))
+        .transform(transform)
+        .build()
+        .unwrap()
+        .into()
+}
+
+// The three spheres of the book's early chapters, a big green one in the middle with a smaller
+// green one to its right and a yellow one to its left, all resting on y = 0
+pub fn three_spheres() -> Vec<Shape> {
+    vec![
+        sphere("left", Color::new(1.0, 0.8, 0.1), Matrix::translation(-1.5, 0.33, -0.75) * Matrix::scaling(0.33, 0.33, 0.33)),
+        sphere("middle", Color::new(0.5, 1.0, 0.1), Matrix::translation(-0.5, 1.0, 0.5) * Matrix::scaling(0.5, 0.5, 0.5)),
+        sphere("right", Color::new(0.5, 1.0, 0.1), Matrix::translation(1.5, 0.5, -0.5) * Matrix::scaling(0.33, 0.33, 0.33)),
+    ]
+}
+
+// Looking at the three spheres from a little above and in front of them, with a size by size
+// image
+pub fn three_sphere_camera(size: usize) -> Camera {
+    let mut camera = Camera::new(size, size, PI / 3.0);
+    camera.set_transform(Matrix::view_transform(
+        Tuple::point(0.0, 1.5, -5.0),
+        Tuple::point(0.0, 1.0, 0.0),
+        Tuple::vector(0.0, 1.0, 0.0),
+    )).unwrap();
+    camera
+}
+
+// The three spheres on a pinkish floor, lit from above and to the left of the camera
+pub fn three_sphere_scene(size: usize) -> (World, Camera) {
+    let floor: Shape = PlaneBuilder::default()
+        .name("floor")
+        .material(matte(Color::new(1.0, 0.9, 0.9)))
+        .build()
+        .unwrap()
+        .into();
+    let mut objects = vec![floor];
+    objects.extend(three_spheres());
+    let light = Light::point(Tuple::point(-10.0, 10.0, -10.0), Color::white());
+
+    (World::new(objects, light), three_sphere_camera(size))
+}
+
+// A box size across with its middle at the origin and the side towards -z left open, a red wall
+// on the left, a green one on the right and a light just under the ceiling. The walls are planes,
+// so they carry on past the open side
+pub fn cornell_box(size: f64) -> World {
+    let half = size / 2.0;
+    let wall = |name: &str, color: Color, transform: Matrix<4>| -> Shape {
+        PlaneBuilder::default()
+            .name(name)
+            .material(matte(color))
+            .transform(transform)
+            .build()
+            .unwrap()
+            .into()
+    };
+
+    let walls = vec![
+        wall("floor", Color::new(0.73, 0.73, 0.73), Matrix::translation(0.0, -half, 0.0)),
+        wall("ceiling", Color::new(0.8, 0.8, 0.7), Matrix::translation(0.0, half, 0.0)),
+        wall("back_wall", Color::new(0.6, 0.6, 0.75), Matrix::translation(0.0, 0.0, half) * Matrix::rotation_x(PI / 2.0)),
+        wall("left_wall", Color::new(0.63, 0.065, 0.05), Matrix::translation(-half, 0.0, 0.0) * Matrix::rotation_z(PI / 2.0)),
+        wall("right_wall", Color::new(0.14, 0.45, 0.091), Matrix::translation(half, 0.0, 0.0) * Matrix::rotation_z(PI / 2.0)),
+    ];
+    let light = Light::point(Tuple::point(0.0, half * 0.9, 0.0), Color::white());
+
+    World::new(walls, light)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        assert_fuzzy_eq, camera::Camera, color::Color, light::Light, shape::ShapeFuncs,
+        tuple::Tuple, util::FuzzyEq, world::World,
+    };
+
+    use super::*;
+
+    fn children(shape: &Shape) -> &[Shape] {
+        match shape {
+            Shape::Group(g) => g.children(),
+            s => panic!("expected a group, got {:?}", s),
+        }
+    }
+
+    #[test]
+    fn hexagon_has_six_sides_of_two_children() {
+        let hex = hexagon();
+
+        assert_eq!(6, children(&hex).len());
+        for side in children(&hex) {
+            let parts = children(side);
+            assert_eq!(2, parts.len());
+            assert!(matches!(parts[0], Shape::Sphere(_)));
+            assert!(matches!(parts[1], Shape::Cylinder(_)));
+        }
+    }
+
+    #[test]
+    fn composed_transforms_of_first_corner_and_edge() {
+        let hex = hexagon();
+        let side = &children(&hex)[0];
+        let corner = &children(side)[0];
+        let edge = &children(side)[1];
+
+        let corner_world = side.transform() * corner.transform();
+        let edge_world = side.transform() * edge.transform();
+
+        assert_fuzzy_eq!(
+            Matrix::translation(0.0, 0.0, -1.0) * Matrix::scaling(0.25, 0.25, 0.25),
+            corner_world
+        );
+        assert_fuzzy_eq!(
+            Tuple::point(0.0, 0.0, -1.0),
+            corner_world * Tuple::point(0.0, 0.0, 0.0)
+        );
+        // The edge runs from this side's corner to the next side's corner
+        assert_fuzzy_eq!(
+            Tuple::point(0.0, 0.0, -1.0),
+            edge_world * Tuple::point(0.0, 0.0, 0.0)
+        );
+        assert_fuzzy_eq!(
+            Tuple::point(0.86603, 0.0, -0.5),
+            edge_world * Tuple::point(0.0, 1.0, 0.0)
+        );
+        let next_side = &children(&hex)[5];
+        let next_corner = &children(next_side)[0];
+        assert_fuzzy_eq!(
+            Tuple::point(0.86603, 0.0, -0.5),
+            next_side.transform() * next_corner.transform() * Tuple::point(0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn rendering_hexagon_matches_stored_pixels() {
+        let light = Light::point(Tuple::point(-10.0, 10.0, -10.0), Color::white());
+        let w = World::new(vec![hexagon()], light);
+        let mut camera = Camera::new(11, 11, PI / 3.0);
+        camera.set_transform(Matrix::view_transform(
+            Tuple::point(0.0, 2.5, -2.5),
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0),
+        )).unwrap();
+
+        let image = camera.render(&w);
+        let expected = [
+            ((5, 5), 0.0),
+            ((5, 4), 0.96824),
+            ((3, 5), 0.76828),
+            ((7, 5), 0.37886),
+            ((3, 6), 0.97679),
+            ((5, 7), 0.78493),
+        ];
+        for ((x, y), v) in expected {
+            assert_fuzzy_eq!(Color::new(v, v, v), image.pixel_at(x, y));
+        }
+    }
+
+    #[test]
+    fn rendering_three_sphere_scene_matches_stored_pixels() {
+        let (w, camera) = three_sphere_scene(11);
+        let image = camera.render(&w);
+
+        let expected = [
+            ((4, 5), Color::new(0.27450, 0.54899, 0.05490)),
+            ((2, 6), Color::new(0.34619, 0.27695, 0.03462)),
+            ((8, 6), Color::new(0.38004, 0.75087, 0.08338)),
+            ((5, 10), Color::new(0.66608, 0.59947, 0.59947)),
+            ((0, 0), Color::black()),
+        ];
+        for ((x, y), color) in expected {
+            assert_fuzzy_eq!(color, image.pixel_at(x, y));
+        }
+    }
+
+    #[test]
+    fn cornell_box_walls_are_named() {
+        let w = cornell_box(2.0);
+
+        for name in ["floor", "ceiling", "back_wall", "left_wall", "right_wall"] {
+            assert!(w.object_by_name(name).is_some(), "{}", name);
+        }
+        assert_eq!(5, w.objects().len());
+    }
+
+    #[test]
+    fn rendering_cornell_box_matches_stored_pixels() {
+        let w = cornell_box(2.0);
+        let mut camera = Camera::new(11, 11, PI / 3.0);
+        camera.set_transform(Matrix::view_transform(
+            Tuple::point(0.0, 0.0, -2.0),
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0),
+        )).unwrap();
+
+        let image = camera.render(&w);
+        let expected = [
+            ((5, 5), Color::new(0.46138, 0.46138, 0.57672)),
+            ((1, 5), Color::new(0.46846, 0.04833, 0.03718)),
+            ((9, 5), Color::new(0.10410, 0.33461, 0.06767)),
+            ((5, 1), Color::new(0.26253, 0.26253, 0.22971)),
+            ((5, 9), Color::new(0.71714, 0.71714, 0.71714)),
+        ];
+        for ((x, y), color) in expected {
+            assert_fuzzy_eq!(color, image.pixel_at(x, y));
+        }
+    }
+}
@@ -1,9 +1,13 @@
-use crate::{matrix::Matrix, tuple::Tuple, util::FuzzyEq};
+use crate::{error::Error, matrix::Matrix, tuple::Tuple, util::FuzzyEq};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Ray {
     pub origin: Tuple,
     pub direction: Tuple,
+    // When in the frame the ray is cast, from 0 to 1. Moving shapes are intersected where they
+    // are at that moment
+    pub time: f64,
+    inverse_direction: Tuple,
 }
 
 impl FuzzyEq<Self> for Ray {
@@ -22,10 +26,33 @@ impl FuzzyEq<Self> for Ray {
 
 impl Ray {
     pub fn new(origin: Tuple, direction: Tuple) -> Self {
+        Self::try_new(origin, direction).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(origin: Tuple, direction: Tuple) -> Result<Self, Error> {
         if !origin.is_point() || !direction.is_vector() {
-            panic!("Given origin or vector are not of the correct tuple type")
+            return Err(Error::InvalidRay { origin, direction });
         }
-        Ray { origin, direction }
+
+        Ok(Ray {
+            origin,
+            direction,
+            time: 0.0,
+            inverse_direction: Self::reciprocal(direction),
+        })
+    }
+
+    pub fn at_time(self, time: f64) -> Self {
+        Self { time, ..self }
+    }
+
+    fn reciprocal(direction: Tuple) -> Tuple {
+        Tuple::vector(1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z)
+    }
+
+    // Zero components become infinities, which the slab test below relies on
+    pub fn inverse_direction(&self) -> Tuple {
+        self.inverse_direction
     }
 
     pub fn position(&self, t: f64) -> Tuple {
@@ -33,9 +60,48 @@ impl Ray {
     }
 
     pub fn transform(&self, m: Matrix<4>) -> Self {
+        let direction = m * self.direction;
         Self {
             origin: m * self.origin,
-            direction: m * self.direction,
+            direction,
+            time: self.time,
+            inverse_direction: Self::reciprocal(direction),
+        }
+    }
+
+    // Only boxes that are at least partly in front of the origin
+    pub fn intersect_aabb(&self, min: Tuple, max: Tuple) -> Option<(f64, f64)> {
+        self.aabb_interval(min, max).filter(|&(_, tmax)| tmax >= 0.0)
+    }
+
+    // Slab test against an axis aligned box, giving the interval of t where the ray's line is
+    // inside it, behind the origin too. A ray parallel to a slab gets ±inf for that axis, or NaN
+    // for a face it lies exactly on. Picking the near and far face by the sign of the reciprocal
+    // keeps such a NaN on the side where f64::max and f64::min drop it, so zero components need
+    // no special cases
+    pub fn aabb_interval(&self, min: Tuple, max: Tuple) -> Option<(f64, f64)> {
+        let inv = self.inverse_direction;
+        let mut tmin = f64::NEG_INFINITY;
+        let mut tmax = f64::INFINITY;
+
+        for (o, i, lo, hi) in [
+            (self.origin.x, inv.x, min.x, max.x),
+            (self.origin.y, inv.y, min.y, max.y),
+            (self.origin.z, inv.z, min.z, max.z),
+        ] {
+            let (near, far) = if i.is_sign_negative() {
+                (hi, lo)
+            } else {
+                (lo, hi)
+            };
+            tmin = tmin.max((near - o) * i);
+            tmax = tmax.min((far - o) * i);
+        }
+
+        if tmin > tmax {
+            None
+        } else {
+            Some((tmin, tmax))
         }
     }
 }
@@ -84,4 +150,124 @@ mod tests {
         assert_fuzzy_eq!(Tuple::point(2.0, 6.0, 12.0), r2.origin);
         assert_fuzzy_eq!(Tuple::vector(0.0, 3.0, 0.0), r2.direction);
     }
+
+    fn unit_box() -> (Tuple, Tuple) {
+        (Tuple::point(-1.0, -1.0, -1.0), Tuple::point(1.0, 1.0, 1.0))
+    }
+
+    #[test]
+    fn inverse_direction_is_cached_reciprocal() {
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(2.0, -4.0, 0.0));
+
+        assert_fuzzy_eq!(0.5, r.inverse_direction().x);
+        assert_fuzzy_eq!(-0.25, r.inverse_direction().y);
+        assert_eq!(f64::INFINITY, r.inverse_direction().z);
+
+        let r2 = r.transform(Matrix::scaling(2.0, 1.0, 1.0));
+        assert_fuzzy_eq!(0.25, r2.inverse_direction().x);
+    }
+
+    #[test]
+    fn ray_hits_aabb_along_each_axis() {
+        let (min, max) = unit_box();
+        let rays = [
+            (Tuple::point(5.0, 0.5, 0.0), Tuple::vector(-1.0, 0.0, 0.0)),
+            (Tuple::point(-5.0, 0.5, 0.0), Tuple::vector(1.0, 0.0, 0.0)),
+            (Tuple::point(0.5, 5.0, 0.0), Tuple::vector(0.0, -1.0, 0.0)),
+            (Tuple::point(0.5, -5.0, 0.0), Tuple::vector(0.0, 1.0, 0.0)),
+            (Tuple::point(0.5, 0.0, 5.0), Tuple::vector(0.0, 0.0, -1.0)),
+            (Tuple::point(0.5, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0)),
+        ];
+
+        for (origin, direction) in rays {
+            let (t1, t2) = Ray::new(origin, direction).intersect_aabb(min, max).unwrap();
+            assert_fuzzy_eq!(4.0, t1);
+            assert_fuzzy_eq!(6.0, t2);
+        }
+    }
+
+    #[test]
+    fn ray_hits_aabb_diagonally() {
+        let (min, max) = unit_box();
+        let r = Ray::new(Tuple::point(-3.0, -3.0, 0.0), Tuple::vector(1.0, 1.0, 0.0));
+
+        let (t1, t2) = r.intersect_aabb(min, max).unwrap();
+        assert_fuzzy_eq!(2.0, t1);
+        assert_fuzzy_eq!(4.0, t2);
+    }
+
+    #[test]
+    fn ray_starting_inside_aabb() {
+        let (min, max) = unit_box();
+        let r = Ray::new(Tuple::point(0.0, 0.5, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        let (t1, t2) = r.intersect_aabb(min, max).unwrap();
+        assert_fuzzy_eq!(-1.0, t1);
+        assert_fuzzy_eq!(1.0, t2);
+    }
+
+    #[test]
+    fn ray_grazing_aabb_edge() {
+        let (min, max) = unit_box();
+        // Runs along the edge where the x = 1 and y = 1 faces meet
+        let r = Ray::new(Tuple::point(1.0, 1.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let (t1, t2) = r.intersect_aabb(min, max).unwrap();
+        assert_fuzzy_eq!(4.0, t1);
+        assert_fuzzy_eq!(6.0, t2);
+
+        // Touches the box only at the corner (1, 1, 1)
+        let r = Ray::new(Tuple::point(3.0, -1.0, 1.0), Tuple::vector(-1.0, 1.0, 0.0));
+        let (t1, t2) = r.intersect_aabb(min, max).unwrap();
+        assert_fuzzy_eq!(2.0, t1);
+        assert_fuzzy_eq!(2.0, t2);
+    }
+
+    #[test]
+    fn ray_misses_aabb() {
+        let (min, max) = unit_box();
+        let rays = [
+            (Tuple::point(-2.0, 0.0, 0.0), Tuple::vector(0.2673, 0.5345, 0.8018)),
+            (Tuple::point(0.0, 2.0, 2.0), Tuple::vector(0.0, 0.0, -1.0)),
+            (Tuple::point(2.0, 2.0, 0.0), Tuple::vector(-1.0, 0.0, 0.0)),
+            // The box is entirely behind these
+            (Tuple::point(0.0, 0.0, 5.0), Tuple::vector(0.0, 0.0, 1.0)),
+            (Tuple::point(-3.0, -3.0, 0.0), Tuple::vector(-1.0, -1.0, 0.0)),
+        ];
+
+        for (origin, direction) in rays {
+            assert_eq!(None, Ray::new(origin, direction).intersect_aabb(min, max));
+        }
+    }
+
+    #[test]
+    fn aabb_interval_keeps_a_box_behind_the_origin() {
+        let (min, max) = unit_box();
+        let r = Ray::new(Tuple::point(0.0, 0.0, 5.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        let (t1, t2) = r.aabb_interval(min, max).unwrap();
+        assert_fuzzy_eq!(-6.0, t1);
+        assert_fuzzy_eq!(-4.0, t2);
+        assert_eq!(None, r.intersect_aabb(min, max));
+    }
+
+    #[test]
+    fn transforming_a_ray_keeps_its_time() {
+        let r = Ray::new(Tuple::point(1.0, 2.0, 3.0), Tuple::vector(0.0, 1.0, 0.0)).at_time(0.25);
+        let r2 = r.transform(Matrix::translation(3.0, 4.0, 5.0));
+
+        assert_eq!(0.0, Ray::new(r.origin, r.direction).time);
+        assert_eq!(0.25, r2.time);
+    }
+
+    #[test]
+    fn ray_from_two_points_is_an_error_naming_them() {
+        let err = Ray::try_new(Tuple::point(1.0, 2.0, 3.0), Tuple::point(4.0, 5.0, 6.0)).unwrap_err();
+
+        assert_eq!(
+            Error::InvalidRay { origin: Tuple::point(1.0, 2.0, 3.0), direction: Tuple::point(4.0, 5.0, 6.0) },
+            err
+        );
+        let message = err.to_string();
+        assert!(message.contains(&format!("{:?}", Tuple::point(4.0, 5.0, 6.0))), "{}", message);
+    }
 }
@@ -0,0 +1,137 @@
+#[cfg(feature = "ray-counters")]
+use std::cell::RefCell;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+#[cfg(feature = "ray-counters")]
+thread_local! {
+    // The counters rays cast on this thread go to, if anyone asked for them
+    static ACTIVE: RefCell<Option<Arc<RayCounters>>> = const { RefCell::new(None) };
+}
+
+// How many rays of each kind went into a render, and how many times a ray was tested against a
+// shape, children of groups included. All zero unless the ray-counters feature is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RayCounts {
+    pub primary_rays: usize,
+    pub shadow_rays: usize,
+    pub secondary_rays: usize,
+    pub intersection_tests: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Counter {
+    Primary,
+    Shadow,
+    Secondary,
+    IntersectionTest,
+}
+
+// Shared by every render thread. Nothing is ordered by the counts, so relaxed adds are enough
+#[derive(Debug, Default)]
+pub struct RayCounters {
+    primary_rays: AtomicUsize,
+    shadow_rays: AtomicUsize,
+    secondary_rays: AtomicUsize,
+    intersection_tests: AtomicUsize,
+}
+
+impl RayCounters {
+    pub fn counts(&self) -> RayCounts {
+        RayCounts {
+            primary_rays: self.primary_rays.load(Ordering::Relaxed),
+            shadow_rays: self.shadow_rays.load(Ordering::Relaxed),
+            secondary_rays: self.secondary_rays.load(Ordering::Relaxed),
+            intersection_tests: self.intersection_tests.load(Ordering::Relaxed),
+        }
+    }
+
+    #[cfg(feature = "ray-counters")]
+    fn add(&self, counter: Counter) {
+        let total = match counter {
+            Counter::Primary => &self.primary_rays,
+            Counter::Shadow => &self.shadow_rays,
+            Counter::Secondary => &self.secondary_rays,
+            Counter::IntersectionTest => &self.intersection_tests,
+        };
+        total.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// Runs op with the rays cast on this thread counted in counters. Without counters, or for rays
+// cast outside of op, counting is a look at an empty slot
+#[cfg(feature = "ray-counters")]
+pub(crate) fn counting<R>(counters: Option<&Arc<RayCounters>>, op: impl FnOnce() -> R) -> R {
+    let Some(counters) = counters else {
+        return op();
+    };
+
+    // Puts back whatever was counting before, even when op panics
+    struct Restore(Option<Arc<RayCounters>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            ACTIVE.with(|active| *active.borrow_mut() = self.0.take());
+        }
+    }
+
+    let _restore = Restore(ACTIVE.with(|active| active.borrow_mut().replace(counters.clone())));
+    op()
+}
+
+#[cfg(feature = "ray-counters")]
+pub(crate) fn count(counter: Counter) {
+    ACTIVE.with(|active| {
+        if let Some(counters) = active.borrow().as_ref() {
+            counters.add(counter);
+        }
+    });
+}
+
+#[cfg(not(feature = "ray-counters"))]
+pub(crate) fn counting<R>(_counters: Option<&Arc<RayCounters>>, op: impl FnOnce() -> R) -> R {
+    op()
+}
+
+#[cfg(not(feature = "ray-counters"))]
+#[inline(always)]
+pub(crate) fn count(_counter: Counter) {}
+
+#[cfg(all(test, feature = "ray-counters"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_counts_inside_counting() {
+        let counters = Arc::new(RayCounters::default());
+
+        count(Counter::Primary);
+        counting(Some(&counters), || {
+            count(Counter::Primary);
+            count(Counter::Shadow);
+            count(Counter::Shadow);
+        });
+        count(Counter::Shadow);
+
+        assert_eq!(
+            RayCounts { primary_rays: 1, shadow_rays: 2, secondary_rays: 0, intersection_tests: 0 },
+            counters.counts()
+        );
+    }
+
+    #[test]
+    fn nested_counting_goes_back_to_outer_counters() {
+        let outer = Arc::new(RayCounters::default());
+        let inner = Arc::new(RayCounters::default());
+
+        counting(Some(&outer), || {
+            counting(Some(&inner), || count(Counter::Secondary));
+            count(Counter::Secondary);
+            counting(None, || count(Counter::Secondary));
+        });
+
+        assert_eq!(2, outer.counts().secondary_rays);
+        assert_eq!(1, inner.counts().secondary_rays);
+    }
+}
@@ -1,3 +1,57 @@
+// How a 0 to 1 channel value is turned into the 0 to 1 value that gets quantized. Linear writes
+// it as is, like the book does. Srgb applies the sRGB transfer function image viewers expect,
+// without which mid-tones come out too dark
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Linear,
+    Srgb,
+}
+
+impl Encoding {
+    pub fn encode(self, value: f64) -> f64 {
+        match self {
+            Self::Linear => value,
+            Self::Srgb if value <= 0.0031308 => value * 12.92,
+            Self::Srgb => 1.055 * value.powf(1.0 / 2.4) - 0.055,
+        }
+    }
+}
+
+// The settings image writers take. The default is what to_png and to_ppm write
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ExportOptions {
+    pub encoding: Encoding,
+}
+
 pub trait ToRgbA32 {
-    fn to_rgba32(&self) -> Vec<u8>;
+    fn to_rgba32(&self) -> Vec<u8> {
+        self.to_rgba32_with(Encoding::Linear).to_vec()
+    }
+
+    fn to_rgba32_with(&self, encoding: Encoding) -> [u8; 4];
+}
+
+// The channels as they are, for formats that store floats
+pub trait ToRgbF32 {
+    fn to_rgbf32(&self) -> [f32; 3];
+}
+
+// Sixteen bits a channel, for gradients too smooth for eight
+pub trait ToRgbA64 {
+    fn to_rgba64_with(&self, encoding: Encoding) -> [u16; 4];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_brightens_mid_tones_but_keeps_black_and_white() {
+        assert_eq!(0.5, Encoding::Linear.encode(0.5));
+        assert!((Encoding::Srgb.encode(0.5) - 0.735357).abs() < 0.00001);
+        assert_eq!(0.0, Encoding::Srgb.encode(0.0));
+        assert!((Encoding::Srgb.encode(1.0) - 1.0).abs() < 1e-12);
+        assert_eq!(0.001 * 12.92, Encoding::Srgb.encode(0.001));
+    }
 }
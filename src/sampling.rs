@@ -0,0 +1,126 @@
+use std::f64::consts::PI;
+
+use crate::tuple::Tuple;
+
+// A source of random numbers for the stochastic parts of a render. Everything is drawn from
+// next_f64, so an implementation only has to provide that
+pub trait Sampler {
+    // Uniform in [0, 1)
+    fn next_f64(&mut self) -> f64;
+
+    // Uniform over the unit disk, which needs the square root to not bunch up in the middle
+    fn next_in_unit_disk(&mut self) -> (f64, f64) {
+        let r = self.next_f64().sqrt();
+        let theta = 2.0 * PI * self.next_f64();
+
+        (r * theta.cos(), r * theta.sin())
+    }
+
+    // A unit vector, uniform over the half of the sphere the normal points into
+    fn next_in_hemisphere(&mut self, normal: Tuple) -> Tuple {
+        let z = 1.0 - 2.0 * self.next_f64();
+        let phi = 2.0 * PI * self.next_f64();
+        let r = (1.0 - z * z).sqrt();
+        let v = Tuple::vector(r * phi.cos(), r * phi.sin(), z);
+
+        if v.dot(normal) < 0.0 {
+            -v
+        } else {
+            v
+        }
+    }
+}
+
+// xorshift64* started from a hash of the seed, pixel and sample. Every sample gets a stream of
+// its own, so a pixel comes out the same no matter which other pixels are rendered
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelSampler {
+    state: u64,
+}
+
+impl PixelSampler {
+    pub fn new(seed: u64, x: usize, y: usize, sample: usize) -> Self {
+        let state = [x, y, sample]
+            .into_iter()
+            .fold(splitmix(seed), |state, v| splitmix(state ^ v as u64));
+
+        // An all zero state would only ever give zeroes
+        Self { state: state.max(1) }
+    }
+}
+
+impl Sampler for PixelSampler {
+    fn next_f64(&mut self) -> f64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+
+        (x.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn splitmix(state: u64) -> u64 {
+    let mut z = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_fuzzy_eq, util::FuzzyEq};
+
+    use super::*;
+
+    fn draws(mut sampler: impl Sampler, n: usize) -> Vec<f64> {
+        (0..n).map(|_| sampler.next_f64()).collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_stream() {
+        assert_eq!(draws(PixelSampler::new(7, 3, 4, 1), 16), draws(PixelSampler::new(7, 3, 4, 1), 16));
+    }
+
+    #[test]
+    fn seed_pixel_and_sample_each_change_the_stream() {
+        let base = draws(PixelSampler::new(7, 3, 4, 1), 4);
+
+        assert_ne!(base, draws(PixelSampler::new(8, 3, 4, 1), 4));
+        assert_ne!(base, draws(PixelSampler::new(7, 4, 4, 1), 4));
+        assert_ne!(base, draws(PixelSampler::new(7, 3, 5, 1), 4));
+        assert_ne!(base, draws(PixelSampler::new(7, 3, 4, 2), 4));
+    }
+
+    #[test]
+    fn draws_stay_in_unit_interval() {
+        let values = draws(PixelSampler::new(0, 0, 0, 0), 10_000);
+
+        assert!(values.iter().all(|v| (0.0..1.0).contains(v)));
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        assert!((mean - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn disk_points_stay_inside_the_unit_circle() {
+        let mut sampler = PixelSampler::new(1, 2, 3, 4);
+
+        for _ in 0..1000 {
+            let (x, y) = sampler.next_in_unit_disk();
+            assert!(x * x + y * y <= 1.0);
+        }
+    }
+
+    #[test]
+    fn hemisphere_directions_face_the_normal() {
+        let mut sampler = PixelSampler::new(1, 2, 3, 4);
+        let normal = Tuple::vector(0.0, 1.0, 0.0);
+
+        for _ in 0..1000 {
+            let v = sampler.next_in_hemisphere(normal);
+            assert_fuzzy_eq!(1.0, v.magnitude());
+            assert!(v.dot(normal) >= 0.0);
+        }
+    }
+}
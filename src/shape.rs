@@ -1,50 +1,173 @@
-use std::fmt::Debug;
+use std::{
+    cmp::Ordering,
+    fmt::Debug,
+    sync::atomic::{AtomicU64, Ordering as AtomicOrdering},
+};
 
 use crate::{
-    intersection::Intersections, material::Material, matrix::Matrix, plane::Plane, ray::Ray,
-    sphere::Sphere, tuple::Tuple, util::FuzzyEq,
+    bounding_box::BoundingBox, cone::Cone, error::Error, cube::Cube, cylinder::Cylinder, disk::Disk,
+    group::Group, intersection::Intersections, light::LightId, material::Material, matrix::Matrix, mesh::Mesh,
+    plane::Plane, ray::Ray,
+    ray_counters::{self, Counter}, sphere::Sphere, torus::Torus, triangle::Triangle, tuple::Tuple,
+    util::FuzzyEq,
 };
 
+#[cfg(test)]
+use crate::test_shape::TestShape;
+
+// For the shape builders, as a shape whose transform can't be inverted can't be intersected
+pub(crate) fn validate_transforms(transform: Option<Matrix<4>>, transform_at_t1: Option<Matrix<4>>) -> Result<(), String> {
+    match [transform, transform_at_t1].into_iter().flatten().find(|t| !t.is_invertible()) {
+        Some(matrix) => Err(Error::NonInvertibleTransform { matrix: Box::new(matrix) }.to_string()),
+        None => Ok(()),
+    }
+}
+
+static NEXT_SHAPE_ID: AtomicU64 = AtomicU64::new(1);
+
+pub fn next_shape_id() -> u64 {
+    NEXT_SHAPE_ID.fetch_add(1, AtomicOrdering::Relaxed)
+}
+
 pub trait ShapeFuncs {
-    fn intersect(&self, ray: Ray) -> Intersections;
-    fn normal_at(&self, object_point: Tuple) -> Tuple;
-    fn world_point_to_object_point(&self, world_point: Tuple) -> Tuple;
+    // Moves the world space ray into object space, so shapes only implement local_intersect
+    fn intersect(&self, ray: Ray) -> Intersections {
+        ray_counters::count(Counter::IntersectionTest);
+        self.local_intersect(ray.transform(self.transform_at(ray.time).inverse()))
+    }
+    fn local_intersect(&self, object_space_ray: Ray) -> Intersections;
+    // Converts the object space normal from local_normal_at back into world space, after the
+    // material's normal map has had its say
+    fn normal_at(&self, world_point: Tuple) -> Tuple {
+        let object_point = self.world_point_to_object_point(world_point);
+        let mut object_normal = self.local_normal_at(object_point);
+        if let Some(map) = self.material().normal_perturbation {
+            object_normal = object_normal + map.perturbation_at(object_point);
+        }
+        let mut world_normal = self.transform().inverse().tranpose() * object_normal;
+
+        world_normal.w = 0.0;
+        world_normal.normalize()
+    }
+    fn local_normal_at(&self, object_point: Tuple) -> Tuple;
+    fn world_point_to_object_point(&self, world_point: Tuple) -> Tuple {
+        self.transform().inverse() * world_point
+    }
     fn material(&self) -> Material;
     fn transform(&self) -> Matrix<4>;
+    fn bounds(&self) -> BoundingBox;
+
+    fn casts_shadows(&self) -> bool {
+        true
+    }
+
+    // Whether something along the ray before max_t blocks all light, which takes a shape that
+    // casts shadows and lets nothing through. Any hit will do, so shapes that can find one
+    // without the rest are free to
+    fn is_occluding(&self, ray: Ray, max_t: f64) -> bool {
+        self.intersect(ray).intersections.iter().any(|i| {
+            i.t > 0.0 && i.t < max_t && i.object.casts_shadows() && i.object.material().transparency() <= 0.0
+        })
+    }
+
+    fn excluded_lights(&self) -> &[LightId] {
+        &[]
+    }
+
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
+    // Where a moving shape ends up at time 1, it starts out at transform. Shapes hand themselves
+    // out in intersections already moved to where the ray caught them
+    fn transform_at_t1(&self) -> Option<Matrix<4>> {
+        None
+    }
+
+    fn transform_at(&self, time: f64) -> Matrix<4> {
+        match self.transform_at_t1() {
+            Some(t1) => self.transform().lerp(t1, time),
+            None => self.transform(),
+        }
+    }
+
+    // A moving shape is bounded over its whole path, which the boxes at both ends cover
+    fn parent_space_bounds(&self) -> BoundingBox {
+        let mut bounds = self.bounds().transform(self.transform());
+        if let Some(t1) = self.transform_at_t1() {
+            bounds.merge(self.bounds().transform(t1));
+        }
+        bounds
+    }
 }
 
-#[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(tag = "type"))]
 pub enum Shape {
     Sphere(Sphere),
     Plane(Plane),
+    Cube(Cube),
+    Cylinder(Cylinder),
+    Cone(Cone),
+    Group(Group),
+    Torus(Torus),
+    Disk(Disk),
+    Triangle(Triangle),
+    Mesh(Mesh),
+    #[cfg(test)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    TestShape(TestShape),
 }
 
 impl ShapeFuncs for Shape {
-    fn intersect(&self, ray: Ray) -> Intersections {
-        match self {
-            Self::Sphere(s) => s.intersect(ray),
-            Self::Plane(p) => p.intersect(ray),
-        }
-    }
-
-    fn normal_at(&self, object_point: Tuple) -> Tuple {
+    fn local_intersect(&self, object_space_ray: Ray) -> Intersections {
         match self {
-            Self::Sphere(s) => s.normal_at(object_point),
-            Self::Plane(p) => p.normal_at(object_point),
+            Self::Sphere(s) => s.local_intersect(object_space_ray),
+            Self::Plane(p) => p.local_intersect(object_space_ray),
+            Self::Cube(c) => c.local_intersect(object_space_ray),
+            Self::Cylinder(c) => c.local_intersect(object_space_ray),
+            Self::Cone(c) => c.local_intersect(object_space_ray),
+            Self::Group(g) => g.local_intersect(object_space_ray),
+            Self::Torus(t) => t.local_intersect(object_space_ray),
+            Self::Disk(d) => d.local_intersect(object_space_ray),
+            Self::Triangle(t) => t.local_intersect(object_space_ray),
+            Self::Mesh(m) => m.local_intersect(object_space_ray),
+            #[cfg(test)]
+            Self::TestShape(t) => t.local_intersect(object_space_ray),
         }
     }
 
-    fn world_point_to_object_point(&self, world_point: Tuple) -> Tuple {
+    fn local_normal_at(&self, object_point: Tuple) -> Tuple {
         match self {
-            Self::Sphere(s) => s.world_point_to_object_point(world_point),
-            Self::Plane(p) => p.world_point_to_object_point(world_point),
+            Self::Sphere(s) => s.local_normal_at(object_point),
+            Self::Plane(p) => p.local_normal_at(object_point),
+            Self::Cube(c) => c.local_normal_at(object_point),
+            Self::Cylinder(c) => c.local_normal_at(object_point),
+            Self::Cone(c) => c.local_normal_at(object_point),
+            Self::Group(g) => g.local_normal_at(object_point),
+            Self::Torus(t) => t.local_normal_at(object_point),
+            Self::Disk(d) => d.local_normal_at(object_point),
+            Self::Triangle(t) => t.local_normal_at(object_point),
+            Self::Mesh(m) => m.local_normal_at(object_point),
+            #[cfg(test)]
+            Self::TestShape(t) => t.local_normal_at(object_point),
         }
     }
 
     fn material(&self) -> Material {
         match self {
-            Self::Sphere(s) => s.material,
-            Self::Plane(p) => p.material,
+            Self::Sphere(s) => s.material.clone(),
+            Self::Plane(p) => p.material.clone(),
+            Self::Cube(c) => c.material.clone(),
+            Self::Cylinder(c) => c.material.clone(),
+            Self::Cone(c) => c.material.clone(),
+            Self::Group(g) => g.material(),
+            Self::Torus(t) => t.material.clone(),
+            Self::Disk(d) => d.material.clone(),
+            Self::Triangle(t) => t.material.clone(),
+            Self::Mesh(m) => m.material(),
+            #[cfg(test)]
+            Self::TestShape(t) => t.material.clone(),
         }
     }
 
@@ -52,8 +175,206 @@ impl ShapeFuncs for Shape {
         match self {
             Self::Sphere(s) => s.transform,
             Self::Plane(p) => p.transform,
+            Self::Cube(c) => c.transform,
+            Self::Cylinder(c) => c.transform,
+            Self::Cone(c) => c.transform,
+            Self::Group(g) => g.transform,
+            Self::Torus(t) => t.transform,
+            Self::Disk(d) => d.transform,
+            Self::Triangle(t) => t.transform,
+            Self::Mesh(m) => m.transform,
+            #[cfg(test)]
+            Self::TestShape(t) => t.transform,
         }
     }
+
+    fn bounds(&self) -> BoundingBox {
+        match self {
+            Self::Sphere(s) => s.bounds(),
+            Self::Plane(p) => p.bounds(),
+            Self::Cube(c) => c.bounds(),
+            Self::Cylinder(c) => c.bounds(),
+            Self::Cone(c) => c.bounds(),
+            Self::Group(g) => g.bounds(),
+            Self::Torus(t) => t.bounds(),
+            Self::Disk(d) => d.bounds(),
+            Self::Triangle(t) => t.bounds(),
+            Self::Mesh(m) => m.bounds(),
+            #[cfg(test)]
+            Self::TestShape(t) => t.bounds(),
+        }
+    }
+
+    fn casts_shadows(&self) -> bool {
+        match self {
+            Self::Sphere(s) => s.casts_shadows(),
+            Self::Plane(p) => p.casts_shadows(),
+            Self::Cube(c) => c.casts_shadows(),
+            Self::Cylinder(c) => c.casts_shadows(),
+            Self::Cone(c) => c.casts_shadows(),
+            Self::Group(g) => g.casts_shadows(),
+            Self::Torus(t) => t.casts_shadows(),
+            Self::Disk(d) => d.casts_shadows(),
+            Self::Triangle(t) => t.casts_shadows(),
+            Self::Mesh(m) => m.casts_shadows(),
+            #[cfg(test)]
+            Self::TestShape(t) => t.casts_shadows(),
+        }
+    }
+
+    fn transform_at_t1(&self) -> Option<Matrix<4>> {
+        match self {
+            Self::Sphere(s) => s.transform_at_t1(),
+            Self::Plane(p) => p.transform_at_t1(),
+            Self::Cube(c) => c.transform_at_t1(),
+            Self::Cylinder(c) => c.transform_at_t1(),
+            Self::Cone(c) => c.transform_at_t1(),
+            Self::Group(g) => g.transform_at_t1(),
+            Self::Torus(t) => t.transform_at_t1(),
+            Self::Disk(d) => d.transform_at_t1(),
+            Self::Triangle(t) => t.transform_at_t1(),
+            Self::Mesh(m) => m.transform_at_t1(),
+            #[cfg(test)]
+            Self::TestShape(t) => t.transform_at_t1(),
+        }
+    }
+
+    fn excluded_lights(&self) -> &[LightId] {
+        match self {
+            Self::Sphere(s) => s.excluded_lights(),
+            Self::Plane(p) => p.excluded_lights(),
+            Self::Cube(c) => c.excluded_lights(),
+            Self::Cylinder(c) => c.excluded_lights(),
+            Self::Cone(c) => c.excluded_lights(),
+            Self::Group(g) => g.excluded_lights(),
+            Self::Torus(t) => t.excluded_lights(),
+            Self::Disk(d) => d.excluded_lights(),
+            Self::Triangle(t) => t.excluded_lights(),
+            Self::Mesh(m) => m.excluded_lights(),
+            #[cfg(test)]
+            Self::TestShape(t) => t.excluded_lights(),
+        }
+    }
+
+    fn name(&self) -> Option<&str> {
+        match self {
+            Self::Sphere(s) => s.name(),
+            Self::Plane(p) => p.name(),
+            Self::Cube(c) => c.name(),
+            Self::Cylinder(c) => c.name(),
+            Self::Cone(c) => c.name(),
+            Self::Group(g) => g.name(),
+            Self::Torus(t) => t.name(),
+            Self::Disk(d) => d.name(),
+            Self::Triangle(t) => t.name(),
+            Self::Mesh(m) => m.name(),
+            #[cfg(test)]
+            Self::TestShape(t) => t.name(),
+        }
+    }
+}
+
+impl Shape {
+    pub fn set_transform(&mut self, transform: Matrix<4>) {
+        match self {
+            Self::Sphere(s) => s.transform = transform,
+            Self::Plane(p) => p.transform = transform,
+            Self::Cube(c) => c.transform = transform,
+            Self::Cylinder(c) => c.transform = transform,
+            Self::Cone(c) => c.transform = transform,
+            Self::Group(g) => g.transform = transform,
+            Self::Torus(t) => t.transform = transform,
+            Self::Disk(d) => d.transform = transform,
+            Self::Triangle(t) => t.transform = transform,
+            Self::Mesh(m) => m.transform = transform,
+            #[cfg(test)]
+            Self::TestShape(t) => t.transform = transform,
+        }
+    }
+
+    // The shape fixed where it is at the time, so its normals and patterns are resolved there
+    pub fn at_time(mut self, time: f64) -> Self {
+        let transform = self.transform_at(time);
+        let transform_at_t1 = match &mut self {
+            Self::Sphere(s) => &mut s.transform_at_t1,
+            Self::Plane(p) => &mut p.transform_at_t1,
+            Self::Cube(c) => &mut c.transform_at_t1,
+            Self::Cylinder(c) => &mut c.transform_at_t1,
+            Self::Cone(c) => &mut c.transform_at_t1,
+            Self::Group(g) => &mut g.transform_at_t1,
+            Self::Torus(t) => &mut t.transform_at_t1,
+            Self::Disk(d) => &mut d.transform_at_t1,
+            Self::Triangle(t) => &mut t.transform_at_t1,
+            Self::Mesh(m) => &mut m.transform_at_t1,
+            #[cfg(test)]
+            Self::TestShape(t) => &mut t.transform_at_t1,
+        };
+        if transform_at_t1.take().is_some() {
+            self.set_transform(transform);
+        }
+        self
+    }
+
+    pub fn exclude_lights(&mut self, lights: &[LightId]) {
+        let excluded = match self {
+            Self::Sphere(s) => &mut s.excluded_lights,
+            Self::Plane(p) => &mut p.excluded_lights,
+            Self::Cube(c) => &mut c.excluded_lights,
+            Self::Cylinder(c) => &mut c.excluded_lights,
+            Self::Cone(c) => &mut c.excluded_lights,
+            Self::Group(g) => &mut g.excluded_lights,
+            Self::Torus(t) => &mut t.excluded_lights,
+            Self::Disk(d) => &mut d.excluded_lights,
+            Self::Triangle(t) => &mut t.excluded_lights,
+            Self::Mesh(m) => &mut m.excluded_lights,
+            #[cfg(test)]
+            Self::TestShape(t) => &mut t.excluded_lights,
+        };
+        for light in lights {
+            if !excluded.contains(light) {
+                excluded.push(*light);
+            }
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        match self {
+            Self::Sphere(s) => s.id,
+            Self::Plane(p) => p.id,
+            Self::Cube(c) => c.id,
+            Self::Cylinder(c) => c.id,
+            Self::Cone(c) => c.id,
+            Self::Group(g) => g.id,
+            Self::Torus(t) => t.id,
+            Self::Disk(d) => d.id,
+            Self::Triangle(t) => t.id,
+            Self::Mesh(m) => m.id,
+            #[cfg(test)]
+            Self::TestShape(t) => t.id,
+        }
+    }
+
+    // Whether other is this shape or somewhere among its children
+    pub fn includes(&self, other: &Shape) -> bool {
+        match self {
+            Self::Group(g) => self.id() == other.id() || g.children().iter().any(|c| c.includes(other)),
+            _ => self.id() == other.id(),
+        }
+    }
+}
+
+// Shapes are compared by identity, two shapes with the same settings are still different objects.
+// Use fuzzy_eq to compare their values
+impl PartialEq for Shape {
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id()
+    }
+}
+
+impl PartialOrd for Shape {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.id().partial_cmp(&other.id())
+    }
 }
 
 impl FuzzyEq<Self> for Shape {
@@ -77,3 +398,58 @@ impl From<Plane> for Shape {
         Self::Plane(p)
     }
 }
+
+impl From<Cube> for Shape {
+    fn from(c: Cube) -> Self {
+        Self::Cube(c)
+    }
+}
+
+impl From<Cylinder> for Shape {
+    fn from(c: Cylinder) -> Self {
+        Self::Cylinder(c)
+    }
+}
+
+impl From<Cone> for Shape {
+    fn from(c: Cone) -> Self {
+        Self::Cone(c)
+    }
+}
+
+impl From<Group> for Shape {
+    fn from(g: Group) -> Self {
+        Self::Group(g)
+    }
+}
+
+impl From<Torus> for Shape {
+    fn from(t: Torus) -> Self {
+        Self::Torus(t)
+    }
+}
+
+impl From<Disk> for Shape {
+    fn from(d: Disk) -> Self {
+        Self::Disk(d)
+    }
+}
+
+impl From<Triangle> for Shape {
+    fn from(t: Triangle) -> Self {
+        Self::Triangle(t)
+    }
+}
+
+impl From<Mesh> for Shape {
+    fn from(m: Mesh) -> Self {
+        Self::Mesh(m)
+    }
+}
+
+#[cfg(test)]
+impl From<TestShape> for Shape {
+    fn from(t: TestShape) -> Self {
+        Self::TestShape(t)
+    }
+}
@@ -1,24 +1,103 @@
 use crate::{
+    bounding_box::BoundingBox,
     intersection::{Intersection, Intersections},
+    light::LightId,
     material::Material,
     matrix::Matrix,
     ray::Ray,
-    shape::{Shape, ShapeFuncs},
+    shape::{next_shape_id, validate_transforms, Shape, ShapeFuncs},
     tuple::Tuple,
     util::FuzzyEq,
 };
 
-#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Builder, Default)]
+#[derive(Debug, Clone, PartialEq, PartialOrd, Builder)]
+#[builder(build_fn(validate = "Self::validate"))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sphere {
+    #[cfg_attr(feature = "serde", serde(skip, default = "next_shape_id"))]
+    #[builder(setter(skip), default = "next_shape_id()")]
+    pub(crate) id: u64,
     #[builder(default)]
     pub transform: Matrix<4>,
+    // Moves the sphere from transform to this over the frame, which blurs it when the camera
+    // samples the frame over time
+    #[builder(default, setter(strip_option))]
+    pub transform_at_t1: Option<Matrix<4>>,
     #[builder(default)]
     pub material: Material,
+    #[builder(default = "true")]
+    pub cast_shadows: bool,
+    // Points the normal inward, for domes and rooms the camera sits inside of
+    #[builder(default = "false")]
+    pub invert_normal: bool,
+    // Lights that skip this sphere entirely, for staged renders
+    #[builder(default)]
+    pub excluded_lights: Vec<LightId>,
+    // How scenes and World::object_by_name refer to the sphere
+    #[builder(default, setter(into, strip_option))]
+    pub name: Option<String>,
+}
+
+impl Default for Sphere {
+    fn default() -> Self {
+        Self {
+            id: next_shape_id(),
+            transform: Matrix::identity(),
+            transform_at_t1: None,
+            material: Material::default(),
+            cast_shadows: true,
+            invert_normal: false,
+            excluded_lights: vec![],
+            name: None,
+        }
+    }
+}
+
+impl Sphere {
+    // The scaling is baked into the transform, so further placement composes on the left:
+    // `Matrix::translation(..) * ellipsoid.transform` keeps the radii intact
+    pub fn ellipsoid(a: f64, b: f64, c: f64) -> Self {
+        Self {
+            transform: Matrix::scaling(a, b, c),
+            ..Default::default()
+        }
+    }
+
+    // A solid glass ball, the usual test subject for refraction. Only the transparency and
+    // refractive index differ from the default material, see Material::glass for a nicer look
+    pub fn glass_sphere() -> Self {
+        Self {
+            material: Material::default().with_transparency(1.0).with_refractive_index(1.5),
+            ..Default::default()
+        }
+    }
+
+    // Length of each transformed axis, which is the scale as long as any shearing or
+    // non-uniform scaling happened before the rotation
+    pub fn radii(&self) -> (f64, f64, f64) {
+        let axis = |column: usize| {
+            (0..3)
+                .map(|row| self.transform[row][column].powi(2))
+                .sum::<f64>()
+                .sqrt()
+        };
+
+        (axis(0), axis(1), axis(2))
+    }
+}
+
+impl SphereBuilder {
+    pub fn ellipsoid(&mut self, a: f64, b: f64, c: f64) -> &mut Self {
+        self.transform(Matrix::scaling(a, b, c))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        validate_transforms(self.transform, self.transform_at_t1.flatten())
+    }
 }
 
 impl ShapeFuncs for Sphere {
-    fn intersect(&self, ray: Ray) -> Intersections {
-        let object_space_ray = ray.transform(self.transform.inverse());
+    fn local_intersect(&self, object_space_ray: Ray) -> Intersections {
         let sphere_to_ray = object_space_ray.origin - Tuple::point(0.0, 0.0, 0.0);
 
         let a = object_space_ray.direction.dot(object_space_ray.direction);
@@ -30,32 +109,50 @@ impl ShapeFuncs for Sphere {
             return Intersections::new(vec![]);
         }
 
-        let t1 = Intersection::new((-b - discriminant.sqrt()) / (2.0 * a), Shape::from(*self));
-        let t2 = Intersection::new((-b + discriminant.sqrt()) / (2.0 * a), Shape::from(*self));
+        let object = Shape::from(self.clone()).at_time(object_space_ray.time);
+        let t1 = Intersection::new((-b - discriminant.sqrt()) / (2.0 * a), object.clone());
+        let t2 = Intersection::new((-b + discriminant.sqrt()) / (2.0 * a), object);
 
         Intersections::new(vec![t1, t2])
     }
 
-    fn normal_at(&self, world_point: Tuple) -> Tuple {
-        let object_point = self.world_point_to_object_point(world_point);
+    fn local_normal_at(&self, object_point: Tuple) -> Tuple {
         let object_normal = object_point - Tuple::point(0.0, 0.0, 0.0);
-        let mut world_normal = self.transform.inverse().tranpose() * object_normal;
 
-        world_normal.w = 0.0;
-        world_normal.normalize()
+        if self.invert_normal {
+            -object_normal
+        } else {
+            object_normal
+        }
     }
 
-    fn world_point_to_object_point(&self, world_point: Tuple) -> Tuple {
-        self.transform.inverse() * world_point
+    fn material(&self) -> Material {
+        self.material.clone()
     }
 
-    fn material(&self) -> Material {
-        self.material
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
     }
 
     fn transform(&self) -> Matrix<4> {
         self.transform
     }
+
+    fn transform_at_t1(&self) -> Option<Matrix<4>> {
+        self.transform_at_t1
+    }
+
+    fn casts_shadows(&self) -> bool {
+        self.cast_shadows
+    }
+
+    fn excluded_lights(&self) -> &[LightId] {
+        &self.excluded_lights
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Tuple::point(-1.0, -1.0, -1.0), Tuple::point(1.0, 1.0, 1.0))
+    }
 }
 
 impl FuzzyEq<Self> for Sphere {
@@ -76,7 +173,9 @@ impl FuzzyEq<Self> for Sphere {
 mod tests {
     use std::f64::consts::PI;
 
-    use crate::{assert_fuzzy_eq, color::Color};
+    use crate::{
+        assert_fuzzy_eq, camera::Camera, color::Color, light::Light, util::FuzzyEq, world::World,
+    };
 
     use super::*;
 
@@ -234,7 +333,227 @@ mod tests {
     #[test]
     fn sphere_may_be_assigned_material() {
         let m = Material::new(Color::black(), 1.0, 2.0, 3.0, 4.0);
-        let s = SphereBuilder::default().material(m).build().unwrap();
+        let s = SphereBuilder::default().material(m.clone()).build().unwrap();
         assert_fuzzy_eq!(m, s.material);
     }
+
+    #[test]
+    fn sphere_has_bounding_box() {
+        let b = Sphere::default().bounds();
+
+        assert_fuzzy_eq!(Tuple::point(-1.0, -1.0, -1.0), b.min);
+        assert_fuzzy_eq!(Tuple::point(1.0, 1.0, 1.0), b.max);
+    }
+
+    #[test]
+    fn two_default_spheres_are_not_the_same_object() {
+        let s1: Shape = Sphere::default().into();
+        let s2: Shape = Sphere::default().into();
+
+        assert_ne!(s1.id(), s2.id());
+        assert_ne!(s1, s2);
+        assert_fuzzy_eq!(s1, s2.clone());
+        assert_eq!(s1, s1.clone());
+    }
+
+    #[test]
+    fn intersection_refers_to_the_intersected_sphere() {
+        let s = SphereBuilder::default().build().unwrap();
+        let other = SphereBuilder::default().build().unwrap();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        let xs = s.intersect(r);
+        assert_eq!(Shape::from(s), xs.intersections[0].object);
+        assert_ne!(Shape::from(other), xs.intersections[0].object);
+    }
+
+    #[test]
+    fn intersecting_ellipsoid_matches_analytic_hits() {
+        let e = Sphere::ellipsoid(2.0, 1.0, 1.0);
+        let cases = vec![
+            (
+                Tuple::point(-5.0, 0.0, 0.0),
+                Tuple::vector(1.0, 0.0, 0.0),
+                3.0,
+                7.0,
+            ),
+            (
+                Tuple::point(0.0, 0.0, -5.0),
+                Tuple::vector(0.0, 0.0, 1.0),
+                4.0,
+                6.0,
+            ),
+            // x^2 / 4 + 0.5^2 = 1 gives x = +/- sqrt(3)
+            (
+                Tuple::point(-5.0, 0.5, 0.0),
+                Tuple::vector(1.0, 0.0, 0.0),
+                5.0 - 3.0_f64.sqrt(),
+                5.0 + 3.0_f64.sqrt(),
+            ),
+        ];
+
+        for (origin, direction, t1, t2) in cases {
+            let xs = e.intersect(Ray::new(origin, direction));
+            assert_eq!(2, xs.intersections.len());
+            assert_fuzzy_eq!(t1, xs.intersections[0].t);
+            assert_fuzzy_eq!(t2, xs.intersections[1].t);
+        }
+    }
+
+    #[test]
+    fn normal_on_ellipsoid() {
+        let e = SphereBuilder::default()
+            .ellipsoid(2.0, 1.0, 1.0)
+            .build()
+            .unwrap();
+
+        assert_fuzzy_eq!(
+            Tuple::vector(1.0, 0.0, 0.0),
+            e.normal_at(Tuple::point(2.0, 0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn radii_survive_further_transforms() {
+        let mut e = Sphere::ellipsoid(2.0, 3.0, 4.0);
+        assert_eq!((2.0, 3.0, 4.0), e.radii());
+
+        e.transform =
+            Matrix::translation(1.0, -2.0, 5.0) * Matrix::rotation_y(PI / 3.0) * e.transform;
+        let (a, b, c) = e.radii();
+        assert_fuzzy_eq!(2.0, a);
+        assert_fuzzy_eq!(3.0, b);
+        assert_fuzzy_eq!(4.0, c);
+    }
+
+    fn dome() -> Sphere {
+        let material = Material {
+            ambient: Color::grey(0.5),
+            ..Default::default()
+        };
+
+        SphereBuilder::default()
+            .transform(Matrix::scaling(10.0, 10.0, 10.0))
+            .material(material)
+            .invert_normal(true)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn inverted_sphere_normal_points_inward() {
+        let s = dome();
+
+        assert_fuzzy_eq!(
+            Tuple::vector(0.0, 0.0, -1.0),
+            s.normal_at(Tuple::point(0.0, 0.0, 10.0))
+        );
+    }
+
+    #[test]
+    fn hit_inside_inverted_sphere_is_not_flagged_as_inside() {
+        let s: Shape = dome().into();
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        let xs = s.intersect(r);
+        let comps = xs.hit().unwrap().as_computed(r, &xs);
+
+        assert!(!comps.inside);
+        assert_fuzzy_eq!(Tuple::vector(0.0, 0.0, -1.0), comps.normalv);
+        // Nudged towards the camera, i.e. into the dome
+        assert!(comps.over_point.z < 10.0);
+    }
+
+    #[test]
+    fn camera_inside_inverted_sphere_sees_lit_surface() {
+        let light = Light::point(Tuple::point(0.0, 5.0, 0.0), Color::white());
+        let w = World::new(vec![dome().into()], light);
+        let mut camera = Camera::new(5, 5, PI / 2.0);
+        camera.set_transform(Matrix::view_transform(
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::point(0.0, 0.0, 1.0),
+            Tuple::vector(0.0, 1.0, 0.0),
+        )).unwrap();
+
+        let image = camera.render(&w);
+        let center = image.pixel_at(2, 2);
+        let ambient_only = Color::new(0.5, 0.5, 0.5);
+        assert!(center.fuzzy_ne(Color::black()));
+        assert!(center.fuzzy_ne(ambient_only));
+    }
+
+    #[test]
+    fn shadows_inside_inverted_sphere() {
+        let light = Light::point(Tuple::point(0.0, 5.0, 0.0), Color::white());
+        let blocker = SphereBuilder::default()
+            .transform(Matrix::translation(0.0, 0.0, 5.0))
+            .build()
+            .unwrap();
+        let w = World::new(vec![dome().into(), blocker.into()], light);
+
+        assert_fuzzy_eq!(1.0, light.intensity_at(Tuple::point(0.0, 0.0, -9.9), 0.0, &w));
+        // Straight behind the blocker as seen from the light
+        assert_fuzzy_eq!(0.0, light.intensity_at(Tuple::point(0.0, -3.0, 8.0), 0.0, &w));
+    }
+
+    #[test]
+    fn glass_sphere_helper() {
+        let s = Sphere::glass_sphere();
+
+        assert_fuzzy_eq!(Matrix::identity(), s.transform);
+        assert_fuzzy_eq!(1.0, s.material.transparency());
+        assert_fuzzy_eq!(1.5, s.material.refractive_index());
+    }
+
+    fn moving_sphere() -> Sphere {
+        SphereBuilder::default()
+            .transform_at_t1(Matrix::translation(4.0, 0.0, 0.0))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn moving_sphere_is_hit_where_the_ray_time_puts_it() {
+        let s = moving_sphere();
+        let at_start = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let at_end = Ray::new(Tuple::point(4.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        assert_eq!(2, s.intersect(at_start).intersections.len());
+        assert!(s.intersect(at_start.at_time(1.0)).intersections.is_empty());
+        assert!(s.intersect(at_end).intersections.is_empty());
+        assert_eq!(2, s.intersect(at_end.at_time(1.0)).intersections.len());
+    }
+
+    #[test]
+    fn moving_sphere_is_halfway_at_half_time() {
+        let s = moving_sphere();
+        let r = Ray::new(Tuple::point(2.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0)).at_time(0.5);
+
+        let xs = s.intersect(r);
+        assert_eq!(2, xs.intersections.len());
+        assert_fuzzy_eq!(4.0, xs.intersections[0].t);
+        assert_fuzzy_eq!(6.0, xs.intersections[1].t);
+
+        // The hit object stays put where it was caught
+        let n = xs.intersections[0].object.normal_at(Tuple::point(3.0, 0.0, 0.0));
+        assert_fuzzy_eq!(Tuple::vector(1.0, 0.0, 0.0), n);
+    }
+
+    #[test]
+    fn moving_sphere_is_bounded_over_its_whole_path() {
+        let b = moving_sphere().parent_space_bounds();
+
+        assert_fuzzy_eq!(Tuple::point(-1.0, -1.0, -1.0), b.min);
+        assert_fuzzy_eq!(Tuple::point(5.0, 1.0, 1.0), b.max);
+    }
+
+    #[test]
+    fn sphere_with_a_flat_transform_is_rejected() {
+        let flat = Matrix::scaling(1.0, 0.0, 1.0);
+
+        let message = SphereBuilder::default().transform(flat).build().unwrap_err().to_string();
+        assert!(message.contains(&format!("{:?}", flat)), "{}", message);
+        assert!(SphereBuilder::default().transform_at_t1(flat).build().is_err());
+        assert!(SphereBuilder::default().ellipsoid(1.0, 2.0, 3.0).build().is_ok());
+    }
 }
@@ -0,0 +1,324 @@
+use std::{
+    fmt,
+    io::{self, Read},
+};
+
+use crate::{group::Group, shape::Shape, triangle::Triangle, tuple::Tuple};
+
+const HEADER_LEN: usize = 80;
+const RECORD_LEN: usize = 50;
+
+#[derive(Debug)]
+pub enum StlError {
+    Io(io::Error),
+    Truncated { expected: usize, actual: usize },
+    InvalidFacet { line: usize },
+    MissingEndSolid,
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read STL data: {}", e),
+            Self::Truncated { expected, actual } => write!(
+                f,
+                "truncated binary STL: expected {} bytes, got {}",
+                expected, actual
+            ),
+            Self::InvalidFacet { line } => write!(f, "invalid facet on line {}", line),
+            Self::MissingEndSolid => write!(f, "ASCII STL ended without endsolid"),
+        }
+    }
+}
+
+impl std::error::Error for StlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for StlError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+pub fn parse_stl(mut reader: impl Read) -> Result<Shape, StlError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    // Binary files are allowed to start their header with "solid" too, so a size that matches
+    // the binary layout exactly, or any NUL byte, wins over the ASCII keyword
+    let is_ascii =
+        bytes.starts_with(b"solid") && !binary_size_matches(&bytes) && !bytes.contains(&0);
+    let triangles = if is_ascii {
+        parse_ascii(&String::from_utf8_lossy(&bytes))?
+    } else {
+        parse_binary(&bytes)?
+    };
+
+    let mut group = Group::default();
+    for triangle in triangles {
+        group.add_child(triangle);
+    }
+
+    Ok(group.into())
+}
+
+fn binary_size_matches(bytes: &[u8]) -> bool {
+    bytes.len() >= HEADER_LEN + 4
+        && HEADER_LEN + 4 + triangle_count(bytes) * RECORD_LEN == bytes.len()
+}
+
+fn triangle_count(bytes: &[u8]) -> usize {
+    u32::from_le_bytes(bytes[HEADER_LEN..HEADER_LEN + 4].try_into().unwrap()) as usize
+}
+
+fn parse_binary(bytes: &[u8]) -> Result<Vec<Triangle>, StlError> {
+    if bytes.len() < HEADER_LEN + 4 {
+        return Err(StlError::Truncated {
+            expected: HEADER_LEN + 4,
+            actual: bytes.len(),
+        });
+    }
+
+    let count = triangle_count(bytes);
+    let expected = HEADER_LEN + 4 + count * RECORD_LEN;
+    if bytes.len() < expected {
+        return Err(StlError::Truncated {
+            expected,
+            actual: bytes.len(),
+        });
+    }
+
+    let triangles = bytes[HEADER_LEN + 4..expected]
+        .chunks_exact(RECORD_LEN)
+        .map(|record| {
+            // Twelve little-endian f32s (normal and three vertices) followed by a u16 attribute
+            let value =
+                |i: usize| f32::from_le_bytes(record[i * 4..i * 4 + 4].try_into().unwrap()) as f64;
+            let vector = |i: usize| Tuple::vector(value(i), value(i + 1), value(i + 2));
+            let point = |i: usize| Tuple::point(value(i), value(i + 1), value(i + 2));
+
+            oriented_triangle(vector(0), point(3), point(6), point(9))
+        })
+        .collect();
+
+    Ok(triangles)
+}
+
+fn parse_ascii(text: &str) -> Result<Vec<Triangle>, StlError> {
+    let mut triangles = Vec::new();
+    let mut normal = Tuple::vector(0.0, 0.0, 0.0);
+    let mut vertices = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let mut tokens = line.split_whitespace();
+
+        match tokens.next() {
+            Some("facet") => {
+                let values = parse_values(tokens.skip(1), line_number)?;
+                normal = Tuple::vector(values[0], values[1], values[2]);
+                vertices.clear();
+            }
+            Some("vertex") => {
+                let values = parse_values(tokens, line_number)?;
+                vertices.push(Tuple::point(values[0], values[1], values[2]));
+            }
+            Some("endfacet") => {
+                if vertices.len() != 3 {
+                    return Err(StlError::InvalidFacet { line: line_number });
+                }
+                triangles.push(oriented_triangle(
+                    normal,
+                    vertices[0],
+                    vertices[1],
+                    vertices[2],
+                ));
+            }
+            Some("endsolid") => return Ok(triangles),
+            _ => {}
+        }
+    }
+
+    Err(StlError::MissingEndSolid)
+}
+
+fn parse_values<'a>(
+    tokens: impl Iterator<Item = &'a str>,
+    line: usize,
+) -> Result<Vec<f64>, StlError> {
+    let values = tokens
+        .map(|t| t.parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| StlError::InvalidFacet { line })?;
+
+    if values.len() < 3 {
+        return Err(StlError::InvalidFacet { line });
+    }
+
+    Ok(values)
+}
+
+// CAD tools often write a zero normal, in which case the vertex order is kept as is
+fn oriented_triangle(normal: Tuple, p1: Tuple, p2: Tuple, p3: Tuple) -> Triangle {
+    let triangle = Triangle::new(p1, p2, p3);
+
+    if triangle.normal.dot(normal) < 0.0 {
+        Triangle::new(p1, p3, p2)
+    } else {
+        triangle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_fuzzy_eq, util::FuzzyEq};
+
+    use super::*;
+
+    fn children(shape: Shape) -> Vec<Triangle> {
+        match shape {
+            Shape::Group(g) => g
+                .into_children()
+                .into_iter()
+                .map(|c| match c {
+                    Shape::Triangle(t) => t,
+                    s => panic!("expected a triangle, got {:?}", s),
+                })
+                .collect(),
+            s => panic!("expected a group, got {:?}", s),
+        }
+    }
+
+    fn binary_stl(records: &[[f32; 12]]) -> Vec<u8> {
+        let mut bytes = b"solid but actually binary".to_vec();
+        bytes.resize(HEADER_LEN, 0);
+        bytes.extend((records.len() as u32).to_le_bytes());
+        for record in records {
+            for value in record {
+                bytes.extend(value.to_le_bytes());
+            }
+            bytes.extend(0u16.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn parsing_binary_single_triangle() {
+        let bytes = binary_stl(&[[0.0, 0.0, -1.0, 0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 1.0, 0.0, 0.0]]);
+
+        let triangles = children(parse_stl(bytes.as_slice()).unwrap());
+        assert_eq!(1, triangles.len());
+        assert_fuzzy_eq!(Tuple::point(0.0, 1.0, 0.0), triangles[0].p1);
+        assert_fuzzy_eq!(Tuple::point(-1.0, 0.0, 0.0), triangles[0].p2);
+        assert_fuzzy_eq!(Tuple::point(1.0, 0.0, 0.0), triangles[0].p3);
+        assert_fuzzy_eq!(Tuple::vector(0.0, 0.0, -1.0), triangles[0].normal);
+    }
+
+    #[test]
+    fn stored_normal_orients_triangle() {
+        let bytes = binary_stl(&[[0.0, 0.0, 1.0, 0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 1.0, 0.0, 0.0]]);
+
+        let triangles = children(parse_stl(bytes.as_slice()).unwrap());
+        assert_fuzzy_eq!(Tuple::vector(0.0, 0.0, 1.0), triangles[0].normal);
+    }
+
+    #[test]
+    fn truncated_binary_stl_is_an_error() {
+        let mut bytes = binary_stl(&[[0.0; 12], [0.0; 12]]);
+        bytes.truncate(bytes.len() - 10);
+
+        let result = parse_stl(&bytes[..]);
+        assert!(matches!(
+            result,
+            Err(StlError::Truncated {
+                expected: 184,
+                actual: 174
+            })
+        ));
+
+        let result = parse_stl(&bytes[..40]);
+        assert!(matches!(result, Err(StlError::Truncated { .. })));
+    }
+
+    fn ascii_cube() -> String {
+        let corners = |x: f64, y: f64, z: f64| format!("{} {} {}", x, y, z);
+        let quads = [
+            (
+                "0 0 -1",
+                [(0., 0., 0.), (0., 1., 0.), (1., 1., 0.), (1., 0., 0.)],
+            ),
+            (
+                "0 0 1",
+                [(0., 0., 1.), (1., 0., 1.), (1., 1., 1.), (0., 1., 1.)],
+            ),
+            (
+                "-1 0 0",
+                [(0., 0., 0.), (0., 0., 1.), (0., 1., 1.), (0., 1., 0.)],
+            ),
+            (
+                "1 0 0",
+                [(1., 0., 0.), (1., 1., 0.), (1., 1., 1.), (1., 0., 1.)],
+            ),
+            (
+                "0 -1 0",
+                [(0., 0., 0.), (1., 0., 0.), (1., 0., 1.), (0., 0., 1.)],
+            ),
+            (
+                "0 1 0",
+                [(0., 1., 0.), (0., 1., 1.), (1., 1., 1.), (1., 1., 0.)],
+            ),
+        ];
+
+        let mut text = String::from("solid cube\n");
+        for (normal, q) in quads {
+            for tri in [[q[0], q[1], q[2]], [q[0], q[2], q[3]]] {
+                text.push_str(&format!("  facet normal {}\n    outer loop\n", normal));
+                for (x, y, z) in tri {
+                    text.push_str(&format!("      vertex {}\n", corners(x, y, z)));
+                }
+                text.push_str("    endloop\n  endfacet\n");
+            }
+        }
+        text.push_str("endsolid cube\n");
+        text
+    }
+
+    #[test]
+    fn parsing_ascii_cube() {
+        let triangles = children(parse_stl(ascii_cube().as_bytes()).unwrap());
+
+        assert_eq!(12, triangles.len());
+        assert_fuzzy_eq!(Tuple::vector(0.0, 0.0, -1.0), triangles[0].normal);
+        assert_fuzzy_eq!(Tuple::vector(0.0, 1.0, 0.0), triangles[11].normal);
+    }
+
+    #[test]
+    fn truncated_ascii_stl_is_an_error() {
+        let cube = ascii_cube();
+        let truncated = &cube[..cube.len() / 2];
+
+        let result = parse_stl(truncated.as_bytes());
+        assert!(matches!(result, Err(StlError::MissingEndSolid)));
+    }
+
+    #[test]
+    fn facet_with_missing_vertex_is_an_error() {
+        let text = "solid broken\n\
+                    facet normal 0 0 1\n\
+                    outer loop\n\
+                    vertex 0 0 0\n\
+                    vertex 1 0 0\n\
+                    endloop\n\
+                    endfacet\n\
+                    endsolid broken\n";
+
+        let result = parse_stl(text.as_bytes());
+        assert!(matches!(result, Err(StlError::InvalidFacet { line: 7 })));
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    cmp::Ordering,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    bounding_box::BoundingBox,
+    intersection::Intersections,
+    light::LightId,
+    material::Material,
+    matrix::Matrix,
+    ray::Ray,
+    shape::{next_shape_id, ShapeFuncs},
+    tuple::Tuple,
+    util::FuzzyEq,
+};
+
+// A shape for tests that only records the object space ray it was intersected with. The record
+// is shared between clones, so it can still be read after the shape is moved into a group
+#[derive(Debug, Clone)]
+pub struct TestShape {
+    pub(crate) id: u64,
+    pub transform: Matrix<4>,
+    pub transform_at_t1: Option<Matrix<4>>,
+    pub material: Material,
+    pub excluded_lights: Vec<LightId>,
+    pub saved_ray: Arc<Mutex<Option<Ray>>>,
+}
+
+impl Default for TestShape {
+    fn default() -> Self {
+        Self {
+            id: next_shape_id(),
+            transform: Matrix::identity(),
+            transform_at_t1: None,
+            material: Material::default(),
+            excluded_lights: vec![],
+            saved_ray: Arc::default(),
+        }
+    }
+}
+
+impl TestShape {
+    pub fn saved_ray(&self) -> Option<Ray> {
+        *self.saved_ray.lock().unwrap()
+    }
+}
+
+impl ShapeFuncs for TestShape {
+    fn local_intersect(&self, object_space_ray: Ray) -> Intersections {
+        *self.saved_ray.lock().unwrap() = Some(object_space_ray);
+
+        Intersections::new(vec![])
+    }
+
+    fn local_normal_at(&self, object_point: Tuple) -> Tuple {
+        Tuple::vector(object_point.x, object_point.y, object_point.z)
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn transform_at_t1(&self) -> Option<Matrix<4>> {
+        self.transform_at_t1
+    }
+
+    fn excluded_lights(&self) -> &[LightId] {
+        &self.excluded_lights
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Tuple::point(-1.0, -1.0, -1.0), Tuple::point(1.0, 1.0, 1.0))
+    }
+}
+
+impl PartialEq for TestShape {
+    fn eq(&self, other: &Self) -> bool {
+        self.transform == other.transform && self.material == other.material
+    }
+}
+
+impl PartialOrd for TestShape {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.transform.partial_cmp(&other.transform) {
+            Some(Ordering::Equal) => self.material.partial_cmp(&other.material),
+            ordering => ordering,
+        }
+    }
+}
+
+impl FuzzyEq<Self> for TestShape {
+    fn fuzzy_eq(&self, other: Self) -> bool {
+        self.transform.fuzzy_eq(other.transform) && self.material.fuzzy_eq(other.material)
+    }
+
+    fn fuzzy_ne(&self, other: Self) -> bool {
+        !self.fuzzy_eq(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{assert_fuzzy_eq, shape::Shape};
+
+    use super::*;
+
+    #[test]
+    fn intersecting_scaled_shape_with_ray() {
+        let s = TestShape {
+            transform: Matrix::scaling(2.0, 2.0, 2.0),
+            ..Default::default()
+        };
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        s.intersect(r);
+        let saved_ray = s.saved_ray().unwrap();
+        assert_fuzzy_eq!(Tuple::point(0.0, 0.0, -2.5), saved_ray.origin);
+        assert_fuzzy_eq!(Tuple::vector(0.0, 0.0, 0.5), saved_ray.direction);
+    }
+
+    #[test]
+    fn intersecting_translated_shape_with_ray() {
+        let s = TestShape {
+            transform: Matrix::translation(5.0, 0.0, 0.0),
+            ..Default::default()
+        };
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        s.intersect(r);
+        let saved_ray = s.saved_ray().unwrap();
+        assert_fuzzy_eq!(Tuple::point(-5.0, 0.0, -5.0), saved_ray.origin);
+        assert_fuzzy_eq!(Tuple::vector(0.0, 0.0, 1.0), saved_ray.direction);
+    }
+
+    #[test]
+    fn intersecting_through_shape_enum_uses_same_helper() {
+        let s = TestShape {
+            transform: Matrix::scaling(2.0, 2.0, 2.0),
+            ..Default::default()
+        };
+        let shape: Shape = s.clone().into();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        shape.intersect(r);
+        assert_fuzzy_eq!(Tuple::point(0.0, 0.0, -2.5), s.saved_ray().unwrap().origin);
+    }
+
+    #[test]
+    fn computing_normal_on_translated_shape() {
+        let s = TestShape {
+            transform: Matrix::translation(0.0, 1.0, 0.0),
+            ..Default::default()
+        };
+
+        let frac = std::f64::consts::FRAC_1_SQRT_2;
+        let n = s.normal_at(Tuple::point(0.0, 1.0 + frac, -frac));
+        assert_fuzzy_eq!(Tuple::vector(0.0, frac, -frac), n);
+    }
+
+    #[test]
+    fn computing_normal_on_transformed_shape() {
+        let s = TestShape {
+            transform: Matrix::scaling(1.0, 0.5, 1.0)
+                * Matrix::rotation_z(std::f64::consts::PI / 5.0),
+            ..Default::default()
+        };
+
+        let sqrt_2_2 = 2.0_f64.sqrt() / 2.0;
+        let n = s.normal_at(Tuple::point(0.0, sqrt_2_2, -sqrt_2_2));
+        assert_fuzzy_eq!(Tuple::vector(0.0, 0.97014, -0.24254), n);
+    }
+}
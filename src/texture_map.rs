@@ -0,0 +1,601 @@
+use std::f64::consts::PI;
+
+use crate::{color::Color, matrix::Matrix, pattern::PatternFuncs, tuple::Tuple};
+
+// A pattern drawn on the unit square, with u running across and v up
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(tag = "type"))]
+pub enum UvPattern {
+    Checkers {
+        width: f64,
+        height: f64,
+        color_a: Color,
+        color_b: Color,
+    },
+    // A main color with a different one in each corner, for checking which way up a face is
+    AlignCheck {
+        main: Color,
+        upper_left: Color,
+        upper_right: Color,
+        bottom_left: Color,
+        bottom_right: Color,
+    },
+}
+
+impl UvPattern {
+    // Width and height are the number of squares across and up
+    pub fn checkers(width: f64, height: f64, color_a: Color, color_b: Color) -> Self {
+        Self::Checkers {
+            width,
+            height,
+            color_a,
+            color_b,
+        }
+    }
+
+    pub fn color_at(&self, u: f64, v: f64) -> Color {
+        match *self {
+            Self::Checkers {
+                width,
+                height,
+                color_a,
+                color_b,
+            } => {
+                if ((u * width).floor() + (v * height).floor()) as i64 % 2 == 0 {
+                    color_a
+                } else {
+                    color_b
+                }
+            }
+            Self::AlignCheck {
+                main,
+                upper_left,
+                upper_right,
+                bottom_left,
+                bottom_right,
+            } => match (u, v) {
+                (u, v) if u < 0.2 && v > 0.8 => upper_left,
+                (u, v) if u > 0.8 && v > 0.8 => upper_right,
+                (u, v) if u < 0.2 && v < 0.2 => bottom_left,
+                (u, v) if u > 0.8 && v < 0.2 => bottom_right,
+                _ => main,
+            },
+        }
+    }
+}
+
+// How a point in pattern space is flattened onto the unit square. There is no default, the right
+// one depends on the shape the pattern is wrapped around
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UvMapping {
+    Spherical,
+    Planar,
+    Cylindrical,
+}
+
+impl UvMapping {
+    pub fn map(&self, point: Tuple) -> (f64, f64) {
+        match self {
+            Self::Spherical => spherical_map(point),
+            Self::Planar => planar_map(point),
+            Self::Cylindrical => cylindrical_map(point),
+        }
+    }
+}
+
+// The angle around the y axis as a fraction of a turn, going from 0 at -z towards +x
+fn azimuth(point: Tuple) -> f64 {
+    let raw_u = point.x.atan2(point.z) / (2.0 * PI);
+    1.0 - (raw_u + 0.5)
+}
+
+// Latitude and longitude on a sphere around the origin, with v = 0 at the south pole
+pub fn spherical_map(point: Tuple) -> (f64, f64) {
+    let radius = Tuple::vector(point.x, point.y, point.z).magnitude();
+    let phi = (point.y / radius).acos();
+
+    (azimuth(point), 1.0 - phi / PI)
+}
+
+// Repeats every unit across the xz plane
+pub fn planar_map(point: Tuple) -> (f64, f64) {
+    (point.x.rem_euclid(1.0), point.z.rem_euclid(1.0))
+}
+
+// Wraps once around the y axis and repeats every unit along it
+pub fn cylindrical_map(point: Tuple) -> (f64, f64) {
+    (azimuth(point), point.y.rem_euclid(1.0))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum CubeFace {
+    Left,
+    Right,
+    Front,
+    Back,
+    Up,
+    Down,
+}
+
+// The face of the unit cube around the origin a point is on, going by its largest coordinate
+pub fn face_from_point(point: Tuple) -> CubeFace {
+    let coord = point.x.abs().max(point.y.abs()).max(point.z.abs());
+
+    if coord == point.x {
+        CubeFace::Right
+    } else if coord == -point.x {
+        CubeFace::Left
+    } else if coord == point.y {
+        CubeFace::Up
+    } else if coord == -point.y {
+        CubeFace::Down
+    } else if coord == point.z {
+        CubeFace::Front
+    } else {
+        CubeFace::Back
+    }
+}
+
+// Each face is unfolded so that u runs left to right and v bottom to top as seen from outside
+pub fn cube_uv_front(point: Tuple) -> (f64, f64) {
+    (
+        (point.x + 1.0).rem_euclid(2.0) / 2.0,
+        (point.y + 1.0).rem_euclid(2.0) / 2.0,
+    )
+}
+
+pub fn cube_uv_back(point: Tuple) -> (f64, f64) {
+    (
+        (1.0 - point.x).rem_euclid(2.0) / 2.0,
+        (point.y + 1.0).rem_euclid(2.0) / 2.0,
+    )
+}
+
+pub fn cube_uv_left(point: Tuple) -> (f64, f64) {
+    (
+        (point.z + 1.0).rem_euclid(2.0) / 2.0,
+        (point.y + 1.0).rem_euclid(2.0) / 2.0,
+    )
+}
+
+pub fn cube_uv_right(point: Tuple) -> (f64, f64) {
+    (
+        (1.0 - point.z).rem_euclid(2.0) / 2.0,
+        (point.y + 1.0).rem_euclid(2.0) / 2.0,
+    )
+}
+
+pub fn cube_uv_up(point: Tuple) -> (f64, f64) {
+    (
+        (point.x + 1.0).rem_euclid(2.0) / 2.0,
+        (1.0 - point.z).rem_euclid(2.0) / 2.0,
+    )
+}
+
+pub fn cube_uv_down(point: Tuple) -> (f64, f64) {
+    (
+        (point.x + 1.0).rem_euclid(2.0) / 2.0,
+        (point.z + 1.0).rem_euclid(2.0) / 2.0,
+    )
+}
+
+// Wraps a cube in six UV patterns, one per face, for skyboxes and dice
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UvCubeMap {
+    pub transform: Matrix<4>,
+    pub left: UvPattern,
+    pub right: UvPattern,
+    pub front: UvPattern,
+    pub back: UvPattern,
+    pub up: UvPattern,
+    pub down: UvPattern,
+}
+
+impl UvCubeMap {
+    pub fn new(
+        left: UvPattern,
+        right: UvPattern,
+        front: UvPattern,
+        back: UvPattern,
+        up: UvPattern,
+        down: UvPattern,
+    ) -> Self {
+        Self {
+            transform: Matrix::identity(),
+            left,
+            right,
+            front,
+            back,
+            up,
+            down,
+        }
+    }
+}
+
+impl PatternFuncs for UvCubeMap {
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn color_at(&self, point: Tuple) -> Color {
+        let (face, (u, v)) = match face_from_point(point) {
+            CubeFace::Left => (self.left, cube_uv_left(point)),
+            CubeFace::Right => (self.right, cube_uv_right(point)),
+            CubeFace::Front => (self.front, cube_uv_front(point)),
+            CubeFace::Back => (self.back, cube_uv_back(point)),
+            CubeFace::Up => (self.up, cube_uv_up(point)),
+            CubeFace::Down => (self.down, cube_uv_down(point)),
+        };
+
+        face.color_at(u, v)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextureMapPattern {
+    pub transform: Matrix<4>,
+    pub uv_pattern: UvPattern,
+    pub mapping: UvMapping,
+}
+
+impl TextureMapPattern {
+    pub fn new(uv_pattern: UvPattern, mapping: UvMapping) -> Self {
+        Self {
+            transform: Matrix::identity(),
+            uv_pattern,
+            mapping,
+        }
+    }
+}
+
+impl PatternFuncs for TextureMapPattern {
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn color_at(&self, point: Tuple) -> Color {
+        let (u, v) = self.mapping.map(point);
+        self.uv_pattern.color_at(u, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        assert_fuzzy_eq, camera::Camera, cube::CubeBuilder, cylinder::CylinderBuilder,
+        light::Light, material::Material, pattern::Pattern, ray::Ray, util::FuzzyEq, world::World,
+    };
+
+    use super::*;
+
+    #[test]
+    fn checker_pattern_in_2d() {
+        let checkers = UvPattern::checkers(2.0, 2.0, Color::black(), Color::white());
+        let cases = [
+            (0.0, 0.0, Color::black()),
+            (0.5, 0.0, Color::white()),
+            (0.0, 0.5, Color::white()),
+            (0.5, 0.5, Color::black()),
+            (1.0, 1.0, Color::black()),
+        ];
+
+        for (u, v, expected) in cases {
+            assert_fuzzy_eq!(expected, checkers.color_at(u, v));
+        }
+    }
+
+    fn assert_mapping(map: fn(Tuple) -> (f64, f64), cases: &[(Tuple, f64, f64)]) {
+        for &(point, u, v) in cases {
+            let (actual_u, actual_v) = map(point);
+            assert_fuzzy_eq!(u, actual_u);
+            assert_fuzzy_eq!(v, actual_v);
+        }
+    }
+
+    #[test]
+    fn spherical_mapping_on_a_3d_point() {
+        let sqrt_2_2 = 2.0_f64.sqrt() / 2.0;
+        assert_mapping(
+            spherical_map,
+            &[
+                (Tuple::point(0.0, 0.0, -1.0), 0.0, 0.5),
+                (Tuple::point(1.0, 0.0, 0.0), 0.25, 0.5),
+                (Tuple::point(0.0, 0.0, 1.0), 0.5, 0.5),
+                (Tuple::point(-1.0, 0.0, 0.0), 0.75, 0.5),
+                (Tuple::point(0.0, 1.0, 0.0), 0.5, 1.0),
+                (Tuple::point(0.0, -1.0, 0.0), 0.5, 0.0),
+                (Tuple::point(sqrt_2_2, sqrt_2_2, 0.0), 0.25, 0.75),
+            ],
+        );
+    }
+
+    #[test]
+    fn planar_mapping_on_a_3d_point() {
+        assert_mapping(
+            planar_map,
+            &[
+                (Tuple::point(0.25, 0.0, 0.5), 0.25, 0.5),
+                (Tuple::point(0.25, 0.0, -0.25), 0.25, 0.75),
+                (Tuple::point(0.25, 0.5, -0.25), 0.25, 0.75),
+                (Tuple::point(1.25, 0.0, 0.5), 0.25, 0.5),
+                (Tuple::point(0.25, 0.0, -1.75), 0.25, 0.25),
+                (Tuple::point(1.0, 0.0, -1.0), 0.0, 0.0),
+                (Tuple::point(0.0, 0.0, 0.0), 0.0, 0.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn cylindrical_mapping_on_a_3d_point() {
+        let sqrt_2_2 = 2.0_f64.sqrt() / 2.0;
+        assert_mapping(
+            cylindrical_map,
+            &[
+                (Tuple::point(0.0, 0.0, -1.0), 0.0, 0.0),
+                (Tuple::point(0.0, 0.5, -1.0), 0.0, 0.5),
+                (Tuple::point(0.0, 1.0, -1.0), 0.0, 0.0),
+                (Tuple::point(sqrt_2_2, 0.5, -sqrt_2_2), 0.125, 0.5),
+                (Tuple::point(1.0, 0.5, 0.0), 0.25, 0.5),
+                (Tuple::point(sqrt_2_2, 0.5, sqrt_2_2), 0.375, 0.5),
+                (Tuple::point(0.0, -0.25, 1.0), 0.5, 0.75),
+                (Tuple::point(-sqrt_2_2, 0.5, sqrt_2_2), 0.625, 0.5),
+                (Tuple::point(-1.0, 1.25, 0.0), 0.75, 0.25),
+                (Tuple::point(-sqrt_2_2, 0.5, -sqrt_2_2), 0.875, 0.5),
+            ],
+        );
+    }
+
+    #[test]
+    fn texture_map_with_spherical_mapping() {
+        let checkers = UvPattern::checkers(16.0, 8.0, Color::black(), Color::white());
+        let pattern: Pattern = TextureMapPattern::new(checkers, UvMapping::Spherical).into();
+        let cases = [
+            (Tuple::point(0.4315, 0.4670, 0.7719), Color::white()),
+            (Tuple::point(-0.9654, 0.2552, -0.0534), Color::black()),
+            (Tuple::point(0.1039, 0.7090, 0.6975), Color::white()),
+            (Tuple::point(-0.4986, -0.7856, -0.3663), Color::black()),
+            (Tuple::point(-0.0317, -0.9395, 0.3411), Color::black()),
+            (Tuple::point(0.4809, -0.7721, 0.4154), Color::black()),
+            (Tuple::point(0.0285, -0.9612, -0.2747), Color::black()),
+            (Tuple::point(-0.5734, -0.2162, -0.7903), Color::white()),
+            (Tuple::point(0.7688, -0.1470, 0.6223), Color::black()),
+            (Tuple::point(-0.7652, 0.2175, 0.6060), Color::black()),
+        ];
+
+        for (point, expected) in cases {
+            assert_fuzzy_eq!(expected, pattern.color_at(point));
+        }
+    }
+
+    #[test]
+    fn cylinder_wrapped_in_checkers_shows_even_stripes() {
+        let checkers = UvPattern::checkers(16.0, 1.0, Color::black(), Color::white());
+        let cylinder = CylinderBuilder::default()
+            .minimum(0.0)
+            .maximum(1.0)
+            .material(
+                Material::default()
+                    .with_ambient(1.0)
+                    .with_diffuse(0.0)
+                    .with_specular(0.0)
+                    .with_pattern(TextureMapPattern::new(checkers, UvMapping::Cylindrical).into()),
+            )
+            .build()
+            .unwrap();
+        let light = Light::point(Tuple::point(0.0, 10.0, 0.0), Color::white());
+        let w = World::new(vec![cylinder.into()], light);
+
+        // Look at the side from all the way around, half a texel apart at the seam so no sample
+        // lands exactly on a boundary
+        let samples = 16 * 20;
+        let colors: Vec<Color> = (0..samples)
+            .map(|i| {
+                let angle = (i as f64 + 0.5) / samples as f64 * 2.0 * PI;
+                let origin = Tuple::point(5.0 * angle.sin(), 0.5, -5.0 * angle.cos());
+                let direction = Tuple::vector(-angle.sin(), 0.0, angle.cos());
+                w.color_at(Ray::new(origin, direction))
+            })
+            .collect();
+
+        // Every run of one color is exactly a texel wide, the one across the seam included
+        let changes: Vec<usize> = (0..samples)
+            .filter(|&i| colors[i].fuzzy_ne(colors[(i + 1) % samples]))
+            .collect();
+        assert_eq!(16, changes.len());
+        for pair in changes.windows(2) {
+            assert_eq!(20, pair[1] - pair[0]);
+        }
+        assert_eq!(20, changes[0] + samples - changes[15]);
+    }
+
+    #[test]
+    fn layout_of_align_check_pattern() {
+        let pattern = UvPattern::AlignCheck {
+            main: Color::white(),
+            upper_left: Color::red(),
+            upper_right: Color::new(1.0, 1.0, 0.0),
+            bottom_left: Color::green(),
+            bottom_right: Color::new(0.0, 1.0, 1.0),
+        };
+        let cases = [
+            (0.5, 0.5, Color::white()),
+            (0.1, 0.9, Color::red()),
+            (0.9, 0.9, Color::new(1.0, 1.0, 0.0)),
+            (0.1, 0.1, Color::green()),
+            (0.9, 0.1, Color::new(0.0, 1.0, 1.0)),
+        ];
+
+        for (u, v, expected) in cases {
+            assert_fuzzy_eq!(expected, pattern.color_at(u, v));
+        }
+    }
+
+    #[test]
+    fn identifying_the_face_of_a_cube_from_a_point() {
+        let cases = [
+            (Tuple::point(-1.0, 0.5, -0.25), CubeFace::Left),
+            (Tuple::point(1.1, -0.75, 0.8), CubeFace::Right),
+            (Tuple::point(0.1, 0.6, 0.9), CubeFace::Front),
+            (Tuple::point(-0.7, 0.0, -2.0), CubeFace::Back),
+            (Tuple::point(0.5, 1.0, 0.9), CubeFace::Up),
+            (Tuple::point(-0.2, -1.3, 1.1), CubeFace::Down),
+        ];
+
+        for (point, face) in cases {
+            assert_eq!(face, face_from_point(point));
+        }
+    }
+
+    #[test]
+    fn uv_mapping_each_face_of_a_cube() {
+        assert_mapping(
+            cube_uv_front,
+            &[
+                (Tuple::point(-0.5, 0.5, 1.0), 0.25, 0.75),
+                (Tuple::point(0.5, -0.5, 1.0), 0.75, 0.25),
+            ],
+        );
+        assert_mapping(
+            cube_uv_back,
+            &[
+                (Tuple::point(0.5, 0.5, -1.0), 0.25, 0.75),
+                (Tuple::point(-0.5, -0.5, -1.0), 0.75, 0.25),
+            ],
+        );
+        assert_mapping(
+            cube_uv_left,
+            &[
+                (Tuple::point(-1.0, 0.5, -0.5), 0.25, 0.75),
+                (Tuple::point(-1.0, -0.5, 0.5), 0.75, 0.25),
+            ],
+        );
+        assert_mapping(
+            cube_uv_right,
+            &[
+                (Tuple::point(1.0, 0.5, 0.5), 0.25, 0.75),
+                (Tuple::point(1.0, -0.5, -0.5), 0.75, 0.25),
+            ],
+        );
+        assert_mapping(
+            cube_uv_up,
+            &[
+                (Tuple::point(-0.5, 1.0, -0.5), 0.25, 0.75),
+                (Tuple::point(0.5, 1.0, 0.5), 0.75, 0.25),
+            ],
+        );
+        assert_mapping(
+            cube_uv_down,
+            &[
+                (Tuple::point(-0.5, -1.0, 0.5), 0.25, 0.75),
+                (Tuple::point(0.5, -1.0, -0.5), 0.75, 0.25),
+            ],
+        );
+    }
+
+    fn align_check(main: Color, ul: Color, ur: Color, bl: Color, br: Color) -> UvPattern {
+        UvPattern::AlignCheck {
+            main,
+            upper_left: ul,
+            upper_right: ur,
+            bottom_left: bl,
+            bottom_right: br,
+        }
+    }
+
+    #[test]
+    fn finding_colors_on_a_mapped_cube() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let yellow = Color::new(1.0, 1.0, 0.0);
+        let brown = Color::new(1.0, 0.5, 0.0);
+        let green = Color::new(0.0, 1.0, 0.0);
+        let cyan = Color::new(0.0, 1.0, 1.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        let purple = Color::new(1.0, 0.0, 1.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let pattern = UvCubeMap::new(
+            align_check(yellow, cyan, red, blue, brown),
+            align_check(red, yellow, purple, green, white),
+            align_check(cyan, red, yellow, brown, green),
+            align_check(green, purple, cyan, white, blue),
+            align_check(brown, cyan, purple, red, yellow),
+            align_check(purple, brown, green, blue, white),
+        );
+        let cases = [
+            (Tuple::point(-1.0, 0.0, 0.0), yellow),
+            (Tuple::point(-1.0, 0.9, -0.9), cyan),
+            (Tuple::point(-1.0, 0.9, 0.9), red),
+            (Tuple::point(-1.0, -0.9, -0.9), blue),
+            (Tuple::point(-1.0, -0.9, 0.9), brown),
+            (Tuple::point(0.0, 0.0, 1.0), cyan),
+            (Tuple::point(-0.9, 0.9, 1.0), red),
+            (Tuple::point(0.9, 0.9, 1.0), yellow),
+            (Tuple::point(-0.9, -0.9, 1.0), brown),
+            (Tuple::point(0.9, -0.9, 1.0), green),
+            (Tuple::point(1.0, 0.0, 0.0), red),
+            (Tuple::point(1.0, 0.9, 0.9), yellow),
+            (Tuple::point(1.0, 0.9, -0.9), purple),
+            (Tuple::point(1.0, -0.9, 0.9), green),
+            (Tuple::point(1.0, -0.9, -0.9), white),
+            (Tuple::point(0.0, 0.0, -1.0), green),
+            (Tuple::point(0.9, 0.9, -1.0), purple),
+            (Tuple::point(-0.9, 0.9, -1.0), cyan),
+            (Tuple::point(0.9, -0.9, -1.0), white),
+            (Tuple::point(-0.9, -0.9, -1.0), blue),
+            (Tuple::point(0.0, 1.0, 0.0), brown),
+            (Tuple::point(-0.9, 1.0, -0.9), cyan),
+            (Tuple::point(0.9, 1.0, -0.9), purple),
+            (Tuple::point(-0.9, 1.0, 0.9), red),
+            (Tuple::point(0.9, 1.0, 0.9), yellow),
+            (Tuple::point(0.0, -1.0, 0.0), purple),
+            (Tuple::point(-0.9, -1.0, 0.9), brown),
+            (Tuple::point(0.9, -1.0, 0.9), green),
+            (Tuple::point(-0.9, -1.0, -0.9), blue),
+            (Tuple::point(0.9, -1.0, -0.9), white),
+        ];
+
+        for (point, expected) in cases {
+            assert_fuzzy_eq!(expected, pattern.color_at(point));
+        }
+    }
+
+    #[test]
+    fn rendering_a_cube_with_a_color_per_face() {
+        let solid = |c: Color| UvPattern::checkers(1.0, 1.0, c, c);
+        let pattern = UvCubeMap::new(
+            solid(Color::red()),
+            solid(Color::green()),
+            solid(Color::blue()),
+            solid(Color::new(1.0, 1.0, 0.0)),
+            solid(Color::new(0.0, 1.0, 1.0)),
+            solid(Color::new(1.0, 0.0, 1.0)),
+        );
+        let cube = CubeBuilder::default()
+            .material(
+                Material::default()
+                    .with_ambient(1.0)
+                    .with_diffuse(0.0)
+                    .with_specular(0.0)
+                    .with_pattern(pattern.into()),
+            )
+            .build()
+            .unwrap();
+        let w = World::new(vec![cube.into()], Light::default());
+        let mut camera = Camera::new(21, 21, PI / 3.0);
+        // Looking down at the corner between the right, up and front faces. The world is left
+        // handed, so from here +x is on the left of the image
+        camera.set_transform(Matrix::view_transform(
+            Tuple::point(3.0, 3.0, 4.0),
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0),
+        )).unwrap();
+
+        let image = camera.render(&w);
+        assert_fuzzy_eq!(Color::green(), image.pixel_at(8, 10));
+        assert_fuzzy_eq!(Color::new(0.0, 1.0, 1.0), image.pixel_at(10, 7));
+        assert_fuzzy_eq!(Color::blue(), image.pixel_at(12, 10));
+        assert_fuzzy_eq!(Color::black(), image.pixel_at(2, 2));
+    }
+}
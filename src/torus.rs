@@ -0,0 +1,214 @@
+use crate::{
+    bounding_box::BoundingBox,
+    intersection::{Intersection, Intersections},
+    light::LightId,
+    material::Material,
+    matrix::Matrix,
+    ray::Ray,
+    shape::{next_shape_id, validate_transforms, Shape, ShapeFuncs},
+    tuple::Tuple,
+    util::{solve_quartic, FuzzyEq},
+};
+
+// A torus lying in the xz plane around the y axis
+#[derive(Debug, Clone, PartialEq, PartialOrd, Builder)]
+#[builder(build_fn(validate = "Self::validate"))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Torus {
+    #[cfg_attr(feature = "serde", serde(skip, default = "next_shape_id"))]
+    #[builder(setter(skip), default = "next_shape_id()")]
+    pub(crate) id: u64,
+    #[builder(default)]
+    pub transform: Matrix<4>,
+    #[builder(default, setter(strip_option))]
+    pub transform_at_t1: Option<Matrix<4>>,
+    #[builder(default)]
+    pub material: Material,
+    #[builder(default = "1.0")]
+    pub major_radius: f64,
+    #[builder(default = "0.25")]
+    pub minor_radius: f64,
+    #[builder(default)]
+    pub excluded_lights: Vec<LightId>,
+    #[builder(default, setter(into, strip_option))]
+    pub name: Option<String>,
+}
+
+impl Default for Torus {
+    fn default() -> Self {
+        Self {
+            id: next_shape_id(),
+            transform: Matrix::identity(),
+            transform_at_t1: None,
+            material: Material::default(),
+            major_radius: 1.0,
+            minor_radius: 0.25,
+            excluded_lights: vec![],
+            name: None,
+        }
+    }
+}
+
+impl TorusBuilder {
+    fn validate(&self) -> Result<(), String> {
+        validate_transforms(self.transform, self.transform_at_t1.flatten())
+    }
+}
+
+impl ShapeFuncs for Torus {
+    fn local_intersect(&self, object_space_ray: Ray) -> Intersections {
+        let origin = object_space_ray.origin;
+        let direction = object_space_ray.direction;
+
+        let major_sq = self.major_radius.powi(2);
+        let minor_sq = self.minor_radius.powi(2);
+
+        let origin_vector = Tuple::vector(origin.x, origin.y, origin.z);
+        let sum_d_sq = direction.dot(direction);
+        let e = origin_vector.dot(origin_vector) - major_sq - minor_sq;
+        let f = origin_vector.dot(direction);
+        let four_major_sq = 4.0 * major_sq;
+
+        let roots = solve_quartic(
+            sum_d_sq.powi(2),
+            4.0 * sum_d_sq * f,
+            2.0 * sum_d_sq * e + 4.0 * f.powi(2) + four_major_sq * direction.y.powi(2),
+            4.0 * f * e + 2.0 * four_major_sq * origin.y * direction.y,
+            e.powi(2) - four_major_sq * (minor_sq - origin.y.powi(2)),
+        );
+
+        let xs = roots
+            .into_iter()
+            .map(|t| Intersection::new(t, Shape::from(self.clone()).at_time(object_space_ray.time)))
+            .collect();
+
+        Intersections::new(xs)
+    }
+
+    fn local_normal_at(&self, p: Tuple) -> Tuple {
+        let major_sq = self.major_radius.powi(2);
+
+        // Gradient of the implicit torus function
+        let q = p.x.powi(2) + p.y.powi(2) + p.z.powi(2) - major_sq - self.minor_radius.powi(2);
+        Tuple::vector(p.x * q, p.y * (q + 2.0 * major_sq), p.z * q)
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn transform_at_t1(&self) -> Option<Matrix<4>> {
+        self.transform_at_t1
+    }
+
+    fn excluded_lights(&self) -> &[LightId] {
+        &self.excluded_lights
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let outer = self.major_radius + self.minor_radius;
+
+        BoundingBox::new(
+            Tuple::point(-outer, -self.minor_radius, -outer),
+            Tuple::point(outer, self.minor_radius, outer),
+        )
+    }
+}
+
+impl FuzzyEq<Self> for Torus {
+    fn fuzzy_eq(&self, other: Self) -> bool {
+        self.transform.fuzzy_eq(other.transform)
+            && self.material.fuzzy_eq(other.material)
+            && self.major_radius.fuzzy_eq(other.major_radius)
+            && self.minor_radius.fuzzy_eq(other.minor_radius)
+    }
+
+    fn fuzzy_ne(&self, other: Self) -> bool {
+        !self.fuzzy_eq(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_fuzzy_eq;
+
+    use super::*;
+
+    #[test]
+    fn ray_through_the_tube_hits_four_times() {
+        let t = Torus::default();
+        let r = Ray::new(Tuple::point(-5.0, 0.0, 0.0), Tuple::vector(1.0, 0.0, 0.0));
+
+        let xs = t.intersect(r);
+        assert_eq!(4, xs.intersections.len());
+        assert_fuzzy_eq!(3.75, xs.intersections[0].t);
+        assert_fuzzy_eq!(4.25, xs.intersections[1].t);
+        assert_fuzzy_eq!(5.75, xs.intersections[2].t);
+        assert_fuzzy_eq!(6.25, xs.intersections[3].t);
+    }
+
+    #[test]
+    fn ray_through_the_hole_hits_twice() {
+        let t = Torus::default();
+        // Passes through the tube at (-1, 0, 0), then through the hole below the far side
+        let r = Ray::new(
+            Tuple::point(-3.0, 1.0, 0.0),
+            Tuple::vector(1.0, -0.5, 0.0).normalize(),
+        );
+
+        let xs = t.intersect(r);
+        assert_eq!(2, xs.intersections.len());
+
+        let center = Tuple::point(-1.0, 0.0, 0.0);
+        for i in xs.intersections {
+            assert_fuzzy_eq!(0.25, (r.position(i.t) - center).magnitude());
+        }
+    }
+
+    #[test]
+    fn ray_misses_torus() {
+        let t = Torus::default();
+        let r = Ray::new(Tuple::point(0.0, 2.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        let xs = t.intersect(r);
+        assert_eq!(0, xs.intersections.len());
+
+        let r = Ray::new(Tuple::point(0.0, 5.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        let xs = t.intersect(r);
+        assert_eq!(0, xs.intersections.len());
+    }
+
+    #[test]
+    fn ray_grazing_the_tube() {
+        let t = Torus::default();
+        let r = Ray::new(Tuple::point(-5.0, 0.25, 0.0), Tuple::vector(1.0, 0.0, 0.0));
+
+        let xs = t.intersect(r);
+        assert!(!xs.intersections.is_empty());
+        assert!(xs.intersections[0].t.fuzzy_eq(4.0));
+        assert!(xs.intersections.last().unwrap().t.fuzzy_eq(6.0));
+    }
+
+    #[test]
+    fn normal_on_torus() {
+        let t = Torus::default();
+        let cases = vec![
+            (Tuple::point(1.25, 0.0, 0.0), Tuple::vector(1.0, 0.0, 0.0)),
+            (Tuple::point(0.75, 0.0, 0.0), Tuple::vector(-1.0, 0.0, 0.0)),
+            (Tuple::point(1.0, 0.25, 0.0), Tuple::vector(0.0, 1.0, 0.0)),
+            (Tuple::point(0.0, -0.25, 1.0), Tuple::vector(0.0, -1.0, 0.0)),
+        ];
+
+        for (point, expected) in cases {
+            assert_fuzzy_eq!(expected, t.normal_at(point));
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{
+    bounding_box::BoundingBox,
+    intersection::{Intersection, Intersections},
+    light::LightId,
+    material::Material,
+    matrix::Matrix,
+    ray::Ray,
+    shape::{next_shape_id, Shape, ShapeFuncs},
+    tuple::Tuple,
+    util::{FuzzyEq, EPSILON},
+};
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Triangle {
+    #[cfg_attr(feature = "serde", serde(skip, default = "next_shape_id"))]
+    pub(crate) id: u64,
+    pub p1: Tuple,
+    pub p2: Tuple,
+    pub p3: Tuple,
+    pub e1: Tuple,
+    pub e2: Tuple,
+    pub normal: Tuple,
+    pub transform: Matrix<4>,
+    pub transform_at_t1: Option<Matrix<4>>,
+    pub material: Material,
+    pub excluded_lights: Vec<LightId>,
+}
+
+impl Triangle {
+    pub fn new(p1: Tuple, p2: Tuple, p3: Tuple) -> Self {
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+        let normal = e2.cross(e1).normalize();
+
+        Self {
+            id: next_shape_id(),
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal,
+            transform: Matrix::identity(),
+            transform_at_t1: None,
+            material: Material::default(),
+            excluded_lights: vec![],
+        }
+    }
+}
+
+// Möller–Trumbore, shared with Mesh faces
+pub(crate) fn intersect_triangle(ray: Ray, p1: Tuple, e1: Tuple, e2: Tuple) -> Option<f64> {
+    let dir_cross_e2 = ray.direction.cross(e2);
+    let det = e1.dot(dir_cross_e2);
+    if det.abs() < EPSILON {
+        return None;
+    }
+
+    let f = 1.0 / det;
+    let p1_to_origin = ray.origin - p1;
+    let u = f * p1_to_origin.dot(dir_cross_e2);
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let origin_cross_e1 = p1_to_origin.cross(e1);
+    let v = f * ray.direction.dot(origin_cross_e1);
+    if v < 0.0 || (u + v) > 1.0 {
+        return None;
+    }
+
+    Some(f * e2.dot(origin_cross_e1))
+}
+
+impl ShapeFuncs for Triangle {
+    fn local_intersect(&self, object_space_ray: Ray) -> Intersections {
+        match intersect_triangle(object_space_ray, self.p1, self.e1, self.e2) {
+            Some(t) => Intersections::new(vec![Intersection::new(t, Shape::from(self.clone()).at_time(object_space_ray.time))]),
+            None => Intersections::new(vec![]),
+        }
+    }
+
+    fn local_normal_at(&self, _object_point: Tuple) -> Tuple {
+        self.normal
+    }
+
+    fn material(&self) -> Material {
+        self.material.clone()
+    }
+
+    fn transform(&self) -> Matrix<4> {
+        self.transform
+    }
+
+    fn transform_at_t1(&self) -> Option<Matrix<4>> {
+        self.transform_at_t1
+    }
+
+    fn excluded_lights(&self) -> &[LightId] {
+        &self.excluded_lights
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut bounds = BoundingBox::default();
+        bounds.add_point(self.p1);
+        bounds.add_point(self.p2);
+        bounds.add_point(self.p3);
+        bounds
+    }
+}
+
+impl FuzzyEq<Self> for Triangle {
+    fn fuzzy_eq(&self, other: Self) -> bool {
+        self.p1.fuzzy_eq(other.p1)
+            && self.p2.fuzzy_eq(other.p2)
+            && self.p3.fuzzy_eq(other.p3)
+            && self.transform.fuzzy_eq(other.transform)
+            && self.material.fuzzy_eq(other.material)
+    }
+
+    fn fuzzy_ne(&self, other: Self) -> bool {
+        !self.fuzzy_eq(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_fuzzy_eq;
+
+    use super::*;
+
+    fn default_triangle() -> Triangle {
+        Triangle::new(
+            Tuple::point(0.0, 1.0, 0.0),
+            Tuple::point(-1.0, 0.0, 0.0),
+            Tuple::point(1.0, 0.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn constructing_a_triangle() {
+        let t = default_triangle();
+
+        assert_fuzzy_eq!(Tuple::vector(-1.0, -1.0, 0.0), t.e1);
+        assert_fuzzy_eq!(Tuple::vector(1.0, -1.0, 0.0), t.e2);
+        assert_fuzzy_eq!(Tuple::vector(0.0, 0.0, -1.0), t.normal);
+    }
+
+    #[test]
+    fn finding_normal_on_triangle() {
+        let t = default_triangle();
+
+        assert_fuzzy_eq!(t.normal, t.normal_at(Tuple::point(0.0, 0.5, 0.0)));
+        assert_fuzzy_eq!(t.normal, t.normal_at(Tuple::point(-0.5, 0.75, 0.0)));
+        assert_fuzzy_eq!(t.normal, t.normal_at(Tuple::point(0.5, 0.25, 0.0)));
+    }
+
+    #[test]
+    fn intersecting_ray_parallel_to_triangle() {
+        let t = default_triangle();
+        let r = Ray::new(Tuple::point(0.0, -1.0, -2.0), Tuple::vector(0.0, 1.0, 0.0));
+
+        assert!(t.intersect(r).intersections.is_empty());
+    }
+
+    #[test]
+    fn ray_misses_edges_of_triangle() {
+        let t = default_triangle();
+        let origins = vec![
+            Tuple::point(1.0, 1.0, -2.0),
+            Tuple::point(-1.0, 1.0, -2.0),
+            Tuple::point(0.0, -1.0, -2.0),
+        ];
+
+        for origin in origins {
+            let r = Ray::new(origin, Tuple::vector(0.0, 0.0, 1.0));
+            assert!(t.intersect(r).intersections.is_empty());
+        }
+    }
+
+    #[test]
+    fn ray_strikes_triangle() {
+        let t = default_triangle();
+        let r = Ray::new(Tuple::point(0.0, 0.5, -2.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        let xs = t.intersect(r);
+        assert_eq!(1, xs.intersections.len());
+        assert_fuzzy_eq!(2.0, xs.intersections[0].t);
+    }
+}
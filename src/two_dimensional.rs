@@ -1,4 +1,17 @@
+use crate::error::Error;
+
 pub trait TwoDimensional {
     fn width(&self) -> usize;
     fn height(&self) -> usize;
+
+    // Where the pixel is in a buffer laid out row by row. A column past the width would otherwise
+    // quietly land on the next row
+    fn checked_index(&self, x: usize, y: usize) -> Result<usize, Error> {
+        let (width, height) = (self.width(), self.height());
+        if x >= width || y >= height {
+            return Err(Error::PixelOutOfBounds { x, y, width, height });
+        }
+
+        Ok(y * width + x)
+    }
 }
@@ -26,6 +26,38 @@ impl FuzzyEq<&f64> for f64 {
     }
 }
 
+// JSON has no infinities, so those are written as the strings "inf" and "-inf" instead
+#[cfg(feature = "serde")]
+pub mod serde_float {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        match *value {
+            f64::INFINITY => serializer.serialize_str("inf"),
+            f64::NEG_INFINITY => serializer.serialize_str("-inf"),
+            v => serializer.serialize_f64(v),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Float {
+            Number(f64),
+            Text(String),
+        }
+
+        match Float::deserialize(deserializer)? {
+            Float::Number(v) => Ok(v),
+            Float::Text(text) => match text.as_str() {
+                "inf" => Ok(f64::INFINITY),
+                "-inf" => Ok(f64::NEG_INFINITY),
+                _ => Err(D::Error::custom(format!("expected a number, inf or -inf, got {:?}", text))),
+            },
+        }
+    }
+}
+
 #[macro_export]
 macro_rules! assert_fuzzy_eq {
     ($left:expr, $right:expr $(,)?) => {{
@@ -57,3 +89,135 @@ macro_rules! assert_fuzzy_ne {
         }
     }};
 }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    fn norm(&self) -> f64 {
+        (self.re.powi(2) + self.im.powi(2)).sqrt()
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+
+    fn div(self, other: Self) -> Self {
+        let denominator = other.re.powi(2) + other.im.powi(2);
+        Self::new(
+            (self.re * other.re + self.im * other.im) / denominator,
+            (self.im * other.re - self.re * other.im) / denominator,
+        )
+    }
+}
+
+// Finds the real roots of a*t^4 + b*t^3 + c*t^2 + d*t + e in ascending order using the
+// Durand-Kerner iteration. Roots with an imaginary part within EPSILON count as real, so
+// nearly grazing (double) roots are not lost.
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    let coefficients = [b / a, c / a, d / a, e / a];
+    let evaluate = |t: Complex| {
+        coefficients.iter().fold(Complex::new(1.0, 0.0), |acc, &k| {
+            acc.mul(t).add(Complex::new(k, 0.0))
+        })
+    };
+
+    // Every root lies within the Cauchy bound, so start on a circle of that radius
+    let bound = 1.0 + coefficients.iter().fold(0.0_f64, |acc, k| acc.max(k.abs()));
+    let seed = Complex::new(0.4, 0.9);
+    let mut roots = [Complex::new(1.0, 0.0); 4];
+    for i in 1..4 {
+        roots[i] = roots[i - 1].mul(seed);
+    }
+    for root in roots.iter_mut() {
+        *root = root.mul(Complex::new(bound, 0.0));
+    }
+
+    for _ in 0..500 {
+        let mut max_change: f64 = 0.0;
+
+        for i in 0..4 {
+            let mut denominator = Complex::new(1.0, 0.0);
+            for j in 0..4 {
+                if i != j {
+                    denominator = denominator.mul(roots[i].sub(roots[j]));
+                }
+            }
+
+            let change = evaluate(roots[i]).div(denominator);
+            roots[i] = roots[i].sub(change);
+            max_change = max_change.max(change.norm());
+        }
+
+        if max_change < 1e-14 * bound {
+            break;
+        }
+    }
+
+    let mut real_roots: Vec<f64> = roots
+        .iter()
+        .filter(|r| r.im.abs() < EPSILON)
+        .map(|r| polish_root(&coefficients, r.re))
+        .collect();
+    real_roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    real_roots
+}
+
+// Refines a root of the monic quartic with a few Newton steps on the real polynomial
+fn polish_root(coefficients: &[f64; 4], mut t: f64) -> f64 {
+    for _ in 0..4 {
+        let (value, derivative) = coefficients
+            .iter()
+            .fold((1.0, 0.0), |(p, dp), &k| (p * t + k, dp * t + p));
+
+        if derivative.abs() < EPSILON {
+            break;
+        }
+        t -= value / derivative;
+    }
+
+    t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solving_quartic_with_four_real_roots() {
+        // (t - 1)(t - 2)(t - 3)(t - 4)
+        let roots = solve_quartic(1.0, -10.0, 35.0, -50.0, 24.0);
+
+        assert_eq!(4, roots.len());
+        for (expected, actual) in [1.0, 2.0, 3.0, 4.0].iter().zip(roots) {
+            assert!(actual.fuzzy_eq(expected));
+        }
+    }
+
+    #[test]
+    fn solving_quartic_without_real_roots() {
+        // (t^2 + 1)(t^2 + 4)
+        let roots = solve_quartic(1.0, 0.0, 5.0, 0.0, 4.0);
+
+        assert!(roots.is_empty());
+    }
+}
@@ -1,74 +1,574 @@
+use std::{cell::RefCell, fmt};
+
 use crate::{
+    animation::Animated,
+    bvh::BvhCache,
     color::Color,
-    intersection::{ComputedIntersection, Intersections},
+    debug_hooks::{DebugHooks, HookSlot, RayKind},
+    intersection::{ComputedIntersection, Intersection, Intersections},
     light::Light,
-    material::Material,
+    material::{Material, Surface},
     matrix::Matrix,
     ray::Ray,
+    ray_counters::{self, Counter},
     shape::{Shape, ShapeFuncs},
     sphere::{SphereBuilder},
     tuple::Tuple,
 };
 
+// How many times a ray may bounce off reflective or through transparent surfaces, unless the
+// world says otherwise
+pub const MAX_DEPTH: usize = 5;
+
+thread_local! {
+    // Intersection buffers for the rays traced on this thread. A ray keeps its buffer while the
+    // rays it spawns take others, so there's one per level of recursion in use at most
+    static SCRATCH: RefCell<Vec<Intersections>> = const { RefCell::new(vec![]) };
+}
+
+// Lends out a buffer from this thread's pool, handing it back afterwards with its capacity intact
+fn with_scratch<R>(op: impl FnOnce(&mut Intersections) -> R) -> R {
+    let mut xs = SCRATCH
+        .with(|pool| pool.borrow_mut().pop())
+        .unwrap_or_else(|| Intersections::new(vec![]));
+    let result = op(&mut xs);
+    SCRATCH.with(|pool| pool.borrow_mut().push(xs));
+    result
+}
+
+// Everything that went into the color a ray came back with, up to its first hit. The reflected
+// and refracted rays only show up in the color
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShadingTrace {
+    pub ray: Ray,
+    pub intersections: Intersections,
+    pub hit: Option<Intersection>,
+    pub comps: Option<ComputedIntersection>,
+    // One for every light of the world, how much of it reaches the hit where 0 is full shadow.
+    // None for the lights the hit object excludes or that don't reach that far
+    pub light_intensities: Vec<Option<f64>>,
+    pub color: Color,
+}
+
+impl fmt::Display for ShadingTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tuple = |t: Tuple| format!("({:.5}, {:.5}, {:.5})", t.x, t.y, t.z);
+
+        writeln!(f, "ray from {} towards {}", tuple(self.ray.origin), tuple(self.ray.direction))?;
+        writeln!(f, "{} intersections", self.intersections.intersections.len())?;
+        for i in &self.intersections.intersections {
+            let marker = if Some(i) == self.hit.as_ref() { "  <- hit" } else { "" };
+            writeln!(f, "  t = {:.5} on {} #{}{}", i.t, shape_kind(&i.object), i.object.id(), marker)?;
+        }
+
+        match &self.comps {
+            None => writeln!(f, "no hit, background")?,
+            Some(comps) => {
+                writeln!(f, "point {}, over point {}", tuple(comps.point), tuple(comps.over_point))?;
+                writeln!(f, "eye {}, normal {}", tuple(comps.eyev), tuple(comps.normalv))?;
+                writeln!(f, "inside: {}, n1 = {}, n2 = {}", comps.inside, comps.n1, comps.n2)?;
+                for (light, intensity) in self.light_intensities.iter().enumerate() {
+                    match intensity {
+                        None => writeln!(f, "light {}: excluded or out of range", light)?,
+                        Some(i) if *i == 0.0 => writeln!(f, "light {}: in shadow", light)?,
+                        Some(i) => writeln!(f, "light {}: intensity {:.5}", light, i)?,
+                    }
+                }
+            }
+        }
+
+        write!(f, "color {:?}", self.color)
+    }
+}
+
+fn shape_kind(shape: &Shape) -> &'static str {
+    match shape {
+        Shape::Sphere(_) => "sphere",
+        Shape::Plane(_) => "plane",
+        Shape::Cube(_) => "cube",
+        Shape::Cylinder(_) => "cylinder",
+        Shape::Cone(_) => "cone",
+        Shape::Group(_) => "group",
+        Shape::Torus(_) => "torus",
+        Shape::Disk(_) => "disk",
+        Shape::Triangle(_) => "triangle",
+        Shape::Mesh(_) => "mesh",
+        #[cfg(test)]
+        Shape::TestShape(_) => "test shape",
+    }
+}
+
+// What a ray sees when it misses every object
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(tag = "type"))]
+pub enum Background {
+    Solid(Color),
+    // Blends from the horizon color straight ahead to the zenith color straight up, anything
+    // looking down gets the horizon color
+    VerticalGradient { zenith: Color, horizon: Color },
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        Self::Solid(Color::black())
+    }
+}
+
+impl Background {
+    pub fn color_at(&self, ray: Ray) -> Color {
+        match *self {
+            Self::Solid(color) => color,
+            Self::VerticalGradient { zenith, horizon } => {
+                let t = ray.direction.normalize().y.max(0.0);
+                horizon + (zenith - horizon) * t
+            }
+        }
+    }
+}
+
+// How quickly fog thickens with distance. Linear fog starts at start and is as thick as it gets
+// by end, exponential fog thickens by the density with every unit travelled
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(tag = "type"))]
+pub enum FogMode {
+    Linear { start: f64, end: f64 },
+    Exponential,
+}
+
+// Blends what a ray sees towards the fog color the further away it is. Rays that miss everything
+// are taken to have gone max_distance
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fog {
+    pub color: Color,
+    pub density: f64,
+    pub mode: FogMode,
+    pub max_distance: f64,
+}
+
+impl Fog {
+    // How much of the fog color there is at the distance, from 0 to 1
+    pub fn amount(&self, distance: f64) -> f64 {
+        let amount = match self.mode {
+            FogMode::Linear { start, end } => {
+                self.density * ((distance - start) / (end - start)).clamp(0.0, 1.0)
+            }
+            FogMode::Exponential => 1.0 - (-self.density * distance).exp(),
+        };
+        amount.clamp(0.0, 1.0)
+    }
+
+    pub fn apply(&self, color: Color, distance: f64) -> Color {
+        let amount = self.amount(distance);
+        color * (1.0 - amount) + self.color * amount
+    }
+}
+
+// Two top level objects can't share a name when the world is built. Adding one later that does
+// hides the earlier one from the name lookups, which find the last
 #[derive(Debug, Clone, PartialEq, PartialOrd, Builder)]
+#[builder(build_fn(validate = "Self::validate"))]
+#[cfg_attr(feature = "serde", builder(derive(serde::Deserialize)))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(try_from = "WorldBuilder"))]
 pub struct World {
+    // Only reachable through the methods below, which keep the acceleration structure in step
+    #[builder(default)]
+    objects: Vec<Shape>,
+    #[builder(default = "vec![Light::default()]")]
+    pub lights: Vec<Light>,
     #[builder(default)]
-    pub objects: Vec<Shape>,
+    pub background: Background,
+    #[builder(default, setter(strip_option))]
+    pub fog: Option<Fog>,
+    #[builder(default = "MAX_DEPTH")]
+    pub max_recursion_depth: usize,
+    // Transforms that change over time, for the object at each index. Only at_time applies them
     #[builder(default)]
-    pub light_source: Light,
+    pub animations: Vec<(usize, Animated<Matrix<4>>)>,
+    // Built over objects on the first intersect. The methods that change objects throw it away
+    #[builder(setter(skip))]
+    #[cfg_attr(feature = "serde", serde(skip), builder_field_attr(serde(skip)))]
+    bvh: BvhCache,
+    #[builder(setter(skip))]
+    #[cfg_attr(feature = "serde", serde(skip), builder_field_attr(serde(skip)))]
+    hooks: HookSlot,
+}
+
+impl WorldBuilder {
+    // Adds to the objects set so far, where objects replaces them all
+    pub fn add_object(&mut self, s: impl Into<Shape>) -> &mut Self {
+        self.objects.get_or_insert_with(Vec::new).push(s.into());
+        self
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let objects = self.objects.as_deref().unwrap_or_default();
+        for (i, name) in objects.iter().enumerate().filter_map(|(i, o)| Some((i, o.name()?))) {
+            if objects[..i].iter().any(|o| o.name() == Some(name)) {
+                return Err(format!("more than one object is named {:?}", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Read back through the builder, so a scene file gets the same checks as a world built in code
+#[cfg(feature = "serde")]
+impl TryFrom<WorldBuilder> for World {
+    type Error = WorldBuilderError;
+
+    fn try_from(builder: WorldBuilder) -> Result<Self, Self::Error> {
+        builder.build()
+    }
 }
 
 impl World {
     pub fn new(objects: Vec<Shape>, light_source: Light) -> Self {
+        Self::with_lights(objects, vec![light_source])
+    }
+
+    pub fn with_lights(objects: Vec<Shape>, lights: Vec<Light>) -> Self {
         Self {
             objects,
-            light_source,
+            lights,
+            background: Background::default(),
+            fog: None,
+            max_recursion_depth: MAX_DEPTH,
+            animations: vec![],
+            bvh: BvhCache::default(),
+            hooks: HookSlot::default(),
+        }
+    }
+
+    // Returns the index the shape can be found at with object_mut
+    pub fn add_object(&mut self, s: impl Into<Shape>) -> usize {
+        self.objects_changed();
+        self.objects.push(s.into());
+        self.objects.len() - 1
+    }
+
+    pub fn objects(&self) -> &[Shape] {
+        &self.objects
+    }
+
+    pub fn object_mut(&mut self, idx: usize) -> Option<&mut Shape> {
+        self.objects_changed();
+        self.objects.get_mut(idx)
+    }
+
+    // Every object after the removed one moves down an index, the ones before it keep theirs
+    pub fn remove_object(&mut self, idx: usize) -> Option<Shape> {
+        if idx >= self.objects.len() {
+            return None;
+        }
+
+        self.objects_changed();
+        self.animations.retain(|(i, _)| *i != idx);
+        for (i, _) in self.animations.iter_mut().filter(|(i, _)| *i > idx) {
+            *i -= 1;
+        }
+        Some(self.objects.remove(idx))
+    }
+
+    // Replaces the animation the object had, if any
+    pub fn animate(&mut self, idx: usize, transform: Animated<Matrix<4>>) {
+        self.animations.retain(|(i, _)| *i != idx);
+        self.animations.push((idx, transform));
+    }
+
+    // A copy with every animated object moved to where it is at the time
+    pub fn at_time(&self, time: f64) -> World {
+        let mut world = self.clone();
+        for (idx, transform) in &self.animations {
+            if let Some(object) = world.objects.get_mut(*idx) {
+                object.set_transform(transform.at(time));
+            }
         }
+        if !self.animations.is_empty() {
+            world.objects_changed();
+        }
+        world
+    }
+
+    // Copies of the world made from here on share the hooks
+    pub fn set_debug_hooks(&mut self, hooks: DebugHooks) {
+        self.hooks = HookSlot::new(hooks);
+    }
+
+    pub fn clear_debug_hooks(&mut self) {
+        self.hooks = HookSlot::default();
+    }
+
+    // Has the next intersect rebuild the acceleration structure over objects
+    fn objects_changed(&mut self) {
+        self.bvh = BvhCache::default();
     }
 
     pub fn intersect(&self, ray: Ray) -> Intersections {
-        let xs = self.objects.iter().flat_map(|o| o.intersect(ray)).collect();
+        let mut xs = Intersections::new(vec![]);
+        self.intersect_into(ray, &mut xs);
+        xs
+    }
+
+    // The same as intersect, but fills out instead of a new buffer. Whatever was in it is gone
+    pub fn intersect_into(&self, ray: Ray, out: &mut Intersections) {
+        out.intersections.clear();
+        out.intersections.extend(self.intersections_iter(ray));
+        out.sort();
+    }
+
+    // Every intersection along the ray as it's found, an object at a time. Each object's come in
+    // order of t, but the objects are in no particular order and the ones whose bounds the ray
+    // misses are left out
+    pub fn intersections_iter(&self, ray: Ray) -> impl Iterator<Item = Intersection> + '_ {
+        self.bvh
+            .get(&self.objects)
+            .candidates(ray)
+            .flat_map(move |i| self.objects[i].intersect(ray))
+    }
+
+    // The same hit as intersect(ray).hit(), keeping only the nearest one seen so far instead of
+    // gathering and sorting them all
+    pub fn first_hit(&self, ray: Ray) -> Option<Intersection> {
+        self.intersections_iter(ray)
+            .filter(|i| i.t > 0.0)
+            .fold(None, |nearest, i| match nearest {
+                Some(nearest) if nearest.t <= i.t => Some(nearest),
+                _ => Some(i),
+            })
+    }
 
-        Intersections::new(xs)
+    pub fn object_by_name(&self, name: &str) -> Option<&Shape> {
+        self.objects.iter().rev().find(|o| o.name() == Some(name))
     }
 
-    pub fn shade_hit(&self, comp: ComputedIntersection) -> Color {
-        let in_shadow = self.is_shadowed(comp.over_point);
+    pub fn object_by_name_mut(&mut self, name: &str) -> Option<&mut Shape> {
+        self.objects_changed();
+        self.objects.iter_mut().rev().find(|o| o.name() == Some(name))
+    }
+
+    // Where in objects the shape is, which for a shape handed out by a group is the index of the
+    // group it sits in
+    pub fn object_index(&self, shape: &Shape) -> Option<usize> {
+        self.objects.iter().position(|o| o.includes(shape))
+    }
+
+    // Sums the contribution of every light, leaving out the ones the object excludes along
+    // with their shadow rays, then follows the reflected and refracted rays the kind of surface
+    // calls for
+    pub fn shade_hit(&self, comp: ComputedIntersection, remaining: usize) -> Color {
+        self.shade_hit_traced(comp, remaining, None)
+    }
+
+    fn shade_hit_traced(&self, comp: ComputedIntersection, remaining: usize, mut trace: Option<&mut ShadingTrace>) -> Color {
+        let object = &comp.intersection.object;
+        let material = object.material();
+
+        let surface = self.lights.iter().fold(Color::black(), |color, light| {
+            if object.excluded_lights().contains(&light.id()) || !light.reaches(comp.over_point) {
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.light_intensities.push(None);
+                }
+                return color;
+            }
+
+            let intensity = light.intensity_at(comp.over_point, comp.time, self);
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.light_intensities.push(Some(intensity));
+            }
+            let point = comp.point;
+            color + material.lighting(object, point, *light, comp.eyev, comp.normalv, intensity)
+        });
 
-        comp.intersection.object.material().lighting(
-            comp.point,
-            self.light_source,
-            comp.eyev,
-            comp.normalv,
-            in_shadow,
-        )
+        match material.surface {
+            Surface::Lambertian | Surface::Phong { .. } => surface,
+            Surface::Metal { .. } => surface + self.reflected_color(&comp, remaining),
+            Surface::Dielectric { .. } => {
+                surface
+                    + self.reflected_color(&comp, remaining)
+                    + self.refracted_color(&comp, remaining)
+            }
+        }
     }
 
+    // Starts a camera ray with the full recursion budget of the world
     pub fn color_at(&self, ray: Ray) -> Color {
-        let xs = self.intersect(ray);
-        let hit = xs.hit();
-
-        match hit {
-            None => Color::black(),
-            Some(i) => {
-                let comp = i.as_computed(ray);
-                self.shade_hit(comp)
+        self.hooks.ray(&ray, RayKind::Primary);
+        self.color_at_depth(ray, self.max_recursion_depth)
+    }
+
+    pub fn color_at_depth(&self, ray: Ray, remaining: usize) -> Color {
+        self.trace(ray, remaining).0
+    }
+
+    // The color seen along the ray and how far away the hit was, if there was one
+    fn trace(&self, ray: Ray, remaining: usize) -> (Color, Option<f64>) {
+        self.trace_recording(ray, remaining, None)
+    }
+
+    // Same as color_at, but keeps track of how the color came about
+    pub fn trace_shading(&self, ray: Ray) -> ShadingTrace {
+        let mut trace = ShadingTrace {
+            ray,
+            intersections: Intersections::new(vec![]),
+            hit: None,
+            comps: None,
+            light_intensities: vec![],
+            color: Color::black(),
+        };
+        self.hooks.ray(&ray, RayKind::Primary);
+        trace.color = self.trace_recording(ray, self.max_recursion_depth, Some(&mut trace)).0;
+        trace
+    }
+
+    fn trace_recording(&self, ray: Ray, remaining: usize, mut trace: Option<&mut ShadingTrace>) -> (Color, Option<f64>) {
+        with_scratch(|xs| {
+            // Telling what a transparent hit refracts between takes every intersection along the
+            // ray, as does the trace. Anything else is shaded from the hit alone
+            let hit = match self.first_hit(ray) {
+                Some(hit) if trace.is_none() && hit.object.material().transparency() <= 0.0 => {
+                    xs.intersections.clear();
+                    xs.intersections.push(hit.clone());
+                    Some(hit)
+                }
+                None if trace.is_none() => None,
+                _ => {
+                    self.intersect_into(ray, xs);
+                    xs.hit()
+                }
+            };
+
+            let result = match &hit {
+                None => (self.background.color_at(ray), None),
+                Some(i) => {
+                    let comp = i.as_computed(ray, xs);
+                    self.hooks.hit(&comp);
+                    if let Some(trace) = trace.as_deref_mut() {
+                        trace.comps = Some(comp.clone());
+                    }
+                    (self.shade_hit_traced(comp, remaining, trace.as_deref_mut()), Some(i.t))
+                }
+            };
+
+            // The fog is between the ray's origin and whatever it sees, the background included
+            let result = match (self.fog, result) {
+                (Some(fog), (color, t)) => {
+                    let distance = t.map_or(fog.max_distance, |t| t * ray.direction.magnitude());
+                    (fog.apply(color, distance), t)
+                }
+                (None, result) => result,
+            };
+            self.hooks.shade(&result.0);
+
+            if let Some(trace) = trace {
+                trace.intersections = xs.clone();
+                trace.hit = hit;
             }
+            result
+        })
+    }
+
+    pub fn reflected_color(&self, comp: &ComputedIntersection, remaining: usize) -> Color {
+        let reflective = comp.intersection.object.material().reflective();
+        if remaining == 0 || reflective == 0.0 {
+            return Color::black();
         }
+
+        ray_counters::count(Counter::Secondary);
+        let reflect_ray = Ray::new(comp.over_point, comp.reflectv).at_time(comp.time);
+        self.hooks.ray(&reflect_ray, RayKind::Reflected);
+        self.color_at_depth(reflect_ray, remaining - 1) * reflective
     }
 
-    pub fn is_shadowed(&self, point: Tuple) -> bool {
-        let v = self.light_source.position - point;
-        let distance = v.magnitude();
-        let direction = v.normalize();
+    pub fn refracted_color(&self, comp: &ComputedIntersection, remaining: usize) -> Color {
+        let material = comp.intersection.object.material();
+        let transparency = material.transparency();
+        if remaining == 0 || transparency == 0.0 {
+            return Color::black();
+        }
 
-        let ray = Ray::new(point, direction);
-        let xs = self.intersect(ray);
-        let hit = xs.hit();
-        match hit {
-            None => false,
-            Some(i) => i.t < distance,
+        // Snell's law, where sin(theta_t) past 1 means total internal reflection
+        let n_ratio = comp.n1 / comp.n2;
+        let cos_i = comp.eyev.dot(comp.normalv);
+        let sin2_t = n_ratio.powi(2) * (1.0 - cos_i.powi(2));
+        if sin2_t > 1.0 {
+            return Color::black();
         }
+
+        let cos_t = (1.0 - sin2_t).sqrt();
+        let direction = comp.normalv * (n_ratio * cos_i - cos_t) - comp.eyev * n_ratio;
+        ray_counters::count(Counter::Secondary);
+        let refract_ray = Ray::new(comp.under_point, direction).at_time(comp.time);
+        self.hooks.ray(&refract_ray, RayKind::Refracted);
+        let (color, distance) = self.trace(refract_ray, remaining - 1);
+
+        // Entering the object, the ray travels through it until the next hit and loses some of
+        // each channel on the way
+        let color = match distance {
+            Some(distance) if !comp.inside => {
+                color * (material.attenuation * -distance * direction.magnitude()).exp()
+            }
+            _ => color,
+        };
+
+        color * transparency
+    }
+
+    #[deprecated(note = "use Light::intensity_at, which also handles partially lit points")]
+    pub fn is_shadowed(&self, point: Tuple) -> bool {
+        self.lights
+            .iter()
+            .any(|light| light.casts_shadows && self.is_occluded(light.position, point, 0.0))
+    }
+
+    // Whether an opaque shape that casts shadows sits between the point and a position on a light,
+    // with moving shapes where they are at the time. Stops at the first object that blocks it,
+    // without gathering or sorting any hits
+    pub fn is_occluded(&self, light_position: Tuple, point: Tuple, time: f64) -> bool {
+        let (ray, distance) = self.shadow_ray(light_position, point, time);
+
+        self.objects.iter().any(|o| o.is_occluding(ray, distance))
+    }
+
+    // The fraction of light that passes every shadow casting shape between the point and a
+    // position on a light, each one letting through its material's transparency
+    pub fn transmittance(&self, light_position: Tuple, point: Tuple, time: f64) -> f64 {
+        let (ray, distance) = self.shadow_ray(light_position, point, time);
+        with_scratch(|xs| {
+            self.intersect_into(ray, xs);
+
+            // A shape is usually hit twice on the way, but only filters the light once
+            let mut seen = vec![];
+            let mut transmittance = 1.0;
+            for i in xs
+                .intersections
+                .iter()
+                .filter(|i| i.t > 0.0 && i.t < distance && i.object.casts_shadows())
+            {
+                if seen.contains(&i.object.id()) {
+                    continue;
+                }
+                seen.push(i.object.id());
+
+                transmittance *= i.object.material().transparency();
+                if transmittance <= 0.0 {
+                    return 0.0;
+                }
+            }
+
+            transmittance
+        })
+    }
+
+    // From the point towards the light at the time of the hit, along with how far away the
+    // light is
+    fn shadow_ray(&self, light_position: Tuple, point: Tuple, time: f64) -> (Ray, f64) {
+        ray_counters::count(Counter::Shadow);
+
+        let v = light_position - point;
+        let ray = Ray::new(point, v.normalize()).at_time(time);
+        self.hooks.ray(&ray, RayKind::Shadow);
+        (ray, v.magnitude())
     }
 }
 
@@ -94,7 +594,19 @@ impl Default for World {
 
 #[cfg(test)]
 mod tests {
-    use crate::{assert_fuzzy_eq, intersection::Intersection, util::FuzzyEq};
+    use crate::{
+        assert_fuzzy_eq,
+        camera::Camera,
+        debug_hooks::counting_rays,
+        cube::CubeBuilder,
+        group::GroupBuilder,
+        intersection::Intersection,
+        pattern::{CheckerPattern3DBuilder, StripePatternBuilder, TestPattern},
+        plane::PlaneBuilder,
+        sampling::{PixelSampler, Sampler},
+        sphere::Sphere,
+        util::FuzzyEq,
+    };
 
     use super::*;
 
@@ -102,12 +614,12 @@ mod tests {
     fn default_world() {
         let light = Light::point(Tuple::point(-10.0, 10.0, -10.0), Color::white());
         let material = Material::new(Color::new(0.8, 1.0, 0.6), 0.1, 0.7, 0.2, 200.0);
-        let s1 = SphereBuilder::default()
+        let s1: Shape = SphereBuilder::default()
             .material(material)
             .build()
             .unwrap()
             .into();
-        let s2 = SphereBuilder::default()
+        let s2: Shape = SphereBuilder::default()
             .transform(Matrix::scaling(0.5, 0.5, 0.5))
             .build()
             .unwrap()
@@ -115,9 +627,54 @@ mod tests {
 
         let w = World::default();
 
-        assert_eq!(light, w.light_source);
-        assert!(w.objects.contains(&s1));
-        assert!(w.objects.contains(&s2));
+        assert_eq!(1, w.lights.len());
+        assert_fuzzy_eq!(light.position, w.lights[0].position);
+        assert_fuzzy_eq!(light.color, w.lights[0].color);
+        // The default world builds its own spheres, so they can only match by value
+        assert!(w.objects().iter().any(|o| o.fuzzy_eq(s1.clone())));
+        assert!(w.objects().iter().any(|o| o.fuzzy_eq(s2.clone())));
+    }
+
+    fn named_sphere(name: &str, x: f64) -> Shape {
+        SphereBuilder::default()
+            .name(name)
+            .transform(Matrix::translation(x, 0.0, 0.0))
+            .build()
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn objects_are_found_by_name() {
+        let mut w = WorldBuilder::default()
+            .add_object(named_sphere("left", -2.0))
+            .add_object(named_sphere("right", 2.0))
+            .build()
+            .unwrap();
+
+        assert_eq!(Some(&w.objects()[0]), w.object_by_name("left"));
+        assert_eq!(Some(&w.objects()[1]), w.object_by_name("right"));
+        assert_eq!(None, w.object_by_name("middle"));
+
+        if let Some(Shape::Sphere(s)) = w.object_by_name_mut("right") {
+            s.material = s.material.clone().with_reflective(0.5);
+        }
+        assert_fuzzy_eq!(0.5, w.objects()[1].material().reflective());
+        assert_fuzzy_eq!(0.0, w.objects()[0].material().reflective());
+    }
+
+    #[test]
+    fn duplicate_names_are_rejected_by_builder_and_last_wins_after() {
+        let err = WorldBuilder::default()
+            .add_object(named_sphere("ball", -2.0))
+            .add_object(named_sphere("ball", 2.0))
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("\"ball\""), "{}", err);
+
+        let mut w = WorldBuilder::default().add_object(named_sphere("ball", -2.0)).build().unwrap();
+        let idx = w.add_object(named_sphere("ball", 2.0));
+        assert_eq!(Some(&w.objects()[idx]), w.object_by_name("ball"));
     }
 
     #[test]
@@ -133,30 +690,244 @@ mod tests {
         xs.intersections[3].t.fuzzy_eq(6.0);
     }
 
+    fn brute_force_intersect(w: &World, r: Ray) -> Vec<(f64, u64)> {
+        let xs = Intersections::new(w.objects().iter().flat_map(|o| o.intersect(r)).collect());
+        xs.intersections.iter().map(|i| (i.t, i.object.id())).collect()
+    }
+
+    fn accelerated_intersect(w: &World, r: Ray) -> Vec<(f64, u64)> {
+        w.intersect(r).intersections.iter().map(|i| (i.t, i.object.id())).collect()
+    }
+
+    fn scattered_spheres(n: usize) -> World {
+        let mut sampler = PixelSampler::new(3, 0, 0, 0);
+        let mut coordinate = || sampler.next_f64() * 40.0 - 20.0;
+        let mut objects: Vec<Shape> = (0..n)
+            .map(|_| {
+                let transform = Matrix::translation(coordinate(), coordinate(), coordinate()) * Matrix::scaling(0.5, 0.5, 0.5);
+                SphereBuilder::default().transform(transform).build().unwrap().into()
+            })
+            .collect();
+        objects.push(PlaneBuilder::default().transform(Matrix::translation(0.0, -25.0, 0.0)).build().unwrap().into());
+
+        World::new(objects, Light::default())
+    }
+
+    #[test]
+    fn accelerated_intersect_matches_brute_force_for_default_world() {
+        let w = World::default();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        assert_eq!(4, accelerated_intersect(&w, r).len());
+        assert_eq!(brute_force_intersect(&w, r), accelerated_intersect(&w, r));
+    }
+
+    #[test]
+    fn accelerated_intersect_matches_brute_force_for_scattered_spheres() {
+        let w = scattered_spheres(200);
+        let mut sampler = PixelSampler::new(4, 0, 0, 0);
+        let mut hits = 0;
+
+        for _ in 0..500 {
+            let origin = Tuple::point(0.0, 0.0, -30.0);
+            let target = Tuple::point(
+                sampler.next_f64() * 40.0 - 20.0,
+                sampler.next_f64() * 40.0 - 20.0,
+                sampler.next_f64() * 40.0 - 20.0,
+            );
+            let r = Ray::new(origin, (target - origin).normalize());
+
+            let expected = brute_force_intersect(&w, r);
+            hits += expected.len();
+            assert_eq!(expected, accelerated_intersect(&w, r));
+        }
+        assert!(hits > 0);
+    }
+
+    #[cfg(feature = "ray-counters")]
+    #[test]
+    fn accelerated_intersect_skips_objects_a_missing_ray_is_nowhere_near() {
+        let w = scattered_spheres(200);
+        let r = Ray::new(Tuple::point(0.0, 30.0, 0.0), Tuple::vector(0.0, 1.0, 0.0));
+        let counters = std::sync::Arc::new(ray_counters::RayCounters::default());
+
+        let xs = ray_counters::counting(Some(&counters), || w.intersect(r));
+
+        assert!(xs.intersections.iter().all(|i| i.t < 0.0));
+        // Only the plane, and maybe a few spheres sharing a leaf with nothing up there
+        assert!(counters.counts().intersection_tests < 10);
+    }
+
+    #[test]
+    fn first_hit_matches_hit_of_all_intersections() {
+        let rays = [
+            Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0)),
+            Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0)),
+            Ray::new(Tuple::point(0.0, 0.0, 0.75), Tuple::vector(0.0, 0.0, -1.0)),
+            Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0)),
+            Ray::new(Tuple::point(0.0, 0.0, 5.0), Tuple::vector(0.0, 0.0, 1.0)),
+        ];
+        for r in rays {
+            let w = World::default();
+            assert_eq!(w.intersect(r).hit(), w.first_hit(r));
+        }
+
+        let w = scattered_spheres(200);
+        let mut sampler = PixelSampler::new(5, 0, 0, 0);
+        for _ in 0..200 {
+            let origin = Tuple::point(0.0, 0.0, -30.0);
+            let target = Tuple::point(sampler.next_f64() * 40.0 - 20.0, sampler.next_f64() * 40.0 - 20.0, 0.0);
+            let r = Ray::new(origin, (target - origin).normalize());
+            assert_eq!(w.intersect(r).hit(), w.first_hit(r));
+        }
+    }
+
+    #[test]
+    fn streamed_intersections_are_the_collected_ones_an_object_at_a_time() {
+        let w = scattered_spheres(200);
+        let r = Ray::new(Tuple::point(0.0, 0.0, -30.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        let streamed: Vec<_> = w.intersections_iter(r).collect();
+        assert_eq!(w.intersect(r), Intersections::new(streamed.clone()));
+        // Each object's intersections come together, nearest first
+        for objects in streamed.chunk_by(|a, b| a.object.id() == b.object.id()) {
+            assert!(objects.windows(2).all(|pair| pair[0].t <= pair[1].t));
+            assert_eq!(objects.len(), streamed.iter().filter(|i| i.object.id() == objects[0].object.id()).count());
+        }
+    }
+
+    #[test]
+    fn color_from_hit_alone_matches_color_from_all_intersections() {
+        let mut w = scattered_spheres(50);
+        if let Some(Shape::Sphere(s)) = w.object_mut(1) {
+            s.material = Material::default().with_transparency(0.9).with_refractive_index(1.5);
+        }
+        let camera_at = Tuple::point(0.0, 0.0, -30.0);
+
+        for object in &w.objects()[..4] {
+            let r = Ray::new(camera_at, (object.transform() * Tuple::point(0.0, 0.0, 0.0) - camera_at).normalize());
+            assert!(w.first_hit(r).is_some());
+            assert_eq!(w.trace_shading(r).color, w.color_at(r));
+        }
+    }
+
+    #[test]
+    fn changing_objects_rebuilds_the_acceleration_structure() {
+        let mut w = World::default();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(4, w.intersect(r).intersections.len());
+
+        w.object_mut(0).unwrap().set_transform(Matrix::translation(10.0, 0.0, 0.0));
+        assert_eq!(2, w.intersect(r).intersections.len());
+
+        w.remove_object(1);
+        assert_eq!(0, w.intersect(r).intersections.len());
+
+        w.add_object(Sphere::default());
+        assert_eq!(2, w.intersect(r).intersections.len());
+        assert_eq!(brute_force_intersect(&w, r), accelerated_intersect(&w, r));
+    }
+
+    #[test]
+    fn static_world_is_unchanged_at_any_time() {
+        let w = World::default();
+
+        assert_eq!(w, w.at_time(0.0));
+        assert_eq!(w, w.at_time(0.7));
+    }
+
+    #[test]
+    fn world_at_time_bakes_animated_transforms() {
+        let mut w = World::default();
+        w.animate(1, Animated::keyframes(vec![(0.0, Matrix::identity()), (1.0, Matrix::translation(0.0, 4.0, 0.0))]));
+        let r = Ray::new(Tuple::point(0.0, 2.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        assert_eq!(0, w.intersect(r).intersections.len());
+        let later = w.at_time(0.5);
+        assert_fuzzy_eq!(Matrix::translation(0.0, 2.0, 0.0), later.objects()[1].transform());
+        assert_eq!(2, later.intersect(r).intersections.len());
+        assert_fuzzy_eq!(Matrix::scaling(0.5, 0.5, 0.5), w.objects()[1].transform());
+    }
+
+    #[test]
+    fn removing_an_object_moves_later_animations_down() {
+        let mut w = World::default();
+        w.add_object(Shape::Cube(Default::default()));
+        w.animate(0, Animated::fixed(Matrix::translation(1.0, 0.0, 0.0)));
+        w.animate(2, Animated::fixed(Matrix::translation(2.0, 0.0, 0.0)));
+
+        w.remove_object(0);
+
+        assert_eq!(vec![(1, Animated::fixed(Matrix::translation(2.0, 0.0, 0.0)))], w.animations);
+    }
+
+    #[test]
+    fn intersecting_into_a_buffer_matches_intersect() {
+        let w = World::default();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Intersections::new(vec![Intersection::new(1.0, w.objects()[1].clone())]);
+
+        w.intersect_into(r, &mut xs);
+
+        assert_eq!(4, xs.intersections.len());
+        assert_eq!(w.intersect(r), xs);
+    }
+
+    #[test]
+    fn intersect_into_reuses_the_buffer() {
+        let w = World::default();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Intersections::new(vec![]);
+        w.intersect_into(r, &mut xs);
+        let (buffer, capacity) = (xs.intersections.as_ptr(), xs.intersections.capacity());
+
+        for _ in 0..10 {
+            w.intersect_into(r, &mut xs);
+            assert_eq!(buffer, xs.intersections.as_ptr());
+            assert_eq!(capacity, xs.intersections.capacity());
+        }
+    }
+
+    #[test]
+    fn tracing_reuses_the_thread_scratch_buffers() {
+        let w = World::default();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let buffers = || SCRATCH.with(|pool| pool.borrow().iter().map(|xs| xs.intersections.as_ptr()).collect::<Vec<_>>());
+
+        let color = w.color_at(r);
+        let pool = buffers();
+        assert!(!pool.is_empty());
+
+        for _ in 0..10 {
+            assert_eq!(color, w.color_at(r));
+            assert_eq!(pool, buffers());
+        }
+    }
+
     #[test]
     fn shading_an_intersection() {
         let w = World::default();
         let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
-        let s = w.objects[0];
+        let s = w.objects()[0].clone();
         let i = Intersection::new(4.0, s);
-        let comp = i.as_computed(r);
+        let comp = i.as_computed(r, &Intersections::new(vec![i.clone()]));
 
-        let c = w.shade_hit(comp);
+        let c = w.shade_hit(comp, MAX_DEPTH);
         assert_fuzzy_eq!(Color::new(0.38066, 0.47583, 0.2855), c);
     }
 
     #[test]
     fn shading_an_intersection_from_inside() {
         let w = World {
-            light_source: Light::point(Tuple::point(0.0, 0.25, 0.0), Color::white()),
+            lights: vec![Light::point(Tuple::point(0.0, 0.25, 0.0), Color::white())],
             ..Default::default()
         };
         let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
-        let s = w.objects[1];
+        let s = w.objects()[1].clone();
         let i = Intersection::new(0.5, s);
 
-        let comp = i.as_computed(r);
-        let c = w.shade_hit(comp);
+        let comp = i.as_computed(r, &Intersections::new(vec![i.clone()]));
+        let c = w.shade_hit(comp, MAX_DEPTH);
 
         assert_fuzzy_eq!(Color::new(0.90498, 0.90498, 0.90498), c);
     }
@@ -164,7 +935,7 @@ mod tests {
     #[test]
     fn shade_hit_is_given_intersection_in_shadow() {
         let w = World {
-            light_source: Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white()),
+            lights: vec![Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white())],
             objects: vec![
                 SphereBuilder::default().build().unwrap().into(),
                 SphereBuilder::default()
@@ -173,12 +944,13 @@ mod tests {
                     .unwrap()
                     .into(),
             ],
+            ..Default::default()
         };
 
         let r = Ray::new(Tuple::point(0.0, 0.0, 5.0), Tuple::vector(0.0, 0.0, 1.0));
-        let i = Intersection::new(4.0, w.objects[1]);
-        let comp = i.as_computed(r);
-        let c = w.shade_hit(comp);
+        let i = Intersection::new(4.0, w.objects()[1].clone());
+        let comp = i.as_computed(r, &Intersections::new(vec![i.clone()]));
+        let c = w.shade_hit(comp, MAX_DEPTH);
         assert_fuzzy_eq!(Color::new(0.1, 0.1, 0.1), c);
     }
 
@@ -201,12 +973,12 @@ mod tests {
     #[test]
     fn color_with_intersection_behind_ray() {
         let mat = Material {
-            ambient: 1.0,
+            ambient: Color::grey(1.0),
             ..Default::default()
         };
         let shapes = vec![
             SphereBuilder::default()
-                .material(mat)
+                .material(mat.clone())
                 .build()
                 .unwrap()
                 .into(),
@@ -218,7 +990,7 @@ mod tests {
         ];
         let w = WorldBuilder::default().objects(shapes).build().unwrap();
 
-        let inner = w.objects[1];
+        let inner = &w.objects()[1];
 
         let r = Ray::new(Tuple::point(0.0, 0.0, 0.75), Tuple::vector(0.0, 0.0, -1.0));
         let c = w.color_at(r);
@@ -231,7 +1003,7 @@ mod tests {
         let w = World::default();
         let p = Tuple::point(0.0, 10.0, 0.0);
 
-        assert!(!w.is_shadowed(p));
+        assert!(!w.is_occluded(w.lights[0].position, p, 0.0));
     }
 
     #[test]
@@ -239,7 +1011,7 @@ mod tests {
         let w = World::default();
         let p = Tuple::point(10.0, -10.0, 10.0);
 
-        assert!(w.is_shadowed(p));
+        assert!(w.is_occluded(w.lights[0].position, p, 0.0));
     }
 
     #[test]
@@ -247,7 +1019,7 @@ mod tests {
         let w = World::default();
         let p = Tuple::point(-20.0, 20.0, -20.0);
 
-        assert!(!w.is_shadowed(p));
+        assert!(!w.is_occluded(w.lights[0].position, p, 0.0));
     }
 
     #[test]
@@ -255,6 +1027,967 @@ mod tests {
         let w = World::default();
         let p = Tuple::point(-2.0, 2.0, -2.0);
 
-        assert!(!w.is_shadowed(p));
+        assert!(!w.is_occluded(w.lights[0].position, p, 0.0));
+    }
+
+    #[test]
+    fn no_shadow_when_object_between_point_and_light_casts_none() {
+        let mut w = World::default();
+        *w.object_mut(0).unwrap() = SphereBuilder::default()
+            .cast_shadows(false)
+            .build()
+            .unwrap()
+            .into();
+        *w.object_mut(1).unwrap() = SphereBuilder::default()
+            .transform(Matrix::scaling(0.5, 0.5, 0.5))
+            .cast_shadows(false)
+            .build()
+            .unwrap()
+            .into();
+        let p = Tuple::point(10.0, -10.0, 10.0);
+
+        assert!(!w.is_occluded(w.lights[0].position, p, 0.0));
+    }
+
+    #[test]
+    fn moving_occluder_shadows_where_the_hit_time_puts_it() {
+        let light = Light::point(Tuple::point(0.0, 10.0, 0.0), Color::white());
+        let occluder: Shape = CubeBuilder::default()
+            .transform(Matrix::translation(0.0, 5.0, 0.0) * Matrix::scaling(0.5, 0.5, 0.5))
+            .transform_at_t1(Matrix::translation(10.0, 5.0, 0.0) * Matrix::scaling(0.5, 0.5, 0.5))
+            .build()
+            .unwrap()
+            .into();
+        let w = World::new(vec![PlaneBuilder::default().build().unwrap().into(), occluder], light);
+        let p = Tuple::point(0.0, 0.0001, 0.0);
+
+        assert!(w.is_occluded(light.position, p, 0.0));
+        assert!(!w.is_occluded(light.position, p, 1.0));
+        assert_fuzzy_eq!(0.0, w.transmittance(light.position, p, 0.0));
+        assert_fuzzy_eq!(1.0, light.intensity_at(p, 1.0, &w));
+
+        let r = Ray::new(Tuple::point(0.0, 1.0, -5.0), Tuple::vector(0.0, -1.0, 5.0).normalize());
+        let shadowed = w.color_at(r.at_time(0.0));
+        let lit = w.color_at(r.at_time(1.0));
+        assert_fuzzy_eq!(Color::new(0.1, 0.1, 0.1), shadowed);
+        assert!(lit.max_difference(shadowed) > 0.5);
+    }
+
+    #[test]
+    fn early_exit_occlusion_agrees_with_transmittance() {
+        let cases = [
+            (World::default(), Tuple::point(0.0, 10.0, 0.0)),
+            (World::default(), Tuple::point(10.0, -10.0, 10.0)),
+            (World::default(), Tuple::point(-20.0, 20.0, -20.0)),
+            (World::default(), Tuple::point(-2.0, 2.0, -2.0)),
+            (World::new(vec![occluder(0.0, 0.5), occluder(3.0, 0.5)], Light::default()), Tuple::point(0.0, 0.0, 5.0)),
+            (World::new(vec![occluder(0.0, 1.0), occluder(3.0, 0.0)], Light::default()), Tuple::point(0.0, 0.0, 5.0)),
+        ];
+
+        for (w, p) in cases {
+            let light = w.lights[0].position;
+            assert_eq!(w.transmittance(light, p, 0.0) == 0.0, w.is_occluded(light, p, 0.0), "{:?}", p);
+        }
+    }
+
+    #[cfg(feature = "ray-counters")]
+    #[test]
+    fn occlusion_stops_at_first_blocking_object() {
+        let light = Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white());
+        let objects = (0..10).map(|i| occluder(i as f64 * 3.0, 0.0)).collect();
+        let w = World::new(objects, light);
+        let counters = std::sync::Arc::new(ray_counters::RayCounters::default());
+
+        let occluded = ray_counters::counting(Some(&counters), || w.is_occluded(light.position, Tuple::point(0.0, 0.0, 40.0), 0.0));
+
+        assert!(occluded);
+        assert_eq!(1, counters.counts().intersection_tests);
+    }
+
+    fn occluder(z: f64, transparency: f64) -> Shape {
+        SphereBuilder::default()
+            .transform(Matrix::translation(0.0, 0.0, z))
+            .material(Material::default().with_transparency(transparency))
+            .build()
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn fully_transparent_occluder_casts_no_shadow() {
+        let light = Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white());
+        let w = World::new(vec![occluder(0.0, 1.0)], light);
+
+        assert_fuzzy_eq!(1.0, light.intensity_at(Tuple::point(0.0, 0.0, 5.0), 0.0, &w));
+    }
+
+    #[test]
+    fn half_transparent_occluder_halves_diffuse_and_specular() {
+        let light = Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white());
+        let w = World::new(vec![occluder(0.0, 0.5)], light);
+        let point = Tuple::point(0.0, 0.0, 5.0);
+
+        let intensity = light.intensity_at(point, 0.0, &w);
+        assert_fuzzy_eq!(0.5, intensity);
+
+        let m = Material::default();
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let object = &w.objects()[0];
+        let ambient = m.lighting(object, point, light, eyev, normalv, 0.0);
+        let lit = m.lighting(object, point, light, eyev, normalv, 1.0);
+        assert_fuzzy_eq!(
+            ambient + (lit - ambient) * 0.5,
+            m.lighting(object, point, light, eyev, normalv, intensity)
+        );
+    }
+
+    #[test]
+    fn stacked_half_transparent_occluders_quarter_light() {
+        let light = Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white());
+        let w = World::new(vec![occluder(0.0, 0.5), occluder(3.0, 0.5)], light);
+
+        assert_fuzzy_eq!(0.25, light.intensity_at(Tuple::point(0.0, 0.0, 5.0), 0.0, &w));
+        // Only the nearer sphere is between these and the light
+        assert_fuzzy_eq!(0.5, light.intensity_at(Tuple::point(0.0, 0.0, 1.5), 0.0, &w));
+    }
+
+    #[test]
+    fn transparent_occluder_that_casts_no_shadow_is_skipped() {
+        let light = Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white());
+        let skipped: Shape = SphereBuilder::default()
+            .transform(Matrix::translation(0.0, 0.0, 3.0))
+            .cast_shadows(false)
+            .build()
+            .unwrap()
+            .into();
+        let w = World::new(vec![occluder(0.0, 0.5), skipped], light);
+
+        assert_fuzzy_eq!(0.5, light.intensity_at(Tuple::point(0.0, 0.0, 5.0), 0.0, &w));
+    }
+
+    #[test]
+    fn excluded_light_only_lights_other_objects() {
+        let light_a = Light::point(Tuple::point(-10.0, 10.0, -10.0), Color::white());
+        let light_b = Light::point(Tuple::point(10.0, 10.0, -10.0), Color::new(0.5, 0.5, 0.5));
+        let linked: Shape = SphereBuilder::default()
+            .transform(Matrix::translation(-2.0, 0.0, 0.0))
+            .excluded_lights(vec![light_b.id()])
+            .build()
+            .unwrap()
+            .into();
+        let unlinked: Shape = SphereBuilder::default()
+            .transform(Matrix::translation(2.0, 0.0, 0.0))
+            .build()
+            .unwrap()
+            .into();
+        let objects = vec![linked.clone(), unlinked.clone()];
+        let both = World::with_lights(objects.clone(), vec![light_a, light_b]);
+        let only_a = World::new(objects.clone(), light_a);
+        let only_b = World::new(objects, light_b);
+
+        let shade = |w: &World, x: f64, object: &Shape| {
+            let r = Ray::new(Tuple::point(x, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+            let i = Intersection::new(4.0, object.clone());
+            w.shade_hit(i.as_computed(r, &Intersections::new(vec![i.clone()])), MAX_DEPTH)
+        };
+
+        assert_fuzzy_eq!(shade(&only_a, -2.0, &linked), shade(&both, -2.0, &linked));
+        assert_fuzzy_eq!(
+            shade(&only_a, 2.0, &unlinked) + shade(&only_b, 2.0, &unlinked),
+            shade(&both, 2.0, &unlinked)
+        );
+    }
+
+    #[test]
+    fn excluded_light_casts_no_shadow_ray() {
+        let light = Light::point(Tuple::point(-10.0, 10.0, -10.0), Color::white());
+        let s: Shape = SphereBuilder::default()
+            .excluded_lights(vec![light.id()])
+            .build()
+            .unwrap()
+            .into();
+        let mut w = World::new(vec![s.clone()], light);
+        let (hooks, shadow_rays) = counting_rays(RayKind::Shadow);
+        w.set_debug_hooks(hooks);
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        let i = Intersection::new(4.0, s);
+        let c = w.shade_hit(i.as_computed(r, &Intersections::new(vec![i.clone()])), MAX_DEPTH);
+        assert_fuzzy_eq!(Color::black(), c);
+        assert_eq!(0, shadow_rays.load(std::sync::atomic::Ordering::Relaxed));
+    }
+
+    #[test]
+    fn excluded_light_is_left_out_for_any_kind_of_shape() {
+        let light_a = Light::point(Tuple::point(-10.0, 10.0, -10.0), Color::white());
+        let light_b = Light::point(Tuple::point(10.0, 10.0, -10.0), Color::new(0.5, 0.5, 0.5));
+        let cube: Shape = CubeBuilder::default()
+            .transform(Matrix::translation(-2.0, 0.0, 0.0))
+            .excluded_lights(vec![light_b.id()])
+            .build()
+            .unwrap()
+            .into();
+        let group: Shape = GroupBuilder::default()
+            .transform(Matrix::translation(2.0, 0.0, 0.0))
+            .children(vec![Sphere::default().into()])
+            .excluded_lights(vec![light_b.id()])
+            .build()
+            .unwrap()
+            .into();
+        let objects = vec![cube, group];
+        let both = World::with_lights(objects.clone(), vec![light_a, light_b]);
+        let only_a = World::new(objects, light_a);
+
+        for x in [-2.0, 2.0] {
+            let r = Ray::new(Tuple::point(x, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+            assert_ne!(Color::black(), only_a.color_at(r));
+            assert_fuzzy_eq!(only_a.color_at(r), both.color_at(r));
+        }
+    }
+
+    fn fog(density: f64, mode: FogMode) -> Fog {
+        Fog { color: Color::new(0.5, 0.6, 0.7), density, mode, max_distance: 100.0 }
+    }
+
+    #[test]
+    fn fog_without_density_changes_nothing() {
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let missed = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0));
+
+        for mode in [FogMode::Linear { start: 0.0, end: 10.0 }, FogMode::Exponential] {
+            let w = World { fog: Some(fog(0.0, mode)), ..World::default() };
+            assert_eq!(World::default().color_at(r), w.color_at(r));
+            assert_eq!(World::default().color_at(missed), w.color_at(missed));
+        }
+    }
+
+    #[test]
+    fn hit_at_end_of_linear_fog_is_fog_color() {
+        let linear = fog(1.0, FogMode::Linear { start: 0.0, end: 4.0 });
+        let w = World { fog: Some(linear), ..World::default() };
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        assert_fuzzy_eq!(linear.color, w.color_at(r));
+        assert_fuzzy_eq!(linear.color, w.color_at(Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0))));
+    }
+
+    #[test]
+    fn closer_hit_blends_in_proportion_to_distance() {
+        let linear = fog(1.0, FogMode::Linear { start: 2.0, end: 10.0 });
+        let w = World { fog: Some(linear), ..World::default() };
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let clear = World::default().color_at(r);
+
+        // The hit is 4 away, a quarter of the way from start to end
+        assert_fuzzy_eq!(clear * 0.75 + linear.color * 0.25, w.color_at(r));
+        assert_fuzzy_eq!(0.0, linear.amount(1.0));
+        assert_fuzzy_eq!(1.0 - (-0.5f64).exp(), fog(0.1, FogMode::Exponential).amount(5.0));
+    }
+
+    #[test]
+    fn light_only_reaches_points_within_its_range() {
+        let light = Light::point(Tuple::point(0.0, 0.0, -5.0), Color::white());
+        let ranged = light.with_range(5.0);
+        // A wall facing the light, the given distance away from it, and how many shadow rays
+        // shading it took
+        let shade = |light: Light, distance: f64| {
+            let wall = PlaneBuilder::default()
+                .transform(Matrix::translation(0.0, 0.0, distance - 5.0) * Matrix::rotation_x(std::f64::consts::PI / 2.0))
+                .build()
+                .unwrap();
+            let mut w = World::new(vec![wall.into()], light);
+            let (hooks, shadow_rays) = counting_rays(RayKind::Shadow);
+            w.set_debug_hooks(hooks);
+            let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+            let xs = w.intersect(r);
+            let color = w.shade_hit(xs.hit().unwrap().as_computed(r, &xs), MAX_DEPTH);
+            (color, shadow_rays.load(std::sync::atomic::Ordering::Relaxed))
+        };
+
+        assert_fuzzy_eq!(shade(light, 4.0).0, shade(ranged, 4.0).0);
+        assert_fuzzy_eq!(Color::black(), shade(ranged, 6.0).0);
+        assert_ne!(Color::black(), shade(light, 6.0).0);
+
+        assert_eq!(0, shade(ranged, 6.0).1);
+        assert_eq!(1, shade(ranged, 4.0).1);
+    }
+
+    #[test]
+    fn debug_hooks_see_every_ray_and_hit_of_a_render() {
+        use std::sync::{Arc, Mutex};
+
+        let rays = Arc::new(Mutex::new(vec![]));
+        let hits = Arc::new(Mutex::new(vec![]));
+        let shades = Arc::new(Mutex::new(0));
+        let mut w = World::default();
+        let (r, h, c) = (rays.clone(), hits.clone(), shades.clone());
+        w.set_debug_hooks(
+            DebugHooks::default()
+                .on_ray(move |ray, kind| r.lock().unwrap().push((*ray, kind)))
+                .on_hit(move |comp| h.lock().unwrap().push(comp.intersection.clone()))
+                .on_shade(move |_| *c.lock().unwrap() += 1),
+        );
+        let mut camera = Camera::new(3, 3, std::f64::consts::PI / 2.0);
+        camera.set_transform(Matrix::view_transform(
+            Tuple::point(0.0, 0.0, -5.0),
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0),
+        )).unwrap();
+
+        camera.render(&w);
+
+        let rays = rays.lock().unwrap();
+        let primary: Vec<Ray> = rays.iter().filter(|(_, kind)| *kind == RayKind::Primary).map(|(ray, _)| *ray).collect();
+        let mut expected: Vec<_> = primary.iter().filter_map(|&ray| w.intersect(ray).hit()).collect();
+        let mut hits = hits.lock().unwrap().clone();
+        expected.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        hits.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+
+        assert_eq!(9, primary.len());
+        assert!(!hits.is_empty());
+        assert_eq!(expected, hits);
+        assert_eq!(hits.len(), rays.iter().filter(|(_, kind)| *kind == RayKind::Shadow).count());
+        assert_eq!(9 + hits.len(), rays.len());
+        assert_eq!(9, *shades.lock().unwrap());
+    }
+
+    #[test]
+    fn debug_hooks_see_reflected_rays_until_cleared() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let reflected = Arc::new(AtomicUsize::new(0));
+        let counter = reflected.clone();
+        let mut w = World::default();
+        w.add_object(reflective_floor());
+        w.set_debug_hooks(DebugHooks::default().on_ray(move |_, kind| {
+            if kind == RayKind::Reflected {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        }));
+        let r = Ray::new(Tuple::point(0.0, 0.0, -3.0), Tuple::vector(0.0, -2f64.sqrt() / 2.0, 2f64.sqrt() / 2.0));
+
+        w.color_at(r);
+        assert_eq!(1, reflected.load(Ordering::Relaxed));
+
+        w.clear_debug_hooks();
+        w.color_at(r);
+        assert_eq!(1, reflected.load(Ordering::Relaxed));
+    }
+
+    fn sky() -> World {
+        WorldBuilder::default()
+            .background(Background::VerticalGradient {
+                zenith: Color::new(0.2, 0.4, 1.0),
+                horizon: Color::white(),
+            })
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn missed_ray_straight_up_sees_zenith() {
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 2.0, 0.0));
+
+        assert_fuzzy_eq!(Color::new(0.2, 0.4, 1.0), sky().color_at(r));
+    }
+
+    #[test]
+    fn missed_ray_straight_ahead_sees_horizon() {
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(1.0, 0.0, 0.0));
+
+        assert_fuzzy_eq!(Color::white(), sky().color_at(r));
+    }
+
+    #[test]
+    fn missed_ray_halfway_up_blends_gradient() {
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(3.0_f64.sqrt(), 1.0, 0.0));
+
+        assert_fuzzy_eq!(Color::new(0.6, 0.7, 1.0), sky().color_at(r));
+    }
+
+    #[test]
+    fn default_world_background_is_black() {
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0));
+
+        assert_eq!(Background::Solid(Color::black()), World::default().background);
+        assert_fuzzy_eq!(Color::black(), World::default().color_at(r));
+        let built = WorldBuilder::default().build().unwrap();
+        assert_fuzzy_eq!(Color::black(), built.color_at(r));
+    }
+
+    fn with_material(shape: &Shape, material: Material) -> Shape {
+        match shape {
+            Shape::Sphere(s) => Sphere {
+                material,
+                ..s.clone()
+            }
+            .into(),
+            s => panic!("expected a sphere, got {:?}", s),
+        }
+    }
+
+    fn computed(i: &Intersection, r: Ray) -> ComputedIntersection {
+        i.as_computed(r, &Intersections::new(vec![i.clone()]))
+    }
+
+    #[test]
+    fn reflected_color_for_nonreflective_material() {
+        let mut w = World::default();
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        *w.object_mut(1).unwrap() = with_material(
+            &w.objects()[1],
+            Material {
+                ambient: Color::grey(1.0),
+                ..w.objects()[1].material()
+            },
+        );
+        let i = Intersection::new(1.0, w.objects()[1].clone());
+
+        let c = w.reflected_color(&computed(&i, r), MAX_DEPTH);
+        assert_fuzzy_eq!(Color::black(), c);
+    }
+
+    fn reflective_floor() -> Shape {
+        PlaneBuilder::default()
+            .material(Material::default().with_reflective(0.5))
+            .transform(Matrix::translation(0.0, -1.0, 0.0))
+            .build()
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn reflected_color_for_reflective_material() {
+        let mut w = World::default();
+        let shape = reflective_floor();
+        w.add_object(shape.clone());
+        let sqrt_2_2 = 2.0_f64.sqrt() / 2.0;
+        let r = Ray::new(Tuple::point(0.0, 0.0, -3.0), Tuple::vector(0.0, -sqrt_2_2, sqrt_2_2));
+        let i = Intersection::new(2.0_f64.sqrt(), shape);
+
+        let c = w.reflected_color(&computed(&i, r), MAX_DEPTH);
+        assert_fuzzy_eq!(Color::new(0.19033, 0.23791, 0.14274), c);
+    }
+
+    #[test]
+    fn shade_hit_with_reflective_material() {
+        let mut w = World::default();
+        let shape = reflective_floor();
+        w.add_object(shape.clone());
+        let sqrt_2_2 = 2.0_f64.sqrt() / 2.0;
+        let r = Ray::new(Tuple::point(0.0, 0.0, -3.0), Tuple::vector(0.0, -sqrt_2_2, sqrt_2_2));
+        let i = Intersection::new(2.0_f64.sqrt(), shape);
+
+        let c = w.shade_hit(computed(&i, r), MAX_DEPTH);
+        assert_fuzzy_eq!(Color::new(0.87676, 0.92434, 0.82917), c);
+    }
+
+    #[test]
+    fn color_at_with_mutually_reflective_surfaces_terminates() {
+        let mirror = |y: f64| -> Shape {
+            PlaneBuilder::default()
+                .material(Material::default().with_reflective(1.0))
+                .transform(Matrix::translation(0.0, y, 0.0))
+                .build()
+                .unwrap()
+                .into()
+        };
+        let light = Light::point(Tuple::point(0.0, 0.0, 0.0), Color::white());
+        let w = World::new(vec![mirror(-1.0), mirror(1.0)], light);
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 1.0, 0.0));
+
+        // Each bounce adds the surface color, so the result is finite once the depth runs out
+        let c = w.color_at(r);
+        assert_fuzzy_eq!(c, c.clamp(0.0, 100.0));
+    }
+
+    fn mirrors_facing_each_other() -> World {
+        let mirror = |y: f64| -> Shape {
+            PlaneBuilder::default()
+                .material(Material::default().with_reflective(0.5))
+                .transform(Matrix::translation(0.0, y, 0.0))
+                .build()
+                .unwrap()
+                .into()
+        };
+        let light = Light::point(Tuple::point(0.0, 0.0, 0.0), Color::white());
+
+        World::new(vec![mirror(-1.0), mirror(1.0)], light)
+    }
+
+    #[test]
+    fn default_max_recursion_depth() {
+        assert_eq!(MAX_DEPTH, World::default().max_recursion_depth);
+        assert_eq!(5, WorldBuilder::default().build().unwrap().max_recursion_depth);
+    }
+
+    #[test]
+    fn depth_zero_renders_only_local_shading() {
+        let r = Ray::new(Tuple::point(0.0, 0.0, -3.0), Tuple::vector(0.0, -1.0, 1.0).normalize());
+        let mut reflective = World::default();
+        reflective.add_object(reflective_floor());
+        reflective.max_recursion_depth = 0;
+        let mut matte = World::default();
+        matte.add_object(
+            PlaneBuilder::default()
+                .transform(Matrix::translation(0.0, -1.0, 0.0))
+                .build()
+                .unwrap(),
+        );
+
+        assert_fuzzy_eq!(matte.color_at(r), reflective.color_at(r));
+    }
+
+    #[test]
+    fn mutually_reflective_surfaces_terminate_at_any_depth() {
+        let mut w = mirrors_facing_each_other();
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 1.0, 0.0));
+
+        for depth in [0, 1, 5, 50] {
+            w.max_recursion_depth = depth;
+            let c = w.color_at(r);
+            assert_fuzzy_eq!(c, c.clamp(0.0, 100.0));
+        }
+    }
+
+    #[test]
+    fn deeper_recursion_brightens_double_mirror() {
+        let mut w = mirrors_facing_each_other();
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 1.0, 0.0));
+
+        let mut previous = Color::black();
+        for depth in 1..=5 {
+            w.max_recursion_depth = depth;
+            let c = w.color_at(r);
+            assert_fuzzy_eq!(c - previous, (c - previous).clamp(0.0, f64::INFINITY));
+            assert!(c.fuzzy_ne(previous));
+            previous = c;
+        }
+    }
+
+    #[test]
+    fn reflected_color_at_maximum_recursive_depth() {
+        let mut w = World::default();
+        let shape = reflective_floor();
+        w.add_object(shape.clone());
+        let sqrt_2_2 = 2.0_f64.sqrt() / 2.0;
+        let r = Ray::new(Tuple::point(0.0, 0.0, -3.0), Tuple::vector(0.0, -sqrt_2_2, sqrt_2_2));
+        let i = Intersection::new(2.0_f64.sqrt(), shape);
+
+        let c = w.reflected_color(&computed(&i, r), 0);
+        assert_fuzzy_eq!(Color::black(), c);
+    }
+
+    #[test]
+    fn refracted_color_with_opaque_surface() {
+        let w = World::default();
+        let shape = w.objects()[0].clone();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = Intersections::new(vec![
+            Intersection::new(4.0, shape.clone()),
+            Intersection::new(6.0, shape),
+        ]);
+
+        let comp = xs.intersections[0].as_computed(r, &xs);
+        assert_fuzzy_eq!(Color::black(), w.refracted_color(&comp, MAX_DEPTH));
+    }
+
+    fn glass_material(w: &World, index: usize) -> Material {
+        w.objects()[index].material().with_transparency(1.0).with_refractive_index(1.5)
+    }
+
+    #[test]
+    fn refracted_color_at_maximum_recursive_depth() {
+        let mut w = World::default();
+        *w.object_mut(0).unwrap() = with_material(&w.objects()[0], glass_material(&w, 0));
+        let shape = w.objects()[0].clone();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = Intersections::new(vec![
+            Intersection::new(4.0, shape.clone()),
+            Intersection::new(6.0, shape),
+        ]);
+
+        let comp = xs.intersections[0].as_computed(r, &xs);
+        assert_fuzzy_eq!(Color::black(), w.refracted_color(&comp, 0));
+    }
+
+    #[test]
+    fn refracted_color_under_total_internal_reflection() {
+        let mut w = World::default();
+        *w.object_mut(0).unwrap() = with_material(&w.objects()[0], glass_material(&w, 0));
+        let shape = w.objects()[0].clone();
+        let sqrt_2_2 = 2.0_f64.sqrt() / 2.0;
+        let r = Ray::new(Tuple::point(0.0, 0.0, sqrt_2_2), Tuple::vector(0.0, 1.0, 0.0));
+        let xs = Intersections::new(vec![
+            Intersection::new(-sqrt_2_2, shape.clone()),
+            Intersection::new(sqrt_2_2, shape),
+        ]);
+
+        // Inside the sphere, so the hit is the second intersection
+        let comp = xs.intersections[1].as_computed(r, &xs);
+        assert_fuzzy_eq!(Color::black(), w.refracted_color(&comp, MAX_DEPTH));
+    }
+
+    #[test]
+    fn refracted_color_with_refracted_ray() {
+        let mut w = World::default();
+        *w.object_mut(0).unwrap() = with_material(
+            &w.objects()[0],
+            Material {
+                ambient: Color::grey(1.0),
+                pattern: Some(TestPattern::default().into()),
+                ..w.objects()[0].material()
+            },
+        );
+        *w.object_mut(1).unwrap() = with_material(&w.objects()[1], glass_material(&w, 1));
+        let a = w.objects()[0].clone();
+        let b = w.objects()[1].clone();
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.1), Tuple::vector(0.0, 1.0, 0.0));
+        let xs = Intersections::new(vec![
+            Intersection::new(-0.9899, a.clone()),
+            Intersection::new(-0.4899, b.clone()),
+            Intersection::new(0.4899, b),
+            Intersection::new(0.9899, a),
+        ]);
+
+        let comp = xs.intersections[2].as_computed(r, &xs);
+        let c = w.refracted_color(&comp, MAX_DEPTH);
+        assert_fuzzy_eq!(Color::new(0.0, 0.99888, 0.04722), c);
+    }
+
+    #[test]
+    fn shade_hit_with_transparent_material() {
+        let mut w = World::default();
+        let floor: Shape = PlaneBuilder::default()
+            .transform(Matrix::translation(0.0, -1.0, 0.0))
+            .material(Material::default().with_transparency(0.5).with_refractive_index(1.5))
+            .build()
+            .unwrap()
+            .into();
+        let ball: Shape = SphereBuilder::default()
+            .transform(Matrix::translation(0.0, -3.5, -0.5))
+            .material(Material {
+                color: Color::new(1.0, 0.0, 0.0),
+                ambient: Color::grey(0.5),
+                ..Default::default()
+            })
+            .build()
+            .unwrap()
+            .into();
+        w.add_object(floor.clone());
+        w.add_object(ball);
+        let sqrt_2_2 = 2.0_f64.sqrt() / 2.0;
+        let r = Ray::new(Tuple::point(0.0, 0.0, -3.0), Tuple::vector(0.0, -sqrt_2_2, sqrt_2_2));
+        let i = Intersection::new(2.0_f64.sqrt(), floor);
+
+        // The book has the ball fully shadowed and gets 0.93642 for red, but the half
+        // transparent floor lets half the light through to it here
+        let c = w.shade_hit(computed(&i, r), MAX_DEPTH);
+        assert_fuzzy_eq!(Color::new(1.12547, 0.68643, 0.68643), c);
+    }
+
+    #[test]
+    fn rendering_striped_floor_shows_stripes() {
+        let stripes = StripePatternBuilder::default()
+            .color_a(Color::white())
+            .color_b(Color::black())
+            .build()
+            .unwrap();
+        let floor: Shape = PlaneBuilder::default()
+            .material(Material {
+                ambient: Color::grey(1.0),
+                diffuse: Color::grey(0.0),
+                pattern: Some(stripes.into()),
+                surface: Surface::Lambertian,
+                ..Default::default()
+            })
+            .build()
+            .unwrap()
+            .into();
+        let w = World::new(vec![floor], Light::default());
+        // Looking straight down, one pixel covers one unit of floor
+        let mut camera = Camera::new(10, 1, std::f64::consts::PI / 2.0);
+        camera.set_transform(Matrix::view_transform(
+            Tuple::point(0.0, 5.0, 0.0),
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(0.0, 0.0, 1.0),
+        )).unwrap();
+
+        let image = camera.render(&w);
+        for x in 0..9 {
+            let (a, b) = (image.pixel_at(x, 0), image.pixel_at(x + 1, 0));
+            assert!(a.fuzzy_eq(Color::white()) || a.fuzzy_eq(Color::black()));
+            assert!(a.fuzzy_ne(b));
+        }
+    }
+
+    #[test]
+    fn checkered_floor_has_no_speckles_at_grazing_angle() {
+        let checkers = CheckerPattern3DBuilder::default()
+            .color_a(Color::white())
+            .color_b(Color::black())
+            .build()
+            .unwrap();
+        // Moving the floor off the origin means the hit points no longer land exactly on y = 0
+        let floor: Shape = PlaneBuilder::default()
+            .transform(Matrix::translation(0.0, 0.1, 0.0) * Matrix::rotation_y(0.3))
+            .material(Material {
+                ambient: Color::grey(1.0),
+                diffuse: Color::grey(0.0),
+                pattern: Some(checkers.into()),
+                surface: Surface::Lambertian,
+                ..Default::default()
+            })
+            .build()
+            .unwrap()
+            .into();
+        let w = World::new(vec![floor], Light::default());
+        let mut camera = Camera::new(50, 50, std::f64::consts::PI / 3.0);
+        camera.set_transform(Matrix::view_transform(
+            Tuple::point(0.0, 1.0, -5.0),
+            Tuple::point(0.0, 0.1, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0),
+        )).unwrap();
+
+        let image = camera.render(&w);
+        // The squares close to the camera are several pixels wide, so a lone pixel in a row is
+        // a hit point that flipped sides of a boundary
+        for y in 40..50 {
+            let runs = (0..50).map(|x| image.pixel_at(x, y)).collect::<Vec<_>>();
+            let runs = runs.chunk_by(|a, b| a.fuzzy_eq(*b)).collect::<Vec<_>>();
+            for run in &runs[1..runs.len() - 1] {
+                assert!(run.len() >= 2, "single pixel flip in row {}", y);
+            }
+        }
+    }
+
+    // A ray straight through the middle of a clear sphere that doesn't bend light, with only
+    // the white background behind it
+    fn refracted_through_absorbing_sphere(radius: f64, attenuation: Color) -> Color {
+        let sphere: Shape = SphereBuilder::default()
+            .transform(Matrix::scaling(radius, radius, radius))
+            .material(Material {
+                ambient: Color::grey(0.0),
+                diffuse: Color::grey(0.0),
+                attenuation,
+                surface: Surface::Lambertian.with_transparency(1.0),
+                ..Default::default()
+            })
+            .build()
+            .unwrap()
+            .into();
+        let mut w = World::with_lights(vec![sphere.clone()], vec![]);
+        w.background = Background::Solid(Color::white());
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = w.intersect(r);
+
+        let comp = xs.intersections[0].as_computed(r, &xs);
+        w.refracted_color(&comp, MAX_DEPTH)
+    }
+
+    #[test]
+    fn refraction_without_attenuation_is_unchanged() {
+        assert_fuzzy_eq!(Color::white(), refracted_through_absorbing_sphere(1.0, Color::black()));
+    }
+
+    #[test]
+    fn refraction_attenuates_by_distance_inside() {
+        let attenuation = Color::new(0.1, 0.2, 0.3);
+        let thin = refracted_through_absorbing_sphere(1.0, attenuation);
+        let thick = refracted_through_absorbing_sphere(2.0, attenuation);
+
+        assert_fuzzy_eq!(Color::new((-0.2_f64).exp(), (-0.4_f64).exp(), (-0.6_f64).exp()), thin);
+        assert_fuzzy_eq!(thin * thin, thick);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn is_shadowed_shim_follows_light_intensity() {
+        let w = World::default();
+
+        assert!(!w.is_shadowed(Tuple::point(0.0, 10.0, 0.0)));
+        assert!(w.is_shadowed(Tuple::point(10.0, -10.0, 10.0)));
+    }
+
+    #[test]
+    fn shading_behind_object_without_shadow_matches_unshadowed_color() {
+        let light = Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white());
+        let lit: Shape = SphereBuilder::default()
+            .transform(Matrix::translation(0.0, 0.0, 10.0))
+            .build()
+            .unwrap()
+            .into();
+        let blocker: Shape = SphereBuilder::default()
+            .cast_shadows(false)
+            .build()
+            .unwrap()
+            .into();
+        let unshadowed = World::new(vec![lit.clone()], light);
+        let w = World::new(vec![blocker.clone(), lit], light);
+
+        let r = Ray::new(Tuple::point(0.0, 0.0, 5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_fuzzy_eq!(unshadowed.color_at(r), w.color_at(r));
+
+        // The object is still hit by primary rays
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(blocker, w.intersect(r).hit().unwrap().object);
+    }
+
+    #[test]
+    fn object_index_finds_shapes_inside_groups() {
+        let mut w = World::default();
+        let mut g = crate::group::Group::default();
+        g.add_child(Sphere::default());
+        w.add_object(g);
+        w.object_mut(2).unwrap().set_transform(Matrix::translation(0.0, 5.0, 0.0));
+        let r = Ray::new(Tuple::point(0.0, 5.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+
+        let hit = w.intersect(r).hit().unwrap();
+        assert_eq!(Some(2), w.object_index(&hit.object));
+        assert_eq!(Some(0), w.object_index(&w.objects()[0].clone()));
+        assert_eq!(None, w.object_index(&Sphere::default().into()));
+    }
+
+    #[test]
+    fn tracing_a_miss_records_only_the_background() {
+        let w = World::default();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0));
+        let trace = w.trace_shading(r);
+
+        assert!(trace.intersections.intersections.is_empty());
+        assert_eq!(None, trace.hit);
+        assert_eq!(None, trace.comps);
+        assert!(trace.light_intensities.is_empty());
+        assert_eq!(w.color_at(r), trace.color);
+    }
+
+    #[test]
+    fn tracing_records_shadowed_and_excluded_lights() {
+        let mut w = World::default();
+        let shadowed = Light::point(Tuple::point(0.0, 0.0, 10.0), Color::white());
+        let excluded = Light::point(Tuple::point(0.0, 0.0, -10.0), Color::white());
+        w.lights = vec![w.lights[0], shadowed, excluded];
+        if let Some(Shape::Sphere(s)) = w.object_mut(0) {
+            s.excluded_lights = vec![excluded.id()];
+        }
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let trace = w.trace_shading(r);
+
+        assert_eq!(vec![Some(1.0), Some(0.0), None], trace.light_intensities);
+        assert_eq!(w.color_at(r), trace.color);
+    }
+
+    #[test]
+    fn changing_an_object_through_object_mut() {
+        let mut w = World::default();
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.75), Tuple::vector(0.0, 0.0, -1.0));
+        for idx in 0..2 {
+            if let Some(Shape::Sphere(s)) = w.object_mut(idx) {
+                s.material.ambient = Color::white();
+            }
+        }
+
+        let inner = w.objects()[1].material();
+        assert_fuzzy_eq!(inner.color, w.color_at(r));
+    }
+
+    #[test]
+    fn adding_objects_gives_their_index() {
+        let mut w = World::default();
+
+        assert_eq!(2, w.add_object(Sphere::default()));
+        assert_eq!(3, w.add_object(PlaneBuilder::default().build().unwrap()));
+        assert!(w.object_mut(3).is_some());
+        assert!(w.object_mut(4).is_none());
+    }
+
+    #[test]
+    fn removing_an_object_shifts_the_ones_after_it() {
+        let mut w = World::default();
+        let third = w.add_object(Sphere::default());
+        let (first, second, last) = (w.objects()[0].id(), w.objects()[1].id(), w.objects()[third].id());
+
+        assert_eq!(Some(second), w.remove_object(1).map(|s| s.id()));
+        assert_eq!(vec![first, last], w.objects().iter().map(|o| o.id()).collect::<Vec<_>>());
+        assert!(w.remove_object(2).is_none());
+    }
+
+    #[test]
+    fn builder_adds_objects_one_by_one() {
+        let s1 = Sphere::default();
+        let s2 = Sphere::default();
+        let w = WorldBuilder::default()
+            .objects(vec![s1.clone().into()])
+            .add_object(s2.clone())
+            .build()
+            .unwrap();
+
+        assert_eq!(vec![Shape::from(s1), Shape::from(s2)], w.objects());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn world_and_camera_survive_a_json_round_trip() {
+        let w = World::default();
+        let mut c = Camera::new(160, 120, std::f64::consts::PI / 3.0);
+        c.set_transform(Matrix::view_transform(
+            Tuple::point(1.0, 2.0, -5.0),
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0),
+        )).unwrap();
+
+        let json = serde_json::to_string(&(&w, &c)).unwrap();
+        let (read, read_camera): (World, Camera) = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(w.objects().len(), read.objects().len());
+        for (a, b) in w.objects().iter().zip(read.objects()) {
+            assert_fuzzy_eq!(a.clone(), b.clone());
+            assert_fuzzy_eq!(a.material(), b.material());
+            assert_fuzzy_eq!(a.transform(), b.transform());
+        }
+        let (light, read_light) = (w.lights[0], read.lights[0]);
+        assert_eq!(light.id(), read_light.id());
+        assert_fuzzy_eq!(light.position, read_light.position);
+        assert_fuzzy_eq!(light.color, read_light.color);
+        assert_fuzzy_eq!(light.intensity, read_light.intensity);
+        assert_eq!((c.hsize, c.vsize), (read_camera.hsize, read_camera.vsize));
+        assert_fuzzy_eq!(c.fov, read_camera.fov);
+        assert_fuzzy_eq!(c.transform(), read_camera.transform());
+        assert_fuzzy_eq!(c.ray_for_pixel(17, 93), read_camera.ray_for_pixel(17, 93));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn unknown_shape_tag_is_an_error() {
+        let result = serde_json::from_str::<Shape>(r#"{"type":"Teapot","transform":[]}"#);
+
+        assert!(result.unwrap_err().to_string().contains("Teapot"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn names_survive_a_json_round_trip() {
+        let w = WorldBuilder::default().add_object(named_sphere("floor", 0.0)).add_object(Shape::Cube(Default::default())).build().unwrap();
+
+        let read: World = serde_json::from_str(&serde_json::to_string(&w).unwrap()).unwrap();
+
+        assert_eq!(Some("floor"), read.objects()[0].name());
+        assert_eq!(None, read.objects()[1].name());
+        assert!(read.object_by_name("floor").is_some());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn reading_a_world_with_duplicate_names_is_an_error() {
+        let mut w = WorldBuilder::default().add_object(named_sphere("ball", 0.0)).build().unwrap();
+        w.add_object(named_sphere("ball", 2.0));
+
+        let result = serde_json::from_str::<World>(&serde_json::to_string(&w).unwrap());
+
+        assert!(result.unwrap_err().to_string().contains("more than one object is named \"ball\""));
     }
 }